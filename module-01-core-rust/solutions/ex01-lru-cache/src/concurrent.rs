@@ -0,0 +1,231 @@
+use crate::LRUCache;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Default number of shards used by `ConcurrentLRUCache::new`.
+const DEFAULT_SHARDS: usize = 16;
+
+/// An LRU cache that can be shared between tokio tasks.
+///
+/// Keys are spread over several independently locked `LRUCache` shards by
+/// hash, so tasks touching different keys rarely wait on each other.
+/// Recency is tracked per shard: when a shard is full it evicts its own
+/// least recently used key, which may not be the oldest key overall.
+///
+/// Cloning is cheap and every clone refers to the same cache.
+///
+/// # Examples
+///
+/// ```
+/// use lru_cache::ConcurrentLRUCache;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let cache = ConcurrentLRUCache::new(100);
+///
+/// let writer = cache.clone();
+/// tokio::spawn(async move { writer.put(1, "a").await }).await.unwrap();
+///
+/// assert_eq!(cache.get(&1).await, Some("a"));
+/// # }
+/// ```
+pub struct ConcurrentLRUCache<K, V> {
+    shards: Arc<[Mutex<LRUCache<K, V>>]>,
+    shard_capacity: usize,
+    hasher: RandomState,
+}
+
+impl<K, V> ConcurrentLRUCache<K, V>
+where
+    K: Eq + Hash + Clone,
+{
+    /// Creates a cache holding about `capacity` items over the default
+    /// number of shards (fewer if `capacity` is small).
+    ///
+    /// # Panics
+    ///
+    /// Panics if capacity is 0.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_shards(capacity, DEFAULT_SHARDS.min(capacity.max(1)))
+    }
+
+    /// Creates a cache split over `shards` shards.
+    ///
+    /// Each shard holds `capacity / shards` items, rounded up.
+    ///
+    /// # Panics
+    ///
+    /// Panics if capacity or shards is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lru_cache::ConcurrentLRUCache;
+    ///
+    /// let cache: ConcurrentLRUCache<u32, u32> = ConcurrentLRUCache::with_shards(10, 4);
+    /// assert_eq!(cache.shard_count(), 4);
+    /// assert_eq!(cache.capacity(), 12);
+    /// ```
+    pub fn with_shards(capacity: usize, shards: usize) -> Self {
+        assert!(capacity > 0, "Capacity must be greater than 0");
+        assert!(shards > 0, "Shard count must be greater than 0");
+
+        let shard_capacity = capacity.div_ceil(shards);
+        ConcurrentLRUCache {
+            shards: (0..shards)
+                .map(|_| Mutex::new(LRUCache::new(shard_capacity)))
+                .collect(),
+            shard_capacity,
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Inserts or updates a key-value pair, evicting the least recently used
+    /// item of the key's shard if that shard is full.
+    pub async fn put(&self, key: K, value: V) {
+        self.shard(&key).lock().await.put(key, value);
+    }
+
+    /// Returns the number of items currently in the cache.
+    ///
+    /// Shards are counted one at a time, so with concurrent writers the
+    /// result may not match any single moment.
+    pub async fn len(&self) -> usize {
+        let mut len = 0;
+        for shard in self.shards.iter() {
+            len += shard.lock().await.len();
+        }
+        len
+    }
+
+    /// Returns `true` if the cache contains no items.
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// Removes all items from the cache.
+    pub async fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.lock().await.clear();
+        }
+    }
+
+    /// Returns the total capacity over all shards.
+    pub fn capacity(&self) -> usize {
+        self.shards.len() * self.shard_capacity
+    }
+
+    /// Returns the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    fn shard(&self, key: &K) -> &Mutex<LRUCache<K, V>> {
+        let index = self.hasher.hash_one(key) as usize % self.shards.len();
+        &self.shards[index]
+    }
+}
+
+impl<K, V> ConcurrentLRUCache<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    /// Gets a clone of a value and marks it as recently used.
+    pub async fn get(&self, key: &K) -> Option<V> {
+        self.shard(key).lock().await.get(key)
+    }
+}
+
+impl<K, V> Clone for ConcurrentLRUCache<K, V> {
+    fn clone(&self) -> Self {
+        ConcurrentLRUCache {
+            shards: Arc::clone(&self.shards),
+            shard_capacity: self.shard_capacity,
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_basic_operations() {
+        let cache = ConcurrentLRUCache::with_shards(4, 2);
+        assert!(cache.is_empty().await);
+
+        cache.put("a", 1).await;
+        cache.put("b", 2).await;
+        assert_eq!(cache.get(&"a").await, Some(1));
+        assert_eq!(cache.get(&"c").await, None);
+        assert_eq!(cache.len().await, 2);
+
+        cache.clear().await;
+        assert!(cache.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_shards_never_exceed_capacity() {
+        let cache = ConcurrentLRUCache::with_shards(8, 4);
+        for i in 0..100 {
+            cache.put(i, i).await;
+        }
+
+        assert!(cache.len().await <= cache.capacity());
+        for shard in cache.shards.iter() {
+            assert_eq!(shard.lock().await.len(), 2);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Shard count must be greater than 0")]
+    fn test_zero_shards_panics() {
+        let _cache: ConcurrentLRUCache<i32, i32> = ConcurrentLRUCache::with_shards(10, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_many_tasks() {
+        const TASKS: u64 = 32;
+        const KEYS_PER_TASK: u64 = 50;
+
+        // Big enough that nothing is evicted, so every write must be visible
+        let cache = ConcurrentLRUCache::new((TASKS * KEYS_PER_TASK * 2) as usize);
+
+        let handles: Vec<_> = (0..TASKS)
+            .map(|task| {
+                let cache = cache.clone();
+                tokio::spawn(async move {
+                    for round in 0..10 {
+                        for i in 0..KEYS_PER_TASK {
+                            let key = task * KEYS_PER_TASK + i;
+                            cache.put(key, (key, round)).await;
+                            // Tasks own disjoint keys, so reads see their own writes
+                            assert_eq!(cache.get(&key).await, Some((key, round)));
+                            // Shared keys are read by everyone
+                            let _ = cache.get(&(i % 8)).await;
+                            let _ = cache.len().await;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let all = async {
+            for handle in handles {
+                handle.await.unwrap();
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(30), all)
+            .await
+            .expect("tasks deadlocked");
+
+        assert_eq!(cache.len().await, (TASKS * KEYS_PER_TASK) as usize);
+        for key in 0..TASKS * KEYS_PER_TASK {
+            assert_eq!(cache.get(&key).await, Some((key, 9)));
+        }
+    }
+}
@@ -1,6 +1,12 @@
 use std::collections::{HashMap, VecDeque};
 use std::hash::Hash;
 
+#[cfg(feature = "concurrent")]
+mod concurrent;
+
+#[cfg(feature = "concurrent")]
+pub use concurrent::ConcurrentLRUCache;
+
 /// An LRU (Least Recently Used) cache with a fixed capacity.
 ///
 /// When the cache reaches capacity, the least recently used item is evicted.
@@ -27,7 +33,6 @@ pub struct LRUCache<K, V> {
 impl<K, V> LRUCache<K, V>
 where
     K: Eq + Hash + Clone,
-    V: Clone,
 {
     /// Creates a new LRU cache with the specified capacity.
     ///
@@ -52,9 +57,36 @@ where
         }
     }
 
-    /// Gets a value from the cache and marks it as recently used.
+    /// Rebuilds a cache from entries ordered least to most recently used.
     ///
-    /// Returns `None` if the key is not found in the cache.
+    /// This is the inverse of `to_entries`. If there are more entries than
+    /// `capacity`, the least recently used ones are dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if capacity is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lru_cache::LRUCache;
+    ///
+    /// let mut cache = LRUCache::from_entries(2, vec![(1, "a"), (2, "b")]);
+    /// cache.put(3, "c");  // Evicts key 1, the first entry
+    /// assert_eq!(cache.get(&1), None);
+    /// ```
+    pub fn from_entries(capacity: usize, entries: Vec<(K, V)>) -> Self {
+        let mut cache = LRUCache::new(capacity);
+        for (key, value) in entries {
+            cache.put(key, value);
+        }
+        cache
+    }
+
+    /// Gets a reference to a value and marks it as recently used.
+    ///
+    /// Unlike `get`, this does not clone the value, so it works for value
+    /// types that are expensive to clone or don't implement `Clone` at all.
     ///
     /// # Examples
     ///
@@ -62,11 +94,11 @@ where
     /// use lru_cache::LRUCache;
     ///
     /// let mut cache = LRUCache::new(2);
-    /// cache.put(1, "value");
-    /// assert_eq!(cache.get(&1), Some("value"));
-    /// assert_eq!(cache.get(&2), None);
+    /// cache.put(1, vec![0u8; 1024]);
+    /// assert_eq!(cache.get_ref(&1).map(|buf| buf.len()), Some(1024));
+    /// assert_eq!(cache.get_ref(&2), None);
     /// ```
-    pub fn get(&mut self, key: &K) -> Option<V> {
+    pub fn get_ref(&mut self, key: &K) -> Option<&V> {
         if !self.map.contains_key(key) {
             return None;
         }
@@ -74,10 +106,25 @@ where
         // Update recency: move to back (most recently used)
         self.update_recency(key);
 
-        // Return cloned value
-        // Note: We clone because returning a reference would require lifetimes
-        // and complicate the API. For most use cases, this is acceptable.
-        self.map.get(key).cloned()
+        self.map.get(key)
+    }
+
+    /// Gets a reference to a value without marking it as recently used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lru_cache::LRUCache;
+    ///
+    /// let mut cache = LRUCache::new(2);
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// assert_eq!(cache.peek(&1), Some(&"a"));
+    /// cache.put(3, "c");  // Still evicts key 1
+    /// assert_eq!(cache.peek(&1), None);
+    /// ```
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.map.get(key)
     }
 
     /// Inserts or updates a key-value pair in the cache.
@@ -194,11 +241,100 @@ where
     }
 }
 
+impl<K, V> LRUCache<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    /// Gets a value from the cache and marks it as recently used.
+    ///
+    /// Returns `None` if the key is not found in the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lru_cache::LRUCache;
+    ///
+    /// let mut cache = LRUCache::new(2);
+    /// cache.put(1, "value");
+    /// assert_eq!(cache.get(&1), Some("value"));
+    /// assert_eq!(cache.get(&2), None);
+    /// ```
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        // Return cloned value
+        // Note: Use `get_ref` to borrow instead when cloning is expensive.
+        self.get_ref(key).cloned()
+    }
+
+    /// Returns all entries ordered from least to most recently used.
+    ///
+    /// Pass the result to `from_entries` to rebuild an identical cache, e.g.
+    /// after persisting it to disk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lru_cache::LRUCache;
+    ///
+    /// let mut cache = LRUCache::new(2);
+    /// cache.put(1, "a");
+    /// cache.put(2, "b");
+    /// cache.get(&1);
+    /// assert_eq!(cache.to_entries(), vec![(2, "b"), (1, "a")]);
+    /// ```
+    pub fn to_entries(&self) -> Vec<(K, V)> {
+        self.order
+            .iter()
+            .map(|key| (key.clone(), self.map[key].clone()))
+            .collect()
+    }
+}
+
+// Serialized as `{ capacity, entries }` with entries in LRU order
+#[cfg(feature = "serde")]
+impl<K, V> serde::Serialize for LRUCache<K, V>
+where
+    K: Eq + Hash + Clone + serde::Serialize,
+    V: serde::Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let entries: Vec<(&K, &V)> = self.order.iter().map(|key| (key, &self.map[key])).collect();
+
+        let mut state = serializer.serialize_struct("LRUCache", 2)?;
+        state.serialize_field("capacity", &self.capacity)?;
+        state.serialize_field("entries", &entries)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V> serde::Deserialize<'de> for LRUCache<K, V>
+where
+    K: Eq + Hash + Clone + serde::Deserialize<'de>,
+    V: serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Snapshot<K, V> {
+            capacity: usize,
+            entries: Vec<(K, V)>,
+        }
+
+        let snapshot = Snapshot::deserialize(deserializer)?;
+        if snapshot.capacity == 0 {
+            return Err(serde::de::Error::custom("Capacity must be greater than 0"));
+        }
+
+        Ok(LRUCache::from_entries(snapshot.capacity, snapshot.entries))
+    }
+}
+
 // Bonus: Implement Debug for easier debugging
 impl<K, V> std::fmt::Debug for LRUCache<K, V>
 where
     K: Eq + Hash + Clone + std::fmt::Debug,
-    V: Clone + std::fmt::Debug,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("LRUCache")
@@ -325,6 +461,99 @@ mod tests {
         assert_eq!(cache.get(&4), Some("d"));
     }
 
+    /// Deliberately not `Clone`
+    #[derive(Debug, PartialEq)]
+    struct Buffer(Vec<u8>);
+
+    #[test]
+    fn test_get_ref_without_clone() {
+        let mut cache = LRUCache::new(2);
+
+        cache.put(1, Buffer(vec![1, 2, 3]));
+        assert_eq!(cache.get_ref(&1), Some(&Buffer(vec![1, 2, 3])));
+        assert_eq!(cache.get_ref(&2), None);
+        assert_eq!(cache.peek(&1), Some(&Buffer(vec![1, 2, 3])));
+    }
+
+    #[test]
+    fn test_get_ref_updates_recency() {
+        let mut cache = LRUCache::new(2);
+
+        cache.put(1, Buffer(vec![1]));
+        cache.put(2, Buffer(vec![2]));
+        cache.get_ref(&1);              // Make 1 more recent
+        cache.put(3, Buffer(vec![3]));  // Should evict 2, not 1
+
+        assert!(cache.peek(&1).is_some());
+        assert!(cache.peek(&2).is_none());
+        assert!(cache.peek(&3).is_some());
+    }
+
+    #[test]
+    fn test_peek_does_not_update_recency() {
+        let mut cache = LRUCache::new(2);
+
+        cache.put(1, "a");
+        cache.put(2, "b");
+        assert_eq!(cache.peek(&1), Some(&"a"));
+        cache.put(3, "c");  // Should still evict 1
+
+        assert_eq!(cache.peek(&1), None);
+        assert_eq!(cache.peek(&2), Some(&"b"));
+    }
+
+    #[test]
+    fn test_entries_round_trip() {
+        let mut cache = LRUCache::new(3);
+
+        cache.put(1, "a");
+        cache.put(2, "b");
+        cache.put(3, "c");
+        cache.get(&1);      // Order: 2, 3, 1
+
+        let entries = cache.to_entries();
+        assert_eq!(entries, vec![(2, "b"), (3, "c"), (1, "a")]);
+
+        let mut restored = LRUCache::from_entries(3, entries);
+        assert_eq!(restored.len(), 3);
+        assert_eq!(restored.to_entries(), cache.to_entries());
+
+        restored.put(4, "d");  // Should evict 2, the oldest entry
+        assert_eq!(restored.get(&2), None);
+        restored.put(5, "e");  // Then 3
+        assert_eq!(restored.get(&3), None);
+        assert_eq!(restored.get(&1), Some("a"));
+    }
+
+    #[test]
+    fn test_from_entries_over_capacity() {
+        let cache = LRUCache::from_entries(2, vec![(1, "a"), (2, "b"), (3, "c")]);
+
+        // The least recently used entry is dropped
+        assert_eq!(cache.to_entries(), vec![(2, "b"), (3, "c")]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut cache = LRUCache::new(2);
+        cache.put("one".to_string(), 1);
+        cache.put("two".to_string(), 2);
+        cache.get(&"one".to_string());
+
+        let json = serde_json::to_string(&cache).unwrap();
+        let mut restored: LRUCache<String, i32> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.capacity(), 2);
+        assert_eq!(restored.to_entries(), cache.to_entries());
+
+        restored.put("three".to_string(), 3);  // Should evict "two"
+        assert_eq!(restored.get(&"two".to_string()), None);
+
+        let zero = r#"{"capacity": 0, "entries": []}"#;
+        assert!(serde_json::from_str::<LRUCache<String, i32>>(zero).is_err());
+    }
+
     #[test]
     fn test_update_moves_to_recent() {
         let mut cache = LRUCache::new(2);
@@ -1,10 +1,49 @@
-use crate::message::{parse_input, validate_nickname, Command, Message, HELP_TEXT};
+use crate::message::{
+    parse_input, parse_json_input, validate_nickname, Command, Message, HELP_TEXT,
+};
 use crate::server::ChatServer;
+use std::io;
 use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
 use tokio::net::TcpStream;
 use tokio::select;
 use tokio::sync::mpsc;
+use tokio::time::{sleep, timeout, Instant};
+
+/// Turn away a connection that arrived while the server is full
+pub async fn reject_connection(mut socket: TcpStream) {
+    let _ = socket
+        .write_all(b"Server is full, please try again later\n")
+        .await;
+    let _ = socket.shutdown().await;
+}
+
+/// Write half of a connection, speaking either the text or JSON protocol
+struct ClientWriter {
+    writer: OwnedWriteHalf,
+    json: bool,
+}
+
+impl ClientWriter {
+    fn new(writer: OwnedWriteHalf) -> Self {
+        ClientWriter { writer, json: false }
+    }
+
+    async fn send(&mut self, msg: &Message) -> io::Result<()> {
+        let line = if self.json { msg.to_json() } else { msg.format() };
+        self.writer.write_all(format!("{}\n", line).as_bytes()).await
+    }
+
+    /// Send a plain server reply, wrapped as a system message in JSON mode
+    async fn send_text(&mut self, text: &str) -> io::Result<()> {
+        if self.json {
+            self.send(&Message::system(text.trim().to_string())).await
+        } else {
+            self.writer.write_all(text.as_bytes()).await
+        }
+    }
+}
 
 /// Handle a client connection
 pub async fn handle_client(socket: TcpStream, server: Arc<ChatServer>) {
@@ -25,8 +64,12 @@ pub async fn handle_client(socket: TcpStream, server: Arc<ChatServer>) {
     }
 
     // Read nickname
-    let nickname = match lines.next_line().await {
-        Ok(Some(line)) => line.trim().to_string(),
+    let nickname = match timeout(server.idle_timeout(), lines.next_line()).await {
+        Ok(Ok(Some(line))) => line.trim().to_string(),
+        Err(_) => {
+            let _ = writer.write_all(b"\nTimed out waiting for a nickname\n").await;
+            return;
+        }
         _ => {
             eprintln!("Failed to read nickname from {}", addr);
             return;
@@ -44,8 +87,8 @@ pub async fn handle_client(socket: TcpStream, server: Arc<ChatServer>) {
     // Create channel for outgoing messages to this client
     let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
 
-    // Register user
-    if let Err(e) = server.register_user(nickname.clone(), tx.clone()).await {
+    // Register user (the server holds the only sender, so dropping it disconnects us)
+    if let Err(e) = server.register_user(nickname.clone(), tx).await {
         let _ = writer
             .write_all(format!("Registration failed: {}\n", e).as_bytes())
             .await;
@@ -54,12 +97,14 @@ pub async fn handle_client(socket: TcpStream, server: Arc<ChatServer>) {
 
     println!("{} ({}) connected", nickname, addr);
 
+    let mut writer = ClientWriter::new(writer);
+
     // Send welcome message
     let welcome = format!(
         "Welcome {}! You are in #lobby\nType /help for available commands\n",
         nickname
     );
-    if writer.write_all(welcome.as_bytes()).await.is_err() {
+    if writer.send_text(&welcome).await.is_err() {
         server.unregister_user(&nickname).await;
         return;
     }
@@ -79,17 +124,42 @@ pub async fn handle_client(socket: TcpStream, server: Arc<ChatServer>) {
     let nickname_clone = nickname.clone();
     let server_clone = server.clone();
 
+    // Reset every time the client sends a line
+    let idle_timeout = server.idle_timeout();
+    let idle = sleep(idle_timeout);
+    tokio::pin!(idle);
+
     loop {
         select! {
             // Incoming messages from user
             line = lines.next_line() => {
                 match line {
                     Ok(Some(line)) => {
+                        idle.as_mut().reset(Instant::now() + idle_timeout);
+
                         let line = line.trim();
                         if line.is_empty() {
                             continue;
                         }
 
+                        let json_line;
+                        let line = if writer.json {
+                            match parse_json_input(line) {
+                                Ok(translated) => {
+                                    json_line = translated;
+                                    json_line.as_str()
+                                }
+                                Err(e) => {
+                                    if writer.send(&Message::error(e)).await.is_err() {
+                                        break;
+                                    }
+                                    continue;
+                                }
+                            }
+                        } else {
+                            line
+                        };
+
                         // Try to parse as command
                         match parse_input(line) {
                             Ok(cmd) => {
@@ -106,6 +176,13 @@ pub async fn handle_client(socket: TcpStream, server: Arc<ChatServer>) {
                             }
                             Err(_) => {
                                 // Not a command, treat as regular message
+                                if let Err(e) = server_clone.check_rate_limit(&nickname_clone).await {
+                                    if writer.send(&Message::error(e)).await.is_err() {
+                                        break;
+                                    }
+                                    continue;
+                                }
+
                                 let current_room = server_clone.get_user_room(&nickname_clone).await;
                                 if let Some(room_name) = current_room {
                                     let msg = Message::chat(nickname_clone.clone(), line.to_string());
@@ -125,8 +202,7 @@ pub async fn handle_client(socket: TcpStream, server: Arc<ChatServer>) {
             msg = rx.recv() => {
                 match msg {
                     Some(msg) => {
-                        let formatted = format!("{}\n", msg.format());
-                        if writer.write_all(formatted.as_bytes()).await.is_err() {
+                        if writer.send(&msg).await.is_err() {
                             break;
                         }
                     }
@@ -134,12 +210,22 @@ pub async fn handle_client(socket: TcpStream, server: Arc<ChatServer>) {
                 }
             }
 
+            // Client went quiet for too long
+            () = &mut idle => {
+                let msg = Message::system(format!(
+                    "Disconnected after {} seconds of inactivity",
+                    idle_timeout.as_secs()
+                ));
+                let _ = writer.send(&msg).await;
+                println!("{} ({}) timed out", nickname, addr);
+                break;
+            }
+
             // Broadcast messages from current room
             msg = room_rx.recv() => {
                 match msg {
                     Ok(msg) => {
-                        let formatted = format!("{}\n", msg.format());
-                        if writer.write_all(formatted.as_bytes()).await.is_err() {
+                        if writer.send(&msg).await.is_err() {
                             break;
                         }
                     }
@@ -155,7 +241,7 @@ pub async fn handle_client(socket: TcpStream, server: Arc<ChatServer>) {
     println!("{} ({}) disconnected", nickname, addr);
     server.unregister_user(&nickname).await;
 
-    let _ = writer.write_all(b"Goodbye!\n").await;
+    let _ = writer.send_text("Goodbye!\n").await;
 }
 
 /// Handle a command from the user
@@ -164,15 +250,14 @@ async fn handle_command(
     cmd: Command,
     nickname: &str,
     server: &ChatServer,
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     room_rx: &mut tokio::sync::broadcast::Receiver<Message>,
 ) -> bool {
     match cmd {
         Command::Nick(new_nick) => {
             // Validate new nickname
             if let Err(e) = validate_nickname(&new_nick) {
-                let msg = Message::error(e);
-                let _ = writer.write_all(format!("{}\n", msg.format()).as_bytes()).await;
+                let _ = writer.send(&Message::error(e)).await;
                 return true;
             }
 
@@ -180,11 +265,10 @@ async fn handle_command(
             match server.change_nickname(nickname, new_nick.clone()).await {
                 Ok(()) => {
                     let msg = format!("You are now known as {}\n", new_nick);
-                    let _ = writer.write_all(msg.as_bytes()).await;
+                    let _ = writer.send_text(&msg).await;
                 }
                 Err(e) => {
-                    let msg = Message::error(e);
-                    let _ = writer.write_all(format!("{}\n", msg.format()).as_bytes()).await;
+                    let _ = writer.send(&Message::error(e)).await;
                 }
             }
         }
@@ -193,7 +277,7 @@ async fn handle_command(
             match server.join_room(nickname, room_name.clone()).await {
                 Ok(()) => {
                     let msg = format!("You joined #{}\n", room_name);
-                    let _ = writer.write_all(msg.as_bytes()).await;
+                    let _ = writer.send_text(&msg).await;
 
                     // Subscribe to new room
                     if let Some(new_rx) = server.subscribe_to_room(&room_name).await {
@@ -201,8 +285,7 @@ async fn handle_command(
                     }
                 }
                 Err(e) => {
-                    let msg = Message::error(e);
-                    let _ = writer.write_all(format!("{}\n", msg.format()).as_bytes()).await;
+                    let _ = writer.send(&Message::error(e)).await;
                 }
             }
         }
@@ -212,7 +295,7 @@ async fn handle_command(
             match server.join_room(nickname, "lobby".to_string()).await {
                 Ok(()) => {
                     let msg = "You returned to #lobby\n";
-                    let _ = writer.write_all(msg.as_bytes()).await;
+                    let _ = writer.send_text(msg).await;
 
                     // Subscribe to lobby
                     if let Some(new_rx) = server.subscribe_to_room("lobby").await {
@@ -220,8 +303,7 @@ async fn handle_command(
                     }
                 }
                 Err(e) => {
-                    let msg = Message::error(e);
-                    let _ = writer.write_all(format!("{}\n", msg.format()).as_bytes()).await;
+                    let _ = writer.send(&Message::error(e)).await;
                 }
             }
         }
@@ -232,7 +314,7 @@ async fn handle_command(
             for (name, count) in rooms {
                 output.push_str(&format!("  #{} ({} users)\n", name, count));
             }
-            let _ = writer.write_all(output.as_bytes()).await;
+            let _ = writer.send_text(&output).await;
         }
 
         Command::Users => {
@@ -246,27 +328,89 @@ async fn handle_command(
                         output.push_str(&format!("  {}\n", user));
                     }
                 }
-                let _ = writer.write_all(output.as_bytes()).await;
+                let _ = writer.send_text(&output).await;
             }
         }
 
         Command::Msg { recipient, content } => {
-            let msg = Message::private(nickname.to_string(), recipient.clone(), content.clone());
+            send_private(nickname, recipient, content, server, writer).await;
+        }
 
-            match server.send_private_message(&recipient, msg).await {
+        Command::Reply(content) => match server.get_reply_target(nickname).await {
+            Some(recipient) => {
+                send_private(nickname, recipient, content, server, writer).await;
+            }
+            None => {
+                let _ = writer.send(&Message::error("No one to reply to".to_string())).await;
+            }
+        },
+
+        Command::Away(away_message) => {
+            let confirmation = match &away_message {
+                Some(_) => "You are now marked as away\n",
+                None => "You are no longer away\n",
+            };
+
+            match server.set_away(nickname, away_message).await {
                 Ok(()) => {
-                    let confirmation = format!("[Private to {}]: {}\n", recipient, content);
-                    let _ = writer.write_all(confirmation.as_bytes()).await;
+                    let _ = writer.send_text(confirmation).await;
                 }
                 Err(e) => {
-                    let msg = Message::error(e);
-                    let _ = writer.write_all(format!("{}\n", msg.format()).as_bytes()).await;
+                    let _ = writer.send(&Message::error(e)).await;
                 }
             }
         }
 
+        Command::History(count) => {
+            if let Some(current_room) = server.get_user_room(nickname).await {
+                let history = server
+                    .room_history(&current_room, count.unwrap_or(usize::MAX))
+                    .await;
+                let mut output = format!("Recent messages in #{}:\n", current_room);
+                for msg in history {
+                    output.push_str(&format!("  {}\n", msg.format()));
+                }
+                let _ = writer.send_text(&output).await;
+            }
+        }
+
+        Command::Topic(None) => {
+            if let Some(current_room) = server.get_user_room(nickname).await {
+                let output = match server.room_topic(&current_room).await {
+                    Some(topic) => format!("Topic for #{}: {}\n", current_room, topic),
+                    None => format!("No topic set for #{}\n", current_room),
+                };
+                let _ = writer.send_text(&output).await;
+            }
+        }
+
+        Command::Topic(Some(topic)) => {
+            if let Err(e) = server.set_topic(nickname, topic).await {
+                let _ = writer.send(&Message::error(e)).await;
+            }
+        }
+
+        Command::Kick(target) => {
+            if let Err(e) = server.kick_user(nickname, &target).await {
+                let _ = writer.send(&Message::error(e)).await;
+            }
+        }
+
+        Command::Ban(target) => {
+            if let Err(e) = server.ban_user(nickname, &target).await {
+                let _ = writer.send(&Message::error(e)).await;
+            }
+        }
+
+        Command::Json => {
+            writer.json = true;
+            let _ = writer
+                .send_text("Switched to JSON mode, send one JSON object per line\n")
+                .await;
+        }
+
         Command::Help => {
-            let _ = writer.write_all(HELP_TEXT.as_bytes()).await;
+            let _ = writer.send_text(HELP_TEXT).await;
         }
 
         Command::Quit => {
@@ -276,3 +420,118 @@ async fn handle_command(
 
     true
 }
+
+/// Send a private message on behalf of a user and confirm it to them
+async fn send_private(
+    nickname: &str,
+    recipient: String,
+    content: String,
+    server: &ChatServer,
+    writer: &mut ClientWriter,
+) {
+    if let Err(e) = server.check_rate_limit(nickname).await {
+        let _ = writer.send(&Message::error(e)).await;
+        return;
+    }
+
+    let msg = Message::private(nickname.to_string(), recipient.clone(), content.clone());
+
+    match server.send_private_message(&recipient, msg).await {
+        Ok(()) => {
+            let confirmation = format!("[Private to {}]: {}\n", recipient, content);
+            let _ = writer.send_text(&confirmation).await;
+        }
+        Err(e) => {
+            let _ = writer.send(&Message::error(e)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    /// Run `handle_client` for a single connection and return the client side
+    async fn connect(server: Arc<ChatServer>) -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            handle_client(socket, server).await;
+        });
+
+        TcpStream::connect(addr).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_idle_client_is_disconnected() {
+        let server = Arc::new(
+            ChatServer::new(10).with_idle_timeout(Duration::from_millis(100)),
+        );
+        let mut client = connect(Arc::clone(&server)).await;
+        client.write_all(b"Alice\n").await.unwrap();
+
+        let mut lines = BufReader::new(client).lines();
+        let mut transcript = Vec::new();
+        let read_all = async {
+            while let Ok(Some(line)) = lines.next_line().await {
+                transcript.push(line);
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), read_all)
+            .await
+            .expect("idle client was never disconnected");
+
+        assert!(transcript.iter().any(|line| line.contains("inactivity")));
+        assert!(!server.is_nickname_taken("Alice").await);
+    }
+
+    #[tokio::test]
+    async fn test_activity_resets_idle_timer() {
+        let server = Arc::new(
+            ChatServer::new(10).with_idle_timeout(Duration::from_millis(200)),
+        );
+        let mut client = connect(Arc::clone(&server)).await;
+        client.write_all(b"Alice\n").await.unwrap();
+
+        // Keep talking for longer than the timeout
+        for _ in 0..4 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            client.write_all(b"/rooms\n").await.unwrap();
+        }
+        assert!(server.is_nickname_taken("Alice").await);
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(!server.is_nickname_taken("Alice").await);
+    }
+
+    #[tokio::test]
+    async fn test_json_mode() {
+        let server = Arc::new(ChatServer::new(10));
+        let mut client = connect(server).await;
+        client
+            .write_all(b"Alice\n/json\n{\"type\": \"command\", \"command\": \"rooms\"}\n")
+            .await
+            .unwrap();
+
+        let mut lines = BufReader::new(client).lines();
+        let find_rooms = async {
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if let Ok(value) = serde_json::from_str::<serde_json::Value>(&line) {
+                    if value["content"].as_str().unwrap_or("").contains("Available rooms") {
+                        return value;
+                    }
+                }
+            }
+            panic!("connection closed before the rooms reply");
+        };
+
+        let reply = tokio::time::timeout(Duration::from_secs(5), find_rooms)
+            .await
+            .unwrap();
+        assert_eq!(reply["type"], "system");
+    }
+}
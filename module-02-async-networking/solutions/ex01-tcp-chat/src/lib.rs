@@ -0,0 +1,9 @@
+pub mod client;
+pub mod message;
+pub mod rate_limit;
+pub mod room;
+pub mod server;
+
+pub use client::{handle_client, reject_connection};
+pub use rate_limit::RateLimitConfig;
+pub use server::ChatServer;
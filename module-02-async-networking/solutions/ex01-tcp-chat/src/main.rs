@@ -1,23 +1,33 @@
-mod client;
-mod message;
-mod room;
-mod server;
-
-use client::handle_client;
-use server::ChatServer;
+use tcp_chat_server::{handle_client, reject_connection, ChatServer, RateLimitConfig};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::signal;
 
 const DEFAULT_PORT: u16 = 8080;
 const MAX_CONNECTIONS: usize = 1000;
+const HISTORY_SIZE: usize = 50;
+const MESSAGES_PER_SECOND: f64 = 5.0;
+const MESSAGE_BURST: u32 = 10;
+const MAX_ROOM_MEMBERS: usize = 200;
+const IDLE_TIMEOUT: Duration = Duration::from_secs(600);
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Starting TCP Chat Server...");
 
     // Create shared server state
-    let server = Arc::new(ChatServer::new(MAX_CONNECTIONS));
+    let server = Arc::new(
+        ChatServer::new(MAX_CONNECTIONS)
+            .with_history_size(HISTORY_SIZE)
+            .with_rate_limit(RateLimitConfig {
+                messages_per_second: MESSAGES_PER_SECOND,
+                burst: MESSAGE_BURST,
+                ..Default::default()
+            })
+            .with_max_room_members(MAX_ROOM_MEMBERS)
+            .with_idle_timeout(IDLE_TIMEOUT),
+    );
 
     // Bind to TCP port
     let addr = format!("0.0.0.0:{}", DEFAULT_PORT);
@@ -25,6 +35,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("Server listening on {}", addr);
     println!("Maximum connections: {}", MAX_CONNECTIONS);
+    println!("Maximum room members: {}", MAX_ROOM_MEMBERS);
+    println!("Idle timeout: {}s", IDLE_TIMEOUT.as_secs());
     println!("Press Ctrl+C to shutdown");
 
     // Spawn accept loop
@@ -32,10 +44,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let accept_task = tokio::spawn(async move {
         loop {
             match listener.accept().await {
-                Ok((socket, _addr)) => {
+                Ok((socket, addr)) => {
+                    // Enforce the cap before reading anything from the socket
+                    let Some(permit) = server_clone.try_acquire_connection() else {
+                        println!("Rejecting {}: server full", addr);
+                        tokio::spawn(reject_connection(socket));
+                        continue;
+                    };
+
                     let server = Arc::clone(&server_clone);
                     tokio::spawn(async move {
                         handle_client(socket, server).await;
+                        drop(permit);
                     });
                 }
                 Err(e) => {
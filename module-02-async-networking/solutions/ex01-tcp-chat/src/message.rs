@@ -1,4 +1,5 @@
 use chrono::Utc;
+use serde::{Deserialize, Serialize};
 
 /// Message types that can be sent between server and clients
 #[derive(Debug, Clone)]
@@ -65,6 +66,90 @@ impl Message {
             }
         }
     }
+
+    /// Serialize message as a single JSON line for clients in JSON mode
+    pub fn to_json(&self) -> String {
+        let wire = match self {
+            Message::Chat { sender, content, timestamp } => JsonMessage {
+                kind: "chat",
+                sender: Some(sender),
+                recipient: None,
+                content,
+                timestamp: Some(*timestamp),
+            },
+            Message::Private { from, to, content, timestamp } => JsonMessage {
+                kind: "private",
+                sender: Some(from),
+                recipient: Some(to),
+                content,
+                timestamp: Some(*timestamp),
+            },
+            Message::System(content) => JsonMessage {
+                kind: "system",
+                sender: None,
+                recipient: None,
+                content,
+                timestamp: None,
+            },
+            Message::Error(content) => JsonMessage {
+                kind: "error",
+                sender: None,
+                recipient: None,
+                content,
+                timestamp: None,
+            },
+        };
+
+        serde_json::to_string(&wire).expect("message serializes to JSON")
+    }
+}
+
+/// Wire format of a `Message` in JSON mode
+#[derive(Serialize)]
+struct JsonMessage<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sender: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recipient: Option<&'a str>,
+    content: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<chrono::DateTime<Utc>>,
+}
+
+/// A line sent by a client in JSON mode
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonInput {
+    /// `{"type": "chat", "content": "hello"}`
+    Chat { content: String },
+    /// `{"type": "command", "command": "/join rust"}`
+    Command { command: String },
+}
+
+/// Translate a JSON-mode line into the equivalent text protocol line
+pub fn parse_json_input(input: &str) -> Result<String, String> {
+    let parsed: JsonInput =
+        serde_json::from_str(input).map_err(|e| format!("Invalid JSON command: {}", e))?;
+
+    match parsed {
+        JsonInput::Chat { content } => {
+            // Keep chat text from being mistaken for a command
+            if content.trim_start().starts_with('/') {
+                return Err("Chat content cannot start with '/', use a command instead".to_string());
+            }
+            Ok(content)
+        }
+        JsonInput::Command { command } => {
+            let command = command.trim();
+            if command.starts_with('/') {
+                Ok(command.to_string())
+            } else {
+                Ok(format!("/{}", command))
+            }
+        }
+    }
 }
 
 /// Commands that users can send
@@ -76,6 +161,13 @@ pub enum Command {
     Rooms,
     Users,
     Msg { recipient: String, content: String },
+    Reply(String),
+    Away(Option<String>),
+    History(Option<usize>),
+    Topic(Option<String>),
+    Kick(String),
+    Ban(String),
+    Json,
     Help,
     Quit,
 }
@@ -124,6 +216,51 @@ pub fn parse_input(input: &str) -> Result<Command, String> {
                 content: msg_parts[1].to_string(),
             })
         }
+        "reply" | "r" => {
+            if parts.len() < 2 {
+                return Err("Usage: /reply <message>".to_string());
+            }
+            Ok(Command::Reply(parts[1].to_string()))
+        }
+        "away" => {
+            let message = parts
+                .get(1)
+                .map(|m| m.trim())
+                .filter(|m| !m.is_empty())
+                .map(|m| m.to_string());
+            Ok(Command::Away(message))
+        }
+        "history" => {
+            if parts.len() < 2 {
+                return Ok(Command::History(None));
+            }
+            let count = parts[1]
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| "Usage: /history [count]".to_string())?;
+            Ok(Command::History(Some(count)))
+        }
+        "topic" => {
+            let topic = parts
+                .get(1)
+                .map(|t| t.trim())
+                .filter(|t| !t.is_empty())
+                .map(|t| t.to_string());
+            Ok(Command::Topic(topic))
+        }
+        "kick" => {
+            if parts.len() < 2 {
+                return Err("Usage: /kick <user>".to_string());
+            }
+            Ok(Command::Kick(parts[1].trim().to_string()))
+        }
+        "ban" => {
+            if parts.len() < 2 {
+                return Err("Usage: /ban <user>".to_string());
+            }
+            Ok(Command::Ban(parts[1].trim().to_string()))
+        }
+        "json" => Ok(Command::Json),
         "help" => Ok(Command::Help),
         "quit" | "exit" => Ok(Command::Quit),
         _ => Err(format!("Unknown command: /{}. Type /help for available commands", command)),
@@ -155,6 +292,13 @@ Available commands:
   /rooms              - List all rooms with user counts
   /users              - List users in current room
   /msg <user> <text>  - Send private message
+  /reply <text>       - Reply to the last private message
+  /away [message]     - Set an away message (no message clears it)
+  /history [n]        - Show recent messages in current room
+  /topic [text]       - Show the room topic, or set it (operators only)
+  /kick <user>        - Disconnect a user from the room (operators only)
+  /ban <user>         - Disconnect and ban a user from the room (operators only)
+  /json               - Switch this connection to the JSON line protocol
   /help               - Show this help
   /quit               - Disconnect from server
 
@@ -199,6 +343,67 @@ mod tests {
         assert!(parse_input("/unknown").is_err());
     }
 
+    #[test]
+    fn test_parse_history_command() {
+        match parse_input("/history 5").unwrap() {
+            Command::History(count) => assert_eq!(count, Some(5)),
+            _ => panic!("Expected History command"),
+        }
+
+        match parse_input("/history").unwrap() {
+            Command::History(count) => assert_eq!(count, None),
+            _ => panic!("Expected History command"),
+        }
+
+        assert!(parse_input("/history lots").is_err());
+    }
+
+    #[test]
+    fn test_parse_moderation_commands() {
+        match parse_input("/kick Bob").unwrap() {
+            Command::Kick(nick) => assert_eq!(nick, "Bob"),
+            _ => panic!("Expected Kick command"),
+        }
+
+        match parse_input("/ban Bob").unwrap() {
+            Command::Ban(nick) => assert_eq!(nick, "Bob"),
+            _ => panic!("Expected Ban command"),
+        }
+
+        assert!(parse_input("/kick").is_err());
+    }
+
+    #[test]
+    fn test_parse_reply_and_away_commands() {
+        match parse_input("/reply see you soon").unwrap() {
+            Command::Reply(content) => assert_eq!(content, "see you soon"),
+            _ => panic!("Expected Reply command"),
+        }
+
+        match parse_input("/away at lunch").unwrap() {
+            Command::Away(message) => assert_eq!(message, Some("at lunch".to_string())),
+            _ => panic!("Expected Away command"),
+        }
+
+        match parse_input("/away").unwrap() {
+            Command::Away(message) => assert_eq!(message, None),
+            _ => panic!("Expected Away command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_topic_command() {
+        match parse_input("/topic Rust 2024 edition talk").unwrap() {
+            Command::Topic(topic) => assert_eq!(topic, Some("Rust 2024 edition talk".to_string())),
+            _ => panic!("Expected Topic command"),
+        }
+
+        match parse_input("/topic  ").unwrap() {
+            Command::Topic(topic) => assert_eq!(topic, None),
+            _ => panic!("Expected Topic command"),
+        }
+    }
+
     #[test]
     fn test_message_format() {
         let msg = Message::system("Test joined the room".to_string());
@@ -207,4 +412,78 @@ mod tests {
         let msg = Message::error("Invalid command".to_string());
         assert!(msg.format().contains("ERROR:"));
     }
+
+    fn json(msg: &Message) -> serde_json::Value {
+        serde_json::from_str(&msg.to_json()).unwrap()
+    }
+
+    #[test]
+    fn test_chat_to_json() {
+        let msg = Message::chat("Alice".to_string(), "hi all".to_string());
+        let timestamp = match &msg {
+            Message::Chat { timestamp, .. } => serde_json::to_value(timestamp).unwrap(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            json(&msg),
+            serde_json::json!({
+                "type": "chat",
+                "sender": "Alice",
+                "content": "hi all",
+                "timestamp": timestamp,
+            })
+        );
+    }
+
+    #[test]
+    fn test_private_to_json() {
+        let msg = Message::private("Alice".to_string(), "Bob".to_string(), "psst".to_string());
+        let value = json(&msg);
+
+        assert_eq!(value["type"], "private");
+        assert_eq!(value["sender"], "Alice");
+        assert_eq!(value["recipient"], "Bob");
+        assert_eq!(value["content"], "psst");
+        assert!(value["timestamp"].is_string());
+        assert_eq!(value.as_object().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_system_and_error_to_json() {
+        assert_eq!(
+            json(&Message::system("Bob joined the room".to_string())),
+            serde_json::json!({ "type": "system", "content": "Bob joined the room" })
+        );
+        assert_eq!(
+            json(&Message::error("Unknown room".to_string())),
+            serde_json::json!({ "type": "error", "content": "Unknown room" })
+        );
+    }
+
+    #[test]
+    fn test_to_json_is_single_line() {
+        let msg = Message::system("line one\nline two".to_string());
+        assert!(!msg.to_json().contains('\n'));
+    }
+
+    #[test]
+    fn test_parse_json_input() {
+        assert_eq!(
+            parse_json_input(r#"{"type": "chat", "content": "hello"}"#),
+            Ok("hello".to_string())
+        );
+        assert_eq!(
+            parse_json_input(r#"{"type": "command", "command": "join rust"}"#),
+            Ok("/join rust".to_string())
+        );
+        assert_eq!(
+            parse_json_input(r#"{"type": "command", "command": "/rooms"}"#),
+            Ok("/rooms".to_string())
+        );
+
+        assert!(parse_json_input(r#"{"type": "chat", "content": "/quit"}"#).is_err());
+        assert!(parse_json_input(r#"{"type": "dance"}"#).is_err());
+        assert!(parse_json_input("hello").is_err());
+    }
 }
@@ -0,0 +1,158 @@
+use std::time::{Duration, Instant};
+
+/// Flood protection settings applied to every user
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Sustained messages allowed per second
+    pub messages_per_second: f64,
+    /// Messages that may be sent in a quick burst
+    pub burst: u32,
+    /// Dropped messages before the user is muted
+    pub max_violations: u32,
+    /// How long a mute lasts
+    pub mute_duration: Duration,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            messages_per_second: 5.0,
+            burst: 10,
+            max_violations: 5,
+            mute_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Token bucket refilled continuously at a fixed rate
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_rate: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, refill_rate: f64) -> Self {
+        TokenBucket {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refill_rate,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Take one token if available
+    pub fn try_acquire(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_rate).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Per-user limiter that mutes users after repeated violations
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    bucket: TokenBucket,
+    violations: u32,
+    muted_until: Option<Instant>,
+    config: RateLimitConfig,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimiter {
+            bucket: TokenBucket::new(config.burst, config.messages_per_second),
+            violations: 0,
+            muted_until: None,
+            config,
+        }
+    }
+
+    /// Check whether the user may send a message right now
+    ///
+    /// Returns a warning for the user when the message should be dropped.
+    pub fn check(&mut self) -> Result<(), String> {
+        let now = Instant::now();
+
+        if let Some(until) = self.muted_until {
+            if now < until {
+                let remaining = until.duration_since(now).as_secs() + 1;
+                return Err(format!("You are muted for another {} seconds", remaining));
+            }
+            self.muted_until = None;
+        }
+
+        if self.bucket.try_acquire() {
+            return Ok(());
+        }
+
+        self.violations += 1;
+        if self.violations >= self.config.max_violations {
+            self.violations = 0;
+            self.muted_until = Some(now + self.config.mute_duration);
+            return Err(format!(
+                "You have been muted for {} seconds for flooding",
+                self.config.mute_duration.as_secs()
+            ));
+        }
+
+        Err("You are sending messages too fast, message dropped".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst() {
+        let mut bucket = TokenBucket::new(3, 1.0);
+
+        assert!(bucket.try_acquire());
+        assert!(bucket.try_acquire());
+        assert!(bucket.try_acquire());
+        assert!(!bucket.try_acquire());
+    }
+
+    #[test]
+    fn test_bucket_refills() {
+        let mut bucket = TokenBucket::new(1, 100.0);
+
+        assert!(bucket.try_acquire());
+        assert!(!bucket.try_acquire());
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(bucket.try_acquire());
+    }
+
+    #[test]
+    fn test_mute_after_violations() {
+        let mut limiter = RateLimiter::new(RateLimitConfig {
+            messages_per_second: 1000.0,
+            burst: 1,
+            max_violations: 2,
+            mute_duration: Duration::from_secs(60),
+        });
+
+        // Drain the bucket without giving it time to refill
+        limiter.bucket = TokenBucket::new(1, 0.0);
+        assert!(limiter.check().is_ok());
+        assert!(limiter.check().is_err());
+        assert!(limiter.check().is_err());
+
+        // Muted even once tokens are available again
+        limiter.bucket = TokenBucket::new(10, 0.0);
+        let err = limiter.check().unwrap_err();
+        assert!(err.contains("muted"));
+    }
+}
@@ -1,15 +1,27 @@
 use crate::message::Message;
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 use tokio::sync::broadcast;
 
 /// Maximum number of messages buffered in room channel
 const ROOM_CHANNEL_SIZE: usize = 100;
 
+/// Chat messages kept for scrollback unless configured otherwise
+pub const DEFAULT_HISTORY_SIZE: usize = 50;
+
 /// A chat room that broadcasts messages to all members
 pub struct Room {
     pub name: String,
     pub members: HashSet<String>,
+    /// Members allowed to kick and ban
+    pub operators: HashSet<String>,
+    /// Nicknames that may not join this room
+    pub banned: HashSet<String>,
+    /// Shown to users as they join; set by operators with `/topic`
+    pub topic: Option<String>,
     pub tx: broadcast::Sender<Message>,
+    /// Recent chat messages, oldest first
+    history: VecDeque<Message>,
+    history_size: usize,
 }
 
 impl Room {
@@ -19,18 +31,58 @@ impl Room {
         Room {
             name,
             members: HashSet::new(),
+            operators: HashSet::new(),
+            banned: HashSet::new(),
+            topic: None,
             tx,
+            history: VecDeque::with_capacity(DEFAULT_HISTORY_SIZE),
+            history_size: DEFAULT_HISTORY_SIZE,
         }
     }
 
+    /// Keep up to `history_size` chat messages for scrollback
+    pub fn with_history_size(mut self, history_size: usize) -> Self {
+        self.history = VecDeque::with_capacity(history_size);
+        self.history_size = history_size;
+        self
+    }
+
     /// Add a user to this room
+    ///
+    /// The user becomes an operator if the room has none.
     pub fn add_member(&mut self, nickname: String) {
+        if self.operators.is_empty() {
+            self.operators.insert(nickname.clone());
+        }
         self.members.insert(nickname);
     }
 
     /// Remove a user from this room
     pub fn remove_member(&mut self, nickname: &str) {
         self.members.remove(nickname);
+        self.operators.remove(nickname);
+    }
+
+    /// Check if a user is an operator of this room
+    pub fn is_operator(&self, nickname: &str) -> bool {
+        self.operators.contains(nickname)
+    }
+
+    /// Ban a nickname from this room
+    pub fn ban(&mut self, nickname: String) {
+        self.banned.insert(nickname);
+    }
+
+    /// Check if a nickname is banned from this room
+    pub fn is_banned(&self, nickname: &str) -> bool {
+        self.banned.contains(nickname)
+    }
+
+    /// Notice telling a joining user the topic, if one is set
+    pub fn topic_notice(&self) -> Option<Message> {
+        self.topic
+            .as_ref()
+            .map(|topic| Message::system(format!("Topic for #{}: {}", self.name, topic)))
     }
 
     /// Check if room is empty
@@ -44,11 +96,25 @@ impl Room {
     }
 
     /// Broadcast a message to all members in the room
-    pub fn broadcast(&self, message: Message) {
+    pub fn broadcast(&mut self, message: Message) {
+        // Only chat messages are kept for scrollback
+        if matches!(message, Message::Chat { .. }) && self.history_size > 0 {
+            if self.history.len() == self.history_size {
+                self.history.pop_front();
+            }
+            self.history.push_back(message.clone());
+        }
+
         // Ignore error if no receivers (empty room)
         let _ = self.tx.send(message);
     }
 
+    /// Get up to `count` of the most recent chat messages, oldest first
+    pub fn recent_messages(&self, count: usize) -> Vec<Message> {
+        let skip = self.history.len().saturating_sub(count);
+        self.history.iter().skip(skip).cloned().collect()
+    }
+
     /// Subscribe to room messages
     pub fn subscribe(&self) -> broadcast::Receiver<Message> {
         self.tx.subscribe()
@@ -87,7 +153,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_broadcast() {
-        let room = Room::new("test".to_string());
+        let mut room = Room::new("test".to_string());
         let mut rx = room.subscribe();
 
         let msg = Message::system("Test message".to_string());
@@ -99,4 +165,39 @@ mod tests {
             _ => panic!("Expected System message"),
         }
     }
+
+    #[test]
+    fn test_first_member_is_operator() {
+        let mut room = Room::new("test".to_string());
+
+        room.add_member("Alice".to_string());
+        room.add_member("Bob".to_string());
+        assert!(room.is_operator("Alice"));
+        assert!(!room.is_operator("Bob"));
+
+        room.remove_member("Alice");
+        assert!(!room.is_operator("Alice"));
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut room = Room::new("test".to_string()).with_history_size(3);
+
+        room.broadcast(Message::system("not kept".to_string()));
+        for i in 0..5 {
+            room.broadcast(Message::chat("Alice".to_string(), format!("msg{}", i)));
+        }
+
+        let contents: Vec<String> = room
+            .recent_messages(10)
+            .into_iter()
+            .map(|msg| match msg {
+                Message::Chat { content, .. } => content,
+                _ => panic!("Expected Chat message"),
+            })
+            .collect();
+        assert_eq!(contents, vec!["msg2", "msg3", "msg4"]);
+
+        assert_eq!(room.recent_messages(1).len(), 1);
+    }
 }
@@ -1,16 +1,27 @@
 use crate::message::Message;
-use crate::room::Room;
+use crate::rate_limit::{RateLimitConfig, RateLimiter};
+use crate::room::{Room, DEFAULT_HISTORY_SIZE};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, RwLock, Semaphore};
 
 const LOBBY_ROOM: &str = "lobby";
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
 
 /// Shared server state
 pub struct ChatServer {
     rooms: Arc<RwLock<HashMap<String, Room>>>,
     users: Arc<RwLock<HashMap<String, UserInfo>>>,
     max_connections: usize,
+    /// One permit per open connection, taken before the socket is read
+    connection_slots: Arc<Semaphore>,
+    history_size: usize,
+    rate_limit: RateLimitConfig,
+    /// Member cap for rooms other than the lobby
+    max_room_members: Option<usize>,
+    /// Clients that send nothing for this long are disconnected
+    idle_timeout: Duration,
 }
 
 /// Information about a connected user
@@ -19,20 +30,63 @@ pub struct UserInfo {
     pub nickname: String,
     pub current_room: String,
     pub tx: mpsc::UnboundedSender<Message>,
+    pub rate_limiter: RateLimiter,
+    /// Who most recently sent this user a private message
+    pub last_private_sender: Option<String>,
+    /// Auto-reply sent to private messages while set
+    pub away_message: Option<String>,
 }
 
 impl ChatServer {
     pub fn new(max_connections: usize) -> Self {
-        let mut rooms = HashMap::new();
-        rooms.insert(LOBBY_ROOM.to_string(), Room::new(LOBBY_ROOM.to_string()));
-
         ChatServer {
-            rooms: Arc::new(RwLock::new(rooms)),
+            rooms: lobby(DEFAULT_HISTORY_SIZE),
             users: Arc::new(RwLock::new(HashMap::new())),
             max_connections,
+            connection_slots: Arc::new(Semaphore::new(max_connections)),
+            history_size: DEFAULT_HISTORY_SIZE,
+            rate_limit: RateLimitConfig::default(),
+            max_room_members: None,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
         }
     }
 
+    /// Keep up to `history_size` chat messages per room for scrollback
+    pub fn with_history_size(mut self, history_size: usize) -> Self {
+        self.rooms = lobby(history_size);
+        self.history_size = history_size;
+        self
+    }
+
+    /// Limit how many users can be in a single room (the lobby is exempt)
+    pub fn with_max_room_members(mut self, max_room_members: usize) -> Self {
+        self.max_room_members = Some(max_room_members);
+        self
+    }
+
+    /// Use custom flood protection settings
+    pub fn with_rate_limit(mut self, rate_limit: RateLimitConfig) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    /// Disconnect clients that stay silent for longer than `idle_timeout`
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Reserve a connection slot, or `None` if the server is full
+    ///
+    /// The slot is released when the permit is dropped.
+    pub fn try_acquire_connection(&self) -> Option<OwnedSemaphorePermit> {
+        Arc::clone(&self.connection_slots).try_acquire_owned().ok()
+    }
+
     /// Check if nickname is already taken
     pub async fn is_nickname_taken(&self, nickname: &str) -> bool {
         let users = self.users.read().await;
@@ -55,18 +109,37 @@ impl ChatServer {
             return Err("Nickname already taken".to_string());
         }
 
+        let mut rooms = self.rooms.write().await;
+        if rooms
+            .get(LOBBY_ROOM)
+            .is_some_and(|lobby| lobby.is_banned(&nickname))
+        {
+            return Err("You are banned from #lobby".to_string());
+        }
+
         let user_info = UserInfo {
             nickname: nickname.clone(),
             current_room: LOBBY_ROOM.to_string(),
-            tx,
+            tx: tx.clone(),
+            rate_limiter: RateLimiter::new(self.rate_limit.clone()),
+            last_private_sender: None,
+            away_message: None,
         };
 
         users.insert(nickname.clone(), user_info);
 
         // Add to lobby
-        let mut rooms = self.rooms.write().await;
         if let Some(lobby) = rooms.get_mut(LOBBY_ROOM) {
             lobby.add_member(nickname);
+
+            // Send scrollback
+            for msg in lobby.recent_messages(self.history_size) {
+                let _ = tx.send(msg);
+            }
+
+            if let Some(notice) = lobby.topic_notice() {
+                let _ = tx.send(notice);
+            }
         }
 
         Ok(())
@@ -121,8 +194,12 @@ impl ChatServer {
         // Update room membership
         let mut rooms = self.rooms.write().await;
         if let Some(room) = rooms.get_mut(&current_room) {
+            let was_operator = room.is_operator(old_nick);
             room.remove_member(old_nick);
             room.add_member(new_nick.clone());
+            if was_operator {
+                room.operators.insert(new_nick.clone());
+            }
 
             // Notify room
             let msg = Message::system(format!("{} is now known as {}", old_nick, new_nick));
@@ -149,6 +226,21 @@ impl ChatServer {
             return Err("Already in that room".to_string());
         }
 
+        // Can't join a room you are banned from
+        if rooms
+            .get(&room_name)
+            .is_some_and(|room| room.is_banned(nickname))
+        {
+            return Err(format!("You are banned from #{}", room_name));
+        }
+
+        // Can't join a full room
+        if let (Some(max), Some(room)) = (self.max_room_members, rooms.get(&room_name)) {
+            if room_name != LOBBY_ROOM && room.member_count() >= max {
+                return Err("Room is full".to_string());
+            }
+        }
+
         // Remove from old room
         if let Some(old_room) = rooms.get_mut(&old_room_name) {
             old_room.remove_member(nickname);
@@ -165,7 +257,10 @@ impl ChatServer {
 
         // Create room if doesn't exist
         if !rooms.contains_key(&room_name) {
-            rooms.insert(room_name.clone(), Room::new(room_name.clone()));
+            rooms.insert(
+                room_name.clone(),
+                Room::new(room_name.clone()).with_history_size(self.history_size),
+            );
         }
 
         // Add to new room
@@ -175,6 +270,15 @@ impl ChatServer {
             // Notify new room
             let msg = Message::system(format!("{} joined the room", nickname));
             new_room.broadcast(msg);
+
+            // Send scrollback
+            for msg in new_room.recent_messages(self.history_size) {
+                let _ = user_info.tx.send(msg);
+            }
+
+            if let Some(notice) = new_room.topic_notice() {
+                let _ = user_info.tx.send(notice);
+            }
         }
 
         // Update user's current room
@@ -183,6 +287,95 @@ impl ChatServer {
         Ok(())
     }
 
+    /// Kick a user out of the operator's current room, disconnecting them
+    pub async fn kick_user(&self, operator: &str, target: &str) -> Result<(), String> {
+        self.remove_from_room(operator, target, false).await
+    }
+
+    /// Ban a user from the operator's current room, disconnecting them
+    pub async fn ban_user(&self, operator: &str, target: &str) -> Result<(), String> {
+        self.remove_from_room(operator, target, true).await
+    }
+
+    async fn remove_from_room(&self, operator: &str, target: &str, ban: bool) -> Result<(), String> {
+        let mut users = self.users.write().await;
+        let mut rooms = self.rooms.write().await;
+
+        let room_name = users
+            .get(operator)
+            .map(|info| info.current_room.clone())
+            .ok_or("User not found".to_string())?;
+
+        let room = rooms
+            .get_mut(&room_name)
+            .ok_or("Room not found".to_string())?;
+
+        if !room.is_operator(operator) {
+            return Err("You are not an operator of this room".to_string());
+        }
+
+        if operator == target {
+            return Err("You cannot remove yourself".to_string());
+        }
+
+        if !room.members.contains(target) {
+            return Err(format!("{} is not in this room", target));
+        }
+
+        // Dropping the user's sender closes their connection
+        let target_info = users
+            .remove(target)
+            .ok_or("User not found".to_string())?;
+
+        room.remove_member(target);
+
+        let action = if ban {
+            room.ban(target.to_string());
+            "banned"
+        } else {
+            "kicked"
+        };
+
+        let _ = target_info.tx.send(Message::error(format!(
+            "You were {} from #{} by {}",
+            action, room_name, operator
+        )));
+
+        let msg = Message::system(format!("{} was {} by {}", target, action, operator));
+        room.broadcast(msg);
+
+        Ok(())
+    }
+
+    /// Set the topic of the user's current room (operators only)
+    pub async fn set_topic(&self, nickname: &str, topic: String) -> Result<(), String> {
+        let room_name = self
+            .get_user_room(nickname)
+            .await
+            .ok_or("User not found".to_string())?;
+
+        let mut rooms = self.rooms.write().await;
+        let room = rooms
+            .get_mut(&room_name)
+            .ok_or("Room not found".to_string())?;
+
+        if !room.is_operator(nickname) {
+            return Err("You are not an operator of this room".to_string());
+        }
+
+        let msg = Message::system(format!("{} set the topic to: {}", nickname, topic));
+        room.topic = Some(topic);
+        room.broadcast(msg);
+
+        Ok(())
+    }
+
+    /// Get the topic of a room, if it has one
+    pub async fn room_topic(&self, room_name: &str) -> Option<String> {
+        let rooms = self.rooms.read().await;
+        rooms.get(room_name).and_then(|room| room.topic.clone())
+    }
+
     /// Get list of all rooms with member counts
     pub async fn list_rooms(&self) -> Vec<(String, usize)> {
         let rooms = self.rooms.read().await;
@@ -209,25 +402,80 @@ impl ChatServer {
 
     /// Broadcast message to a room
     pub async fn broadcast_to_room(&self, room_name: &str, message: Message) {
-        let rooms = self.rooms.read().await;
-        if let Some(room) = rooms.get(room_name) {
+        let mut rooms = self.rooms.write().await;
+        if let Some(room) = rooms.get_mut(room_name) {
             room.broadcast(message);
         }
     }
 
+    /// Get up to `count` recent chat messages from a room
+    pub async fn room_history(&self, room_name: &str, count: usize) -> Vec<Message> {
+        let rooms = self.rooms.read().await;
+        rooms
+            .get(room_name)
+            .map(|room| room.recent_messages(count))
+            .unwrap_or_default()
+    }
+
     /// Send private message to a user
     pub async fn send_private_message(&self, to: &str, message: Message) -> Result<(), String> {
-        let users = self.users.read().await;
-        let user_info = users.get(to).ok_or("User not found".to_string())?;
+        let mut users = self.users.write().await;
+        let user_info = users.get_mut(to).ok_or("User not found".to_string())?;
+
+        let from = match &message {
+            Message::Private { from, .. } => Some(from.clone()),
+            _ => None,
+        };
 
         user_info
             .tx
             .send(message)
             .map_err(|_| "Failed to send message".to_string())?;
 
+        if let Some(from) = from {
+            user_info.last_private_sender = Some(from.clone());
+
+            // Let the sender know the recipient is away
+            if let Some(away) = user_info.away_message.clone() {
+                if let Some(sender_info) = users.get(&from) {
+                    let msg = Message::system(format!("{} is away: {}", to, away));
+                    let _ = sender_info.tx.send(msg);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the last user who privately messaged this user
+    pub async fn get_reply_target(&self, nickname: &str) -> Option<String> {
+        let users = self.users.read().await;
+        users
+            .get(nickname)
+            .and_then(|info| info.last_private_sender.clone())
+    }
+
+    /// Set or clear a user's away message
+    pub async fn set_away(&self, nickname: &str, away_message: Option<String>) -> Result<(), String> {
+        let mut users = self.users.write().await;
+        let user_info = users
+            .get_mut(nickname)
+            .ok_or("User not found".to_string())?;
+
+        user_info.away_message = away_message;
         Ok(())
     }
 
+    /// Check a user's rate limit before accepting a message from them
+    pub async fn check_rate_limit(&self, nickname: &str) -> Result<(), String> {
+        let mut users = self.users.write().await;
+        let user_info = users
+            .get_mut(nickname)
+            .ok_or("User not found".to_string())?;
+
+        user_info.rate_limiter.check()
+    }
+
     /// Get current room for a user
     pub async fn get_user_room(&self, nickname: &str) -> Option<String> {
         let users = self.users.read().await;
@@ -235,6 +483,16 @@ impl ChatServer {
     }
 }
 
+/// Room map for a new server, holding just the lobby
+fn lobby(history_size: usize) -> Arc<RwLock<HashMap<String, Room>>> {
+    let mut rooms = HashMap::new();
+    rooms.insert(
+        LOBBY_ROOM.to_string(),
+        Room::new(LOBBY_ROOM.to_string()).with_history_size(history_size),
+    );
+    Arc::new(RwLock::new(rooms))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -251,6 +509,35 @@ mod tests {
         assert!(!server.is_nickname_taken("Bob").await);
     }
 
+    #[tokio::test]
+    async fn test_connection_limit() {
+        let server = ChatServer::new(2);
+
+        let first = server.try_acquire_connection().unwrap();
+        let _second = server.try_acquire_connection().unwrap();
+        assert!(server.try_acquire_connection().is_none());
+
+        // Closing a connection frees its slot
+        drop(first);
+        assert!(server.try_acquire_connection().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_register_past_capacity() {
+        let server = ChatServer::new(2);
+
+        for nick in ["Alice", "Bob"] {
+            let (tx, _rx) = mpsc::unbounded_channel();
+            server.register_user(nick.to_string(), tx).await.unwrap();
+        }
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let result = server.register_user("Carol".to_string(), tx).await;
+        assert_eq!(result, Err("Server is full".to_string()));
+        assert!(!server.is_nickname_taken("Carol").await);
+        assert_eq!(server.list_room_users(LOBBY_ROOM).await.len(), 2);
+    }
+
     #[tokio::test]
     async fn test_duplicate_nickname() {
         let server = ChatServer::new(10);
@@ -305,4 +592,308 @@ mod tests {
 
         assert!(!server.is_nickname_taken("Alice").await);
     }
+
+    #[tokio::test]
+    async fn test_history_sent_on_join() {
+        let server = ChatServer::new(10).with_history_size(3);
+        let (tx, _rx) = mpsc::unbounded_channel();
+
+        server.register_user("Alice".to_string(), tx).await.unwrap();
+        server.join_room("Alice", "rust-chat".to_string()).await.unwrap();
+
+        for i in 0..5 {
+            let msg = Message::chat("Alice".to_string(), format!("msg{}", i));
+            server.broadcast_to_room("rust-chat", msg).await;
+        }
+
+        // New user joins and receives the bounded backlog
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        server.register_user("Bob".to_string(), tx).await.unwrap();
+        server.join_room("Bob", "rust-chat".to_string()).await.unwrap();
+
+        let mut backlog = Vec::new();
+        while let Ok(msg) = rx.try_recv() {
+            if let Message::Chat { content, .. } = msg {
+                backlog.push(content);
+            }
+        }
+        assert_eq!(backlog, vec!["msg2", "msg3", "msg4"]);
+
+        let history = server.room_history("rust-chat", 2).await;
+        assert_eq!(history.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_non_operator_cannot_kick() {
+        let server = ChatServer::new(10);
+        let (tx1, _rx1) = mpsc::unbounded_channel();
+        let (tx2, _rx2) = mpsc::unbounded_channel();
+
+        server.register_user("Alice".to_string(), tx1).await.unwrap();
+        server.register_user("Bob".to_string(), tx2).await.unwrap();
+
+        // Alice joined the lobby first and is its operator
+        let result = server.kick_user("Bob", "Alice").await;
+        assert!(result.is_err());
+        assert!(server.is_nickname_taken("Alice").await);
+
+        server.kick_user("Alice", "Bob").await.unwrap();
+        assert!(!server.is_nickname_taken("Bob").await);
+        assert!(!server.list_room_users(LOBBY_ROOM).await.contains(&"Bob".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_kick_disconnects_target() {
+        let server = ChatServer::new(10);
+        let (tx1, _rx1) = mpsc::unbounded_channel();
+        let (tx2, mut rx2) = mpsc::unbounded_channel();
+
+        server.register_user("Alice".to_string(), tx1).await.unwrap();
+        server.register_user("Bob".to_string(), tx2).await.unwrap();
+        server.kick_user("Alice", "Bob").await.unwrap();
+
+        // Bob gets a notice, then his channel closes
+        assert!(matches!(rx2.recv().await, Some(Message::Error(_))));
+        assert!(rx2.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_banned_user_cannot_rejoin() {
+        let server = ChatServer::new(10);
+        let (tx1, _rx1) = mpsc::unbounded_channel();
+        let (tx2, _rx2) = mpsc::unbounded_channel();
+
+        server.register_user("Alice".to_string(), tx1).await.unwrap();
+        server.join_room("Alice", "rust-chat".to_string()).await.unwrap();
+        server.register_user("Bob".to_string(), tx2).await.unwrap();
+        server.join_room("Bob", "rust-chat".to_string()).await.unwrap();
+
+        server.ban_user("Alice", "Bob").await.unwrap();
+        assert!(!server.is_nickname_taken("Bob").await);
+
+        // Bob can reconnect but not re-enter the room
+        let (tx2, _rx2) = mpsc::unbounded_channel();
+        server.register_user("Bob".to_string(), tx2).await.unwrap();
+        let result = server.join_room("Bob", "rust-chat".to_string()).await;
+        assert!(result.is_err());
+        assert_eq!(server.get_user_room("Bob").await, Some(LOBBY_ROOM.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_banned_from_lobby_cannot_register() {
+        let server = ChatServer::new(10);
+        let (tx1, _rx1) = mpsc::unbounded_channel();
+        let (tx2, _rx2) = mpsc::unbounded_channel();
+
+        server.register_user("Alice".to_string(), tx1).await.unwrap();
+        server.register_user("Bob".to_string(), tx2).await.unwrap();
+        server.ban_user("Alice", "Bob").await.unwrap();
+
+        let (tx2, _rx2) = mpsc::unbounded_channel();
+        let result = server.register_user("Bob".to_string(), tx2).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_rejects_flood() {
+        let server = ChatServer::new(10).with_rate_limit(RateLimitConfig {
+            messages_per_second: 0.001,
+            burst: 3,
+            max_violations: 10,
+            mute_duration: std::time::Duration::from_secs(60),
+        });
+        let (tx, _rx) = mpsc::unbounded_channel();
+        server.register_user("Alice".to_string(), tx).await.unwrap();
+
+        // Drive messages much faster than the refill rate
+        let mut results = Vec::new();
+        for _ in 0..5 {
+            results.push(server.check_rate_limit("Alice").await.is_ok());
+        }
+
+        assert_eq!(results, vec![true, true, true, false, false]);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_mutes_repeat_offender() {
+        let server = ChatServer::new(10).with_rate_limit(RateLimitConfig {
+            messages_per_second: 0.001,
+            burst: 1,
+            max_violations: 2,
+            mute_duration: std::time::Duration::from_secs(60),
+        });
+        let (tx, _rx) = mpsc::unbounded_channel();
+        server.register_user("Alice".to_string(), tx).await.unwrap();
+
+        assert!(server.check_rate_limit("Alice").await.is_ok());
+        assert!(server.check_rate_limit("Alice").await.is_err());
+
+        let err = server.check_rate_limit("Alice").await.unwrap_err();
+        assert!(err.contains("muted"));
+    }
+
+    #[tokio::test]
+    async fn test_room_member_limit() {
+        let server = ChatServer::new(10).with_max_room_members(2);
+
+        for nick in ["Alice", "Bob", "Carol"] {
+            let (tx, _rx) = mpsc::unbounded_channel();
+            server.register_user(nick.to_string(), tx).await.unwrap();
+        }
+
+        server.join_room("Alice", "rust-chat".to_string()).await.unwrap();
+        server.join_room("Bob", "rust-chat".to_string()).await.unwrap();
+
+        let result = server.join_room("Carol", "rust-chat".to_string()).await;
+        assert_eq!(result, Err("Room is full".to_string()));
+
+        // Carol stays put and existing members are unaffected
+        assert_eq!(server.get_user_room("Carol").await, Some(LOBBY_ROOM.to_string()));
+        let mut members = server.list_room_users("rust-chat").await;
+        members.sort();
+        assert_eq!(members, vec!["Alice", "Bob"]);
+
+        // A slot opens up once someone leaves
+        server.join_room("Bob", LOBBY_ROOM.to_string()).await.unwrap();
+        server.join_room("Carol", "rust-chat".to_string()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lobby_exempt_from_room_limit() {
+        let server = ChatServer::new(10).with_max_room_members(1);
+
+        for nick in ["Alice", "Bob", "Carol"] {
+            let (tx, _rx) = mpsc::unbounded_channel();
+            server.register_user(nick.to_string(), tx).await.unwrap();
+        }
+
+        assert_eq!(server.list_room_users(LOBBY_ROOM).await.len(), 3);
+
+        server.join_room("Alice", "rust-chat".to_string()).await.unwrap();
+        server.join_room("Alice", LOBBY_ROOM.to_string()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_last_private_sender_tracking() {
+        let server = ChatServer::new(10);
+        let mut receivers = Vec::new();
+        for nick in ["Alice", "Bob", "Carol"] {
+            let (tx, rx) = mpsc::unbounded_channel();
+            server.register_user(nick.to_string(), tx).await.unwrap();
+            receivers.push(rx);
+        }
+
+        assert_eq!(server.get_reply_target("Alice").await, None);
+
+        let msg = Message::private("Bob".to_string(), "Alice".to_string(), "hi".to_string());
+        server.send_private_message("Alice", msg).await.unwrap();
+        assert_eq!(server.get_reply_target("Alice").await, Some("Bob".to_string()));
+
+        let msg = Message::private("Carol".to_string(), "Alice".to_string(), "hey".to_string());
+        server.send_private_message("Alice", msg).await.unwrap();
+        assert_eq!(server.get_reply_target("Alice").await, Some("Carol".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_away_auto_reply() {
+        let server = ChatServer::new(10);
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (tx2, mut rx2) = mpsc::unbounded_channel();
+        server.register_user("Alice".to_string(), tx1).await.unwrap();
+        server.register_user("Bob".to_string(), tx2).await.unwrap();
+
+        server
+            .set_away("Alice", Some("at lunch".to_string()))
+            .await
+            .unwrap();
+
+        let msg = Message::private("Bob".to_string(), "Alice".to_string(), "hi".to_string());
+        server.send_private_message("Alice", msg).await.unwrap();
+
+        // Alice still receives the message
+        assert!(matches!(rx1.try_recv(), Ok(Message::Private { .. })));
+
+        // Bob gets the away message
+        match rx2.try_recv() {
+            Ok(Message::System(content)) => assert_eq!(content, "Alice is away: at lunch"),
+            other => panic!("Expected away notice, got {:?}", other),
+        }
+
+        // No auto-reply once Alice is back
+        server.set_away("Alice", None).await.unwrap();
+        let msg = Message::private("Bob".to_string(), "Alice".to_string(), "back?".to_string());
+        server.send_private_message("Alice", msg).await.unwrap();
+        assert!(rx2.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_operator_sets_topic() {
+        let server = ChatServer::new(10);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        server.register_user("Alice".to_string(), tx).await.unwrap();
+        server.join_room("Alice", "rust-chat".to_string()).await.unwrap();
+
+        let mut room_rx = server.subscribe_to_room("rust-chat").await.unwrap();
+        assert_eq!(server.room_topic("rust-chat").await, None);
+
+        server
+            .set_topic("Alice", "Async Rust".to_string())
+            .await
+            .unwrap();
+        assert_eq!(server.room_topic("rust-chat").await, Some("Async Rust".to_string()));
+
+        // The room is told about the change
+        match room_rx.recv().await {
+            Ok(Message::System(content)) => {
+                assert_eq!(content, "Alice set the topic to: Async Rust")
+            }
+            other => panic!("Expected topic notice, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_non_operator_cannot_set_topic() {
+        let server = ChatServer::new(10);
+        for nick in ["Alice", "Bob"] {
+            let (tx, _rx) = mpsc::unbounded_channel();
+            server.register_user(nick.to_string(), tx).await.unwrap();
+            server.join_room(nick, "rust-chat".to_string()).await.unwrap();
+        }
+        server
+            .set_topic("Alice", "Async Rust".to_string())
+            .await
+            .unwrap();
+
+        let result = server.set_topic("Bob", "Off topic".to_string()).await;
+        assert_eq!(result, Err("You are not an operator of this room".to_string()));
+        assert_eq!(server.room_topic("rust-chat").await, Some("Async Rust".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_topic_sent_on_join() {
+        let server = ChatServer::new(10);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        server.register_user("Alice".to_string(), tx).await.unwrap();
+        server.join_room("Alice", "rust-chat".to_string()).await.unwrap();
+        server
+            .set_topic("Alice", "Async Rust".to_string())
+            .await
+            .unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        server.register_user("Bob".to_string(), tx).await.unwrap();
+        server.join_room("Bob", "rust-chat".to_string()).await.unwrap();
+
+        let mut notices = Vec::new();
+        while let Ok(msg) = rx.try_recv() {
+            if let Message::System(content) = msg {
+                notices.push(content);
+            }
+        }
+        assert_eq!(notices, vec!["Topic for #rust-chat: Async Rust"]);
+
+        // The lobby has no topic, so registering sent nothing
+        server.join_room("Bob", LOBBY_ROOM.to_string()).await.unwrap();
+        assert!(rx.try_recv().is_err());
+    }
 }
@@ -0,0 +1,4 @@
+-- Soft delete: deleted tasks keep their row until purged
+ALTER TABLE tasks ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX idx_tasks_deleted_at ON tasks(deleted_at);
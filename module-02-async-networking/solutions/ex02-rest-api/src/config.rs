@@ -1,9 +1,15 @@
+use crate::db::PoolConfig;
 use std::env;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub database_url: String,
     pub port: u16,
+    /// Requests each client IP may make per `rate_limit_window`
+    pub rate_limit_requests: u32,
+    pub rate_limit_window: Duration,
+    pub pool: PoolConfig,
 }
 
 impl Config {
@@ -18,9 +24,54 @@ impl Config {
             .parse()
             .map_err(|e| format!("Invalid PORT: {}", e))?;
 
+        let rate_limit_requests = env::var("RATE_LIMIT_REQUESTS")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse::<u32>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or("Invalid RATE_LIMIT_REQUESTS: must be a positive integer")?;
+
+        let rate_limit_window = env::var("RATE_LIMIT_WINDOW_SECS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<u64>()
+            .ok()
+            .filter(|&n| n > 0)
+            .map(Duration::from_secs)
+            .ok_or("Invalid RATE_LIMIT_WINDOW_SECS: must be a positive integer")?;
+
+        let max_connections = env::var("DB_MAX_CONNECTIONS")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse::<u32>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or("Invalid DB_MAX_CONNECTIONS: must be a positive integer")?;
+
+        let acquire_timeout = env::var("DB_ACQUIRE_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse::<u64>()
+            .ok()
+            .filter(|&n| n > 0)
+            .map(Duration::from_secs)
+            .ok_or("Invalid DB_ACQUIRE_TIMEOUT_SECS: must be a positive integer")?;
+
+        let idle_timeout = env::var("DB_IDLE_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "600".to_string())
+            .parse::<u64>()
+            .ok()
+            .filter(|&n| n > 0)
+            .map(Duration::from_secs)
+            .ok_or("Invalid DB_IDLE_TIMEOUT_SECS: must be a positive integer")?;
+
         Ok(Config {
             database_url,
             port,
+            rate_limit_requests,
+            rate_limit_window,
+            pool: PoolConfig {
+                max_connections,
+                acquire_timeout,
+                idle_timeout,
+            },
         })
     }
 }
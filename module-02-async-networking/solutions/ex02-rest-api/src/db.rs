@@ -1,13 +1,37 @@
 use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
 use std::time::Duration;
 
-pub async fn create_pool(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
+/// Connection pool tuning
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    /// How long a request waits for a free connection before failing
+    pub acquire_timeout: Duration,
+    /// Idle connections are closed after this long
+    pub idle_timeout: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            max_connections: 5,
+            acquire_timeout: Duration::from_secs(3),
+            idle_timeout: Duration::from_secs(600),
+        }
+    }
+}
+
+pub async fn create_pool(database_url: &str, config: &PoolConfig) -> Result<SqlitePool, sqlx::Error> {
     let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .acquire_timeout(Duration::from_secs(3))
+        .max_connections(config.max_connections)
+        .acquire_timeout(config.acquire_timeout)
+        .idle_timeout(config.idle_timeout)
         .connect(database_url)
         .await?;
 
+    // Fail at startup rather than on the first request
+    ping(&pool).await?;
+
     // Run migrations
     sqlx::migrate!("./migrations")
         .run(&pool)
@@ -16,13 +40,28 @@ pub async fn create_pool(database_url: &str) -> Result<SqlitePool, sqlx::Error>
     Ok(pool)
 }
 
+/// Check the database answers a trivial query
+pub async fn ping(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[tokio::test]
     async fn test_create_pool() {
-        let pool = create_pool("sqlite::memory:").await;
+        let pool = create_pool("sqlite::memory:", &PoolConfig::default()).await;
         assert!(pool.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_ping_fails_once_closed() {
+        let pool = create_pool("sqlite::memory:", &PoolConfig::default()).await.unwrap();
+        assert!(ping(&pool).await.is_ok());
+
+        pool.close().await;
+        assert!(ping(&pool).await.is_err());
+    }
 }
@@ -1,8 +1,9 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use crate::middleware::current_request_id;
 use serde_json::json;
 use std::collections::HashMap;
 use thiserror::Error;
@@ -15,15 +16,40 @@ pub enum AppError {
     #[error("Task not found")]
     NotFound,
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Validation error")]
     Validation(HashMap<String, String>),
 
     #[error("Internal server error")]
     Internal(String),
+
+    #[error("Too many requests")]
+    RateLimited { retry_after_secs: u64 },
+}
+
+impl AppError {
+    /// Stable machine-readable code for clients to match on
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Database(_) => "database",
+            AppError::NotFound => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::Validation(_) => "validation",
+            AppError::Internal(_) => "internal",
+            AppError::RateLimited { .. } => "rate_limited",
+        }
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let code = self.code();
+        let retry_after = match self {
+            AppError::RateLimited { retry_after_secs } => Some(retry_after_secs),
+            _ => None,
+        };
         let (status, error_message, details) = match self {
             AppError::Database(ref e) => {
                 tracing::error!("Database error: {:?}", e);
@@ -38,6 +64,7 @@ impl IntoResponse for AppError {
                 "Resource not found".to_string(),
                 None,
             ),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg, None),
             AppError::Validation(errors) => (
                 StatusCode::BAD_REQUEST,
                 "Validation failed".to_string(),
@@ -51,20 +78,29 @@ impl IntoResponse for AppError {
                     None,
                 )
             }
+            AppError::RateLimited { .. } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests, slow down".to_string(),
+                None,
+            ),
         };
 
-        let body = if let Some(details) = details {
-            json!({
-                "error": error_message,
-                "details": details
-            })
-        } else {
-            json!({
-                "error": error_message
-            })
-        };
+        let mut body = json!({
+            "error": error_message,
+            "code": code,
+            "request_id": current_request_id(),
+        });
+        if let Some(details) = details {
+            body["details"] = json!(details);
+        }
 
-        (status, Json(body)).into_response()
+        let mut response = (status, Json(body)).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
     }
 }
 
@@ -89,3 +125,60 @@ impl From<validator::ValidationErrors> for AppError {
 }
 
 pub type Result<T> = std::result::Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::request_id;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::StatusCode,
+        middleware,
+        routing::get,
+        Router,
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    async fn error_body(response: Response) -> Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_not_found_carries_request_id_and_code() {
+        let app = Router::new()
+            .route("/missing", get(|| async { Err::<(), _>(AppError::NotFound) }))
+            .layer(middleware::from_fn(request_id));
+
+        let request = Request::builder().uri("/missing").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let header = response.headers()["x-request-id"].to_str().unwrap().to_string();
+
+        let body = error_body(response).await;
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["request_id"], header.as_str());
+        assert_eq!(body["error"], "Resource not found");
+    }
+
+    #[tokio::test]
+    async fn test_validation_error_body() {
+        let errors = HashMap::from([("title".to_string(), "Title is required".to_string())]);
+        let body = error_body(AppError::Validation(errors).into_response()).await;
+
+        assert_eq!(body["code"], "validation");
+        assert_eq!(body["details"]["title"], "Title is required");
+        // Outside the middleware there is no request id to report
+        assert!(body["request_id"].is_null());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(AppError::Database(sqlx::Error::RowNotFound).code(), "database");
+        assert_eq!(AppError::Conflict("stale".into()).code(), "conflict");
+        assert_eq!(AppError::Internal("boom".into()).code(), "internal");
+    }
+}
@@ -4,15 +4,29 @@ use axum::{
     Json,
 };
 use chrono::Utc;
-use sqlx::SqlitePool;
+use sqlx::{Executor, QueryBuilder, Sqlite, SqlitePool};
+use std::collections::HashMap;
 use validator::Validate;
 
+use crate::db;
 use crate::error::{AppError, Result};
 use crate::models::*;
 
-// Health check endpoint
-pub async fn health_check() -> StatusCode {
-    StatusCode::OK
+// Maximum number of items accepted by a bulk endpoint
+const MAX_BULK_SIZE: usize = 100;
+
+// Maximum length of a single tag
+const MAX_TAG_LENGTH: usize = 50;
+
+// Health check endpoint: healthy only while the database answers
+pub async fn health_check(State(pool): State<SqlitePool>) -> StatusCode {
+    match db::ping(&pool).await {
+        Ok(()) => StatusCode::OK,
+        Err(e) => {
+            tracing::warn!("Health check failed: {}", e);
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
 }
 
 // Create a new task
@@ -23,26 +37,7 @@ pub async fn create_task(
     // Validate input
     payload.validate()?;
 
-    let status = payload.status.unwrap_or(TaskStatus::Todo);
-    let priority = payload.priority.unwrap_or(Priority::Medium);
-    let now = Utc::now();
-
-    let result = sqlx::query!(
-        r#"
-        INSERT INTO tasks (title, description, status, priority, completed, created_at, updated_at)
-        VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6)
-        "#,
-        payload.title,
-        payload.description,
-        status.to_string(),
-        priority.to_string(),
-        now.to_rfc3339(),
-        now.to_rfc3339()
-    )
-    .execute(&pool)
-    .await?;
-
-    let task_id = result.last_insert_rowid();
+    let task_id = insert_task(&pool, &payload).await?;
 
     // Fetch the created task
     let task = get_task_by_id(&pool, task_id).await?;
@@ -50,6 +45,44 @@ pub async fn create_task(
     Ok((StatusCode::CREATED, Json(task)))
 }
 
+// Create many tasks in a single transaction
+pub async fn bulk_create_tasks(
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<Vec<CreateTaskRequest>>,
+) -> Result<(StatusCode, Json<Vec<Task>>)> {
+    validate_bulk_size("tasks", payload.len())?;
+
+    // Validate everything up front so nothing is written for a bad batch
+    let mut errors = HashMap::new();
+    for (index, request) in payload.iter().enumerate() {
+        if let Err(AppError::Validation(fields)) = request.validate().map_err(AppError::from) {
+            for (field, message) in fields {
+                errors.insert(format!("{}.{}", index, field), message);
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(AppError::Validation(errors));
+    }
+
+    // Dropping the transaction on an error rolls back every insert
+    let mut tx = pool.begin().await?;
+    let mut task_ids = Vec::with_capacity(payload.len());
+
+    for request in &payload {
+        task_ids.push(insert_task(&mut *tx, request).await?);
+    }
+
+    tx.commit().await?;
+
+    let mut tasks = Vec::with_capacity(task_ids.len());
+    for task_id in task_ids {
+        tasks.push(get_task_by_id(&pool, task_id).await?);
+    }
+
+    Ok((StatusCode::CREATED, Json(tasks)))
+}
+
 // Get a single task by ID
 pub async fn get_task(
     State(pool): State<SqlitePool>,
@@ -68,49 +101,33 @@ pub async fn list_tasks(
     let per_page = query.per_page.unwrap_or(10).min(100);
     let offset = ((page - 1) * per_page) as i64;
 
-    // Build dynamic query
-    let mut sql = String::from("SELECT * FROM tasks WHERE 1=1");
-    let mut count_sql = String::from("SELECT COUNT(*) as count FROM tasks WHERE 1=1");
-
-    let mut params: Vec<String> = Vec::new();
-
-    if let Some(status) = &query.status {
-        sql.push_str(" AND status = ?");
-        count_sql.push_str(" AND status = ?");
-        params.push(status.to_string());
-    }
-
-    if let Some(priority) = &query.priority {
-        sql.push_str(" AND priority = ?");
-        count_sql.push_str(" AND priority = ?");
-        params.push(priority.to_string());
-    }
-
-    if let Some(completed) = query.completed {
-        sql.push_str(" AND completed = ?");
-        count_sql.push_str(" AND completed = ?");
-        params.push(if completed { "1" } else { "0" }.to_string());
-    }
-
-    sql.push_str(" ORDER BY created_at DESC LIMIT ? OFFSET ?");
+    let sort = match &query.sort {
+        Some(sort) => sort.parse::<TaskSort>().map_err(|message| {
+            AppError::Validation(HashMap::from([("sort".to_string(), message)]))
+        })?,
+        None => TaskSort::default(),
+    };
 
     // Get total count
-    let total: i64 = sqlx::query_scalar(&count_sql)
-        .bind(params.get(0))
-        .bind(params.get(1))
-        .bind(params.get(2))
-        .fetch_one(&pool)
-        .await?;
+    let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM tasks");
+    push_task_filters(&mut count_query, &query);
+    let total: i64 = count_query.build_query_scalar().fetch_one(&pool).await?;
 
     // Get tasks
-    let tasks = sqlx::query_as::<_, Task>(&sql)
-        .bind(params.get(0))
-        .bind(params.get(1))
-        .bind(params.get(2))
-        .bind(per_page as i64)
-        .bind(offset)
+    let mut tasks_query = QueryBuilder::new("SELECT tasks.* FROM tasks");
+    push_task_filters(&mut tasks_query, &query);
+    tasks_query
+        .push(" ")
+        .push(sort.to_sql())
+        .push(" LIMIT ")
+        .push_bind(per_page as i64)
+        .push(" OFFSET ")
+        .push_bind(offset);
+    let mut tasks = tasks_query
+        .build_query_as::<Task>()
         .fetch_all(&pool)
         .await?;
+    attach_tags(&pool, &mut tasks).await?;
 
     Ok(Json(TaskListResponse {
         tasks,
@@ -120,6 +137,69 @@ pub async fn list_tasks(
     }))
 }
 
+// Append the joins and WHERE conditions shared by the count and page queries
+fn push_task_filters(builder: &mut QueryBuilder<'_, Sqlite>, query: &ListTasksQuery) {
+    if let Some(tag) = &query.tag {
+        builder
+            .push(" JOIN task_tags ON task_tags.task_id = tasks.id AND task_tags.tag = ")
+            .push_bind(tag.clone());
+    }
+
+    builder.push(" WHERE 1=1");
+
+    if !query.include_deleted.unwrap_or(false) {
+        builder.push(" AND deleted_at IS NULL");
+    }
+
+    if let Some(status) = &query.status {
+        builder.push(" AND status = ").push_bind(status.to_string());
+    }
+
+    if let Some(priority) = &query.priority {
+        builder.push(" AND priority = ").push_bind(priority.to_string());
+    }
+
+    if let Some(completed) = query.completed {
+        builder.push(" AND completed = ").push_bind(completed);
+    }
+
+    if let Some(overdue) = query.overdue {
+        // Timestamps are stored as RFC 3339 UTC strings, so they sort as text
+        let condition = if overdue {
+            " AND (due_date IS NOT NULL AND due_date < "
+        } else {
+            " AND NOT (due_date IS NOT NULL AND due_date < "
+        };
+        builder
+            .push(condition)
+            .push_bind(Utc::now().to_rfc3339())
+            .push(" AND completed = 0)");
+    }
+
+    if let Some(q) = query.q.as_deref().filter(|q| !q.is_empty()) {
+        // SQLite's LIKE is case-insensitive for ASCII by default
+        let pattern = format!("%{}%", escape_like(q));
+        builder
+            .push(" AND (title LIKE ")
+            .push_bind(pattern.clone())
+            .push(" ESCAPE '\\' OR description LIKE ")
+            .push_bind(pattern)
+            .push(" ESCAPE '\\')");
+    }
+}
+
+// Escape LIKE wildcards so user input matches literally
+fn escape_like(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 // Update a task
 pub async fn update_task(
     State(pool): State<SqlitePool>,
@@ -130,7 +210,11 @@ pub async fn update_task(
     payload.validate()?;
 
     // Check if task exists
-    get_task_by_id(&pool, id).await?;
+    let current = get_task_by_id(&pool, id).await?;
+
+    if payload.version.is_some_and(|version| version != current.version) {
+        return Err(version_conflict(id));
+    }
 
     let now = Utc::now();
 
@@ -158,6 +242,11 @@ pub async fn update_task(
         params.push(priority.to_string());
     }
 
+    if let Some(due_date) = &payload.due_date {
+        updates.push("due_date = ?");
+        params.push(due_date.to_rfc3339());
+    }
+
     if updates.is_empty() {
         // Nothing to update, return current task
         return get_task(State(pool), Path(id)).await;
@@ -165,9 +254,11 @@ pub async fn update_task(
 
     updates.push("updated_at = ?");
     params.push(now.to_rfc3339());
+    updates.push("version = version + 1");
 
+    // Re-check the version in the UPDATE itself to catch concurrent writers
     let sql = format!(
-        "UPDATE tasks SET {} WHERE id = ?",
+        "UPDATE tasks SET {} WHERE id = ? AND version = ?",
         updates.join(", ")
     );
 
@@ -175,9 +266,12 @@ pub async fn update_task(
     for param in params {
         query = query.bind(param);
     }
-    query = query.bind(id);
+    query = query.bind(id).bind(payload.version.unwrap_or(current.version));
 
-    query.execute(&pool).await?;
+    let result = query.execute(&pool).await?;
+    if result.rows_affected() == 0 {
+        return Err(version_conflict(id));
+    }
 
     // Fetch updated task
     let task = get_task_by_id(&pool, id).await?;
@@ -189,7 +283,118 @@ pub async fn delete_task(
     State(pool): State<SqlitePool>,
     Path(id): Path<i64>,
 ) -> Result<StatusCode> {
-    let result = sqlx::query!("DELETE FROM tasks WHERE id = ?", id)
+    let now = Utc::now().to_rfc3339();
+
+    // Soft delete so the task can be restored
+    let result = sqlx::query!(
+        "UPDATE tasks SET deleted_at = ?, updated_at = ? WHERE id = ? AND deleted_at IS NULL",
+        now,
+        now,
+        id
+    )
+    .execute(&pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Restore a soft-deleted task
+pub async fn restore_task(
+    State(pool): State<SqlitePool>,
+    Path(id): Path<i64>,
+) -> Result<Json<Task>> {
+    let now = Utc::now().to_rfc3339();
+
+    let result = sqlx::query!(
+        "UPDATE tasks SET deleted_at = NULL, updated_at = ? WHERE id = ? AND deleted_at IS NOT NULL",
+        now,
+        id
+    )
+    .execute(&pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    let task = get_task_by_id(&pool, id).await?;
+    Ok(Json(task))
+}
+
+// Delete many tasks by ID
+pub async fn bulk_delete_tasks(
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<BulkDeleteRequest>,
+) -> Result<Json<BulkDeleteResponse>> {
+    validate_bulk_size("ids", payload.ids.len())?;
+
+    let now = Utc::now().to_rfc3339();
+
+    let mut query = QueryBuilder::<Sqlite>::new("UPDATE tasks SET deleted_at = ");
+    query
+        .push_bind(now.clone())
+        .push(", updated_at = ")
+        .push_bind(now)
+        .push(" WHERE deleted_at IS NULL AND id IN (");
+    let mut ids = query.separated(", ");
+    for id in &payload.ids {
+        ids.push_bind(*id);
+    }
+    ids.push_unseparated(")");
+
+    let result = query.build().execute(&pool).await?;
+
+    Ok(Json(BulkDeleteResponse {
+        deleted: result.rows_affected(),
+    }))
+}
+
+// Add tags to a task
+pub async fn add_tags(
+    State(pool): State<SqlitePool>,
+    Path(id): Path<i64>,
+    Json(payload): Json<AddTagsRequest>,
+) -> Result<Json<Task>> {
+    payload.validate()?;
+
+    let tags: Vec<&str> = payload.tags.iter().map(|tag| tag.trim()).collect();
+    if tags.iter().any(|tag| tag.is_empty() || tag.len() > MAX_TAG_LENGTH) {
+        let message = format!("Tags must be between 1 and {} characters", MAX_TAG_LENGTH);
+        return Err(AppError::Validation(HashMap::from([("tags".to_string(), message)])));
+    }
+
+    // Check if task exists
+    get_task_by_id(&pool, id).await?;
+
+    let mut tx = pool.begin().await?;
+    for tag in tags {
+        sqlx::query("INSERT OR IGNORE INTO task_tags (task_id, tag) VALUES (?, ?)")
+            .bind(id)
+            .bind(tag)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    let task = get_task_by_id(&pool, id).await?;
+    Ok(Json(task))
+}
+
+// Remove a tag from a task
+pub async fn remove_tag(
+    State(pool): State<SqlitePool>,
+    Path((id, tag)): Path<(i64, String)>,
+) -> Result<Json<Task>> {
+    // Check if task exists
+    get_task_by_id(&pool, id).await?;
+
+    let result = sqlx::query("DELETE FROM task_tags WHERE task_id = ? AND tag = ?")
+        .bind(id)
+        .bind(&tag)
         .execute(&pool)
         .await?;
 
@@ -197,7 +402,8 @@ pub async fn delete_task(
         return Err(AppError::NotFound);
     }
 
-    Ok(StatusCode::NO_CONTENT)
+    let task = get_task_by_id(&pool, id).await?;
+    Ok(Json(task))
 }
 
 // Toggle task completion
@@ -207,12 +413,12 @@ pub async fn toggle_complete(
 ) -> Result<Json<Task>> {
     let task = get_task_by_id(&pool, id).await?;
     let new_completed = !task.completed;
-    let now = Utc::now();
+    let now = Utc::now().to_rfc3339();
 
     sqlx::query!(
-        "UPDATE tasks SET completed = ?, updated_at = ? WHERE id = ?",
+        "UPDATE tasks SET completed = ?, updated_at = ?, version = version + 1 WHERE id = ?",
         new_completed,
-        now.to_rfc3339(),
+        now,
         id
     )
     .execute(&pool)
@@ -222,27 +428,115 @@ pub async fn toggle_complete(
     Ok(Json(task))
 }
 
-// Helper function to get task by ID
+// Helper function to get task by ID, including its tags
 async fn get_task_by_id(pool: &SqlitePool, id: i64) -> Result<Task> {
-    let task = sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = ?")
+    let task = sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = ? AND deleted_at IS NULL")
         .bind(id)
         .fetch_optional(pool)
         .await?
         .ok_or(AppError::NotFound)?;
 
+    let mut tasks = [task];
+    attach_tags(pool, &mut tasks).await?;
+    let [task] = tasks;
+
     Ok(task)
 }
 
+// Helper function to fill in the tags of each task with a single query
+async fn attach_tags<'e, E>(executor: E, tasks: &mut [Task]) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    if tasks.is_empty() {
+        return Ok(());
+    }
+
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT task_id, tag FROM task_tags WHERE task_id IN (");
+    let mut ids = query.separated(", ");
+    for task in tasks.iter() {
+        ids.push_bind(task.id);
+    }
+    ids.push_unseparated(") ORDER BY tag");
+
+    let rows: Vec<(i64, String)> = query.build_query_as().fetch_all(executor).await?;
+
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    for (task_id, tag) in rows {
+        tags.entry(task_id).or_default().push(tag);
+    }
+    for task in tasks.iter_mut() {
+        task.tags = tags.remove(&task.id).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+// Helper function to insert a validated task, returning its ID
+async fn insert_task<'e, E>(executor: E, payload: &CreateTaskRequest) -> Result<i64>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let status = payload.status.clone().unwrap_or(TaskStatus::Todo).to_string();
+    let priority = payload.priority.clone().unwrap_or(Priority::Medium).to_string();
+    let due_date = payload.due_date.map(|d| d.to_rfc3339());
+    let now = Utc::now().to_rfc3339();
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO tasks (title, description, status, priority, completed, due_date, created_at, updated_at)
+        VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6, ?7)
+        "#,
+        payload.title,
+        payload.description,
+        status,
+        priority,
+        due_date,
+        now,
+        now
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.last_insert_rowid())
+}
+
+// Helper function to build the error for a stale update
+fn version_conflict(id: i64) -> AppError {
+    AppError::Conflict(format!("Task {} was modified by another request", id))
+}
+
+// Helper function to reject empty or oversized bulk requests
+fn validate_bulk_size(field: &str, len: usize) -> Result<()> {
+    if len == 0 || len > MAX_BULK_SIZE {
+        let message = format!("Must contain between 1 and {} items", MAX_BULK_SIZE);
+        return Err(AppError::Validation(HashMap::from([(field.to_string(), message)])));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::db::create_pool;
+    use crate::db::{create_pool, PoolConfig};
 
     async fn setup_db() -> SqlitePool {
-        let pool = create_pool("sqlite::memory:").await.unwrap();
+        let pool = create_pool("sqlite::memory:", &PoolConfig::default()).await.unwrap();
         pool
     }
 
+    #[tokio::test]
+    async fn test_health_check_reflects_database() {
+        let pool = setup_db().await;
+        assert_eq!(health_check(State(pool.clone())).await, StatusCode::OK);
+
+        pool.close().await;
+        assert_eq!(
+            health_check(State(pool)).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
     #[tokio::test]
     async fn test_create_and_get_task() {
         let pool = setup_db().await;
@@ -252,6 +546,7 @@ mod tests {
             description: Some("Description".to_string()),
             status: Some(TaskStatus::Todo),
             priority: Some(Priority::High),
+            due_date: None,
         };
 
         let (status, Json(task)) = create_task(State(pool.clone()), Json(create_req))
@@ -283,6 +578,7 @@ mod tests {
                 description: None,
                 status: Some(if i % 2 == 0 { TaskStatus::Todo } else { TaskStatus::Done }),
                 priority: Some(Priority::Medium),
+                due_date: None,
             };
 
             create_task(State(pool.clone()), Json(create_req))
@@ -320,6 +616,7 @@ mod tests {
             description: None,
             status: Some(TaskStatus::Todo),
             priority: Some(Priority::Low),
+            due_date: None,
         };
 
         let (_, Json(task)) = create_task(State(pool.clone()), Json(create_req))
@@ -331,6 +628,8 @@ mod tests {
             description: Some("New description".to_string()),
             status: Some(TaskStatus::InProgress),
             priority: None,
+            due_date: None,
+            version: None,
         };
 
         let Json(updated_task) = update_task(State(pool), Path(task.id), Json(update_req))
@@ -352,6 +651,7 @@ mod tests {
             description: None,
             status: None,
             priority: None,
+            due_date: None,
         };
 
         let (_, Json(task)) = create_task(State(pool.clone()), Json(create_req))
@@ -378,6 +678,7 @@ mod tests {
             description: None,
             status: None,
             priority: None,
+            due_date: None,
         };
 
         let (_, Json(task)) = create_task(State(pool.clone()), Json(create_req))
@@ -398,4 +699,398 @@ mod tests {
 
         assert!(!toggled_again.completed);
     }
+
+    #[tokio::test]
+    async fn test_list_tasks_sorted() {
+        let pool = setup_db().await;
+
+        for (title, priority) in [
+            ("Bravo", Priority::Medium),
+            ("Alpha", Priority::High),
+            ("Charlie", Priority::Low),
+        ] {
+            let create_req = CreateTaskRequest {
+                title: title.to_string(),
+                description: None,
+                status: None,
+                priority: Some(priority),
+                due_date: None,
+            };
+            let _ = create_task(State(pool.clone()), Json(create_req))
+                .await
+                .unwrap();
+        }
+
+        let list = |sort: &str| {
+            let query = ListTasksQuery {
+                sort: Some(sort.to_string()),
+                ..Default::default()
+            };
+            list_tasks(State(pool.clone()), Query(query))
+        };
+
+        let Json(response) = list("priority").await.unwrap();
+        let priorities: Vec<Priority> = response.tasks.into_iter().map(|t| t.priority).collect();
+        assert_eq!(priorities, vec![Priority::Low, Priority::Medium, Priority::High]);
+
+        let Json(response) = list("-priority").await.unwrap();
+        let priorities: Vec<Priority> = response.tasks.into_iter().map(|t| t.priority).collect();
+        assert_eq!(priorities, vec![Priority::High, Priority::Medium, Priority::Low]);
+
+        let Json(response) = list("title").await.unwrap();
+        let titles: Vec<String> = response.tasks.into_iter().map(|t| t.title).collect();
+        assert_eq!(titles, vec!["Alpha", "Bravo", "Charlie"]);
+
+        let Json(response) = list("-title").await.unwrap();
+        let titles: Vec<String> = response.tasks.into_iter().map(|t| t.title).collect();
+        assert_eq!(titles, vec!["Charlie", "Bravo", "Alpha"]);
+
+        let result = list("password").await;
+        assert!(matches!(result, Err(AppError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_search() {
+        let pool = setup_db().await;
+
+        for (title, description) in [
+            ("Buy milk", None),
+            ("Write report", Some("Quarterly MILK numbers")),
+            ("Walk the dog", Some("100% of the way")),
+        ] {
+            let create_req = CreateTaskRequest {
+                title: title.to_string(),
+                description: description.map(str::to_string),
+                status: None,
+                priority: None,
+                due_date: None,
+            };
+            let _ = create_task(State(pool.clone()), Json(create_req))
+                .await
+                .unwrap();
+        }
+
+        let search = |q: &str| {
+            let query = ListTasksQuery {
+                q: Some(q.to_string()),
+                sort: Some("title".to_string()),
+                ..Default::default()
+            };
+            list_tasks(State(pool.clone()), Query(query))
+        };
+
+        // Matches title and description, ignoring case
+        let Json(response) = search("milk").await.unwrap();
+        assert_eq!(response.total, 2);
+        let titles: Vec<String> = response.tasks.into_iter().map(|t| t.title).collect();
+        assert_eq!(titles, vec!["Buy milk", "Write report"]);
+
+        let Json(response) = search("REPORT").await.unwrap();
+        assert_eq!(response.total, 1);
+
+        // Wildcards in the search term are matched literally
+        let Json(response) = search("0%").await.unwrap();
+        assert_eq!(response.total, 1);
+        assert_eq!(response.tasks[0].title, "Walk the dog");
+
+        let Json(response) = search("laundry").await.unwrap();
+        assert_eq!(response.total, 0);
+        assert!(response.tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bulk_create_and_delete() {
+        let pool = setup_db().await;
+
+        let requests = (0..5)
+            .map(|i| CreateTaskRequest {
+                title: format!("Bulk task {}", i),
+                description: None,
+                status: None,
+                priority: Some(Priority::Low),
+                due_date: None,
+            })
+            .collect();
+
+        let (status, Json(tasks)) = bulk_create_tasks(State(pool.clone()), Json(requests))
+            .await
+            .unwrap();
+
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(tasks.len(), 5);
+        assert_eq!(tasks[4].title, "Bulk task 4");
+
+        let ids = tasks.iter().take(3).map(|t| t.id).collect();
+        let Json(response) = bulk_delete_tasks(State(pool.clone()), Json(BulkDeleteRequest { ids }))
+            .await
+            .unwrap();
+
+        assert_eq!(response.deleted, 3);
+
+        let Json(response) = list_tasks(State(pool.clone()), Query(ListTasksQuery::default()))
+            .await
+            .unwrap();
+        assert_eq!(response.total, 2);
+
+        let result = get_task(State(pool), Path(tasks[0].id)).await;
+        assert!(matches!(result, Err(AppError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_create_rejects_whole_batch() {
+        let pool = setup_db().await;
+
+        let requests = vec![
+            CreateTaskRequest {
+                title: "Fine".to_string(),
+                description: None,
+                status: None,
+                priority: None,
+                due_date: None,
+            },
+            CreateTaskRequest {
+                title: "".to_string(),
+                description: None,
+                status: None,
+                priority: None,
+                due_date: None,
+            },
+        ];
+
+        let result = bulk_create_tasks(State(pool.clone()), Json(requests)).await;
+        match result {
+            Err(AppError::Validation(errors)) => assert!(errors.contains_key("1.title")),
+            other => panic!("Expected validation error, got {:?}", other),
+        }
+
+        let Json(response) = list_tasks(State(pool), Query(ListTasksQuery::default()))
+            .await
+            .unwrap();
+        assert_eq!(response.total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_overdue_filter() {
+        let pool = setup_db().await;
+        let now = Utc::now();
+
+        let mut ids = HashMap::new();
+        for (title, due_date) in [
+            ("Past due", Some(now - chrono::Duration::days(1))),
+            ("Done late", Some(now - chrono::Duration::days(2))),
+            ("Future", Some(now + chrono::Duration::days(1))),
+            ("No deadline", None),
+        ] {
+            let create_req = CreateTaskRequest {
+                title: title.to_string(),
+                description: None,
+                status: None,
+                priority: None,
+                due_date,
+            };
+            let (_, Json(task)) = create_task(State(pool.clone()), Json(create_req))
+                .await
+                .unwrap();
+            ids.insert(title, task.id);
+        }
+
+        // Completed tasks are never overdue
+        let _ = toggle_complete(State(pool.clone()), Path(ids["Done late"]))
+            .await
+            .unwrap();
+
+        let list = |overdue: bool| {
+            let query = ListTasksQuery {
+                overdue: Some(overdue),
+                sort: Some("title".to_string()),
+                ..Default::default()
+            };
+            list_tasks(State(pool.clone()), Query(query))
+        };
+
+        let Json(response) = list(true).await.unwrap();
+        let titles: Vec<String> = response.tasks.into_iter().map(|t| t.title).collect();
+        assert_eq!(titles, vec!["Past due"]);
+
+        let Json(response) = list(false).await.unwrap();
+        let titles: Vec<String> = response.tasks.into_iter().map(|t| t.title).collect();
+        assert_eq!(titles, vec!["Done late", "Future", "No deadline"]);
+    }
+
+    #[tokio::test]
+    async fn test_tags() {
+        let pool = setup_db().await;
+
+        let mut ids = Vec::new();
+        for title in ["Fix login", "Write docs", "Release"] {
+            let create_req = CreateTaskRequest {
+                title: title.to_string(),
+                description: None,
+                status: None,
+                priority: None,
+                due_date: None,
+            };
+            let (_, Json(task)) = create_task(State(pool.clone()), Json(create_req))
+                .await
+                .unwrap();
+            assert!(task.tags.is_empty());
+            ids.push(task.id);
+        }
+
+        let tag = |id: i64, tags: &[&str]| {
+            let request = AddTagsRequest {
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+            };
+            add_tags(State(pool.clone()), Path(id), Json(request))
+        };
+
+        let Json(task) = tag(ids[0], &["bug", "urgent"]).await.unwrap();
+        assert_eq!(task.tags, vec!["bug", "urgent"]);
+        let _ = tag(ids[1], &["docs"]).await.unwrap();
+        let _ = tag(ids[2], &["urgent"]).await.unwrap();
+
+        // Adding an existing tag is a no-op
+        let Json(task) = tag(ids[2], &["urgent"]).await.unwrap();
+        assert_eq!(task.tags, vec!["urgent"]);
+
+        let list = |tag: &str| {
+            let query = ListTasksQuery {
+                tag: Some(tag.to_string()),
+                sort: Some("title".to_string()),
+                ..Default::default()
+            };
+            list_tasks(State(pool.clone()), Query(query))
+        };
+
+        let Json(response) = list("urgent").await.unwrap();
+        assert_eq!(response.total, 2);
+        let titles: Vec<&str> = response.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["Fix login", "Release"]);
+        assert_eq!(response.tasks[0].tags, vec!["bug", "urgent"]);
+
+        let Json(response) = list("bug").await.unwrap();
+        assert_eq!(response.total, 1);
+
+        let Json(task) = remove_tag(State(pool.clone()), Path((ids[0], "urgent".to_string())))
+            .await
+            .unwrap();
+        assert_eq!(task.tags, vec!["bug"]);
+
+        let Json(response) = list("urgent").await.unwrap();
+        assert_eq!(response.total, 1);
+
+        let result = remove_tag(State(pool.clone()), Path((ids[0], "urgent".to_string()))).await;
+        assert!(matches!(result, Err(AppError::NotFound)));
+
+        let result = tag(ids[0], &["  "]).await;
+        assert!(matches!(result, Err(AppError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete_and_restore() {
+        let pool = setup_db().await;
+
+        let create_req = CreateTaskRequest {
+            title: "Oops".to_string(),
+            description: None,
+            status: None,
+            priority: None,
+            due_date: None,
+        };
+        let (_, Json(task)) = create_task(State(pool.clone()), Json(create_req))
+            .await
+            .unwrap();
+        let request = AddTagsRequest {
+            tags: vec!["bug".to_string()],
+        };
+        let _ = add_tags(State(pool.clone()), Path(task.id), Json(request))
+            .await
+            .unwrap();
+
+        delete_task(State(pool.clone()), Path(task.id))
+            .await
+            .unwrap();
+
+        let result = get_task(State(pool.clone()), Path(task.id)).await;
+        assert!(matches!(result, Err(AppError::NotFound)));
+
+        // Tags of a deleted task can't be removed
+        let result = remove_tag(State(pool.clone()), Path((task.id, "bug".to_string()))).await;
+        assert!(matches!(result, Err(AppError::NotFound)));
+
+        // Deleting twice is a 404
+        let result = delete_task(State(pool.clone()), Path(task.id)).await;
+        assert!(matches!(result, Err(AppError::NotFound)));
+
+        let Json(response) = list_tasks(State(pool.clone()), Query(ListTasksQuery::default()))
+            .await
+            .unwrap();
+        assert_eq!(response.total, 0);
+
+        let query = ListTasksQuery {
+            include_deleted: Some(true),
+            ..Default::default()
+        };
+        let Json(response) = list_tasks(State(pool.clone()), Query(query))
+            .await
+            .unwrap();
+        assert_eq!(response.total, 1);
+        assert!(response.tasks[0].deleted_at.is_some());
+
+        let Json(restored) = restore_task(State(pool.clone()), Path(task.id))
+            .await
+            .unwrap();
+        assert_eq!(restored.title, "Oops");
+        assert!(restored.deleted_at.is_none());
+        assert_eq!(restored.tags, vec!["bug"]);
+
+        let Json(fetched) = get_task(State(pool.clone()), Path(task.id))
+            .await
+            .unwrap();
+        assert_eq!(fetched.id, task.id);
+
+        // Only deleted tasks can be restored
+        let result = restore_task(State(pool), Path(task.id)).await;
+        assert!(matches!(result, Err(AppError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_update_version_conflict() {
+        let pool = setup_db().await;
+
+        let create_req = CreateTaskRequest {
+            title: "Shared".to_string(),
+            description: None,
+            status: None,
+            priority: None,
+            due_date: None,
+        };
+        let (_, Json(task)) = create_task(State(pool.clone()), Json(create_req))
+            .await
+            .unwrap();
+        assert_eq!(task.version, 1);
+
+        let update = |title: &str, version: i64| UpdateTaskRequest {
+            title: Some(title.to_string()),
+            description: None,
+            status: None,
+            priority: None,
+            due_date: None,
+            version: Some(version),
+        };
+
+        let Json(updated) = update_task(State(pool.clone()), Path(task.id), Json(update("First", 1)))
+            .await
+            .unwrap();
+        assert_eq!(updated.title, "First");
+        assert_eq!(updated.version, 2);
+
+        // A second client still holding version 1 is rejected
+        let result = update_task(State(pool.clone()), Path(task.id), Json(update("Second", 1))).await;
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+
+        let Json(current) = get_task(State(pool), Path(task.id)).await.unwrap();
+        assert_eq!(current.title, "First");
+        assert_eq!(current.version, 2);
+    }
 }
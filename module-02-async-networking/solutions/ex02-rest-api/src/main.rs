@@ -2,14 +2,18 @@ mod config;
 mod db;
 mod error;
 mod handlers;
+mod middleware;
 mod models;
 
 use axum::{
-    routing::{get, patch, post},
+    middleware::{from_fn, from_fn_with_state},
+    routing::{delete, get, patch, post},
     Router,
 };
 use config::Config;
 use handlers::*;
+use middleware::RateLimiter;
+use std::net::SocketAddr;
 use tower_http::{
     cors::CorsLayer,
     trace::TraceLayer,
@@ -32,20 +36,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("Configuration loaded");
 
     // Create database connection pool
-    let pool = db::create_pool(&config.database_url).await?;
+    let pool = db::create_pool(&config.database_url, &config.pool).await?;
     tracing::info!("Database connected and migrations applied");
 
+    let rate_limiter = RateLimiter::new(config.rate_limit_requests, config.rate_limit_window);
+
     // Build router
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/api/tasks", post(create_task).get(list_tasks))
+        .route(
+            "/api/tasks/bulk",
+            post(bulk_create_tasks).delete(bulk_delete_tasks),
+        )
         .route(
             "/api/tasks/:id",
             get(get_task).put(update_task).delete(delete_task),
         )
         .route("/api/tasks/:id/complete", patch(toggle_complete))
+        .route("/api/tasks/:id/restore", post(restore_task))
+        .route("/api/tasks/:id/tags", post(add_tags))
+        .route("/api/tasks/:id/tags/:tag", delete(remove_tag))
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http())
+        .layer(from_fn_with_state(rate_limiter, middleware::rate_limit))
+        .layer(from_fn(middleware::request_id))
         .with_state(pool);
 
     // Start server
@@ -54,7 +69,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tracing::info!("Server listening on http://{}", addr);
 
-    axum::serve(listener, app).await?;
+    // Peer addresses are needed for per-IP rate limiting
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
@@ -0,0 +1,239 @@
+use crate::error::AppError;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest client-supplied request id we'll propagate
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The id of the request currently being handled, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Tag every request with an `X-Request-Id`
+///
+/// Reuses the caller's id when it sends a sensible one, otherwise generates
+/// a UUID. The id is echoed in the response header and available to
+/// handlers through `current_request_id`.
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    // Log lines emitted while handling the request carry its id
+    let span = tracing::info_span!("request", request_id = %id);
+    let mut response = REQUEST_ID
+        .scope(id.clone(), next.run(request))
+        .instrument(span)
+        .await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(X_REQUEST_ID.clone(), value);
+    }
+    response
+}
+
+/// Number of tracked IPs above which idle buckets are pruned
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Per-IP token bucket rate limiter
+///
+/// Each IP may burst up to `requests` requests, with tokens refilled
+/// continuously at `requests` per `window`.
+#[derive(Clone)]
+pub struct RateLimiter {
+    capacity: f64,
+    /// Tokens added per second
+    refill_rate: f64,
+    window: Duration,
+    buckets: Arc<Mutex<HashMap<IpAddr, Bucket>>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(requests: u32, window: Duration) -> Self {
+        RateLimiter {
+            capacity: requests as f64,
+            refill_rate: requests as f64 / window.as_secs_f64(),
+            window,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Take a token for `ip`, or return how long until one is available
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if !buckets.contains_key(&ip) && buckets.len() >= MAX_TRACKED_CLIENTS {
+            // Anyone idle for a whole window is back to a full bucket anyway
+            buckets.retain(|_, bucket| now.duration_since(bucket.updated_at) < self.window);
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: self.capacity,
+            updated_at: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_rate).min(self.capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_rate))
+        }
+    }
+}
+
+/// Reject requests from IPs that are over their rate limit with 429
+///
+/// Needs the server to be run with `into_make_service_with_connect_info`;
+/// requests without a peer address are let through.
+pub async fn rate_limit(State(limiter): State<RateLimiter>, request: Request, next: Next) -> Response {
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    if let Some(ip) = ip {
+        if let Err(wait) = limiter.check(ip) {
+            tracing::warn!("Rate limit exceeded for {}", ip);
+            // Round up so clients never retry too early
+            let retry_after_secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+            return AppError::RateLimited { retry_after_secs }.into_response();
+        }
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{header, StatusCode},
+        middleware,
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/", get(|| async { current_request_id().unwrap_or_default() }))
+            .layer(middleware::from_fn(request_id))
+    }
+
+    #[tokio::test]
+    async fn test_generates_request_id() {
+        let response = app()
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let header = response.headers()[&X_REQUEST_ID].to_str().unwrap().to_string();
+        assert!(Uuid::parse_str(&header).is_ok());
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, header.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_propagates_request_id() {
+        let request = Request::builder()
+            .uri("/")
+            .header(&X_REQUEST_ID, "abc-123")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+
+        assert_eq!(response.headers()[&X_REQUEST_ID], "abc-123");
+        assert!(current_request_id().is_none());
+    }
+
+    fn request_from(ip: [u8; 4]) -> Request {
+        let mut request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from((ip, 40000))));
+        request
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_returns_429() {
+        let limiter = RateLimiter::new(3, Duration::from_secs(60));
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(limiter, rate_limit));
+
+        for _ in 0..3 {
+            let response = app.clone().oneshot(request_from([10, 0, 0, 1])).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = app.clone().oneshot(request_from([10, 0, 0, 1])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        // One token refills every 20 seconds
+        assert_eq!(response.headers()[header::RETRY_AFTER], "20");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "rate_limited");
+
+        // Other clients have their own budget
+        let response = app.oneshot(request_from([10, 0, 0, 2])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(10));
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        let start = Instant::now();
+
+        assert!(limiter.check_at(ip, start).is_ok());
+        assert!(limiter.check_at(ip, start).is_ok());
+        assert_eq!(limiter.check_at(ip, start), Err(Duration::from_secs(5)));
+
+        // Half the refill interval buys nothing, the full interval one request
+        assert!(limiter.check_at(ip, start + Duration::from_millis(2500)).is_err());
+        assert!(limiter.check_at(ip, start + Duration::from_secs(5)).is_ok());
+        assert!(limiter.check_at(ip, start + Duration::from_secs(5)).is_err());
+
+        // Idle time never banks more than the burst size
+        let later = start + Duration::from_secs(600);
+        assert!(limiter.check_at(ip, later).is_ok());
+        assert!(limiter.check_at(ip, later).is_ok());
+        assert!(limiter.check_at(ip, later).is_err());
+    }
+}
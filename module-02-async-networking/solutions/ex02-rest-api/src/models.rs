@@ -11,8 +11,16 @@ pub struct Task {
     pub status: TaskStatus,
     pub priority: Priority,
     pub completed: bool,
+    pub due_date: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Incremented on every update
+    pub version: i64,
+    /// Loaded from `task_tags` separately
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, sqlx::Type)]
@@ -61,6 +69,7 @@ pub struct CreateTaskRequest {
 
     pub status: Option<TaskStatus>,
     pub priority: Option<Priority>,
+    pub due_date: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -73,6 +82,10 @@ pub struct UpdateTaskRequest {
 
     pub status: Option<TaskStatus>,
     pub priority: Option<Priority>,
+    pub due_date: Option<DateTime<Utc>>,
+
+    /// Version the client last saw; the update fails with 409 if it is stale
+    pub version: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -80,6 +93,16 @@ pub struct ListTasksQuery {
     pub status: Option<TaskStatus>,
     pub priority: Option<Priority>,
     pub completed: Option<bool>,
+    /// Only tasks carrying this tag
+    pub tag: Option<String>,
+    /// Only incomplete tasks past their due date (or, if false, all others)
+    pub overdue: Option<bool>,
+    /// Include soft-deleted tasks
+    pub include_deleted: Option<bool>,
+    /// Case-insensitive search over title and description
+    pub q: Option<String>,
+    /// Sort key such as `priority` or `-created_at` (descending)
+    pub sort: Option<String>,
     pub page: Option<u32>,
     pub per_page: Option<u32>,
 }
@@ -90,12 +113,99 @@ impl Default for ListTasksQuery {
             status: None,
             priority: None,
             completed: None,
+            tag: None,
+            overdue: None,
+            include_deleted: None,
+            q: None,
+            sort: None,
             page: Some(1),
             per_page: Some(10),
         }
     }
 }
 
+/// Columns a task list can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortField {
+    Priority,
+    Title,
+    CreatedAt,
+    UpdatedAt,
+}
+
+/// Parsed `sort` query parameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaskSort {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl Default for TaskSort {
+    fn default() -> Self {
+        Self {
+            field: SortField::CreatedAt,
+            descending: true,
+        }
+    }
+}
+
+impl std::str::FromStr for TaskSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (descending, name) = match s.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, s),
+        };
+
+        let field = match name {
+            "priority" => SortField::Priority,
+            "title" => SortField::Title,
+            "created_at" => SortField::CreatedAt,
+            "updated_at" => SortField::UpdatedAt,
+            _ => return Err(format!("Cannot sort by '{}'", name)),
+        };
+
+        Ok(Self { field, descending })
+    }
+}
+
+impl TaskSort {
+    /// Build the ORDER BY clause
+    ///
+    /// Only whitelisted column names ever reach the SQL string.
+    pub fn to_sql(self) -> String {
+        let column = match self.field {
+            // Priorities are stored as text, so rank them explicitly
+            SortField::Priority => {
+                "CASE priority WHEN 'Low' THEN 0 WHEN 'Medium' THEN 1 ELSE 2 END"
+            }
+            SortField::Title => "title",
+            SortField::CreatedAt => "created_at",
+            SortField::UpdatedAt => "updated_at",
+        };
+        let direction = if self.descending { "DESC" } else { "ASC" };
+
+        format!("ORDER BY {} {}, id {}", column, direction, direction)
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AddTagsRequest {
+    #[validate(length(min = 1, max = 20, message = "Must add between 1 and 20 tags"))]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkDeleteRequest {
+    pub ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkDeleteResponse {
+    pub deleted: u64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct TaskListResponse {
     pub tasks: Vec<Task>,
@@ -116,6 +226,7 @@ mod tests {
             description: Some("Description".to_string()),
             status: Some(TaskStatus::Todo),
             priority: Some(Priority::Medium),
+            due_date: None,
         };
         assert!(valid_request.validate().is_ok());
 
@@ -124,6 +235,7 @@ mod tests {
             description: None,
             status: None,
             priority: None,
+            due_date: None,
         };
         assert!(empty_title.validate().is_err());
 
@@ -132,10 +244,26 @@ mod tests {
             description: None,
             status: None,
             priority: None,
+            due_date: None,
         };
         assert!(long_title.validate().is_err());
     }
 
+    #[test]
+    fn test_parse_sort() {
+        let sort: TaskSort = "priority".parse().unwrap();
+        assert_eq!(sort.field, SortField::Priority);
+        assert!(!sort.descending);
+
+        let sort: TaskSort = "-updated_at".parse().unwrap();
+        assert_eq!(sort.field, SortField::UpdatedAt);
+        assert!(sort.descending);
+        assert_eq!(sort.to_sql(), "ORDER BY updated_at DESC, id DESC");
+
+        assert!("id; DROP TABLE tasks".parse::<TaskSort>().is_err());
+        assert!("-description".parse::<TaskSort>().is_err());
+    }
+
     #[test]
     fn test_task_status_display() {
         assert_eq!(TaskStatus::Todo.to_string(), "Todo");
@@ -0,0 +1,438 @@
+use crate::messages::{Event, ServerMessage};
+use crate::state::AppState;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Nickname the bridge registers with in the TCP chat
+pub const BRIDGE_NICKNAME: &str = "ws-bridge";
+
+/// How often the bridge sends a blank line so the chat's idle timeout
+/// doesn't disconnect it while both sides are quiet
+pub const DEFAULT_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// First wait before reconnecting; doubles up to `MAX_RECONNECT_DELAY`
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Which side of the bridge a chat message was first sent on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    Tcp,
+    #[default]
+    WebSocket,
+}
+
+/// A chat message as it crosses the bridge, used as the `data` of events
+/// on the bridged channel
+///
+/// Events published on the WebSocket side may also carry a bare string,
+/// which is relayed as content with no sender.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayedChat {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender: Option<String>,
+    pub content: String,
+    #[serde(default)]
+    pub origin: Origin,
+}
+
+/// A line written by the TCP chat server in its JSON mode
+#[derive(Deserialize)]
+struct ChatLine {
+    #[serde(rename = "type")]
+    kind: String,
+    sender: Option<String>,
+    content: String,
+    timestamp: Option<DateTime<Utc>>,
+}
+
+impl ChatLine {
+    fn parse(line: &str) -> Option<Self> {
+        serde_json::from_str(line).ok()
+    }
+}
+
+impl RelayedChat {
+    /// Read a room chat message off a JSON line from the TCP chat server
+    ///
+    /// System notices, errors and private messages give `None`.
+    fn from_chat_line(line: ChatLine) -> Option<Self> {
+        if line.kind != "chat" {
+            return None;
+        }
+
+        Some(RelayedChat {
+            sender: line.sender,
+            content: line.content,
+            origin: Origin::Tcp,
+        })
+    }
+
+    /// Read a chat message out of a WebSocket event's data
+    pub fn from_event_data(data: &serde_json::Value) -> Option<Self> {
+        match data {
+            serde_json::Value::String(content) => Some(RelayedChat {
+                sender: None,
+                content: content.clone(),
+                origin: Origin::WebSocket,
+            }),
+            _ => serde_json::from_value(data.clone()).ok(),
+        }
+    }
+
+    pub fn to_event_data(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("chat message serializes to JSON")
+    }
+
+    /// The JSON-mode line that says this message in the chat room
+    ///
+    /// The chat server shows it as coming from the bridge, so the original
+    /// sender is put in front of the content.
+    pub fn to_chat_input(&self) -> String {
+        let content = match &self.sender {
+            Some(sender) => format!("<{}> {}", sender, self.content),
+            None => self.content.clone(),
+        };
+        serde_json::json!({ "type": "chat", "content": content }).to_string()
+    }
+}
+
+/// Relays a TCP chat room to and from the WebSocket channel of the same name
+///
+/// The bridge joins the room as an ordinary chat user, so the chat server's
+/// room broadcast reaches it and what it says reaches every member. Messages
+/// it relayed are tagged with their origin and never sent back.
+pub struct ChatBridge {
+    addr: String,
+    room: String,
+    keepalive: Duration,
+    /// Newest chat message relayed, so scrollback after a reconnect is skipped
+    last_relayed: Option<DateTime<Utc>>,
+}
+
+impl ChatBridge {
+    pub fn new(addr: impl Into<String>, room: impl Into<String>) -> Self {
+        ChatBridge {
+            addr: addr.into(),
+            room: room.into(),
+            keepalive: DEFAULT_KEEPALIVE,
+            last_relayed: None,
+        }
+    }
+
+    /// Set how often to send a keepalive; keep it below the chat's idle timeout
+    pub fn with_keepalive(mut self, keepalive: Duration) -> Self {
+        self.keepalive = keepalive;
+        self
+    }
+
+    /// Relay indefinitely, reconnecting whenever the chat connection fails
+    /// or closes
+    ///
+    /// Waits `RECONNECT_DELAY` after a session that connected, and backs off
+    /// further while connecting keeps failing.
+    pub async fn run_forever(mut self, state: AppState) {
+        let mut delay = RECONNECT_DELAY;
+        loop {
+            match self.session(&state).await {
+                Ok(()) => delay = RECONNECT_DELAY,
+                Err(e) => tracing::warn!("Chat bridge for #{} failed: {}", self.room, e),
+            }
+
+            tracing::info!("Reconnecting chat bridge for #{} in {:?}", self.room, delay);
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// Connect to the chat server and relay until either side closes
+    async fn session(&mut self, state: &AppState) -> std::io::Result<()> {
+        // Subscribe first so no event published during the handshake is missed
+        let mut events = state.broadcast_tx.subscribe();
+
+        let stream = TcpStream::connect(&self.addr).await?;
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        // Answer the nickname prompt, then switch to JSON lines before
+        // joining so everything said in the room arrives as JSON
+        let join = serde_json::json!({ "type": "command", "command": format!("/join {}", self.room) });
+        let handshake = format!("{}\n/json\n{}\n", BRIDGE_NICKNAME, join);
+        writer.write_all(handshake.as_bytes()).await?;
+        tracing::info!("Bridging chat room #{} at {}", self.room, self.addr);
+
+        let mut keepalive = tokio::time::interval(self.keepalive);
+        keepalive.tick().await;
+
+        loop {
+            tokio::select! {
+                line = lines.next_line() => {
+                    let Some(line) = line? else { break };
+                    let Some(line) = ChatLine::parse(&line) else { continue };
+
+                    if line.kind == "error" {
+                        // Such as the chat's rate limit, in which case the message is lost
+                        tracing::warn!("Chat server reported an error: {}", line.content);
+                        continue;
+                    }
+
+                    // Scrollback sent on joining repeats what was already relayed
+                    let sent_at = line.timestamp;
+                    if sent_at.is_some() && sent_at <= self.last_relayed {
+                        continue;
+                    }
+
+                    match RelayedChat::from_chat_line(line) {
+                        // The chat server echoes what the bridge says back to it
+                        Some(chat) if chat.sender.as_deref() != Some(BRIDGE_NICKNAME) => {
+                            self.last_relayed = sent_at.or(self.last_relayed);
+                            let event = Event::new(self.room.clone(), chat.to_event_data());
+                            state.broadcast_event(self.room.clone(), event.to_server_message()).await;
+                        }
+                        _ => {}
+                    }
+                }
+                _ = keepalive.tick() => {
+                    // Blank lines reset the chat's idle timer and are otherwise ignored
+                    writer.write_all(b"\n").await?;
+                }
+                event = events.recv() => match event {
+                    Ok((channel, ServerMessage::Event { data, .. })) if channel == self.room => {
+                        match RelayedChat::from_event_data(&data) {
+                            Some(chat) if chat.origin == Origin::WebSocket => {
+                                let line = format!("{}\n", chat.to_chat_input());
+                                writer.write_all(line.as_bytes()).await?;
+                            }
+                            _ => {}
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Chat bridge skipped {} events for #{}", skipped, self.room);
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            }
+        }
+
+        tracing::info!("Chat bridge for #{} closed", self.room);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::ClientMessage;
+    use futures::{SinkExt, StreamExt};
+    use serde_json::json;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use tcp_chat_server::message::Message as ChatMessage;
+    use tcp_chat_server::{handle_client, ChatServer};
+    use tokio::net::tcp::OwnedWriteHalf;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    type ChatLines = tokio::io::Lines<BufReader<tokio::net::tcp::OwnedReadHalf>>;
+
+    fn relay(line: &str) -> Option<RelayedChat> {
+        ChatLine::parse(line).and_then(RelayedChat::from_chat_line)
+    }
+
+    #[test]
+    fn test_message_mapping() {
+        let line = r#"{"type":"chat","sender":"alice","content":"hi","timestamp":"2024-01-01T00:00:00Z"}"#;
+        let chat = relay(line).unwrap();
+        assert_eq!(
+            chat.to_event_data(),
+            json!({ "sender": "alice", "content": "hi", "origin": "tcp" })
+        );
+        assert_eq!(relay(r#"{"type":"system","content":"x"}"#), None);
+        assert_eq!(relay("Welcome to the chat server!"), None);
+
+        let chat = RelayedChat::from_event_data(&json!({ "sender": "bob", "content": "yo" })).unwrap();
+        assert_eq!(chat.origin, Origin::WebSocket);
+        assert_eq!(chat.to_chat_input(), r#"{"content":"<bob> yo","type":"chat"}"#);
+
+        let chat = RelayedChat::from_event_data(&json!("plain")).unwrap();
+        assert_eq!(chat.to_chat_input(), r#"{"content":"plain","type":"chat"}"#);
+        assert_eq!(RelayedChat::from_event_data(&json!({ "temperature": 21 })), None);
+    }
+
+    /// Run the TCP chat server on an ephemeral port
+    async fn start_chat_server(idle_timeout: Duration) -> (SocketAddr, Arc<ChatServer>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Arc::new(ChatServer::new(16).with_idle_timeout(idle_timeout));
+
+        let accepting = Arc::clone(&server);
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(handle_client(socket, Arc::clone(&accepting)));
+            }
+        });
+
+        (addr, server)
+    }
+
+    /// Connect to the chat as `nickname` and join `room`
+    async fn chat_user(
+        addr: SocketAddr,
+        nickname: &str,
+        room: &str,
+    ) -> (ChatLines, OwnedWriteHalf) {
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        writer
+            .write_all(format!("{}\n/join {}\n", nickname, room).as_bytes())
+            .await
+            .unwrap();
+        read_until(&mut lines, &format!("You joined #{}", room)).await;
+
+        (lines, writer)
+    }
+
+    /// Read chat lines until one contains `needle`, returning it
+    async fn read_until(lines: &mut ChatLines, needle: &str) -> String {
+        loop {
+            let line = lines.next_line().await.unwrap().expect("chat connection closed");
+            if line.contains(needle) {
+                return line;
+            }
+        }
+    }
+
+    /// Wait until `nickname` is, or isn't, a member of `room`
+    async fn wait_for_member(server: &ChatServer, room: &str, nickname: &str, present: bool) {
+        let changed = async {
+            while server.list_room_users(room).await.contains(&nickname.to_string()) != present {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        let change = if present { "joined" } else { "left" };
+        tokio::time::timeout(Duration::from_secs(5), changed)
+            .await
+            .unwrap_or_else(|_| panic!("{} never {} #{}", nickname, change, room));
+    }
+
+    async fn ws_subscriber(
+        state: &AppState,
+        channel: &str,
+    ) -> impl StreamExt<Item = Result<WsMessage, tokio_tungstenite::tungstenite::Error>> + Unpin {
+        let ws_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_addr = ws_listener.local_addr().unwrap();
+        let app = crate::app(state.clone());
+        tokio::spawn(async move { axum::serve(ws_listener, app).await });
+
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", ws_addr))
+            .await
+            .unwrap();
+        let subscribe = ClientMessage::Subscribe {
+            channel: channel.to_string(),
+        };
+        ws.send(WsMessage::Text(serde_json::to_string(&subscribe).unwrap()))
+            .await
+            .unwrap();
+        assert!(matches!(next_message(&mut ws).await, ServerMessage::Subscribed { .. }));
+        ws
+    }
+
+    async fn next_message<S>(ws: &mut S) -> ServerMessage
+    where
+        S: StreamExt<Item = Result<WsMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+    {
+        match ws.next().await {
+            Some(Ok(WsMessage::Text(text))) => serde_json::from_str(&text).unwrap(),
+            other => panic!("expected a text message, got {:?}", other),
+        }
+    }
+
+    async fn next_event_data<S>(ws: &mut S) -> serde_json::Value
+    where
+        S: StreamExt<Item = Result<WsMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+    {
+        match next_message(ws).await {
+            ServerMessage::Event { data, .. } => data,
+            other => panic!("expected an event, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_relays_both_ways_through_chat_server() {
+        let (chat_addr, _server) = start_chat_server(Duration::from_secs(60)).await;
+        let state = AppState::new();
+        let mut ws = ws_subscriber(&state, "general").await;
+
+        let (mut alice, mut alice_writer) = chat_user(chat_addr, "alice", "general").await;
+        tokio::spawn(ChatBridge::new(chat_addr.to_string(), "general").run_forever(state.clone()));
+        read_until(&mut alice, "ws-bridge joined the room").await;
+
+        alice_writer.write_all(b"hi from tcp\n").await.unwrap();
+        assert_eq!(
+            next_event_data(&mut ws).await,
+            json!({ "sender": "alice", "content": "hi from tcp", "origin": "tcp" })
+        );
+
+        // And the other way; the bridge's echo of it isn't relayed back
+        let event = Event::new("general".to_string(), json!({ "sender": "bob", "content": "hi from ws" }));
+        state
+            .broadcast_event("general".to_string(), event.to_server_message())
+            .await;
+        let line = read_until(&mut alice, "hi from ws").await;
+        assert!(line.ends_with("ws-bridge: <bob> hi from ws"), "{}", line);
+        assert_eq!(
+            next_event_data(&mut ws).await,
+            json!({ "sender": "bob", "content": "hi from ws" })
+        );
+
+        // Next is what alice says, not the echo of bob's message
+        alice_writer.write_all(b"still here\n").await.unwrap();
+        assert_eq!(next_event_data(&mut ws).await["content"], "still here");
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_outlasts_idle_timeout() {
+        let (chat_addr, server) = start_chat_server(Duration::from_millis(300)).await;
+        let state = AppState::new();
+
+        let bridge = ChatBridge::new(chat_addr.to_string(), "general")
+            .with_keepalive(Duration::from_millis(50));
+        tokio::spawn(bridge.run_forever(state.clone()));
+        wait_for_member(&server, "general", BRIDGE_NICKNAME, true).await;
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(server.list_room_users("general").await.contains(&BRIDGE_NICKNAME.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_without_repeating_scrollback() {
+        // No keepalive within the idle timeout, so the chat drops the bridge
+        let (chat_addr, server) = start_chat_server(Duration::from_millis(300)).await;
+        let state = AppState::new();
+        let mut ws = ws_subscriber(&state, "lobby").await;
+
+        tokio::spawn(ChatBridge::new(chat_addr.to_string(), "lobby").run_forever(state.clone()));
+        wait_for_member(&server, "lobby", BRIDGE_NICKNAME, true).await;
+
+        let before = ChatMessage::chat("alice".to_string(), "before".to_string());
+        server.broadcast_to_room("lobby", before).await;
+        assert_eq!(next_event_data(&mut ws).await["content"], "before");
+
+        wait_for_member(&server, "lobby", BRIDGE_NICKNAME, false).await;
+        wait_for_member(&server, "lobby", BRIDGE_NICKNAME, true).await;
+
+        // Rejoining replays "before" as scrollback, which isn't relayed again
+        let after = ChatMessage::chat("alice".to_string(), "after".to_string());
+        server.broadcast_to_room("lobby", after).await;
+        assert_eq!(next_event_data(&mut ws).await["content"], "after");
+    }
+}
@@ -1,3 +1,4 @@
+mod bridge;
 mod messages;
 mod state;
 mod websocket;
@@ -9,11 +10,22 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
+use bridge::ChatBridge;
 use messages::{Event, PublishRequest, StatsResponse};
-use state::AppState;
+use state::{AppState, LagPolicy};
+use std::time::Duration;
 use tower_http::cors::CorsLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+/// Messages buffered per WebSocket client before it counts as lagging
+const SEND_QUEUE_CAPACITY: usize = 256;
+
+/// How long a client has to acknowledge an event before it's re-sent
+const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Re-sends of an unacknowledged event before giving up on it
+const MAX_ACK_RETRIES: u32 = 3;
+
 #[tokio::main]
 async fn main() {
     tracing_subscriber::registry()
@@ -22,15 +34,28 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let state = AppState::new();
+    let lag_policy = std::env::var("LAG_POLICY")
+        .ok()
+        .and_then(|name| LagPolicy::from_name(&name))
+        .unwrap_or(LagPolicy::DropOldest);
+    let state = AppState::new().with_backpressure(SEND_QUEUE_CAPACITY, lag_policy)
+        .with_acks(ACK_TIMEOUT, MAX_ACK_RETRIES);
 
-    let app = Router::new()
-        .route("/ws", get(websocket_handler))
-        .route("/api/events", post(publish_event))
-        .route("/api/stats", get(get_stats))
-        .route("/health", get(health_check))
-        .layer(CorsLayer::permissive())
-        .with_state(state);
+    // Share a room with the TCP chat server when one is configured
+    if let Ok(addr) = std::env::var("CHAT_BRIDGE_ADDR") {
+        let room = std::env::var("CHAT_BRIDGE_ROOM").unwrap_or_else(|_| "lobby".to_string());
+        let mut bridge = ChatBridge::new(addr, room);
+        let keepalive = std::env::var("CHAT_BRIDGE_KEEPALIVE_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .filter(|&secs| secs > 0);
+        if let Some(secs) = keepalive {
+            bridge = bridge.with_keepalive(Duration::from_secs(secs));
+        }
+        tokio::spawn(bridge.run_forever(state.clone()));
+    }
+
+    let app = app(state);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
     tracing::info!("Server listening on http://0.0.0.0:3000");
@@ -38,6 +63,16 @@ async fn main() {
     axum::serve(listener, app).await.unwrap();
 }
 
+fn app(state: AppState) -> Router {
+    Router::new()
+        .route("/ws", get(websocket_handler))
+        .route("/api/events", post(publish_event))
+        .route("/api/stats", get(get_stats))
+        .route("/health", get(health_check))
+        .layer(CorsLayer::permissive())
+        .with_state(state)
+}
+
 async fn websocket_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
     ws.on_upgrade(|socket| websocket::handle_websocket(socket, state))
 }
@@ -57,6 +92,11 @@ async fn get_stats(State(state): State<AppState>) -> Json<StatsResponse> {
     Json(StatsResponse {
         active_connections,
         channels,
+        lagged_events: state.lagged_events(),
+        dropped_clients: state.dropped_clients(),
+        unacked_events: state.unacked_events().await,
+        resent_events: state.resent_events(),
+        undelivered_events: state.undelivered_events(),
     })
 }
 
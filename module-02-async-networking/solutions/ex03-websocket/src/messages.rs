@@ -9,12 +9,16 @@ pub enum ClientMessage {
     Subscribe { channel: String },
     Unsubscribe { channel: String },
     Ping,
+    /// Confirms receipt of the event with this id
+    Ack { id: Uuid },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum ServerMessage {
+    /// Must be acknowledged with `{"type": "ack", "id": ...}` or it's re-sent
     Event {
+        id: Uuid,
         channel: String,
         data: serde_json::Value,
     },
@@ -27,6 +31,10 @@ pub enum ServerMessage {
     Error {
         message: String,
     },
+    /// Sent to a slow client after events were dropped from its queue
+    Lagged {
+        missed: u64,
+    },
     Pong,
 }
 
@@ -50,6 +58,7 @@ impl Event {
 
     pub fn to_server_message(&self) -> ServerMessage {
         ServerMessage::Event {
+            id: self.id,
             channel: self.channel.clone(),
             data: self.data.clone(),
         }
@@ -66,4 +75,9 @@ pub struct PublishRequest {
 pub struct StatsResponse {
     pub active_connections: usize,
     pub channels: HashMap<String, usize>,
+    pub lagged_events: u64,
+    pub dropped_clients: u64,
+    pub unacked_events: usize,
+    pub resent_events: u64,
+    pub undelivered_events: u64,
 }
@@ -1,21 +1,169 @@
 use crate::messages::ServerMessage;
-use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, RwLock};
 use uuid::Uuid;
 
 pub type ClientId = Uuid;
 
+/// Default number of messages buffered per connection
+const DEFAULT_QUEUE_CAPACITY: usize = 256;
+
+/// Default time a client has to acknowledge an event before it's re-sent
+const DEFAULT_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default number of times an unacknowledged event is re-sent
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// What to do when a client's send queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LagPolicy {
+    /// Drop the oldest queued message and tell the client how many it missed
+    DropOldest,
+    /// Disconnect the client
+    Disconnect,
+}
+
+impl LagPolicy {
+    /// Parse `drop-oldest` or `disconnect`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "drop-oldest" => Some(LagPolicy::DropOldest),
+            "disconnect" => Some(LagPolicy::Disconnect),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub connections: Arc<RwLock<HashMap<ClientId, ClientInfo>>>,
     pub broadcast_tx: tokio::sync::broadcast::Sender<(String, ServerMessage)>,
+    pub queue_capacity: usize,
+    pub lag_policy: LagPolicy,
+    pub ack_timeout: Duration,
+    pub max_retries: u32,
+    lagged_events: Arc<AtomicU64>,
+    dropped_clients: Arc<AtomicU64>,
+    resent_events: Arc<AtomicU64>,
+    undelivered_events: Arc<AtomicU64>,
 }
 
 pub struct ClientInfo {
     pub id: ClientId,
     pub subscriptions: HashSet<String>,
-    pub tx: mpsc::UnboundedSender<ServerMessage>,
+    pub queue: Arc<ClientQueue>,
+    /// Events sent to this client that it hasn't acknowledged yet
+    unacked: Mutex<HashMap<Uuid, Unacked>>,
+}
+
+struct Unacked {
+    message: ServerMessage,
+    sent_at: Instant,
+    retries: u32,
+}
+
+/// Result of pushing onto a `ClientQueue`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pushed {
+    Queued,
+    /// The queue was full and its oldest message was dropped
+    DroppedOldest,
+    /// The queue was full and this push closed it; the client should be disconnected
+    Closed,
+    /// The queue had already been closed by an earlier push
+    AlreadyClosed,
+}
+
+impl Pushed {
+    /// Whether the client is, or is about to be, disconnected
+    pub fn is_closed(self) -> bool {
+        matches!(self, Pushed::Closed | Pushed::AlreadyClosed)
+    }
+}
+
+/// Bounded queue of messages waiting to be written to one client
+///
+/// Pushing never waits, so a stalled client can't hold up the publisher.
+pub struct ClientQueue {
+    capacity: usize,
+    policy: LagPolicy,
+    inner: Mutex<QueueInner>,
+    notify: Notify,
+}
+
+#[derive(Default)]
+struct QueueInner {
+    messages: VecDeque<ServerMessage>,
+    /// Messages dropped since the client was last told about it
+    missed: u64,
+    closed: bool,
+}
+
+impl ClientQueue {
+    pub fn new(capacity: usize, policy: LagPolicy) -> Self {
+        ClientQueue {
+            capacity: capacity.max(1),
+            policy,
+            inner: Mutex::new(QueueInner::default()),
+            notify: Notify::new(),
+        }
+    }
+
+    pub fn push(&self, message: ServerMessage) -> Pushed {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.closed {
+            return Pushed::AlreadyClosed;
+        }
+
+        let pushed = if inner.messages.len() < self.capacity {
+            Pushed::Queued
+        } else {
+            match self.policy {
+                LagPolicy::DropOldest => {
+                    inner.messages.pop_front();
+                    inner.missed += 1;
+                    Pushed::DroppedOldest
+                }
+                LagPolicy::Disconnect => {
+                    inner.messages.clear();
+                    inner.closed = true;
+                    drop(inner);
+                    self.notify.notify_one();
+                    return Pushed::Closed;
+                }
+            }
+        };
+
+        inner.messages.push_back(message);
+        drop(inner);
+        self.notify.notify_one();
+        pushed
+    }
+
+    /// Wait for the next message to send, or `None` once the queue is closed
+    ///
+    /// After messages were dropped, a `Lagged` notice comes first.
+    pub async fn pop(&self) -> Option<ServerMessage> {
+        loop {
+            {
+                let mut inner = self.inner.lock().unwrap();
+                if inner.closed {
+                    return None;
+                }
+                if inner.missed > 0 {
+                    let missed = std::mem::take(&mut inner.missed);
+                    return Some(ServerMessage::Lagged { missed });
+                }
+                if let Some(message) = inner.messages.pop_front() {
+                    return Some(message);
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
 }
 
 impl AppState {
@@ -24,18 +172,159 @@ impl AppState {
         AppState {
             connections: Arc::new(RwLock::new(HashMap::new())),
             broadcast_tx,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            lag_policy: LagPolicy::DropOldest,
+            ack_timeout: DEFAULT_ACK_TIMEOUT,
+            max_retries: DEFAULT_MAX_RETRIES,
+            lagged_events: Arc::new(AtomicU64::new(0)),
+            dropped_clients: Arc::new(AtomicU64::new(0)),
+            resent_events: Arc::new(AtomicU64::new(0)),
+            undelivered_events: Arc::new(AtomicU64::new(0)),
         }
     }
 
-    pub async fn register_client(&self, id: ClientId, tx: mpsc::UnboundedSender<ServerMessage>) {
+    /// Bound each connection's send queue to `capacity` messages
+    pub fn with_backpressure(mut self, capacity: usize, policy: LagPolicy) -> Self {
+        self.queue_capacity = capacity;
+        self.lag_policy = policy;
+        self
+    }
+
+    /// Re-send events not acknowledged within `timeout`, at most `max_retries` times
+    pub fn with_acks(mut self, timeout: Duration, max_retries: u32) -> Self {
+        self.ack_timeout = timeout;
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Create a send queue configured for this server
+    pub fn new_queue(&self) -> Arc<ClientQueue> {
+        Arc::new(ClientQueue::new(self.queue_capacity, self.lag_policy))
+    }
+
+    pub async fn register_client(&self, id: ClientId, queue: Arc<ClientQueue>) {
         let info = ClientInfo {
             id,
             subscriptions: HashSet::new(),
-            tx,
+            queue,
+            unacked: Mutex::new(HashMap::new()),
         };
         self.connections.write().await.insert(id, info);
     }
 
+    /// Queue a message for a client, recording any lag
+    pub fn send_to(&self, queue: &ClientQueue, message: ServerMessage) -> Pushed {
+        let pushed = queue.push(message);
+        match pushed {
+            Pushed::Queued | Pushed::AlreadyClosed => {}
+            Pushed::DroppedOldest => {
+                self.lagged_events.fetch_add(1, Ordering::Relaxed);
+            }
+            // Only the push that closes the queue counts the client as dropped
+            Pushed::Closed => {
+                self.dropped_clients.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        pushed
+    }
+
+    /// Deliver a broadcast to a client if it's subscribed to `channel`
+    ///
+    /// Events are then tracked until the client acknowledges them. Returns
+    /// `false` if the client fell too far behind and must be dropped.
+    pub async fn deliver(&self, client_id: &ClientId, channel: &str, message: ServerMessage) -> bool {
+        let connections = self.connections.read().await;
+        match connections.get(client_id) {
+            Some(client) if client.subscriptions.contains(channel) => {
+                if let ServerMessage::Event { id, .. } = &message {
+                    let unacked = Unacked {
+                        message: message.clone(),
+                        sent_at: Instant::now(),
+                        retries: 0,
+                    };
+                    client.unacked.lock().unwrap().insert(*id, unacked);
+                }
+                !self.send_to(&client.queue, message).is_closed()
+            }
+            _ => true,
+        }
+    }
+
+    /// Record that a client received an event; `false` if it wasn't awaiting an ack
+    pub async fn ack(&self, client_id: &ClientId, event_id: &Uuid) -> bool {
+        let connections = self.connections.read().await;
+        match connections.get(client_id) {
+            Some(client) => client.unacked.lock().unwrap().remove(event_id).is_some(),
+            None => false,
+        }
+    }
+
+    /// Re-send a client's events that have gone unacknowledged for longer than
+    /// the ack timeout, giving up on those already re-sent `max_retries` times
+    ///
+    /// Returns `false` once the client is gone or fell too far behind.
+    pub async fn resend_unacked(&self, client_id: &ClientId, now: Instant) -> bool {
+        let connections = self.connections.read().await;
+        let Some(client) = connections.get(client_id) else {
+            return false;
+        };
+
+        let mut overdue = Vec::new();
+        {
+            let mut unacked = client.unacked.lock().unwrap();
+            unacked.retain(|_, event| {
+                if now.saturating_duration_since(event.sent_at) < self.ack_timeout {
+                    return true;
+                }
+                if event.retries >= self.max_retries {
+                    self.undelivered_events.fetch_add(1, Ordering::Relaxed);
+                    return false;
+                }
+                event.retries += 1;
+                event.sent_at = now;
+                overdue.push(event.message.clone());
+                true
+            });
+        }
+
+        for message in overdue {
+            self.resent_events.fetch_add(1, Ordering::Relaxed);
+            if self.send_to(&client.queue, message).is_closed() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Events dropped for slow clients so far
+    pub fn lagged_events(&self) -> u64 {
+        self.lagged_events.load(Ordering::Relaxed)
+    }
+
+    /// Clients disconnected for falling behind so far
+    pub fn dropped_clients(&self) -> u64 {
+        self.dropped_clients.load(Ordering::Relaxed)
+    }
+
+    /// Events currently awaiting an ack across all clients
+    pub async fn unacked_events(&self) -> usize {
+        let connections = self.connections.read().await;
+        connections
+            .values()
+            .map(|client| client.unacked.lock().unwrap().len())
+            .sum()
+    }
+
+    /// Events re-sent for lack of an ack so far
+    pub fn resent_events(&self) -> u64 {
+        self.resent_events.load(Ordering::Relaxed)
+    }
+
+    /// Events given up on after running out of retries so far
+    pub fn undelivered_events(&self) -> u64 {
+        self.undelivered_events.load(Ordering::Relaxed)
+    }
+
     pub async fn unregister_client(&self, id: &ClientId) {
         self.connections.write().await.remove(id);
     }
@@ -77,3 +366,147 @@ impl AppState {
         (connections.len(), channel_counts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn event(n: u64) -> ServerMessage {
+        ServerMessage::Event {
+            id: Uuid::new_v4(),
+            channel: "news".to_string(),
+            data: json!(n),
+        }
+    }
+
+    async fn assert_empty(queue: &ClientQueue) {
+        let next = tokio::time::timeout(Duration::from_millis(10), queue.pop()).await;
+        assert!(next.is_err(), "expected an empty queue, got {:?}", next);
+    }
+
+    async fn subscribed_client(state: &AppState) -> (ClientId, Arc<ClientQueue>) {
+        let id = Uuid::new_v4();
+        let queue = state.new_queue();
+        state.register_client(id, queue.clone()).await;
+        state.subscribe(&id, "news".to_string()).await;
+        (id, queue)
+    }
+
+    #[tokio::test]
+    async fn test_stalled_client_drops_oldest() {
+        let state = AppState::new().with_backpressure(4, LagPolicy::DropOldest);
+        let (id, queue) = subscribed_client(&state).await;
+
+        // Nobody reads the queue; publishing must still never block or grow it
+        for n in 0..10 {
+            assert!(state.deliver(&id, "news", event(n)).await);
+        }
+        assert_eq!(state.lagged_events(), 6);
+
+        assert!(matches!(queue.pop().await, Some(ServerMessage::Lagged { missed: 6 })));
+        for n in 6..10 {
+            assert!(matches!(queue.pop().await, Some(ServerMessage::Event { data, .. }) if data == json!(n)));
+        }
+        assert_empty(&queue).await;
+
+        // Once caught up, delivery is back to normal
+        assert!(state.deliver(&id, "news", event(10)).await);
+        assert!(matches!(queue.pop().await, Some(ServerMessage::Event { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_stalled_client_disconnected() {
+        let state = AppState::new().with_backpressure(4, LagPolicy::Disconnect);
+        let (id, queue) = subscribed_client(&state).await;
+
+        for n in 0..4 {
+            assert!(state.deliver(&id, "news", event(n)).await);
+        }
+        assert!(!state.deliver(&id, "news", event(4)).await);
+        assert_eq!(state.dropped_clients(), 1);
+        assert!(queue.pop().await.is_none());
+
+        // Until the handler unregisters it, more traffic still reaches the
+        // closed queue, but the client was only dropped once
+        state.subscribe(&id, "sports".to_string()).await;
+        assert!(!state.deliver(&id, "news", event(5)).await);
+        assert!(!state.deliver(&id, "sports", event(6)).await);
+        assert_eq!(state.send_to(&queue, ServerMessage::Pong), Pushed::AlreadyClosed);
+        assert!(!state.resend_unacked(&id, Instant::now() + Duration::from_secs(3600)).await);
+        assert_eq!(state.dropped_clients(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_deliver_only_to_subscribers() {
+        let state = AppState::new();
+        let (id, queue) = subscribed_client(&state).await;
+
+        assert!(state.deliver(&id, "sports", event(1)).await);
+        assert_empty(&queue).await;
+        assert!(state.deliver(&id, "news", event(2)).await);
+        assert!(matches!(queue.pop().await, Some(ServerMessage::Event { data, .. }) if data == json!(2)));
+    }
+
+    async fn pop_event_id(queue: &ClientQueue) -> Uuid {
+        match queue.pop().await {
+            Some(ServerMessage::Event { id, .. }) => id,
+            other => panic!("expected an event, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_acked_event_not_resent() {
+        let state = AppState::new().with_acks(Duration::from_secs(5), 3);
+        let (id, queue) = subscribed_client(&state).await;
+
+        assert!(state.deliver(&id, "news", event(1)).await);
+        assert_eq!(state.unacked_events().await, 1);
+
+        let event_id = pop_event_id(&queue).await;
+        assert!(state.ack(&id, &event_id).await);
+        assert!(!state.ack(&id, &event_id).await);
+        assert_eq!(state.unacked_events().await, 0);
+
+        assert!(state.resend_unacked(&id, Instant::now() + Duration::from_secs(60)).await);
+        assert_empty(&queue).await;
+        assert_eq!(state.resent_events(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unacked_event_resent_until_retries_run_out() {
+        let state = AppState::new().with_acks(Duration::from_secs(5), 2);
+        let (id, queue) = subscribed_client(&state).await;
+        let start = Instant::now();
+
+        assert!(state.deliver(&id, "news", event(1)).await);
+        let event_id = pop_event_id(&queue).await;
+
+        // Not yet overdue
+        assert!(state.resend_unacked(&id, start).await);
+        assert_empty(&queue).await;
+
+        // Each re-send restarts the timeout and carries the same id
+        assert!(state.resend_unacked(&id, start + Duration::from_secs(6)).await);
+        assert_eq!(pop_event_id(&queue).await, event_id);
+        assert!(state.resend_unacked(&id, start + Duration::from_secs(8)).await);
+        assert_empty(&queue).await;
+        assert!(state.resend_unacked(&id, start + Duration::from_secs(12)).await);
+        assert_eq!(pop_event_id(&queue).await, event_id);
+        assert_eq!(state.resent_events(), 2);
+
+        // Out of retries: dropped rather than re-sent again
+        assert!(state.resend_unacked(&id, start + Duration::from_secs(18)).await);
+        assert_empty(&queue).await;
+        assert_eq!(state.unacked_events().await, 0);
+        assert_eq!(state.undelivered_events(), 1);
+    }
+
+    #[test]
+    fn test_lag_policy_from_name() {
+        assert_eq!(LagPolicy::from_name("drop-oldest"), Some(LagPolicy::DropOldest));
+        assert_eq!(LagPolicy::from_name("disconnect"), Some(LagPolicy::Disconnect));
+        assert_eq!(LagPolicy::from_name("block"), None);
+    }
+}
@@ -1,8 +1,10 @@
 use crate::messages::{ClientMessage, ServerMessage};
-use crate::state::{AppState, ClientId};
+use crate::state::{AppState, ClientId, ClientQueue};
 use axum::extract::ws::{Message, WebSocket};
 use futures::{SinkExt, StreamExt};
-use tokio::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
 use uuid::Uuid;
 
 pub async fn handle_websocket(socket: WebSocket, state: AppState) {
@@ -10,14 +12,15 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState) {
     tracing::info!("WebSocket client {} connected", client_id);
 
     let (mut sender, mut receiver) = socket.split();
-    let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
+    let queue = state.new_queue();
 
-    state.register_client(client_id, tx.clone()).await;
+    state.register_client(client_id, queue.clone()).await;
 
     let mut broadcast_rx = state.broadcast_tx.subscribe();
 
+    let send_queue = queue.clone();
     let send_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
+        while let Some(msg) = send_queue.pop().await {
             let json = serde_json::to_string(&msg).unwrap();
             if sender.send(Message::Text(json)).await.is_err() {
                 break;
@@ -32,7 +35,7 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState) {
             match result {
                 Ok(Message::Text(text)) => {
                     if let Ok(client_msg) = serde_json::from_str::<ClientMessage>(&text) {
-                        handle_client_message(client_msg, &client_id_clone, &state_clone, &tx).await;
+                        handle_client_message(client_msg, &client_id_clone, &state_clone, &queue).await;
                     }
                 }
                 Ok(Message::Close(_)) => break,
@@ -48,12 +51,31 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState) {
     let client_id_clone2 = client_id;
     let state_clone2 = state.clone();
     let broadcast_task = tokio::spawn(async move {
-        while let Ok((channel, message)) = broadcast_rx.recv().await {
-            let connections = state_clone2.connections.read().await;
-            if let Some(client) = connections.get(&client_id_clone2) {
-                if client.subscriptions.contains(&channel) {
-                    let _ = client.tx.send(message);
+        loop {
+            match broadcast_rx.recv().await {
+                Ok((channel, message)) => {
+                    if !state_clone2.deliver(&client_id_clone2, &channel, message).await {
+                        tracing::warn!("Dropping client {}: too far behind", client_id_clone2);
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Client {} skipped {} broadcasts", client_id_clone2, skipped);
                 }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+
+    // Check for overdue acks a couple of times per timeout
+    let state_clone3 = state.clone();
+    let resend_task = tokio::spawn(async move {
+        let period = (state_clone3.ack_timeout / 2).max(Duration::from_millis(10));
+        let mut ticker = tokio::time::interval(period);
+        loop {
+            ticker.tick().await;
+            if !state_clone3.resend_unacked(&client_id, Instant::now()).await {
+                break;
             }
         }
     });
@@ -62,6 +84,7 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState) {
         _ = send_task => {},
         _ = receive_task => {},
         _ = broadcast_task => {},
+        _ = resend_task => {},
     }
 
     state.unregister_client(&client_id).await;
@@ -72,25 +95,30 @@ async fn handle_client_message(
     msg: ClientMessage,
     client_id: &ClientId,
     state: &AppState,
-    tx: &mpsc::UnboundedSender<ServerMessage>,
+    queue: &Arc<ClientQueue>,
 ) {
     match msg {
         ClientMessage::Subscribe { channel } => {
             if state.subscribe(client_id, channel.clone()).await {
-                let _ = tx.send(ServerMessage::Subscribed { channel });
+                state.send_to(queue, ServerMessage::Subscribed { channel });
             } else {
-                let _ = tx.send(ServerMessage::Error {
+                state.send_to(queue, ServerMessage::Error {
                     message: "Failed to subscribe".to_string(),
                 });
             }
         }
         ClientMessage::Unsubscribe { channel } => {
             if state.unsubscribe(client_id, &channel).await {
-                let _ = tx.send(ServerMessage::Unsubscribed { channel });
+                state.send_to(queue, ServerMessage::Unsubscribed { channel });
             }
         }
         ClientMessage::Ping => {
-            let _ = tx.send(ServerMessage::Pong);
+            state.send_to(queue, ServerMessage::Pong);
+        }
+        ClientMessage::Ack { id } => {
+            if !state.ack(client_id, &id).await {
+                tracing::debug!("Client {} acked unknown event {}", client_id, id);
+            }
         }
     }
 }
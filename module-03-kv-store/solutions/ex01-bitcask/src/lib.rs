@@ -1,9 +1,11 @@
 mod error;
 mod log;
+mod options;
 mod store;
 
 pub use error::{KvError, Result};
-pub use store::KvStore;
+pub use options::KvOpenOptions;
+pub use store::{KvStats, KvStore};
 
 #[cfg(test)]
 mod tests {
@@ -45,20 +47,292 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let mut store = KvStore::open(temp_dir.path()).unwrap();
 
-        for i in 0..100 {
+        for i in 0..100u32 {
             store.set(&i.to_le_bytes(), b"value").unwrap();
         }
-        for i in 0..50 {
+        for i in 0..50u32 {
             store.delete(&i.to_le_bytes()).unwrap();
         }
 
         store.compact().unwrap();
 
-        for i in 50..100 {
+        for i in 50..100u32 {
             assert!(store.get(&i.to_le_bytes()).unwrap().is_some());
         }
-        for i in 0..50 {
+        for i in 0..50u32 {
             assert!(store.get(&i.to_le_bytes()).unwrap().is_none());
         }
     }
+
+    #[test]
+    fn test_compaction_clears_dead_bytes() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut store = KvStore::open(temp_dir.path()).unwrap();
+
+        for i in 0..100u32 {
+            store.set(&i.to_le_bytes(), b"value").unwrap();
+        }
+        for i in 0..50u32 {
+            store.delete(&i.to_le_bytes()).unwrap();
+        }
+        assert!(store.stats().dead_bytes > 0);
+
+        store.compact().unwrap();
+        assert_eq!(store.stats().dead_bytes, 0);
+    }
+
+    fn dir_size(dir: &std::path::Path) -> u64 {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().metadata().unwrap().len())
+            .sum()
+    }
+
+    #[test]
+    fn test_stats_track_dead_bytes() {
+        let temp_dir = TempDir::new().unwrap();
+
+        {
+            let mut store = KvStore::open(temp_dir.path()).unwrap();
+            store.set(b"a", b"1").unwrap();
+            store.set(b"b", b"2").unwrap();
+            assert_eq!(store.stats().dead_bytes, 0);
+
+            store.set(b"a", b"3").unwrap();
+            store.delete(b"b").unwrap();
+
+            let stats = store.stats();
+            assert_eq!(stats.live_keys, 1);
+            assert_eq!(stats.total_bytes, dir_size(temp_dir.path()));
+            assert!(stats.dead_ratio() > 0.5);
+        }
+
+        // Replaying the log recovers the same numbers
+        let before = {
+            let store = KvStore::open(temp_dir.path()).unwrap();
+            store.stats()
+        };
+        assert_eq!(before.live_keys, 1);
+        assert_eq!(before.total_bytes, dir_size(temp_dir.path()));
+        assert!(before.dead_ratio() > 0.5);
+    }
+
+    #[test]
+    fn test_auto_compaction_bounds_log_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut store = KvStore::options()
+            .auto_compact_threshold(0.5)
+            .open(temp_dir.path())
+            .unwrap();
+
+        let value = vec![7u8; 1024];
+        for round in 0..500u32 {
+            for key in 0..10u32 {
+                store.set(&key.to_le_bytes(), &value).unwrap();
+            }
+            if round % 100 == 0 {
+                store.delete(&0u32.to_le_bytes()).unwrap();
+            }
+        }
+
+        // ~5MB was written, but dead data never grows far past the minimum
+        assert!(dir_size(temp_dir.path()) < 3 * 1024 * 1024);
+        for key in 0..10u32 {
+            assert_eq!(store.get(&key.to_le_bytes()).unwrap(), Some(value.clone()));
+        }
+    }
+
+    #[test]
+    fn test_options_builder() {
+        let options = KvStore::options()
+            .max_file_bytes(4096)
+            .auto_compact_threshold(0.25)
+            .sync_on_write(true);
+
+        assert_eq!(options.max_file_bytes, Some(4096));
+        assert_eq!(options.auto_compact_threshold, Some(0.25));
+        assert!(options.sync_on_write);
+
+        let defaults = KvOpenOptions::new();
+        assert_eq!(defaults.max_file_bytes, None);
+        assert_eq!(defaults.auto_compact_threshold, None);
+        assert!(!defaults.sync_on_write);
+    }
+
+    #[test]
+    fn test_sync_on_write_store_is_readable() {
+        let temp_dir = TempDir::new().unwrap();
+
+        {
+            let mut store = KvStore::options()
+                .sync_on_write(true)
+                .open(temp_dir.path())
+                .unwrap();
+            store.set(b"durable", b"data").unwrap();
+            store.set(b"gone", b"data").unwrap();
+            store.delete(b"gone").unwrap();
+            assert_eq!(store.get(b"durable").unwrap(), Some(b"data".to_vec()));
+        }
+
+        let store = KvStore::open(temp_dir.path()).unwrap();
+        assert_eq!(store.get(b"durable").unwrap(), Some(b"data".to_vec()));
+        assert_eq!(store.get(b"gone").unwrap(), None);
+    }
+
+    #[test]
+    fn test_max_file_bytes_rolls_over() {
+        let temp_dir = TempDir::new().unwrap();
+
+        {
+            let mut store = KvStore::options()
+                .max_file_bytes(1024)
+                .open(temp_dir.path())
+                .unwrap();
+            for i in 0..100u32 {
+                store.set(&i.to_le_bytes(), &[1u8; 100]).unwrap();
+            }
+        }
+
+        let files = std::fs::read_dir(temp_dir.path()).unwrap().count();
+        assert!(files > 5, "expected several log files, got {}", files);
+
+        let store = KvStore::open(temp_dir.path()).unwrap();
+        for i in 0..100u32 {
+            assert_eq!(store.get(&i.to_le_bytes()).unwrap(), Some(vec![1u8; 100]));
+        }
+    }
+
+    #[test]
+    fn test_reopen_after_rollover_keeps_latest_value() {
+        let temp_dir = TempDir::new().unwrap();
+
+        {
+            let mut store = KvStore::options()
+                .max_file_bytes(256)
+                .open(temp_dir.path())
+                .unwrap();
+            for i in 0..50u32 {
+                store.set(b"k", &i.to_le_bytes()).unwrap();
+            }
+        }
+
+        let files = std::fs::read_dir(temp_dir.path()).unwrap().count();
+        assert!(files > 5, "expected several log files, got {}", files);
+
+        let store = KvStore::open(temp_dir.path()).unwrap();
+        assert_eq!(store.get(b"k").unwrap(), Some(49u32.to_le_bytes().to_vec()));
+    }
+
+    fn increment(current: Option<&[u8]>) -> Vec<u8> {
+        let count = current.map_or(0, |bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+        (count + 1).to_le_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_merge_counter() {
+        let temp_dir = TempDir::new().unwrap();
+
+        {
+            let mut store = KvStore::open(temp_dir.path()).unwrap();
+            for _ in 0..500 {
+                store.merge(b"counter", increment).unwrap();
+            }
+            assert_eq!(store.get(b"counter").unwrap(), Some(500u64.to_le_bytes().to_vec()));
+        }
+
+        let mut store = KvStore::open(temp_dir.path()).unwrap();
+        store.merge(b"counter", increment).unwrap();
+        assert_eq!(store.get(b"counter").unwrap(), Some(501u64.to_le_bytes().to_vec()));
+    }
+
+    #[test]
+    fn test_merge_absent_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut store = KvStore::open(temp_dir.path()).unwrap();
+
+        let mut seen = Some(b"sentinel".to_vec());
+        store
+            .merge(b"missing", |current| {
+                seen = current.map(<[u8]>::to_vec);
+                b"created".to_vec()
+            })
+            .unwrap();
+
+        assert_eq!(seen, None);
+        assert_eq!(store.get(b"missing").unwrap(), Some(b"created".to_vec()));
+
+        // Deleted keys look absent too
+        store.delete(b"missing").unwrap();
+        store.merge(b"missing", increment).unwrap();
+        assert_eq!(store.get(b"missing").unwrap(), Some(1u64.to_le_bytes().to_vec()));
+    }
+
+    fn keys(pairs: &[(Vec<u8>, Vec<u8>)]) -> Vec<&[u8]> {
+        pairs.iter().map(|(key, _)| key.as_slice()).collect()
+    }
+
+    #[test]
+    fn test_scan_range() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut store = KvStore::open(temp_dir.path()).unwrap();
+
+        for key in [b"d", b"a", b"c", b"e", b"b"] {
+            store.set(key, key).unwrap();
+        }
+        store.delete(b"c").unwrap();
+
+        let forward = store.scan(b"b", b"e").unwrap();
+        assert_eq!(keys(&forward), vec![b"b", b"d"]);
+        assert_eq!(forward[0].1, b"b".to_vec());
+
+        let reverse = store.scan_rev(b"b", b"e").unwrap();
+        assert_eq!(keys(&reverse), vec![b"d", b"b"]);
+        assert_eq!(reverse.iter().rev().cloned().collect::<Vec<_>>(), forward);
+
+        assert!(store.scan(b"e", b"b").unwrap().is_empty());
+        assert!(store.scan_rev(b"x", b"z").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_scan_limited() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut store = KvStore::open(temp_dir.path()).unwrap();
+
+        for i in 0..20u32 {
+            store.set(&i.to_be_bytes(), b"value").unwrap();
+        }
+
+        let page = store
+            .scan_limited(&0u32.to_be_bytes(), &20u32.to_be_bytes(), 5)
+            .unwrap();
+        assert_eq!(page.len(), 5);
+        assert_eq!(page[4].0, 4u32.to_be_bytes().to_vec());
+
+        // Resume after the last key of the previous page
+        let next = store
+            .scan_limited(&5u32.to_be_bytes(), &20u32.to_be_bytes(), 5)
+            .unwrap();
+        assert_eq!(next[0].0, 5u32.to_be_bytes().to_vec());
+
+        let rest = store
+            .scan_limited(&18u32.to_be_bytes(), &20u32.to_be_bytes(), 5)
+            .unwrap();
+        assert_eq!(rest.len(), 2);
+    }
+
+    #[test]
+    fn test_auto_compaction_is_opt_in() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut store = KvStore::open(temp_dir.path()).unwrap();
+
+        let value = vec![7u8; 1024];
+        for _ in 0..300 {
+            for key in 0..10u32 {
+                store.set(&key.to_le_bytes(), &value).unwrap();
+            }
+        }
+
+        assert!(dir_size(temp_dir.path()) > 3 * 1024 * 1024);
+        assert!(store.stats().dead_ratio() > 0.9);
+    }
 }
@@ -45,6 +45,11 @@ impl LogWriter {
         Ok((start_offset, entry_size as u32))
     }
 
+    /// Bytes written to the file so far
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
     pub fn sync(&mut self) -> Result<()> {
         self.writer.flush()?;
         self.writer.get_ref().sync_all()?;
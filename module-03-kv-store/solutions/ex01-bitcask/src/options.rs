@@ -0,0 +1,47 @@
+use crate::error::Result;
+use crate::store::KvStore;
+use std::path::Path;
+
+/// Settings used when opening a `KvStore`, built from `KvStore::options()`
+#[derive(Debug, Clone, Default)]
+pub struct KvOpenOptions {
+    pub(crate) max_file_bytes: Option<u64>,
+    pub(crate) auto_compact_threshold: Option<f64>,
+    pub(crate) sync_on_write: bool,
+}
+
+impl KvOpenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a new log file once the active one reaches this size
+    ///
+    /// Unlimited by default. Compaction still writes a single file.
+    pub fn max_file_bytes(mut self, bytes: u64) -> Self {
+        self.max_file_bytes = Some(bytes);
+        self
+    }
+
+    /// Compact automatically once this fraction of the log is dead
+    ///
+    /// Disabled by default. Compaction also waits for a minimum amount of
+    /// dead data so small stores are not rewritten on every write.
+    pub fn auto_compact_threshold(mut self, ratio: f64) -> Self {
+        self.auto_compact_threshold = Some(ratio);
+        self
+    }
+
+    /// Fsync the log after every write
+    ///
+    /// Off by default: writes are flushed to the OS but may be lost on a
+    /// power failure.
+    pub fn sync_on_write(mut self, sync: bool) -> Self {
+        self.sync_on_write = sync;
+        self
+    }
+
+    pub fn open(self, dir: &Path) -> Result<KvStore> {
+        KvStore::open_with(dir, self)
+    }
+}
@@ -1,64 +1,93 @@
 use crate::error::{KvError, Result};
 use crate::log::{open_log_file, LogEntry, LogReader, LogWriter};
-use std::collections::HashMap;
+use crate::options::KvOpenOptions;
+use std::collections::BTreeMap;
 use std::fs;
+use std::ops::Bound;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 struct IndexEntry {
     file_id: u32,
     offset: u64,
+    size: u64,
+}
+
+/// Space usage of the log files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KvStats {
+    pub live_keys: usize,
+    /// Bytes across all log files
+    pub total_bytes: u64,
+    /// Bytes held by overwritten values and tombstones
+    pub dead_bytes: u64,
+}
+
+impl KvStats {
+    /// Fraction of the log that compaction would reclaim
+    pub fn dead_ratio(&self) -> f64 {
+        if self.total_bytes == 0 {
+            0.0
+        } else {
+            self.dead_bytes as f64 / self.total_bytes as f64
+        }
+    }
 }
 
 pub struct KvStore {
     dir: PathBuf,
-    index: HashMap<Vec<u8>, IndexEntry>,
+    /// Sorted so range scans can walk keys in order
+    index: BTreeMap<Vec<u8>, IndexEntry>,
     writer: LogWriter,
     current_file_id: u32,
-    uncompacted_size: u64,
+    total_bytes: u64,
+    dead_bytes: u64,
+    options: KvOpenOptions,
 }
 
 impl KvStore {
-    const COMPACTION_THRESHOLD: u64 = 1024 * 1024; // 1MB
+    /// Dead bytes required before auto-compaction kicks in, so small logs
+    /// are not rewritten on every write
+    const MIN_COMPACTION_BYTES: u64 = 1024 * 1024; // 1MB
 
+    /// Open a store with default options
     pub fn open(dir: &Path) -> Result<Self> {
+        Self::options().open(dir)
+    }
+
+    /// Start configuring a store to open
+    pub fn options() -> KvOpenOptions {
+        KvOpenOptions::new()
+    }
+
+    pub(crate) fn open_with(dir: &Path, options: KvOpenOptions) -> Result<Self> {
         fs::create_dir_all(dir)?;
 
-        let mut index = HashMap::new();
+        let mut index = BTreeMap::new();
         let mut max_file_id = 0;
-        let mut uncompacted_size = 0;
+        let mut total_bytes = 0;
+        let mut dead_bytes = 0;
 
-        // Find all log files
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-
-            if let Some(ext) = path.extension() {
-                if ext == "log" {
-                    if let Some(stem) = path.file_stem() {
-                        if let Ok(file_id) = stem.to_string_lossy().parse::<u32>() {
-                            max_file_id = max_file_id.max(file_id);
-
-                            let file = open_log_file(&path)?;
-                            let mut reader = LogReader::new(file);
-                            let entries = reader.read_all()?;
-
-                            for (offset, entry) in entries {
-                                match entry {
-                                    LogEntry::Set { ref key, ref value } => {
-                                        index.insert(
-                                            key.clone(),
-                                            IndexEntry { file_id, offset },
-                                        );
-                                        uncompacted_size += 8 + key.len() as u64 + value.len() as u64;
-                                    }
-                                    LogEntry::Delete { ref key } => {
-                                        index.remove(key);
-                                        uncompacted_size += 8 + key.len() as u64;
-                                    }
-                                }
-                            }
-                        }
+        // Replay oldest first so later writes to a key win
+        for file_id in Self::log_file_ids(dir)? {
+            max_file_id = max_file_id.max(file_id);
+
+            let file = open_log_file(&Self::log_path(dir, file_id))?;
+            let mut reader = LogReader::new(file);
+            let entries = reader.read_all()?;
+
+            for (offset, entry) in entries {
+                let size = 8 + bincode::serialized_size(&entry)?;
+                total_bytes += size;
+
+                match entry {
+                    LogEntry::Set { key, .. } => {
+                        let old = index.insert(key, IndexEntry { file_id, offset, size });
+                        dead_bytes += old.map_or(0, |old| old.size);
+                    }
+                    LogEntry::Delete { ref key } => {
+                        let old = index.remove(key);
+                        dead_bytes += size + old.map_or(0, |old| old.size);
                     }
                 }
             }
@@ -74,7 +103,9 @@ impl KvStore {
             index,
             writer,
             current_file_id,
-            uncompacted_size,
+            total_bytes,
+            dead_bytes,
+            options,
         })
     }
 
@@ -84,22 +115,20 @@ impl KvStore {
             value: value.to_vec(),
         };
 
-        let (offset, size) = self.writer.append(&entry)?;
-        self.index.insert(
+        let (offset, size) = self.append(&entry)?;
+        let old = self.index.insert(
             key.to_vec(),
             IndexEntry {
                 file_id: self.current_file_id,
                 offset,
+                size: size as u64,
             },
         );
 
-        self.uncompacted_size += size as u64;
+        self.total_bytes += size as u64;
+        self.dead_bytes += old.map_or(0, |old| old.size);
 
-        if self.uncompacted_size > Self::COMPACTION_THRESHOLD {
-            self.compact()?;
-        }
-
-        Ok(())
+        self.maybe_compact()
     }
 
     pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
@@ -117,6 +146,19 @@ impl KvStore {
         }
     }
 
+    /// Replace the value at `key` with `f(current)`
+    ///
+    /// `f` sees `None` when the key is absent. The read and the write happen
+    /// under the same `&mut self` borrow, so no other write can interleave.
+    pub fn merge<F>(&mut self, key: &[u8], f: F) -> Result<()>
+    where
+        F: FnOnce(Option<&[u8]>) -> Vec<u8>,
+    {
+        let current = self.get(key)?;
+        let value = f(current.as_deref());
+        self.set(key, &value)
+    }
+
     pub fn delete(&mut self, key: &[u8]) -> Result<()> {
         if !self.index.contains_key(key) {
             return Ok(());
@@ -126,9 +168,105 @@ impl KvStore {
             key: key.to_vec(),
         };
 
-        let (_, size) = self.writer.append(&entry)?;
-        self.index.remove(key);
-        self.uncompacted_size += size as u64;
+        let (_, size) = self.append(&entry)?;
+        let old = self.index.remove(key);
+
+        // The tombstone itself is dead weight once written
+        self.total_bytes += size as u64;
+        self.dead_bytes += size as u64 + old.map_or(0, |old| old.size);
+
+        self.maybe_compact()
+    }
+
+    /// Live pairs with `start <= key < end`, in ascending key order
+    pub fn scan(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.scan_range(start, end, false, None)
+    }
+
+    /// Like `scan`, but in descending key order
+    pub fn scan_rev(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.scan_range(start, end, true, None)
+    }
+
+    /// Like `scan`, but stops after `limit` pairs
+    pub fn scan_limited(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: usize,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.scan_range(start, end, false, Some(limit))
+    }
+
+    fn scan_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        reverse: bool,
+        limit: Option<usize>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        // BTreeMap::range panics on an inverted range
+        if start >= end {
+            return Ok(Vec::new());
+        }
+
+        let range = self
+            .index
+            .range::<[u8], _>((Bound::Included(start), Bound::Excluded(end)));
+        let keys: Box<dyn Iterator<Item = &Vec<u8>>> = if reverse {
+            Box::new(range.rev().map(|(key, _)| key))
+        } else {
+            Box::new(range.map(|(key, _)| key))
+        };
+
+        let mut pairs = Vec::new();
+        for key in keys.take(limit.unwrap_or(usize::MAX)) {
+            if let Some(value) = self.get(key)? {
+                pairs.push((key.clone(), value));
+            }
+        }
+
+        Ok(pairs)
+    }
+
+    /// Append to the active log, rolling over to a new file when it is full
+    fn append(&mut self, entry: &LogEntry) -> Result<(u64, u32)> {
+        if let Some(max) = self.options.max_file_bytes {
+            if self.writer.offset() > 0 && self.writer.offset() >= max {
+                self.current_file_id += 1;
+                let log_path = Self::log_path(&self.dir, self.current_file_id);
+                self.writer = LogWriter::new(open_log_file(&log_path)?)?;
+            }
+        }
+
+        let result = self.writer.append(entry)?;
+
+        if self.options.sync_on_write {
+            self.writer.sync()?;
+        }
+
+        Ok(result)
+    }
+
+    /// Current space usage of the store
+    pub fn stats(&self) -> KvStats {
+        KvStats {
+            live_keys: self.index.len(),
+            total_bytes: self.total_bytes,
+            dead_bytes: self.dead_bytes,
+        }
+    }
+
+    /// Compact if auto-compaction is enabled and enough space is dead
+    fn maybe_compact(&mut self) -> Result<()> {
+        let threshold = match self.options.auto_compact_threshold {
+            Some(threshold) => threshold,
+            None => return Ok(()),
+        };
+
+        if self.dead_bytes >= Self::MIN_COMPACTION_BYTES && self.stats().dead_ratio() > threshold {
+            self.compact()?;
+        }
 
         Ok(())
     }
@@ -140,7 +278,8 @@ impl KvStore {
         let file = open_log_file(&compaction_path)?;
         let mut compaction_writer = LogWriter::new(file)?;
 
-        let mut new_index = HashMap::new();
+        let mut new_index = BTreeMap::new();
+        let mut total_bytes = 0;
 
         for (key, _) in &self.index {
             if let Some(value) = self.get(key)? {
@@ -149,40 +288,32 @@ impl KvStore {
                     value,
                 };
 
-                let (offset, _) = compaction_writer.append(&entry)?;
+                let (offset, size) = compaction_writer.append(&entry)?;
                 new_index.insert(
                     key.clone(),
                     IndexEntry {
                         file_id: compaction_file_id,
                         offset,
+                        size: size as u64,
                     },
                 );
+                total_bytes += size as u64;
             }
         }
 
         compaction_writer.sync()?;
 
         // Remove old log files
-        for entry in fs::read_dir(&self.dir)? {
-            let entry = entry?;
-            let path = entry.path();
-
-            if let Some(ext) = path.extension() {
-                if ext == "log" {
-                    if let Some(stem) = path.file_stem() {
-                        if let Ok(file_id) = stem.to_string_lossy().parse::<u32>() {
-                            if file_id <= self.current_file_id {
-                                fs::remove_file(&path)?;
-                            }
-                        }
-                    }
-                }
+        for file_id in Self::log_file_ids(&self.dir)? {
+            if file_id <= self.current_file_id {
+                fs::remove_file(Self::log_path(&self.dir, file_id))?;
             }
         }
 
         self.index = new_index;
         self.current_file_id = compaction_file_id + 1;
-        self.uncompacted_size = 0;
+        self.total_bytes = total_bytes;
+        self.dead_bytes = 0;
 
         let new_log_path = Self::log_path(&self.dir, self.current_file_id);
         let new_file = open_log_file(&new_log_path)?;
@@ -191,6 +322,27 @@ impl KvStore {
         Ok(())
     }
 
+    /// Ids of the log files in `dir`, oldest first
+    fn log_file_ids(dir: &Path) -> Result<Vec<u32>> {
+        let mut file_ids = Vec::new();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "log") {
+                if let Some(Ok(file_id)) = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().parse::<u32>())
+                {
+                    file_ids.push(file_id);
+                }
+            }
+        }
+
+        // Directory order is arbitrary, but replay must follow write order
+        file_ids.sort_unstable();
+        Ok(file_ids)
+    }
+
     fn log_path(dir: &Path, file_id: u32) -> PathBuf {
         dir.join(format!("{}.log", file_id))
     }
@@ -0,0 +1,356 @@
+use crate::command::Command;
+use crate::db::{Db, Value};
+use crate::error::{DbError, RespError, Result};
+use crate::resp::RespValue;
+use crate::snapshot::SnapshotEntry;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, MutexGuard};
+use tracing::{error, info, warn};
+
+/// Append-only file logging every write command in RESP
+///
+/// Writes are logged once applied and handed to the OS without an fsync,
+/// so a crashed process loses nothing but a power failure can lose the
+/// most recent writes.
+#[derive(Clone)]
+pub struct Aof {
+    path: Arc<PathBuf>,
+    file: Arc<Mutex<File>>,
+    /// Held from applying a write until it is logged, and while rewriting,
+    /// so the log holds writes in the order they were applied and no write
+    /// is missed by or doubled in a rewrite
+    gate: Arc<Mutex<()>>,
+    /// Set while a background rewrite is running
+    rewriting: Arc<AtomicBool>,
+}
+
+impl Aof {
+    /// Open the log at `path`, creating it if needed
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let file = open_append(&path).await?;
+
+        Ok(Aof {
+            path: Arc::new(path),
+            file: Arc::new(Mutex::new(file)),
+            gate: Arc::new(Mutex::new(())),
+            rewriting: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rebuild `db` by re-executing the log, returning how many commands ran
+    ///
+    /// The database is cleared first since the log holds its whole history.
+    /// A torn command left at the end by a crash mid-append is cut off.
+    pub async fn replay(&self, db: &Db) -> Result<usize> {
+        let _gate = self.gate.lock().await;
+        let bytes = tokio::fs::read(&*self.path).await?;
+        db.clear().await;
+
+        let mut cursor = Cursor::new(&bytes[..]);
+        let mut replayed = 0;
+
+        while (cursor.position() as usize) < bytes.len() {
+            let start = cursor.position();
+            let bad_command = |e: &dyn std::fmt::Display| {
+                DbError::InvalidAof(format!("bad command at byte {}: {}", start, e))
+            };
+
+            let request = match RespValue::parse(&mut cursor) {
+                Ok(request) => request,
+                Err(RespError::Incomplete) => {
+                    warn!(
+                        "Discarding torn command at the end of {}",
+                        self.path.display()
+                    );
+                    self.file.lock().await.set_len(start).await?;
+                    break;
+                }
+                Err(e) => return Err(bad_command(&e)),
+            };
+
+            let command = Command::from_resp(request).map_err(|e| bad_command(&e))?;
+            // Only writes that succeeded are logged, but a key evicted since
+            // can still make a later command fail
+            if let Err(e) = command.execute(db).await {
+                warn!("Replayed command failed: {}", e);
+            }
+            replayed += 1;
+        }
+
+        Ok(replayed)
+    }
+
+    /// Hold off other logged writes and rewrites until the guard is dropped
+    ///
+    /// Take it before applying a write and keep it until the write is logged.
+    pub async fn begin_write(&self) -> MutexGuard<'_, ()> {
+        self.gate.lock().await
+    }
+
+    /// Log a write that was just applied
+    ///
+    /// `expiry_key` is a key the command gave an expiry relative to now.
+    /// Its absolute expiry is logged after the command so a later replay
+    /// doesn't push it back.
+    pub async fn log_write(
+        &self,
+        db: &Db,
+        request: RespValue,
+        expiry_key: Option<&str>,
+    ) -> Result<()> {
+        let mut bytes = request.serialize();
+
+        if let Some(key) = expiry_key {
+            let pttl = db.pttl(key).await?;
+            if pttl >= 0 {
+                let at = SystemTime::now() + Duration::from_millis(pttl as u64);
+                bytes.extend_from_slice(&pexpireat(key, unix_millis(at)).serialize());
+            }
+        }
+
+        let mut file = self.file.lock().await;
+        file.write_all(&bytes).await?;
+        file.flush().await?;
+        Ok(())
+    }
+
+    /// Replace the log with the fewest commands that rebuild the database
+    ///
+    /// Writes wait until the rewrite is done. Returns the number of keys.
+    pub async fn rewrite(&self, db: &Db) -> Result<usize> {
+        let _gate = self.gate.lock().await;
+        let entries = db.snapshot_entries().await;
+
+        let mut bytes = Vec::new();
+        for entry in &entries {
+            for command in entry_commands(entry) {
+                bytes.extend_from_slice(&command.serialize());
+            }
+        }
+
+        // Write to a temporary file first so a crash never leaves a torn log
+        let tmp_path = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, bytes).await?;
+
+        let mut file = self.file.lock().await;
+        tokio::fs::rename(&tmp_path, &*self.path).await?;
+        *file = open_append(&self.path).await?;
+
+        Ok(entries.len())
+    }
+
+    /// Start a rewrite in a background task
+    ///
+    /// Returns `false` if a background rewrite is already running.
+    pub fn start_background_rewrite(&self, db: Db) -> bool {
+        if self.rewriting.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+
+        let aof = self.clone();
+        tokio::spawn(async move {
+            match aof.rewrite(&db).await {
+                Ok(keys) => info!("AOF rewrite finished: {} keys", keys),
+                Err(e) => error!("AOF rewrite failed: {}", e),
+            }
+            aof.rewriting.store(false, Ordering::Release);
+        });
+
+        true
+    }
+}
+
+async fn open_append(path: &Path) -> Result<File> {
+    Ok(OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?)
+}
+
+/// Commands that recreate a single key
+///
+/// An empty collection gives none: its command would have no values, which
+/// replay rejects, and an empty collection reads the same as a missing key.
+fn entry_commands(entry: &SnapshotEntry) -> Vec<RespValue> {
+    let empty = match &entry.value {
+        Value::String(_) => false,
+        Value::List(list) => list.is_empty(),
+        Value::Set(set) => set.is_empty(),
+        Value::Hash(hash) => hash.is_empty(),
+        Value::SortedSet(zset) => zset.is_empty(),
+    };
+    if empty {
+        return Vec::new();
+    }
+
+    let key = entry.key.as_bytes().to_vec();
+
+    let args: Vec<Vec<u8>> = match &entry.value {
+        Value::String(bytes) => vec![b"SET".to_vec(), key, bytes.clone()],
+        Value::List(list) => [b"RPUSH".to_vec(), key]
+            .into_iter()
+            .chain(list.iter().cloned())
+            .collect(),
+        Value::Set(set) => [b"SADD".to_vec(), key]
+            .into_iter()
+            .chain(set.iter().cloned())
+            .collect(),
+        Value::Hash(hash) => [b"HMSET".to_vec(), key]
+            .into_iter()
+            .chain(
+                hash.iter()
+                    .flat_map(|(field, value)| [field.as_bytes().to_vec(), value.clone()]),
+            )
+            .collect(),
+        Value::SortedSet(zset) => [b"ZADD".to_vec(), key]
+            .into_iter()
+            .chain(
+                zset.iter()
+                    .flat_map(|(member, score)| [score.to_string().into_bytes(), member.to_vec()]),
+            )
+            .collect(),
+    };
+
+    let mut commands = vec![request(args)];
+    if let Some(at_ms) = entry.expires_at_ms {
+        commands.push(pexpireat(&entry.key, at_ms));
+    }
+    commands
+}
+
+fn pexpireat(key: &str, at_ms: u64) -> RespValue {
+    request(vec![
+        b"PEXPIREAT".to_vec(),
+        key.as_bytes().to_vec(),
+        at_ms.to_string().into_bytes(),
+    ])
+}
+
+/// Build a command the way a client sends it: an array of bulk strings
+fn request(args: Vec<Vec<u8>>) -> RespValue {
+    RespValue::Array(Some(
+        args.into_iter()
+            .map(|arg| RespValue::BulkString(Some(arg)))
+            .collect(),
+    ))
+}
+
+fn unix_millis(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_replay_truncates_torn_command() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("appendonly.aof");
+
+        let aof = Aof::open(&path).await.unwrap();
+        let db = Db::new();
+        let set = request(vec![b"SET".to_vec(), b"a".to_vec(), b"1".to_vec()]);
+        aof.log_write(&db, set, None).await.unwrap();
+        let complete_len = std::fs::metadata(&path).unwrap().len();
+
+        // A crash partway through appending the next command
+        let mut torn = request(vec![b"SET".to_vec(), b"b".to_vec(), b"2".to_vec()]).serialize();
+        torn.truncate(torn.len() - 4);
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&torn)
+            .unwrap();
+
+        let aof = Aof::open(&path).await.unwrap();
+        assert_eq!(aof.replay(&db).await.unwrap(), 1);
+        assert_eq!(db.get("a").await.unwrap(), Some(b"1".to_vec()));
+        assert_eq!(db.get("b").await.unwrap(), None);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), complete_len);
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_keeps_expiry() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("appendonly.aof");
+
+        let db = Db::new();
+        db.hset("hash", "field".to_string(), b"v".to_vec())
+            .await
+            .unwrap();
+        db.set_ex(
+            "volatile".to_string(),
+            b"soon".to_vec(),
+            Duration::from_secs(100),
+        )
+        .await
+        .unwrap();
+
+        let aof = Aof::open(&path).await.unwrap();
+        assert_eq!(aof.rewrite(&db).await.unwrap(), 2);
+
+        let restored = Db::new();
+        // One command per key plus a PEXPIREAT for the volatile one
+        assert_eq!(aof.replay(&restored).await.unwrap(), 3);
+        assert_eq!(
+            restored.hget("hash", "field").await.unwrap(),
+            Some(b"v".to_vec())
+        );
+        let ttl = restored.ttl("volatile").await.unwrap();
+        assert!(ttl > 90 && ttl <= 100, "ttl was {}", ttl);
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_after_popping_list_empty() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("appendonly.aof");
+
+        let db = Db::new();
+        db.rpush("drained", vec![b"a".to_vec(), b"b".to_vec()])
+            .await
+            .unwrap();
+        db.rpush("kept", vec![b"c".to_vec()]).await.unwrap();
+        db.lpop("drained", 1).await.unwrap();
+        db.rpop("drained", 1).await.unwrap();
+        assert!(!db.exists("drained").await.unwrap());
+
+        let aof = Aof::open(&path).await.unwrap();
+        aof.rewrite(&db).await.unwrap();
+
+        let restored = Db::new();
+        assert_eq!(aof.replay(&restored).await.unwrap(), 1);
+        assert!(!restored.exists("drained").await.unwrap());
+        assert_eq!(
+            restored.lrange("kept", 0, -1).await.unwrap(),
+            vec![b"c".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_empty_collection_has_no_commands() {
+        let entry = SnapshotEntry {
+            key: "empty".to_string(),
+            value: Value::List(Default::default()),
+            expires_at_ms: None,
+        };
+        assert!(entry_commands(&entry).is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::RwLock;
+
+/// What the server knows about one connected client
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub id: u64,
+    /// `ip:port`, or `path:0` for a Unix socket as Redis shows it
+    pub addr: String,
+    pub connected_at: Instant,
+    pub last_active: Instant,
+    /// Lowercased name of the last command run, if any
+    pub last_command: Option<String>,
+}
+
+impl ClientInfo {
+    /// Format as a `CLIENT LIST` line: `id=1 addr=127.0.0.1:5000 age=3 idle=0 cmd=get`
+    pub fn to_line(&self) -> String {
+        format!(
+            "id={} addr={} age={} idle={} cmd={}",
+            self.id,
+            self.addr,
+            self.connected_at.elapsed().as_secs(),
+            self.last_active.elapsed().as_secs(),
+            self.last_command.as_deref().unwrap_or("NULL"),
+        )
+    }
+}
+
+/// Registry of connected clients and server-wide counters
+#[derive(Clone)]
+pub struct ClientRegistry {
+    clients: Arc<RwLock<HashMap<u64, ClientInfo>>>,
+    total_connections: Arc<AtomicU64>,
+    total_commands: Arc<AtomicU64>,
+    started_at: Instant,
+}
+
+impl Default for ClientRegistry {
+    fn default() -> Self {
+        ClientRegistry {
+            clients: Arc::default(),
+            total_connections: Arc::default(),
+            total_commands: Arc::default(),
+            started_at: Instant::now(),
+        }
+    }
+}
+
+impl ClientRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn register(&self, id: u64, addr: String) {
+        let now = Instant::now();
+        self.clients.write().await.insert(
+            id,
+            ClientInfo {
+                id,
+                addr,
+                connected_at: now,
+                last_active: now,
+                last_command: None,
+            },
+        );
+        self.total_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub async fn unregister(&self, id: u64) {
+        self.clients.write().await.remove(&id);
+    }
+
+    /// Note that client `id` just ran `command`
+    pub async fn record_command(&self, id: u64, command: &str) {
+        self.total_commands.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(client) = self.clients.write().await.get_mut(&id) {
+            client.last_active = Instant::now();
+            client.last_command = Some(command.to_lowercase());
+        }
+    }
+
+    /// Connected clients, oldest connection first
+    pub async fn list(&self) -> Vec<ClientInfo> {
+        let mut clients: Vec<_> = self.clients.read().await.values().cloned().collect();
+        clients.sort_by_key(|client| client.id);
+        clients
+    }
+
+    pub async fn connected(&self) -> usize {
+        self.clients.read().await.len()
+    }
+
+    pub fn total_connections(&self) -> u64 {
+        self.total_connections.load(Ordering::Relaxed)
+    }
+
+    pub fn total_commands(&self) -> u64 {
+        self.total_commands.load(Ordering::Relaxed)
+    }
+
+    pub fn uptime_secs(&self) -> u64 {
+        self.started_at.elapsed().as_secs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_register_and_record() {
+        let registry = ClientRegistry::new();
+        let addr = "127.0.0.1:5000".to_string();
+
+        registry.register(2, addr.clone()).await;
+        registry.register(1, addr).await;
+        registry.record_command(1, "GET").await;
+        registry.record_command(1, "SET").await;
+
+        let clients = registry.list().await;
+        assert_eq!(clients.iter().map(|c| c.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(clients[0].last_command.as_deref(), Some("set"));
+        assert_eq!(
+            clients[0].to_line(),
+            "id=1 addr=127.0.0.1:5000 age=0 idle=0 cmd=set"
+        );
+        assert_eq!(
+            clients[1].to_line(),
+            "id=2 addr=127.0.0.1:5000 age=0 idle=0 cmd=NULL"
+        );
+        assert_eq!(registry.total_commands(), 2);
+
+        registry.unregister(2).await;
+        assert_eq!(registry.connected().await, 1);
+        assert_eq!(registry.total_connections(), 2);
+    }
+}
@@ -1,7 +1,10 @@
-use crate::db::Db;
+use crate::db::{Db, ListEnd, SortOptions};
 use crate::error::{CommandError, DbError};
 use crate::resp::RespValue;
-use std::time::Duration;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Keys examined per SCAN call when no COUNT is given
+const DEFAULT_SCAN_COUNT: usize = 10;
 
 #[derive(Debug)]
 pub enum Command {
@@ -15,19 +18,101 @@ pub enum Command {
         px: Option<u64>,
         ex: Option<u64>,
     },
+    Append {
+        key: String,
+        value: Vec<u8>,
+    },
+    StrLen {
+        key: String,
+    },
+    SetNx {
+        key: String,
+        value: Vec<u8>,
+    },
+    SetEx {
+        key: String,
+        seconds: u64,
+        value: Vec<u8>,
+    },
+    GetSet {
+        key: String,
+        value: Vec<u8>,
+    },
+    Incr {
+        key: String,
+    },
+    Decr {
+        key: String,
+    },
+    IncrBy {
+        key: String,
+        delta: i64,
+    },
+    DecrBy {
+        key: String,
+        delta: i64,
+    },
     Del {
         keys: Vec<String>,
     },
     Exists {
         keys: Vec<String>,
     },
+    Keys {
+        pattern: String,
+    },
+    Scan {
+        cursor: u64,
+        pattern: Option<String>,
+        count: usize,
+    },
+    Type {
+        key: String,
+    },
     Expire {
         key: String,
         seconds: u64,
     },
+    ExpireAt {
+        key: String,
+        timestamp: u64,
+    },
+    PExpireAt {
+        key: String,
+        timestamp_ms: u64,
+    },
+    Persist {
+        key: String,
+    },
+    Rename {
+        key: String,
+        newkey: String,
+    },
+    RenameNx {
+        key: String,
+        newkey: String,
+    },
+    Copy {
+        src: String,
+        dst: String,
+        replace: bool,
+    },
+    Dump {
+        key: String,
+    },
+    Restore {
+        key: String,
+        /// Milliseconds to live, 0 for no expiry
+        ttl: u64,
+        payload: Vec<u8>,
+        replace: bool,
+    },
     Ttl {
         key: String,
     },
+    PTtl {
+        key: String,
+    },
 
     // List commands
     LPush {
@@ -54,6 +139,12 @@ pub enum Command {
     LLen {
         key: String,
     },
+    LMove {
+        src: String,
+        dst: String,
+        from: ListEnd,
+        to: ListEnd,
+    },
 
     // Set commands
     SAdd {
@@ -70,6 +161,24 @@ pub enum Command {
     SCard {
         key: String,
     },
+    SRem {
+        key: String,
+        members: Vec<Vec<u8>>,
+    },
+    SInter {
+        keys: Vec<String>,
+    },
+    SUnion {
+        keys: Vec<String>,
+    },
+    SDiff {
+        keys: Vec<String>,
+    },
+
+    Sort {
+        key: String,
+        options: SortOptions,
+    },
 
     // Hash commands
     HSet {
@@ -81,6 +190,22 @@ pub enum Command {
         key: String,
         field: String,
     },
+    HMSet {
+        key: String,
+        pairs: Vec<(String, Vec<u8>)>,
+    },
+    HMGet {
+        key: String,
+        fields: Vec<String>,
+    },
+    HDel {
+        key: String,
+        fields: Vec<String>,
+    },
+    HExists {
+        key: String,
+        field: String,
+    },
     HGetAll {
         key: String,
     },
@@ -88,7 +213,60 @@ pub enum Command {
         key: String,
     },
 
+    // Sorted set commands
+    ZAdd {
+        key: String,
+        /// Score and member pairs, in the order given
+        members: Vec<(f64, Vec<u8>)>,
+    },
+    ZScore {
+        key: String,
+        member: Vec<u8>,
+    },
+    ZRange {
+        key: String,
+        start: i64,
+        stop: i64,
+        with_scores: bool,
+    },
+    ZRank {
+        key: String,
+        member: Vec<u8>,
+    },
+    ZRem {
+        key: String,
+        members: Vec<Vec<u8>>,
+    },
+
+    // Pub/sub commands
+    Subscribe {
+        channels: Vec<String>,
+    },
+    Unsubscribe {
+        channels: Vec<String>,
+    },
+    PSubscribe {
+        patterns: Vec<String>,
+    },
+    PUnsubscribe {
+        patterns: Vec<String>,
+    },
+    Publish {
+        channel: String,
+        message: Vec<u8>,
+    },
+
     // Server commands
+    Save,
+    BgSave,
+    BgRewriteAof,
+    Hello {
+        protover: Option<i64>,
+    },
+    ClientList,
+    Info {
+        section: Option<String>,
+    },
     Ping {
         message: Option<String>,
     },
@@ -170,6 +348,105 @@ impl Command {
                 Ok(Command::Set { key, value, px, ex })
             }
 
+            "APPEND" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity("APPEND".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let value = array[2].as_bytes()?.to_vec();
+                Ok(Command::Append { key, value })
+            }
+
+            "STRLEN" => {
+                if array.len() != 2 {
+                    return Err(CommandError::WrongArity("STRLEN".into()));
+                }
+                Ok(Command::StrLen {
+                    key: array[1].as_str()?.to_string(),
+                })
+            }
+
+            "SETNX" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity("SETNX".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let value = array[2].as_bytes()?.to_vec();
+                Ok(Command::SetNx { key, value })
+            }
+
+            "SETEX" => {
+                if array.len() != 4 {
+                    return Err(CommandError::WrongArity("SETEX".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let seconds = array[2]
+                    .as_str()?
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|&s| s > 0)
+                    .ok_or_else(|| {
+                        CommandError::InvalidArgument(
+                            "invalid expire time in 'setex' command".into(),
+                        )
+                    })?;
+                let value = array[3].as_bytes()?.to_vec();
+                Ok(Command::SetEx {
+                    key,
+                    seconds,
+                    value,
+                })
+            }
+
+            "GETSET" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity("GETSET".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let value = array[2].as_bytes()?.to_vec();
+                Ok(Command::GetSet { key, value })
+            }
+
+            "INCR" => {
+                if array.len() != 2 {
+                    return Err(CommandError::WrongArity("INCR".into()));
+                }
+                Ok(Command::Incr {
+                    key: array[1].as_str()?.to_string(),
+                })
+            }
+
+            "DECR" => {
+                if array.len() != 2 {
+                    return Err(CommandError::WrongArity("DECR".into()));
+                }
+                Ok(Command::Decr {
+                    key: array[1].as_str()?.to_string(),
+                })
+            }
+
+            "INCRBY" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity("INCRBY".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let delta = array[2].as_str()?.parse::<i64>().map_err(|_| {
+                    CommandError::InvalidArgument("INCRBY value must be integer".into())
+                })?;
+                Ok(Command::IncrBy { key, delta })
+            }
+
+            "DECRBY" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity("DECRBY".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let delta = array[2].as_str()?.parse::<i64>().map_err(|_| {
+                    CommandError::InvalidArgument("DECRBY value must be integer".into())
+                })?;
+                Ok(Command::DecrBy { key, delta })
+            }
+
             "DEL" => {
                 if array.len() < 2 {
                     return Err(CommandError::WrongArity("DEL".into()));
@@ -192,6 +469,75 @@ impl Command {
                 Ok(Command::Exists { keys })
             }
 
+            "KEYS" => {
+                if array.len() != 2 {
+                    return Err(CommandError::WrongArity("KEYS".into()));
+                }
+                Ok(Command::Keys {
+                    pattern: array[1].as_str()?.to_string(),
+                })
+            }
+
+            "SCAN" => {
+                if array.len() < 2 {
+                    return Err(CommandError::WrongArity("SCAN".into()));
+                }
+                let cursor = array[1]
+                    .as_str()?
+                    .parse::<u64>()
+                    .map_err(|_| CommandError::InvalidArgument("invalid cursor".into()))?;
+                let mut pattern = None;
+                let mut count = DEFAULT_SCAN_COUNT;
+
+                let mut i = 2;
+                while i < array.len() {
+                    let option = array[i].as_str()?.to_uppercase();
+                    let Some(arg) = array.get(i + 1) else {
+                        return Err(CommandError::InvalidArgument(format!(
+                            "{} needs value",
+                            option
+                        )));
+                    };
+                    match option.as_str() {
+                        "MATCH" => pattern = Some(arg.as_str()?.to_string()),
+                        "COUNT" => {
+                            count = arg
+                                .as_str()?
+                                .parse::<usize>()
+                                .ok()
+                                .filter(|&n| n > 0)
+                                .ok_or_else(|| {
+                                    CommandError::InvalidArgument(
+                                        "COUNT must be a positive integer".into(),
+                                    )
+                                })?;
+                        }
+                        _ => {
+                            return Err(CommandError::InvalidArgument(format!(
+                                "Unknown SCAN option: {}",
+                                option
+                            )))
+                        }
+                    }
+                    i += 2;
+                }
+
+                Ok(Command::Scan {
+                    cursor,
+                    pattern,
+                    count,
+                })
+            }
+
+            "TYPE" => {
+                if array.len() != 2 {
+                    return Err(CommandError::WrongArity("TYPE".into()));
+                }
+                Ok(Command::Type {
+                    key: array[1].as_str()?.to_string(),
+                })
+            }
+
             "EXPIRE" => {
                 if array.len() != 3 {
                     return Err(CommandError::WrongArity("EXPIRE".into()));
@@ -203,6 +549,108 @@ impl Command {
                 Ok(Command::Expire { key, seconds })
             }
 
+            "EXPIREAT" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity("EXPIREAT".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let timestamp = array[2].as_str()?.parse::<i64>().map_err(|_| {
+                    CommandError::InvalidArgument("EXPIREAT value must be integer".into())
+                })?;
+                Ok(Command::ExpireAt {
+                    key,
+                    timestamp: timestamp.max(0) as u64,
+                })
+            }
+
+            "PEXPIREAT" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity("PEXPIREAT".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let timestamp_ms = array[2].as_str()?.parse::<i64>().map_err(|_| {
+                    CommandError::InvalidArgument("PEXPIREAT value must be integer".into())
+                })?;
+                Ok(Command::PExpireAt {
+                    key,
+                    timestamp_ms: timestamp_ms.max(0) as u64,
+                })
+            }
+
+            "PERSIST" => {
+                if array.len() != 2 {
+                    return Err(CommandError::WrongArity("PERSIST".into()));
+                }
+                Ok(Command::Persist {
+                    key: array[1].as_str()?.to_string(),
+                })
+            }
+
+            "RENAME" | "RENAMENX" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity(cmd_name));
+                }
+                let key = array[1].as_str()?.to_string();
+                let newkey = array[2].as_str()?.to_string();
+                Ok(match cmd_name.as_str() {
+                    "RENAME" => Command::Rename { key, newkey },
+                    _ => Command::RenameNx { key, newkey },
+                })
+            }
+
+            "COPY" => {
+                if array.len() < 3 || array.len() > 4 {
+                    return Err(CommandError::WrongArity("COPY".into()));
+                }
+                let src = array[1].as_str()?.to_string();
+                let dst = array[2].as_str()?.to_string();
+                if src == dst {
+                    return Err(CommandError::InvalidArgument(
+                        "source and destination objects are the same".into(),
+                    ));
+                }
+                let replace = parse_replace(array.get(3))?;
+                Ok(Command::Copy { src, dst, replace })
+            }
+
+            "DUMP" => {
+                if array.len() != 2 {
+                    return Err(CommandError::WrongArity("DUMP".into()));
+                }
+                Ok(Command::Dump {
+                    key: array[1].as_str()?.to_string(),
+                })
+            }
+
+            "RESTORE" => {
+                if array.len() < 4 || array.len() > 5 {
+                    return Err(CommandError::WrongArity("RESTORE".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let ttl = array[2].as_str()?.parse::<u64>().map_err(|_| {
+                    CommandError::InvalidArgument(
+                        "RESTORE ttl must be a non-negative integer".into(),
+                    )
+                })?;
+                let payload = array[3].as_bytes()?.to_vec();
+                let replace = parse_replace(array.get(4))?;
+                Ok(Command::Restore {
+                    key,
+                    ttl,
+                    payload,
+                    replace,
+                })
+            }
+
+            "PTTL" => {
+                if array.len() != 2 {
+                    return Err(CommandError::WrongArity("PTTL".into()));
+                }
+                Ok(Command::PTtl {
+                    key: array[1].as_str()?.to_string(),
+                })
+            }
+
             "TTL" => {
                 if array.len() != 2 {
                     return Err(CommandError::WrongArity("TTL".into()));
@@ -289,6 +737,35 @@ impl Command {
                 })
             }
 
+            "LMOVE" => {
+                if array.len() != 5 {
+                    return Err(CommandError::WrongArity("LMOVE".into()));
+                }
+                let end = |arg: &RespValue| {
+                    ListEnd::from_name(arg.as_str()?).ok_or_else(|| {
+                        CommandError::InvalidArgument("LMOVE ends must be LEFT or RIGHT".into())
+                    })
+                };
+                Ok(Command::LMove {
+                    src: array[1].as_str()?.to_string(),
+                    dst: array[2].as_str()?.to_string(),
+                    from: end(&array[3])?,
+                    to: end(&array[4])?,
+                })
+            }
+
+            "RPOPLPUSH" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity("RPOPLPUSH".into()));
+                }
+                Ok(Command::LMove {
+                    src: array[1].as_str()?.to_string(),
+                    dst: array[2].as_str()?.to_string(),
+                    from: ListEnd::Right,
+                    to: ListEnd::Left,
+                })
+            }
+
             "SADD" => {
                 if array.len() < 3 {
                     return Err(CommandError::WrongArity("SADD".into()));
@@ -328,26 +805,133 @@ impl Command {
                 })
             }
 
-            "HSET" => {
-                if array.len() != 4 {
-                    return Err(CommandError::WrongArity("HSET".into()));
+            "SREM" => {
+                if array.len() < 3 {
+                    return Err(CommandError::WrongArity("SREM".into()));
                 }
                 let key = array[1].as_str()?.to_string();
-                let field = array[2].as_str()?.to_string();
-                let value = array[3].as_bytes()?.to_vec();
-                Ok(Command::HSet { key, field, value })
+                let members = array[2..]
+                    .iter()
+                    .map(|v| v.as_bytes().map(|b| b.to_vec()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Command::SRem { key, members })
             }
 
-            "HGET" => {
-                if array.len() != 3 {
-                    return Err(CommandError::WrongArity("HGET".into()));
+            "SINTER" | "SUNION" | "SDIFF" => {
+                if array.len() < 2 {
+                    return Err(CommandError::WrongArity(cmd_name));
                 }
-                let key = array[1].as_str()?.to_string();
-                let field = array[2].as_str()?.to_string();
-                Ok(Command::HGet { key, field })
+                let keys = array[1..]
+                    .iter()
+                    .map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(match cmd_name.as_str() {
+                    "SINTER" => Command::SInter { keys },
+                    "SUNION" => Command::SUnion { keys },
+                    _ => Command::SDiff { keys },
+                })
             }
 
-            "HGETALL" => {
+            "SORT" => {
+                if array.len() < 2 {
+                    return Err(CommandError::WrongArity("SORT".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let mut options = SortOptions::default();
+
+                let mut i = 2;
+                while i < array.len() {
+                    let option = array[i].as_str()?.to_uppercase();
+                    match option.as_str() {
+                        "ALPHA" => options.alpha = true,
+                        "ASC" => options.descending = false,
+                        "DESC" => options.descending = true,
+                        "LIMIT" => {
+                            if i + 2 >= array.len() {
+                                return Err(CommandError::InvalidArgument(
+                                    "LIMIT needs offset and count".into(),
+                                ));
+                            }
+                            let parse = |value: &RespValue| {
+                                value.as_str()?.parse::<i64>().map_err(|_| {
+                                    CommandError::InvalidArgument(
+                                        "LIMIT values must be integers".into(),
+                                    )
+                                })
+                            };
+                            options.limit = Some((parse(&array[i + 1])?, parse(&array[i + 2])?));
+                            i += 2;
+                        }
+                        _ => {
+                            return Err(CommandError::InvalidArgument(format!(
+                                "Unknown SORT option: {}",
+                                option
+                            )))
+                        }
+                    }
+                    i += 1;
+                }
+
+                Ok(Command::Sort { key, options })
+            }
+
+            "HSET" => {
+                if array.len() != 4 {
+                    return Err(CommandError::WrongArity("HSET".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let field = array[2].as_str()?.to_string();
+                let value = array[3].as_bytes()?.to_vec();
+                Ok(Command::HSet { key, field, value })
+            }
+
+            "HGET" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity("HGET".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let field = array[2].as_str()?.to_string();
+                Ok(Command::HGet { key, field })
+            }
+
+            "HMSET" => {
+                if array.len() < 4 || array.len() % 2 != 0 {
+                    return Err(CommandError::WrongArity("HMSET".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let pairs = array[2..]
+                    .chunks(2)
+                    .map(|pair| Ok((pair[0].as_str()?.to_string(), pair[1].as_bytes()?.to_vec())))
+                    .collect::<Result<Vec<_>, CommandError>>()?;
+                Ok(Command::HMSet { key, pairs })
+            }
+
+            "HMGET" | "HDEL" => {
+                if array.len() < 3 {
+                    return Err(CommandError::WrongArity(cmd_name));
+                }
+                let key = array[1].as_str()?.to_string();
+                let fields = array[2..]
+                    .iter()
+                    .map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(if cmd_name == "HMGET" {
+                    Command::HMGet { key, fields }
+                } else {
+                    Command::HDel { key, fields }
+                })
+            }
+
+            "HEXISTS" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity("HEXISTS".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let field = array[2].as_str()?.to_string();
+                Ok(Command::HExists { key, field })
+            }
+
+            "HGETALL" => {
                 if array.len() != 2 {
                     return Err(CommandError::WrongArity("HGETALL".into()));
                 }
@@ -365,6 +949,190 @@ impl Command {
                 })
             }
 
+            "ZADD" => {
+                if array.len() < 4 || array.len() % 2 != 0 {
+                    return Err(CommandError::WrongArity("ZADD".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let members = array[2..]
+                    .chunks(2)
+                    .map(|pair| {
+                        let score = parse_score(&pair[0])?;
+                        Ok((score, pair[1].as_bytes()?.to_vec()))
+                    })
+                    .collect::<Result<Vec<_>, CommandError>>()?;
+                Ok(Command::ZAdd { key, members })
+            }
+
+            "ZSCORE" | "ZRANK" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity(cmd_name));
+                }
+                let key = array[1].as_str()?.to_string();
+                let member = array[2].as_bytes()?.to_vec();
+                Ok(match cmd_name.as_str() {
+                    "ZSCORE" => Command::ZScore { key, member },
+                    _ => Command::ZRank { key, member },
+                })
+            }
+
+            "ZRANGE" => {
+                if array.len() != 4 && array.len() != 5 {
+                    return Err(CommandError::WrongArity("ZRANGE".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let start = array[2]
+                    .as_str()?
+                    .parse::<i64>()
+                    .map_err(|_| CommandError::InvalidArgument("START must be integer".into()))?;
+                let stop = array[3]
+                    .as_str()?
+                    .parse::<i64>()
+                    .map_err(|_| CommandError::InvalidArgument("STOP must be integer".into()))?;
+                let with_scores = match array.get(4) {
+                    None => false,
+                    Some(arg) if arg.as_str()?.eq_ignore_ascii_case("WITHSCORES") => true,
+                    Some(arg) => {
+                        return Err(CommandError::InvalidArgument(format!(
+                            "Unknown ZRANGE option: {}",
+                            arg.as_str()?
+                        )))
+                    }
+                };
+                Ok(Command::ZRange {
+                    key,
+                    start,
+                    stop,
+                    with_scores,
+                })
+            }
+
+            "ZREM" => {
+                if array.len() < 3 {
+                    return Err(CommandError::WrongArity("ZREM".into()));
+                }
+                let key = array[1].as_str()?.to_string();
+                let members = array[2..]
+                    .iter()
+                    .map(|v| v.as_bytes().map(|b| b.to_vec()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Command::ZRem { key, members })
+            }
+
+            "SUBSCRIBE" => {
+                if array.len() < 2 {
+                    return Err(CommandError::WrongArity("SUBSCRIBE".into()));
+                }
+                let channels = array[1..]
+                    .iter()
+                    .map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Command::Subscribe { channels })
+            }
+
+            "UNSUBSCRIBE" => {
+                let channels = array[1..]
+                    .iter()
+                    .map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Command::Unsubscribe { channels })
+            }
+
+            "PSUBSCRIBE" => {
+                if array.len() < 2 {
+                    return Err(CommandError::WrongArity("PSUBSCRIBE".into()));
+                }
+                let patterns = array[1..]
+                    .iter()
+                    .map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Command::PSubscribe { patterns })
+            }
+
+            "PUNSUBSCRIBE" => {
+                let patterns = array[1..]
+                    .iter()
+                    .map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Command::PUnsubscribe { patterns })
+            }
+
+            "PUBLISH" => {
+                if array.len() != 3 {
+                    return Err(CommandError::WrongArity("PUBLISH".into()));
+                }
+                let channel = array[1].as_str()?.to_string();
+                let message = array[2].as_bytes()?.to_vec();
+                Ok(Command::Publish { channel, message })
+            }
+
+            "SAVE" => {
+                if array.len() != 1 {
+                    return Err(CommandError::WrongArity("SAVE".into()));
+                }
+                Ok(Command::Save)
+            }
+
+            "BGSAVE" => {
+                if array.len() != 1 {
+                    return Err(CommandError::WrongArity("BGSAVE".into()));
+                }
+                Ok(Command::BgSave)
+            }
+
+            "BGREWRITEAOF" => {
+                if array.len() != 1 {
+                    return Err(CommandError::WrongArity("BGREWRITEAOF".into()));
+                }
+                Ok(Command::BgRewriteAof)
+            }
+
+            "HELLO" => {
+                if array.len() > 2 {
+                    return Err(CommandError::InvalidArgument(
+                        "HELLO options are not supported".into(),
+                    ));
+                }
+                let protover = if array.len() == 2 {
+                    Some(array[1].as_str()?.parse::<i64>().map_err(|_| {
+                        CommandError::InvalidArgument(
+                            "Protocol version is not an integer or out of range".into(),
+                        )
+                    })?)
+                } else {
+                    None
+                };
+                Ok(Command::Hello { protover })
+            }
+
+            "CLIENT" => {
+                if array.len() < 2 {
+                    return Err(CommandError::WrongArity("CLIENT".into()));
+                }
+                let subcommand = array[1].as_str()?.to_uppercase();
+                match subcommand.as_str() {
+                    "LIST" if array.len() == 2 => Ok(Command::ClientList),
+                    "LIST" => Err(CommandError::InvalidArgument(
+                        "CLIENT LIST options are not supported".into(),
+                    )),
+                    _ => Err(CommandError::InvalidArgument(format!(
+                        "Unknown CLIENT subcommand: {}",
+                        subcommand
+                    ))),
+                }
+            }
+
+            "INFO" => {
+                if array.len() > 2 {
+                    return Err(CommandError::WrongArity("INFO".into()));
+                }
+                let section = match array.get(1) {
+                    Some(section) => Some(section.as_str()?.to_lowercase()),
+                    None => None,
+                };
+                Ok(Command::Info { section })
+            }
+
             "PING" => {
                 let message = if array.len() > 1 {
                     Some(array[1].as_str()?.to_string())
@@ -387,6 +1155,72 @@ impl Command {
         }
     }
 
+    /// Whether the command may be sent by a connection in subscribed mode
+    pub fn allowed_while_subscribed(&self) -> bool {
+        matches!(
+            self,
+            Command::Subscribe { .. }
+                | Command::Unsubscribe { .. }
+                | Command::PSubscribe { .. }
+                | Command::PUnsubscribe { .. }
+                | Command::Ping { .. }
+        )
+    }
+
+    /// Whether the command can modify the keyspace, and so belongs in the AOF
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Command::Set { .. }
+                | Command::Append { .. }
+                | Command::SetNx { .. }
+                | Command::SetEx { .. }
+                | Command::GetSet { .. }
+                | Command::Incr { .. }
+                | Command::Decr { .. }
+                | Command::IncrBy { .. }
+                | Command::DecrBy { .. }
+                | Command::Del { .. }
+                | Command::Expire { .. }
+                | Command::ExpireAt { .. }
+                | Command::PExpireAt { .. }
+                | Command::Persist { .. }
+                | Command::Rename { .. }
+                | Command::RenameNx { .. }
+                | Command::Copy { .. }
+                | Command::Restore { .. }
+                | Command::LPush { .. }
+                | Command::RPush { .. }
+                | Command::LPop { .. }
+                | Command::RPop { .. }
+                | Command::LMove { .. }
+                | Command::SAdd { .. }
+                | Command::SRem { .. }
+                | Command::HSet { .. }
+                | Command::HMSet { .. }
+                | Command::HDel { .. }
+                | Command::ZAdd { .. }
+                | Command::ZRem { .. }
+        )
+    }
+
+    /// Key given an expiry relative to now, which must be logged as an
+    /// absolute time so a replay doesn't extend it
+    pub fn relative_expiry_key(&self) -> Option<&str> {
+        match self {
+            Command::Set {
+                key, px: Some(_), ..
+            }
+            | Command::Set {
+                key, ex: Some(_), ..
+            }
+            | Command::SetEx { key, .. }
+            | Command::Expire { key, .. } => Some(key),
+            Command::Restore { key, ttl, .. } if *ttl > 0 => Some(key),
+            _ => None,
+        }
+    }
+
     /// Execute the command against the database
     pub async fn execute(self, db: &Db) -> Result<RespValue, DbError> {
         match self {
@@ -396,17 +1230,58 @@ impl Command {
             },
 
             Command::Set { key, value, px, ex } => {
-                db.set(key.clone(), value).await?;
-
                 if let Some(millis) = px {
-                    db.expire(&key, Duration::from_millis(millis)).await?;
+                    db.set_ex(key, value, Duration::from_millis(millis)).await?;
                 } else if let Some(seconds) = ex {
-                    db.expire(&key, Duration::from_secs(seconds)).await?;
+                    db.set_ex(key, value, Duration::from_secs(seconds)).await?;
+                } else {
+                    db.set(key, value).await?;
                 }
 
                 Ok(RespValue::SimpleString("OK".to_string()))
             }
 
+            Command::Append { key, value } => {
+                let len = db.append(&key, &value).await?;
+                Ok(RespValue::Integer(len as i64))
+            }
+
+            Command::StrLen { key } => {
+                let len = db.strlen(&key).await?;
+                Ok(RespValue::Integer(len as i64))
+            }
+
+            Command::SetNx { key, value } => {
+                let was_set = db.set_nx(key, value).await?;
+                Ok(RespValue::Integer(if was_set { 1 } else { 0 }))
+            }
+
+            Command::SetEx {
+                key,
+                seconds,
+                value,
+            } => {
+                db.set_ex(key, value, Duration::from_secs(seconds)).await?;
+                Ok(RespValue::SimpleString("OK".to_string()))
+            }
+
+            Command::GetSet { key, value } => {
+                Ok(RespValue::BulkString(db.getset(key, value).await?))
+            }
+
+            Command::Incr { key } => Ok(RespValue::Integer(db.incr_by(&key, 1).await?)),
+
+            Command::Decr { key } => Ok(RespValue::Integer(db.incr_by(&key, -1).await?)),
+
+            Command::IncrBy { key, delta } => {
+                Ok(RespValue::Integer(db.incr_by(&key, delta).await?))
+            }
+
+            Command::DecrBy { key, delta } => {
+                let delta = delta.checked_neg().ok_or(DbError::NotInteger)?;
+                Ok(RespValue::Integer(db.incr_by(&key, delta).await?))
+            }
+
             Command::Del { keys } => {
                 let mut count = 0;
                 for key in keys {
@@ -427,11 +1302,88 @@ impl Command {
                 Ok(RespValue::Integer(count))
             }
 
+            Command::Keys { pattern } => {
+                let keys = db.keys(&pattern).await?;
+                let resp_values = keys
+                    .into_iter()
+                    .map(|k| RespValue::BulkString(Some(k.into_bytes())))
+                    .collect();
+                Ok(RespValue::Array(Some(resp_values)))
+            }
+
+            Command::Scan {
+                cursor,
+                pattern,
+                count,
+            } => {
+                let (next_cursor, keys) = db.scan(cursor, pattern.as_deref(), count).await?;
+                let keys = keys
+                    .into_iter()
+                    .map(|k| RespValue::BulkString(Some(k.into_bytes())))
+                    .collect();
+                Ok(RespValue::Array(Some(vec![
+                    RespValue::BulkString(Some(next_cursor.to_string().into_bytes())),
+                    RespValue::Array(Some(keys)),
+                ])))
+            }
+
+            Command::Type { key } => {
+                let type_name = db.key_type(&key).await?;
+                Ok(RespValue::SimpleString(type_name.to_string()))
+            }
+
             Command::Expire { key, seconds } => {
                 let success = db.expire(&key, Duration::from_secs(seconds)).await?;
                 Ok(RespValue::Integer(if success { 1 } else { 0 }))
             }
 
+            Command::ExpireAt { key, timestamp } => {
+                let at = UNIX_EPOCH + Duration::from_secs(timestamp);
+                let success = db.expire_at(&key, at).await?;
+                Ok(RespValue::Integer(if success { 1 } else { 0 }))
+            }
+
+            Command::PExpireAt { key, timestamp_ms } => {
+                let at = UNIX_EPOCH + Duration::from_millis(timestamp_ms);
+                let success = db.expire_at(&key, at).await?;
+                Ok(RespValue::Integer(if success { 1 } else { 0 }))
+            }
+
+            Command::Persist { key } => {
+                let removed = db.persist(&key).await?;
+                Ok(RespValue::Integer(if removed { 1 } else { 0 }))
+            }
+
+            Command::Rename { key, newkey } => {
+                db.rename(&key, &newkey).await?;
+                Ok(RespValue::SimpleString("OK".to_string()))
+            }
+
+            Command::RenameNx { key, newkey } => {
+                let renamed = db.rename_nx(&key, &newkey).await?;
+                Ok(RespValue::Integer(if renamed { 1 } else { 0 }))
+            }
+
+            Command::Copy { src, dst, replace } => {
+                let copied = db.copy(&src, &dst, replace).await?;
+                Ok(RespValue::Integer(if copied { 1 } else { 0 }))
+            }
+
+            Command::Dump { key } => Ok(RespValue::BulkString(db.dump(&key).await?)),
+
+            Command::Restore {
+                key,
+                ttl,
+                payload,
+                replace,
+            } => {
+                let ttl = (ttl > 0).then(|| Duration::from_millis(ttl));
+                db.restore(&key, ttl, &payload, replace).await?;
+                Ok(RespValue::SimpleString("OK".to_string()))
+            }
+
+            Command::PTtl { key } => Ok(RespValue::Integer(db.pttl(&key).await?)),
+
             Command::Ttl { key } => {
                 let ttl = db.ttl(&key).await?;
                 Ok(RespValue::Integer(ttl))
@@ -491,6 +1443,10 @@ impl Command {
                 Ok(RespValue::Integer(len as i64))
             }
 
+            Command::LMove { src, dst, from, to } => {
+                Ok(RespValue::BulkString(db.lmove(&src, &dst, from, to).await?))
+            }
+
             Command::SAdd { key, members } => {
                 let count = db.sadd(&key, members).await?;
                 Ok(RespValue::Integer(count as i64))
@@ -515,6 +1471,19 @@ impl Command {
                 Ok(RespValue::Integer(count as i64))
             }
 
+            Command::SRem { key, members } => {
+                let count = db.srem(&key, members).await?;
+                Ok(RespValue::Integer(count as i64))
+            }
+
+            Command::SInter { keys } => Ok(members_to_resp(db.sinter(&keys).await?)),
+
+            Command::SUnion { keys } => Ok(members_to_resp(db.sunion(&keys).await?)),
+
+            Command::SDiff { keys } => Ok(members_to_resp(db.sdiff(&keys).await?)),
+
+            Command::Sort { key, options } => Ok(members_to_resp(db.sort(&key, options).await?)),
+
             Command::HSet { key, field, value } => {
                 let is_new = db.hset(&key, field, value).await?;
                 Ok(RespValue::Integer(if is_new { 1 } else { 0 }))
@@ -525,6 +1494,27 @@ impl Command {
                 None => Ok(RespValue::BulkString(None)),
             },
 
+            Command::HMSet { key, pairs } => {
+                db.hmset(&key, pairs).await?;
+                Ok(RespValue::SimpleString("OK".to_string()))
+            }
+
+            Command::HMGet { key, fields } => {
+                let values = db.hmget(&key, &fields).await?;
+                let resp_values = values.into_iter().map(RespValue::BulkString).collect();
+                Ok(RespValue::Array(Some(resp_values)))
+            }
+
+            Command::HDel { key, fields } => {
+                let count = db.hdel(&key, &fields).await?;
+                Ok(RespValue::Integer(count as i64))
+            }
+
+            Command::HExists { key, field } => {
+                let exists = db.hexists(&key, &field).await?;
+                Ok(RespValue::Integer(if exists { 1 } else { 0 }))
+            }
+
             Command::HGetAll { key } => {
                 let hash = db.hgetall(&key).await?;
                 let mut resp_values = Vec::new();
@@ -540,16 +1530,101 @@ impl Command {
                 Ok(RespValue::Integer(len as i64))
             }
 
+            Command::ZAdd { key, members } => {
+                let added = db.zadd(&key, members).await?;
+                Ok(RespValue::Integer(added as i64))
+            }
+
+            Command::ZScore { key, member } => {
+                let score = db.zscore(&key, &member).await?;
+                Ok(RespValue::BulkString(
+                    score.map(|s| s.to_string().into_bytes()),
+                ))
+            }
+
+            Command::ZRange {
+                key,
+                start,
+                stop,
+                with_scores,
+            } => {
+                let members = db.zrange(&key, start, stop).await?;
+                let mut resp_values = Vec::new();
+                for (member, score) in members {
+                    resp_values.push(RespValue::BulkString(Some(member)));
+                    if with_scores {
+                        let score = score.to_string().into_bytes();
+                        resp_values.push(RespValue::BulkString(Some(score)));
+                    }
+                }
+                Ok(RespValue::Array(Some(resp_values)))
+            }
+
+            Command::ZRank { key, member } => match db.zrank(&key, &member).await? {
+                Some(rank) => Ok(RespValue::Integer(rank as i64)),
+                None => Ok(RespValue::BulkString(None)),
+            },
+
+            Command::ZRem { key, members } => {
+                let removed = db.zrem(&key, members).await?;
+                Ok(RespValue::Integer(removed as i64))
+            }
+
             Command::Ping { message } => match message {
                 Some(msg) => Ok(RespValue::BulkString(Some(msg.into_bytes()))),
                 None => Ok(RespValue::SimpleString("PONG".to_string())),
             },
 
             Command::Echo { message } => Ok(RespValue::BulkString(Some(message.into_bytes()))),
+
+            // These need connection state and are handled by the server
+            Command::Subscribe { .. }
+            | Command::Unsubscribe { .. }
+            | Command::PSubscribe { .. }
+            | Command::PUnsubscribe { .. }
+            | Command::Publish { .. }
+            | Command::Hello { .. }
+            | Command::ClientList
+            | Command::Info { .. }
+            | Command::Save
+            | Command::BgSave
+            | Command::BgRewriteAof => Err(DbError::CommandError(CommandError::InvalidArgument(
+                "command must be sent over a client connection".into(),
+            ))),
         }
     }
 }
 
+/// Parse a sorted set score, accepting `inf` and `-inf` but not NaN
+fn parse_score(arg: &RespValue) -> Result<f64, CommandError> {
+    arg.as_str()?
+        .parse::<f64>()
+        .ok()
+        .filter(|score| !score.is_nan())
+        .ok_or_else(|| CommandError::InvalidArgument("score is not a valid float".into()))
+}
+
+/// Parse the optional trailing REPLACE flag of COPY and RESTORE
+fn parse_replace(arg: Option<&RespValue>) -> Result<bool, CommandError> {
+    match arg {
+        None => Ok(false),
+        Some(arg) if arg.as_str()?.eq_ignore_ascii_case("REPLACE") => Ok(true),
+        Some(arg) => Err(CommandError::InvalidArgument(format!(
+            "Unknown option: {}",
+            arg.as_str()?
+        ))),
+    }
+}
+
+/// Encode a list of members as an array of bulk strings
+fn members_to_resp(members: Vec<Vec<u8>>) -> RespValue {
+    let resp_values = members
+        .into_iter()
+        .map(|v| RespValue::BulkString(Some(v)))
+        .collect();
+    RespValue::Array(Some(resp_values))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -593,4 +1668,596 @@ mod tests {
         let cmd = Command::from_resp(resp).unwrap();
         assert!(matches!(cmd, Command::Del { keys } if keys.len() == 2));
     }
+
+    #[test]
+    fn test_parse_lmove() {
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"LMOVE".to_vec())),
+            RespValue::BulkString(Some(b"src".to_vec())),
+            RespValue::BulkString(Some(b"dst".to_vec())),
+            RespValue::BulkString(Some(b"left".to_vec())),
+            RespValue::BulkString(Some(b"RIGHT".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(
+            cmd,
+            Command::LMove {
+                from: ListEnd::Left,
+                to: ListEnd::Right,
+                ..
+            }
+        ));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"RPOPLPUSH".to_vec())),
+            RespValue::BulkString(Some(b"src".to_vec())),
+            RespValue::BulkString(Some(b"dst".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(
+            matches!(cmd, Command::LMove { src, dst, from: ListEnd::Right, to: ListEnd::Left }
+            if src == "src" && dst == "dst")
+        );
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"LMOVE".to_vec())),
+            RespValue::BulkString(Some(b"src".to_vec())),
+            RespValue::BulkString(Some(b"dst".to_vec())),
+            RespValue::BulkString(Some(b"UP".to_vec())),
+            RespValue::BulkString(Some(b"LEFT".to_vec())),
+        ]));
+        assert!(Command::from_resp(resp).is_err());
+    }
+
+    #[test]
+    fn test_parse_incr_decr() {
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"INCR".to_vec())),
+            RespValue::BulkString(Some(b"counter".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::Incr { key } if key == "counter"));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"DECRBY".to_vec())),
+            RespValue::BulkString(Some(b"counter".to_vec())),
+            RespValue::BulkString(Some(b"5".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::DecrBy { key, delta } if key == "counter" && delta == 5));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"INCRBY".to_vec())),
+            RespValue::BulkString(Some(b"counter".to_vec())),
+            RespValue::BulkString(Some(b"abc".to_vec())),
+        ]));
+        assert!(matches!(
+            Command::from_resp(resp),
+            Err(CommandError::InvalidArgument(_))
+        ));
+
+        let resp = RespValue::Array(Some(vec![RespValue::BulkString(Some(b"DECR".to_vec()))]));
+        assert!(matches!(
+            Command::from_resp(resp),
+            Err(CommandError::WrongArity(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_execute_incr_not_integer() {
+        let db = Db::new();
+        db.set("name".to_string(), b"alice".to_vec()).await.unwrap();
+
+        let result = Command::Incr { key: "name".into() }.execute(&db).await;
+        let err = result.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR value is not an integer or out of range"
+        );
+
+        let result = Command::DecrBy {
+            key: "counter".into(),
+            delta: 4,
+        }
+        .execute(&db)
+        .await
+        .unwrap();
+        assert_eq!(result, RespValue::Integer(-4));
+    }
+
+    #[test]
+    fn test_parse_setex() {
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"SETEX".to_vec())),
+            RespValue::BulkString(Some(b"mykey".to_vec())),
+            RespValue::BulkString(Some(b"10".to_vec())),
+            RespValue::BulkString(Some(b"myvalue".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::SetEx { key, seconds, value }
+                if key == "mykey" && seconds == 10 && value == b"myvalue"));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"SETEX".to_vec())),
+            RespValue::BulkString(Some(b"mykey".to_vec())),
+            RespValue::BulkString(Some(b"0".to_vec())),
+            RespValue::BulkString(Some(b"myvalue".to_vec())),
+        ]));
+        assert!(matches!(
+            Command::from_resp(resp),
+            Err(CommandError::InvalidArgument(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_execute_setnx_getset() {
+        let db = Db::new();
+
+        let cmd = Command::SetNx {
+            key: "k".into(),
+            value: b"a".to_vec(),
+        };
+        assert_eq!(cmd.execute(&db).await.unwrap(), RespValue::Integer(1));
+
+        let cmd = Command::SetNx {
+            key: "k".into(),
+            value: b"b".to_vec(),
+        };
+        assert_eq!(cmd.execute(&db).await.unwrap(), RespValue::Integer(0));
+
+        let cmd = Command::GetSet {
+            key: "k".into(),
+            value: b"c".to_vec(),
+        };
+        assert_eq!(
+            cmd.execute(&db).await.unwrap(),
+            RespValue::BulkString(Some(b"a".to_vec()))
+        );
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"KEYS".to_vec())),
+            RespValue::BulkString(Some(b"user:*".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::Keys { pattern } if pattern == "user:*"));
+
+        let resp = RespValue::Array(Some(vec![RespValue::BulkString(Some(b"KEYS".to_vec()))]));
+        assert!(matches!(
+            Command::from_resp(resp),
+            Err(CommandError::WrongArity(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_scan() {
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"SCAN".to_vec())),
+            RespValue::BulkString(Some(b"0".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(
+            cmd,
+            Command::Scan {
+                cursor: 0,
+                pattern: None,
+                count: DEFAULT_SCAN_COUNT
+            }
+        ));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"scan".to_vec())),
+            RespValue::BulkString(Some(b"42".to_vec())),
+            RespValue::BulkString(Some(b"match".to_vec())),
+            RespValue::BulkString(Some(b"user:*".to_vec())),
+            RespValue::BulkString(Some(b"COUNT".to_vec())),
+            RespValue::BulkString(Some(b"100".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(
+            matches!(cmd, Command::Scan { cursor: 42, pattern: Some(p), count: 100 } if p == "user:*")
+        );
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"SCAN".to_vec())),
+            RespValue::BulkString(Some(b"0".to_vec())),
+            RespValue::BulkString(Some(b"COUNT".to_vec())),
+        ]));
+        assert!(matches!(
+            Command::from_resp(resp),
+            Err(CommandError::InvalidArgument(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_execute_scan() {
+        let db = Db::new();
+        for i in 0..50 {
+            db.set(format!("key:{}", i), b"v".to_vec()).await.unwrap();
+        }
+
+        let mut cursor = 0;
+        let mut seen = std::collections::HashSet::new();
+        loop {
+            let cmd = Command::Scan {
+                cursor,
+                pattern: None,
+                count: 10,
+            };
+            let RespValue::Array(Some(reply)) = cmd.execute(&db).await.unwrap() else {
+                panic!("SCAN should reply with an array");
+            };
+            let [RespValue::BulkString(Some(next)), RespValue::Array(Some(keys))] = &reply[..]
+            else {
+                panic!("SCAN reply should be [cursor, keys]");
+            };
+            for key in keys {
+                seen.insert(key.as_str().unwrap().to_string());
+            }
+            cursor = String::from_utf8(next.clone()).unwrap().parse().unwrap();
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(seen.len(), 50);
+    }
+
+    #[test]
+    fn test_parse_sort() {
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"SORT".to_vec())),
+            RespValue::BulkString(Some(b"mylist".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(
+            matches!(cmd, Command::Sort { key, options } if key == "mylist" && options == SortOptions::default())
+        );
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"sort".to_vec())),
+            RespValue::BulkString(Some(b"mylist".to_vec())),
+            RespValue::BulkString(Some(b"limit".to_vec())),
+            RespValue::BulkString(Some(b"0".to_vec())),
+            RespValue::BulkString(Some(b"5".to_vec())),
+            RespValue::BulkString(Some(b"ALPHA".to_vec())),
+            RespValue::BulkString(Some(b"DESC".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        let expected = SortOptions {
+            alpha: true,
+            descending: true,
+            limit: Some((0, 5)),
+        };
+        assert!(matches!(cmd, Command::Sort { options, .. } if options == expected));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"SORT".to_vec())),
+            RespValue::BulkString(Some(b"mylist".to_vec())),
+            RespValue::BulkString(Some(b"LIMIT".to_vec())),
+            RespValue::BulkString(Some(b"0".to_vec())),
+        ]));
+        assert!(matches!(
+            Command::from_resp(resp),
+            Err(CommandError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_set_algebra() {
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"sinter".to_vec())),
+            RespValue::BulkString(Some(b"s1".to_vec())),
+            RespValue::BulkString(Some(b"s2".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::SInter { keys } if keys == vec!["s1", "s2"]));
+
+        let resp = RespValue::Array(Some(vec![RespValue::BulkString(Some(b"SDIFF".to_vec()))]));
+        assert!(matches!(
+            Command::from_resp(resp),
+            Err(CommandError::WrongArity(name)) if name == "SDIFF"
+        ));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"SREM".to_vec())),
+            RespValue::BulkString(Some(b"s1".to_vec())),
+            RespValue::BulkString(Some(b"a".to_vec())),
+            RespValue::BulkString(Some(b"b".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::SRem { key, members } if key == "s1" && members.len() == 2));
+    }
+
+    #[test]
+    fn test_parse_hash_commands() {
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"HMSET".to_vec())),
+            RespValue::BulkString(Some(b"user".to_vec())),
+            RespValue::BulkString(Some(b"name".to_vec())),
+            RespValue::BulkString(Some(b"alice".to_vec())),
+            RespValue::BulkString(Some(b"age".to_vec())),
+            RespValue::BulkString(Some(b"30".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::HMSet { key, pairs } if key == "user" && pairs.len() == 2));
+
+        // Odd number of field/value arguments
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"HMSET".to_vec())),
+            RespValue::BulkString(Some(b"user".to_vec())),
+            RespValue::BulkString(Some(b"name".to_vec())),
+        ]));
+        assert!(matches!(
+            Command::from_resp(resp),
+            Err(CommandError::WrongArity(_))
+        ));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"HMGET".to_vec())),
+            RespValue::BulkString(Some(b"user".to_vec())),
+            RespValue::BulkString(Some(b"name".to_vec())),
+            RespValue::BulkString(Some(b"age".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::HMGet { fields, .. } if fields == vec!["name", "age"]));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"HDEL".to_vec())),
+            RespValue::BulkString(Some(b"user".to_vec())),
+        ]));
+        assert!(matches!(
+            Command::from_resp(resp),
+            Err(CommandError::WrongArity(_))
+        ));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"HEXISTS".to_vec())),
+            RespValue::BulkString(Some(b"user".to_vec())),
+            RespValue::BulkString(Some(b"name".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::HExists { key, field } if key == "user" && field == "name"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_rename() {
+        let db = Db::new();
+        db.set("a".to_string(), b"1".to_vec()).await.unwrap();
+        db.set("b".to_string(), b"2".to_vec()).await.unwrap();
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"RENAMENX".to_vec())),
+            RespValue::BulkString(Some(b"a".to_vec())),
+            RespValue::BulkString(Some(b"b".to_vec())),
+        ]));
+        let result = Command::from_resp(resp)
+            .unwrap()
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(result, RespValue::Integer(0));
+
+        let rename = Command::Rename {
+            key: "a".into(),
+            newkey: "b".into(),
+        };
+        let result = rename.execute(&db).await.unwrap();
+        assert_eq!(result, RespValue::SimpleString("OK".to_string()));
+        assert_eq!(db.get("b").await.unwrap(), Some(b"1".to_vec()));
+
+        let rename = Command::Rename {
+            key: "a".into(),
+            newkey: "b".into(),
+        };
+        assert!(matches!(rename.execute(&db).await, Err(DbError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_execute_dump_restore() {
+        let db = Db::new();
+        db.sadd("set", vec![b"x".to_vec(), b"y".to_vec()])
+            .await
+            .unwrap();
+
+        let result = Command::Dump { key: "set".into() }
+            .execute(&db)
+            .await
+            .unwrap();
+        let RespValue::BulkString(Some(payload)) = result else {
+            panic!("expected a bulk string, got {:?}", result);
+        };
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"RESTORE".to_vec())),
+            RespValue::BulkString(Some(b"restored".to_vec())),
+            RespValue::BulkString(Some(b"5000".to_vec())),
+            RespValue::BulkString(Some(payload)),
+        ]));
+        let result = Command::from_resp(resp)
+            .unwrap()
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(result, RespValue::SimpleString("OK".to_string()));
+
+        let mut members = db.smembers("restored").await.unwrap();
+        members.sort();
+        assert_eq!(members, vec![b"x".to_vec(), b"y".to_vec()]);
+        assert!(db.pttl("restored").await.unwrap() > 4000);
+    }
+
+    #[tokio::test]
+    async fn test_execute_zrange_withscores() {
+        let db = Db::new();
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"ZADD".to_vec())),
+            RespValue::BulkString(Some(b"board".to_vec())),
+            RespValue::BulkString(Some(b"1.5".to_vec())),
+            RespValue::BulkString(Some(b"a".to_vec())),
+            RespValue::BulkString(Some(b"-inf".to_vec())),
+            RespValue::BulkString(Some(b"b".to_vec())),
+        ]));
+        let result = Command::from_resp(resp)
+            .unwrap()
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(result, RespValue::Integer(2));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"ZRANGE".to_vec())),
+            RespValue::BulkString(Some(b"board".to_vec())),
+            RespValue::BulkString(Some(b"0".to_vec())),
+            RespValue::BulkString(Some(b"-1".to_vec())),
+            RespValue::BulkString(Some(b"withscores".to_vec())),
+        ]));
+        let result = Command::from_resp(resp)
+            .unwrap()
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"b".to_vec())),
+                RespValue::BulkString(Some(b"-inf".to_vec())),
+                RespValue::BulkString(Some(b"a".to_vec())),
+                RespValue::BulkString(Some(b"1.5".to_vec())),
+            ]))
+        );
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"ZADD".to_vec())),
+            RespValue::BulkString(Some(b"board".to_vec())),
+            RespValue::BulkString(Some(b"nan".to_vec())),
+            RespValue::BulkString(Some(b"c".to_vec())),
+        ]));
+        assert!(Command::from_resp(resp).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_execute_expireat_in_past() {
+        use std::time::SystemTime;
+
+        let db = Db::new();
+        db.set("key".to_string(), b"value".to_vec()).await.unwrap();
+
+        let past = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            - 60;
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"EXPIREAT".to_vec())),
+            RespValue::BulkString(Some(b"key".to_vec())),
+            RespValue::BulkString(Some(past.to_string().into_bytes())),
+        ]));
+        let result = Command::from_resp(resp)
+            .unwrap()
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(result, RespValue::Integer(1));
+        assert_eq!(db.get("key").await.unwrap(), None);
+
+        let result = Command::PTtl { key: "key".into() }
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(result, RespValue::Integer(-2));
+    }
+
+    #[test]
+    fn test_parse_pubsub() {
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"SUBSCRIBE".to_vec())),
+            RespValue::BulkString(Some(b"news".to_vec())),
+            RespValue::BulkString(Some(b"sports".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(cmd.allowed_while_subscribed());
+        assert!(
+            matches!(cmd, Command::Subscribe { channels } if channels == vec!["news", "sports"])
+        );
+
+        let resp = RespValue::Array(Some(vec![RespValue::BulkString(Some(
+            b"UNSUBSCRIBE".to_vec(),
+        ))]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::Unsubscribe { channels } if channels.is_empty()));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"PSUBSCRIBE".to_vec())),
+            RespValue::BulkString(Some(b"news.*".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(cmd.allowed_while_subscribed());
+        assert!(matches!(cmd, Command::PSubscribe { patterns } if patterns == vec!["news.*"]));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"PUBLISH".to_vec())),
+            RespValue::BulkString(Some(b"news".to_vec())),
+            RespValue::BulkString(Some(b"hello".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(!cmd.allowed_while_subscribed());
+        assert!(matches!(cmd, Command::Publish { channel, message }
+            if channel == "news" && message == b"hello"));
+    }
+
+    #[test]
+    fn test_parse_hello() {
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"HELLO".to_vec())),
+            RespValue::BulkString(Some(b"3".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::Hello { protover: Some(3) }));
+
+        let resp = RespValue::Array(Some(vec![RespValue::BulkString(Some(b"HELLO".to_vec()))]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::Hello { protover: None }));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"HELLO".to_vec())),
+            RespValue::BulkString(Some(b"three".to_vec())),
+        ]));
+        assert!(Command::from_resp(resp).is_err());
+    }
+
+    #[test]
+    fn test_parse_client_and_info() {
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"client".to_vec())),
+            RespValue::BulkString(Some(b"list".to_vec())),
+        ]));
+        assert!(matches!(
+            Command::from_resp(resp).unwrap(),
+            Command::ClientList
+        ));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"CLIENT".to_vec())),
+            RespValue::BulkString(Some(b"KILL".to_vec())),
+        ]));
+        assert!(matches!(
+            Command::from_resp(resp),
+            Err(CommandError::InvalidArgument(_))
+        ));
+
+        let resp = RespValue::Array(Some(vec![RespValue::BulkString(Some(b"INFO".to_vec()))]));
+        assert!(matches!(
+            Command::from_resp(resp).unwrap(),
+            Command::Info { section: None }
+        ));
+
+        let resp = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"INFO".to_vec())),
+            RespValue::BulkString(Some(b"Keyspace".to_vec())),
+        ]));
+        let cmd = Command::from_resp(resp).unwrap();
+        assert!(matches!(cmd, Command::Info { section: Some(s) } if s == "keyspace"));
+    }
 }
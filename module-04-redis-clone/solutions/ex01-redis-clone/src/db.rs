@@ -1,44 +1,379 @@
 use crate::error::{DbError, Result};
-use std::collections::{HashMap, HashSet, VecDeque};
+use crate::glob::glob_match;
+use crate::snapshot::{self, SnapshotEntry};
+use crate::sorted_set::SortedSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
+use tracing::debug;
 
 /// The different value types supported by our Redis clone
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Value {
     String(Vec<u8>),
     List(VecDeque<Vec<u8>>),
     Set(HashSet<Vec<u8>>),
     Hash(HashMap<String, Vec<u8>>),
+    SortedSet(SortedSet),
+}
+
+impl Value {
+    /// Name of the type as reported by the TYPE command
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Set(_) => "set",
+            Value::Hash(_) => "hash",
+            Value::SortedSet(_) => "zset",
+        }
+    }
+
+    /// Rough number of bytes the value occupies in memory
+    fn approx_size(&self) -> usize {
+        match self {
+            Value::String(bytes) => bytes.len(),
+            Value::List(list) => list.iter().map(|item| item.len() + ELEMENT_OVERHEAD).sum(),
+            Value::Set(set) => set
+                .iter()
+                .map(|member| member.len() + ELEMENT_OVERHEAD)
+                .sum(),
+            Value::Hash(hash) => hash
+                .iter()
+                .map(|(field, value)| field.len() + value.len() + ELEMENT_OVERHEAD)
+                .sum(),
+            // Each member is held by both the score map and the ordering
+            Value::SortedSet(zset) => zset
+                .iter()
+                .map(|(member, _)| 2 * member.len() + 8 + ELEMENT_OVERHEAD)
+                .sum(),
+        }
+    }
+}
+
+/// Fixed bookkeeping cost charged for every key
+const ENTRY_OVERHEAD: usize = 64;
+/// Bookkeeping cost charged for every element of a collection
+const ELEMENT_OVERHEAD: usize = 16;
+
+/// Options for `Db::sort`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SortOptions {
+    /// Compare elements as strings instead of numbers
+    pub alpha: bool,
+    pub descending: bool,
+    /// Offset and count of the window to return; a negative count means all
+    pub limit: Option<(i64, i64)>,
+}
+
+/// An end of a list, for `Db::lmove`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+impl ListEnd {
+    /// Parse `LEFT` or `RIGHT`, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "LEFT" => Some(ListEnd::Left),
+            "RIGHT" => Some(ListEnd::Right),
+            _ => None,
+        }
+    }
+
+    fn pop(self, list: &mut VecDeque<Vec<u8>>) -> Option<Vec<u8>> {
+        match self {
+            ListEnd::Left => list.pop_front(),
+            ListEnd::Right => list.pop_back(),
+        }
+    }
+
+    fn push(self, list: &mut VecDeque<Vec<u8>>, value: Vec<u8>) {
+        match self {
+            ListEnd::Left => list.push_front(value),
+            ListEnd::Right => list.push_back(value),
+        }
+    }
+}
+
+/// Which keys to evict once `maxmemory` is exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the least recently used key
+    AllKeysLru,
+    /// Evict the key with the nearest expiry, never touching keys without one
+    VolatileTtl,
+}
+
+impl EvictionPolicy {
+    /// Parse a policy by its Redis config name, e.g. `allkeys-lru`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "allkeys-lru" => Some(EvictionPolicy::AllKeysLru),
+            "volatile-ttl" => Some(EvictionPolicy::VolatileTtl),
+            _ => None,
+        }
+    }
 }
 
 /// An entry in the database with optional expiration
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 struct Entry {
     value: Value,
     expires_at: Option<Instant>,
+    /// Approximate memory used by the key and value
+    size: usize,
+    /// Logical time of the last read or write, for LRU eviction
+    last_access: AtomicU64,
 }
 
 impl Entry {
+    fn new(value: Value, expires_at: Option<Instant>) -> Self {
+        Entry {
+            value,
+            expires_at,
+            size: 0,
+            last_access: AtomicU64::new(0),
+        }
+    }
+
     fn is_expired(&self) -> bool {
         self.expires_at.map_or(false, |exp| Instant::now() >= exp)
     }
 }
 
+/// The key/value map plus the bookkeeping needed to enforce `maxmemory`
+///
+/// Derefs to the underlying map. `get`, `get_mut`, `insert` and `remove`
+/// are shadowed to track recency and memory use, and values modified in
+/// place must be re-measured with `resize`.
+#[derive(Debug, Default)]
+struct Keyspace {
+    entries: HashMap<String, Entry>,
+    /// Every key ordered by `scan_hash`, so SCAN can resume without sorting
+    scan_order: BTreeSet<(u64, String)>,
+    used_memory: usize,
+    maxmemory: Option<usize>,
+    policy: Option<EvictionPolicy>,
+    clock: AtomicU64,
+}
+
+impl Deref for Keyspace {
+    type Target = HashMap<String, Entry>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl DerefMut for Keyspace {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.entries
+    }
+}
+
+impl Keyspace {
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Look up a key, marking it as recently used
+    fn get(&self, key: &str) -> Option<&Entry> {
+        let entry = self.entries.get(key)?;
+        entry.last_access.store(self.tick(), Ordering::Relaxed);
+        Some(entry)
+    }
+
+    /// Look up a key for modification, marking it as recently used
+    ///
+    /// Call `resize` once the value has been changed.
+    fn get_mut(&mut self, key: &str) -> Option<&mut Entry> {
+        let tick = self.tick();
+        let entry = self.entries.get_mut(key)?;
+        *entry.last_access.get_mut() = tick;
+        Some(entry)
+    }
+
+    fn insert(&mut self, key: String, mut entry: Entry) -> Option<Entry> {
+        entry.size = ENTRY_OVERHEAD + key.len() + entry.value.approx_size();
+        *entry.last_access.get_mut() = self.tick();
+        self.used_memory += entry.size;
+
+        let old = self.entries.insert(key.clone(), entry);
+        match &old {
+            Some(old) => self.used_memory -= old.size,
+            None => {
+                self.scan_order.insert((scan_hash(&key), key.clone()));
+            }
+        }
+
+        self.evict(&key);
+        old
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let old = self.entries.remove(key);
+        if let Some(old) = &old {
+            self.used_memory -= old.size;
+            self.scan_order.remove(&(scan_hash(key), key.to_string()));
+        }
+        old
+    }
+
+    /// The live list at `key`, or `None` if it's missing, expired or another type
+    ///
+    /// Call `resize` once the list has been changed.
+    fn list_mut(&mut self, key: &str) -> Option<&mut VecDeque<Vec<u8>>> {
+        match self.get_mut(key) {
+            Some(entry) if !entry.is_expired() => match &mut entry.value {
+                Value::List(list) => Some(list),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.scan_order.clear();
+        self.used_memory = 0;
+    }
+
+    /// Re-measure a value that was modified in place
+    fn resize(&mut self, key: &str) {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return;
+        };
+
+        let size = ENTRY_OVERHEAD + key.len() + entry.value.approx_size();
+        self.used_memory = self.used_memory - entry.size + size;
+        entry.size = size;
+
+        self.evict(key);
+    }
+
+    /// Evict keys per the policy until memory use is back under the limit
+    ///
+    /// `keep` is the key that was just written and is never evicted. If
+    /// nothing else can be evicted the limit is allowed to overshoot.
+    fn evict(&mut self, keep: &str) {
+        let (Some(maxmemory), Some(policy)) = (self.maxmemory, self.policy) else {
+            return;
+        };
+
+        while self.used_memory > maxmemory {
+            let candidates = self.entries.iter().filter(|(key, _)| key.as_str() != keep);
+
+            let victim = match policy {
+                EvictionPolicy::AllKeysLru => candidates
+                    .min_by_key(|(_, entry)| entry.last_access.load(Ordering::Relaxed))
+                    .map(|(key, _)| key.clone()),
+                EvictionPolicy::VolatileTtl => candidates
+                    .filter_map(|(key, entry)| entry.expires_at.map(|at| (key, at)))
+                    .min_by_key(|(_, at)| *at)
+                    .map(|(key, _)| key.clone()),
+            };
+
+            match victim {
+                Some(key) => {
+                    debug!("Evicting {} to stay under maxmemory", key);
+                    self.remove(&key);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+/// Stable position of a key in SCAN order
+fn scan_hash(key: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Version of the DUMP payload format
+const DUMP_VERSION: u16 = 1;
+
+/// Serialize a value as `bincode(value) | version (u16 LE) | checksum (u64 LE)`
+///
+/// The checksum is FNV-1a over everything before it, so a payload mangled
+/// in transit is rejected by RESTORE instead of restoring garbage.
+fn encode_dump(value: &Value) -> Result<Vec<u8>> {
+    let mut buf = bincode::serialize(value)?;
+    buf.extend_from_slice(&DUMP_VERSION.to_le_bytes());
+    let checksum = fnv1a(&buf);
+    buf.extend_from_slice(&checksum.to_le_bytes());
+    Ok(buf)
+}
+
+/// Decode a payload produced by [`encode_dump`]
+fn decode_dump(bytes: &[u8]) -> Result<Value> {
+    let Some(body_len) = bytes.len().checked_sub(8) else {
+        return Err(DbError::InvalidDump);
+    };
+    let (body, checksum) = bytes.split_at(body_len);
+    if fnv1a(body).to_le_bytes() != checksum || body.len() < 2 {
+        return Err(DbError::InvalidDump);
+    }
+
+    let (value, version) = body.split_at(body.len() - 2);
+    if version != DUMP_VERSION.to_le_bytes() {
+        return Err(DbError::InvalidDump);
+    }
+    bincode::deserialize(value).map_err(|_| DbError::InvalidDump)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 /// The main database structure
 #[derive(Clone)]
 pub struct Db {
-    data: Arc<RwLock<HashMap<String, Entry>>>,
+    data: Arc<RwLock<Keyspace>>,
 }
 
 impl Db {
     pub fn new() -> Self {
         Db {
-            data: Arc::new(RwLock::new(HashMap::new())),
+            data: Arc::new(RwLock::new(Keyspace::default())),
         }
     }
 
+    /// Cap memory use at roughly `maxmemory` bytes, evicting keys per `policy`
+    pub async fn set_maxmemory(&self, maxmemory: usize, policy: EvictionPolicy) {
+        let mut data = self.data.write().await;
+        data.maxmemory = Some(maxmemory);
+        data.policy = Some(policy);
+        data.evict("");
+    }
+
+    /// Approximate bytes used by all keys and values
+    pub async fn used_memory(&self) -> usize {
+        self.data.read().await.used_memory
+    }
+
+    /// Number of live keys, and how many of those have an expiry set
+    pub async fn key_counts(&self) -> (usize, usize) {
+        let data = self.data.read().await;
+        data.values()
+            .filter(|entry| !entry.is_expired())
+            .fold((0, 0), |(keys, expires), entry| {
+                (keys + 1, expires + entry.expires_at.is_some() as usize)
+            })
+    }
+
     /// Spawn a background task to clean up expired keys
     pub fn spawn_expiration_task(self) {
         tokio::spawn(async move {
@@ -80,17 +415,113 @@ impl Db {
     }
 
     pub async fn set(&self, key: String, value: Vec<u8>) -> Result<()> {
+        let mut data = self.data.write().await;
+        data.insert(key, Entry::new(Value::String(value), None));
+        Ok(())
+    }
+
+    /// Append to a string, creating it if absent, and return the new length
+    pub async fn append(&self, key: &str, value: &[u8]) -> Result<usize> {
+        let mut data = self.data.write().await;
+
+        match data.get_mut(key) {
+            Some(entry) if !entry.is_expired() => match &mut entry.value {
+                Value::String(bytes) => {
+                    bytes.extend_from_slice(value);
+                    let len = bytes.len();
+                    data.resize(key);
+                    Ok(len)
+                }
+                _ => Err(DbError::WrongType),
+            },
+            _ => {
+                data.insert(
+                    key.to_string(),
+                    Entry::new(Value::String(value.to_vec()), None),
+                );
+                Ok(value.len())
+            }
+        }
+    }
+
+    pub async fn strlen(&self, key: &str) -> Result<usize> {
+        let data = self.data.read().await;
+
+        match data.get(key) {
+            Some(entry) if !entry.is_expired() => match &entry.value {
+                Value::String(bytes) => Ok(bytes.len()),
+                _ => Err(DbError::WrongType),
+            },
+            _ => Ok(0),
+        }
+    }
+
+    /// Set a value only if the key does not already exist
+    pub async fn set_nx(&self, key: String, value: Vec<u8>) -> Result<bool> {
+        let mut data = self.data.write().await;
+
+        if matches!(data.get(&key), Some(entry) if !entry.is_expired()) {
+            return Ok(false);
+        }
+
+        data.insert(key, Entry::new(Value::String(value), None));
+        Ok(true)
+    }
+
+    /// Set a value and its expiry in one step
+    pub async fn set_ex(&self, key: String, value: Vec<u8>, ttl: Duration) -> Result<()> {
         let mut data = self.data.write().await;
         data.insert(
             key,
-            Entry {
-                value: Value::String(value),
-                expires_at: None,
-            },
+            Entry::new(Value::String(value), Some(Instant::now() + ttl)),
         );
         Ok(())
     }
 
+    /// Set a new value and return the previous one
+    pub async fn getset(&self, key: String, value: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        let mut data = self.data.write().await;
+
+        let old = match data.get(&key) {
+            Some(entry) if !entry.is_expired() => match &entry.value {
+                Value::String(bytes) => Some(bytes.clone()),
+                _ => return Err(DbError::WrongType),
+            },
+            _ => None,
+        };
+
+        data.insert(key, Entry::new(Value::String(value), None));
+        Ok(old)
+    }
+
+    /// Add `delta` to the integer stored at `key`, treating a missing key as 0
+    pub async fn incr_by(&self, key: &str, delta: i64) -> Result<i64> {
+        let mut data = self.data.write().await;
+
+        match data.get_mut(key) {
+            Some(entry) if !entry.is_expired() => match &mut entry.value {
+                Value::String(bytes) => {
+                    let current = std::str::from_utf8(bytes)
+                        .ok()
+                        .and_then(|s| s.parse::<i64>().ok())
+                        .ok_or(DbError::NotInteger)?;
+                    let new_value = current.checked_add(delta).ok_or(DbError::NotInteger)?;
+                    *bytes = new_value.to_string().into_bytes();
+                    data.resize(key);
+                    Ok(new_value)
+                }
+                _ => Err(DbError::WrongType),
+            },
+            _ => {
+                data.insert(
+                    key.to_string(),
+                    Entry::new(Value::String(delta.to_string().into_bytes()), None),
+                );
+                Ok(delta)
+            }
+        }
+    }
+
     pub async fn del(&self, key: &str) -> Result<bool> {
         let mut data = self.data.write().await;
         Ok(data.remove(key).is_some())
@@ -104,6 +535,74 @@ impl Db {
         }
     }
 
+    /// Get the type of the value stored at `key`, or "none" if absent
+    pub async fn key_type(&self, key: &str) -> Result<&'static str> {
+        let data = self.data.read().await;
+
+        match data.get(key) {
+            Some(entry) if !entry.is_expired() => Ok(entry.value.type_name()),
+            _ => Ok("none"),
+        }
+    }
+
+    /// Get all live keys matching a glob-style pattern
+    pub async fn keys(&self, pattern: &str) -> Result<Vec<String>> {
+        let data = self.data.read().await;
+
+        Ok(data
+            .iter()
+            .filter(|(key, entry)| {
+                !entry.is_expired() && glob_match(pattern.as_bytes(), key.as_bytes())
+            })
+            .map(|(key, _)| key.clone())
+            .collect())
+    }
+
+    /// Incrementally iterate the keyspace, returning the next cursor and a batch of keys
+    ///
+    /// Keys are visited in order of a fixed hash of their name, and the
+    /// cursor is the hash to resume from, so a key present for the whole
+    /// scan is returned exactly once no matter how the map is modified in
+    /// between calls. `count` bounds how many keys are examined per call;
+    /// `pattern` is applied afterwards, so a batch may come back empty
+    /// before the scan is complete. A next cursor of 0 ends the scan.
+    ///
+    /// Keys are kept ordered by hash as they are added and removed, so a
+    /// call costs O(log N + count) rather than sorting the keyspace.
+    pub async fn scan(
+        &self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+    ) -> Result<(u64, Vec<String>)> {
+        let data = self.data.read().await;
+        let count = count.max(1);
+
+        let mut keys = Vec::new();
+        let mut last_hash = None;
+
+        let batch = data.scan_order.range((cursor, String::new())..);
+        for (examined, (hash, key)) in batch.enumerate() {
+            // Never split keys sharing a hash across batches, or the cursor
+            // would skip some. The next cursor is the first unvisited hash,
+            // which is above every visited one and so never 0.
+            if examined >= count && last_hash != Some(*hash) {
+                return Ok((*hash, keys));
+            }
+            last_hash = Some(*hash);
+
+            let live = data
+                .entries
+                .get(key)
+                .is_some_and(|entry| !entry.is_expired());
+            if live && pattern.is_none_or(|p| glob_match(p.as_bytes(), key.as_bytes())) {
+                keys.push(key.clone());
+            }
+        }
+
+        Ok((0, keys))
+    }
+
     pub async fn expire(&self, key: &str, duration: Duration) -> Result<bool> {
         let mut data = self.data.write().await;
 
@@ -118,6 +617,138 @@ impl Db {
         }
     }
 
+    /// Expire a key at an absolute wall-clock time
+    ///
+    /// A time in the past deletes the key immediately.
+    pub async fn expire_at(&self, key: &str, at: SystemTime) -> Result<bool> {
+        let mut data = self.data.write().await;
+
+        match data.get_mut(key) {
+            Some(entry) if !entry.is_expired() => {
+                match at.duration_since(SystemTime::now()) {
+                    Ok(remaining) if !remaining.is_zero() => {
+                        entry.expires_at = Some(Instant::now() + remaining);
+                    }
+                    _ => {
+                        data.remove(key);
+                    }
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Remove the expiry from a key, returning whether one was removed
+    pub async fn persist(&self, key: &str) -> Result<bool> {
+        let mut data = self.data.write().await;
+
+        match data.get_mut(key) {
+            Some(entry) if !entry.is_expired() => Ok(entry.expires_at.take().is_some()),
+            _ => Ok(false),
+        }
+    }
+
+    /// Move the value at `key` to `newkey`, overwriting whatever was there
+    ///
+    /// The whole entry moves, so the type and any remaining expiry are kept.
+    pub async fn rename(&self, key: &str, newkey: &str) -> Result<()> {
+        let mut data = self.data.write().await;
+
+        let entry = match data.remove(key) {
+            Some(entry) if !entry.is_expired() => entry,
+            _ => return Err(DbError::NotFound),
+        };
+        data.insert(newkey.to_string(), entry);
+        Ok(())
+    }
+
+    /// Like `rename`, but only if `newkey` doesn't exist; returns whether it moved
+    pub async fn rename_nx(&self, key: &str, newkey: &str) -> Result<bool> {
+        let mut data = self.data.write().await;
+
+        match data.get(key) {
+            Some(entry) if !entry.is_expired() => {}
+            _ => return Err(DbError::NotFound),
+        }
+        if matches!(data.get(newkey), Some(entry) if !entry.is_expired()) {
+            return Ok(false);
+        }
+
+        let entry = data.remove(key).expect("checked above");
+        data.insert(newkey.to_string(), entry);
+        Ok(true)
+    }
+
+    /// Copy the value and expiry at `src` to `dst`, returning whether it was copied
+    ///
+    /// Nothing is copied if `src` is missing, or if `dst` exists and
+    /// `replace` is false. The copy shares no data with the original.
+    pub async fn copy(&self, src: &str, dst: &str, replace: bool) -> Result<bool> {
+        let mut data = self.data.write().await;
+
+        let entry = match data.get(src) {
+            Some(entry) if !entry.is_expired() => Entry::new(entry.value.clone(), entry.expires_at),
+            _ => return Ok(false),
+        };
+        if !replace && matches!(data.get(dst), Some(existing) if !existing.is_expired()) {
+            return Ok(false);
+        }
+
+        data.insert(dst.to_string(), entry);
+        Ok(true)
+    }
+
+    /// Serialize the value at `key` for RESTORE, or `None` if it's missing
+    ///
+    /// The expiry isn't included; RESTORE takes it as an argument.
+    pub async fn dump(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let data = self.data.read().await;
+
+        match data.get(key) {
+            Some(entry) if !entry.is_expired() => Ok(Some(encode_dump(&entry.value)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Create `key` from a DUMP payload, expiring after `ttl` if given
+    ///
+    /// Fails with `BusyKey` if the key exists and `replace` is false.
+    pub async fn restore(
+        &self,
+        key: &str,
+        ttl: Option<Duration>,
+        payload: &[u8],
+        replace: bool,
+    ) -> Result<()> {
+        let value = decode_dump(payload)?;
+        let mut data = self.data.write().await;
+
+        if !replace && matches!(data.get(key), Some(existing) if !existing.is_expired()) {
+            return Err(DbError::BusyKey);
+        }
+
+        let expires_at = ttl.map(|ttl| Instant::now() + ttl);
+        data.insert(key.to_string(), Entry::new(value, expires_at));
+        Ok(())
+    }
+
+    /// Remaining time to live in milliseconds (-1 without expiry, -2 if missing)
+    pub async fn pttl(&self, key: &str) -> Result<i64> {
+        let data = self.data.read().await;
+
+        match data.get(key) {
+            Some(entry) if !entry.is_expired() => match entry.expires_at {
+                Some(expires_at) => {
+                    let remaining = expires_at.saturating_duration_since(Instant::now());
+                    Ok(remaining.as_millis() as i64)
+                }
+                None => Ok(-1),
+            },
+            _ => Ok(-2),
+        }
+    }
+
     pub async fn ttl(&self, key: &str) -> Result<i64> {
         let data = self.data.read().await;
 
@@ -149,7 +780,9 @@ impl Db {
                     for value in values.into_iter().rev() {
                         list.push_front(value);
                     }
-                    Ok(list.len())
+                    let len = list.len();
+                    data.resize(key);
+                    Ok(len)
                 }
                 _ => Err(DbError::WrongType),
             },
@@ -159,13 +792,7 @@ impl Db {
                     list.push_front(value);
                 }
                 let len = list.len();
-                data.insert(
-                    key.to_string(),
-                    Entry {
-                        value: Value::List(list),
-                        expires_at: None,
-                    },
-                );
+                data.insert(key.to_string(), Entry::new(Value::List(list), None));
                 Ok(len)
             }
         }
@@ -180,7 +807,9 @@ impl Db {
                     for value in values {
                         list.push_back(value);
                     }
-                    Ok(list.len())
+                    let len = list.len();
+                    data.resize(key);
+                    Ok(len)
                 }
                 _ => Err(DbError::WrongType),
             },
@@ -190,13 +819,7 @@ impl Db {
                     list.push_back(value);
                 }
                 let len = list.len();
-                data.insert(
-                    key.to_string(),
-                    Entry {
-                        value: Value::List(list),
-                        expires_at: None,
-                    },
-                );
+                data.insert(key.to_string(), Entry::new(Value::List(list), None));
                 Ok(len)
             }
         }
@@ -214,6 +837,11 @@ impl Db {
                             result.push(value);
                         }
                     }
+                    if list.is_empty() {
+                        data.remove(key);
+                    } else {
+                        data.resize(key);
+                    }
                     if result.is_empty() {
                         Ok(None)
                     } else {
@@ -238,6 +866,11 @@ impl Db {
                             result.push(value);
                         }
                     }
+                    if list.is_empty() {
+                        data.remove(key);
+                    } else {
+                        data.resize(key);
+                    }
                     if result.is_empty() {
                         Ok(None)
                     } else {
@@ -278,6 +911,62 @@ impl Db {
         }
     }
 
+    /// Atomically pop an element from one end of `src` and push it onto one end of `dst`
+    ///
+    /// Returns the moved element, or `None` if `src` is empty. `src` is
+    /// deleted once empty, and `dst` is created if missing. With `src` and
+    /// `dst` the same key the list is rotated.
+    pub async fn lmove(
+        &self,
+        src: &str,
+        dst: &str,
+        from: ListEnd,
+        to: ListEnd,
+    ) -> Result<Option<Vec<u8>>> {
+        let mut data = self.data.write().await;
+
+        // Check both types before changing anything
+        match data.get(src) {
+            Some(entry) if !entry.is_expired() => {
+                if !matches!(entry.value, Value::List(_)) {
+                    return Err(DbError::WrongType);
+                }
+            }
+            _ => return Ok(None),
+        }
+        if matches!(data.get(dst), Some(entry)
+            if !entry.is_expired() && !matches!(entry.value, Value::List(_)))
+        {
+            return Err(DbError::WrongType);
+        }
+
+        let Some(value) = data.list_mut(src).and_then(|list| from.pop(list)) else {
+            return Ok(None);
+        };
+
+        match data.list_mut(dst) {
+            Some(list) => {
+                to.push(list, value.clone());
+                data.resize(dst);
+            }
+            None => {
+                data.insert(
+                    dst.to_string(),
+                    Entry::new(Value::List(VecDeque::from([value.clone()])), None),
+                );
+            }
+        }
+
+        // Pushed first so a single-element list rotated onto itself survives
+        if data.list_mut(src).is_some_and(|list| list.is_empty()) {
+            data.remove(src);
+        } else {
+            data.resize(src);
+        }
+
+        Ok(Some(value))
+    }
+
     pub async fn llen(&self, key: &str) -> Result<usize> {
         let data = self.data.read().await;
 
@@ -304,6 +993,7 @@ impl Db {
                             count += 1;
                         }
                     }
+                    data.resize(key);
                     Ok(count)
                 }
                 _ => Err(DbError::WrongType),
@@ -314,13 +1004,7 @@ impl Db {
                 for member in members {
                     set.insert(member);
                 }
-                data.insert(
-                    key.to_string(),
-                    Entry {
-                        value: Value::Set(set),
-                        expires_at: None,
-                    },
-                );
+                data.insert(key.to_string(), Entry::new(Value::Set(set), None));
                 Ok(count)
             }
         }
@@ -362,6 +1046,119 @@ impl Db {
         }
     }
 
+    pub async fn srem(&self, key: &str, members: Vec<Vec<u8>>) -> Result<usize> {
+        let mut data = self.data.write().await;
+
+        match data.get_mut(key) {
+            Some(entry) if !entry.is_expired() => match &mut entry.value {
+                Value::Set(set) => {
+                    let count = members.iter().filter(|m| set.remove(*m)).count();
+                    if set.is_empty() {
+                        data.remove(key);
+                    } else {
+                        data.resize(key);
+                    }
+                    Ok(count)
+                }
+                _ => Err(DbError::WrongType),
+            },
+            _ => Ok(0),
+        }
+    }
+
+    pub async fn sinter(&self, keys: &[String]) -> Result<Vec<Vec<u8>>> {
+        let data = self.data.read().await;
+        let sets = collect_sets(&data, keys)?;
+
+        let (first, rest) = match sets.split_first() {
+            Some(split) => split,
+            None => return Ok(Vec::new()),
+        };
+
+        Ok(first
+            .iter()
+            .filter(|member| rest.iter().all(|set| set.contains(*member)))
+            .cloned()
+            .collect())
+    }
+
+    pub async fn sunion(&self, keys: &[String]) -> Result<Vec<Vec<u8>>> {
+        let data = self.data.read().await;
+        let sets = collect_sets(&data, keys)?;
+
+        let union: HashSet<&Vec<u8>> = sets.iter().flat_map(|set| set.iter()).collect();
+        Ok(union.into_iter().cloned().collect())
+    }
+
+    pub async fn sdiff(&self, keys: &[String]) -> Result<Vec<Vec<u8>>> {
+        let data = self.data.read().await;
+        let sets = collect_sets(&data, keys)?;
+
+        let (first, rest) = match sets.split_first() {
+            Some(split) => split,
+            None => return Ok(Vec::new()),
+        };
+
+        Ok(first
+            .iter()
+            .filter(|member| !rest.iter().any(|set| set.contains(*member)))
+            .cloned()
+            .collect())
+    }
+
+    /// Sort the elements of a list or set, leaving the stored value untouched
+    pub async fn sort(&self, key: &str, options: SortOptions) -> Result<Vec<Vec<u8>>> {
+        let mut elements: Vec<Vec<u8>> = {
+            let data = self.data.read().await;
+            match data.get(key) {
+                Some(entry) if !entry.is_expired() => match &entry.value {
+                    Value::List(list) => list.iter().cloned().collect(),
+                    Value::Set(set) => set.iter().cloned().collect(),
+                    Value::SortedSet(zset) => {
+                        zset.iter().map(|(member, _)| member.to_vec()).collect()
+                    }
+                    _ => return Err(DbError::WrongType),
+                },
+                _ => return Ok(Vec::new()),
+            }
+        };
+
+        if options.alpha {
+            elements.sort();
+        } else {
+            let mut scored = elements
+                .into_iter()
+                .map(|element| {
+                    let score = std::str::from_utf8(&element)
+                        .ok()
+                        .and_then(|s| s.trim().parse::<f64>().ok())
+                        .filter(|score| !score.is_nan())
+                        .ok_or(DbError::NotFloat)?;
+                    Ok((score, element))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            // Equal scores fall back to comparing the raw bytes, like Redis
+            scored.sort_by(|(a, x), (b, y)| a.total_cmp(b).then_with(|| x.cmp(y)));
+            elements = scored.into_iter().map(|(_, element)| element).collect();
+        }
+
+        if options.descending {
+            elements.reverse();
+        }
+
+        if let Some((offset, count)) = options.limit {
+            let offset = offset.max(0) as usize;
+            let count = if count < 0 {
+                usize::MAX
+            } else {
+                count as usize
+            };
+            elements = elements.into_iter().skip(offset).take(count).collect();
+        }
+
+        Ok(elements)
+    }
+
     // Hash operations
 
     pub async fn hset(&self, key: &str, field: String, value: Vec<u8>) -> Result<bool> {
@@ -369,24 +1166,64 @@ impl Db {
 
         match data.get_mut(key) {
             Some(entry) if !entry.is_expired() => match &mut entry.value {
-                Value::Hash(hash) => Ok(hash.insert(field, value).is_none()),
+                Value::Hash(hash) => {
+                    let added = hash.insert(field, value).is_none();
+                    data.resize(key);
+                    Ok(added)
+                }
                 _ => Err(DbError::WrongType),
             },
             _ => {
                 let mut hash = HashMap::new();
                 hash.insert(field, value);
+                data.insert(key.to_string(), Entry::new(Value::Hash(hash), None));
+                Ok(true)
+            }
+        }
+    }
+
+    pub async fn hmset(&self, key: &str, pairs: Vec<(String, Vec<u8>)>) -> Result<()> {
+        let mut data = self.data.write().await;
+
+        match data.get_mut(key) {
+            Some(entry) if !entry.is_expired() => match &mut entry.value {
+                Value::Hash(hash) => {
+                    hash.extend(pairs);
+                    data.resize(key);
+                    Ok(())
+                }
+                _ => Err(DbError::WrongType),
+            },
+            _ => {
                 data.insert(
                     key.to_string(),
-                    Entry {
-                        value: Value::Hash(hash),
-                        expires_at: None,
-                    },
+                    Entry::new(Value::Hash(pairs.into_iter().collect()), None),
                 );
-                Ok(true)
+                Ok(())
             }
         }
     }
 
+    pub async fn hdel(&self, key: &str, fields: &[String]) -> Result<usize> {
+        let mut data = self.data.write().await;
+
+        match data.get_mut(key) {
+            Some(entry) if !entry.is_expired() => match &mut entry.value {
+                Value::Hash(hash) => {
+                    let count = fields.iter().filter(|f| hash.remove(*f).is_some()).count();
+                    if hash.is_empty() {
+                        data.remove(key);
+                    } else {
+                        data.resize(key);
+                    }
+                    Ok(count)
+                }
+                _ => Err(DbError::WrongType),
+            },
+            _ => Ok(0),
+        }
+    }
+
     pub async fn hget(&self, key: &str, field: &str) -> Result<Option<Vec<u8>>> {
         let data = self.data.read().await;
 
@@ -399,6 +1236,30 @@ impl Db {
         }
     }
 
+    pub async fn hmget(&self, key: &str, fields: &[String]) -> Result<Vec<Option<Vec<u8>>>> {
+        let data = self.data.read().await;
+
+        match data.get(key) {
+            Some(entry) if !entry.is_expired() => match &entry.value {
+                Value::Hash(hash) => Ok(fields.iter().map(|f| hash.get(f).cloned()).collect()),
+                _ => Err(DbError::WrongType),
+            },
+            _ => Ok(vec![None; fields.len()]),
+        }
+    }
+
+    pub async fn hexists(&self, key: &str, field: &str) -> Result<bool> {
+        let data = self.data.read().await;
+
+        match data.get(key) {
+            Some(entry) if !entry.is_expired() => match &entry.value {
+                Value::Hash(hash) => Ok(hash.contains_key(field)),
+                _ => Err(DbError::WrongType),
+            },
+            _ => Ok(false),
+        }
+    }
+
     pub async fn hgetall(&self, key: &str) -> Result<HashMap<String, Vec<u8>>> {
         let data = self.data.read().await;
 
@@ -422,6 +1283,192 @@ impl Db {
             _ => Ok(0),
         }
     }
+
+    // Sorted set operations
+
+    /// Add members with their scores, updating the score of existing ones
+    ///
+    /// Returns the number of members that were new.
+    pub async fn zadd(&self, key: &str, members: Vec<(f64, Vec<u8>)>) -> Result<usize> {
+        let mut data = self.data.write().await;
+
+        match data.get_mut(key) {
+            Some(entry) if !entry.is_expired() => match &mut entry.value {
+                Value::SortedSet(zset) => {
+                    let count = members
+                        .into_iter()
+                        .filter(|(score, member)| zset.insert(member.clone(), *score))
+                        .count();
+                    data.resize(key);
+                    Ok(count)
+                }
+                _ => Err(DbError::WrongType),
+            },
+            _ => {
+                let mut zset = SortedSet::new();
+                for (score, member) in members {
+                    zset.insert(member, score);
+                }
+                let count = zset.len();
+                data.insert(key.to_string(), Entry::new(Value::SortedSet(zset), None));
+                Ok(count)
+            }
+        }
+    }
+
+    pub async fn zscore(&self, key: &str, member: &[u8]) -> Result<Option<f64>> {
+        let data = self.data.read().await;
+
+        match data.get(key) {
+            Some(entry) if !entry.is_expired() => match &entry.value {
+                Value::SortedSet(zset) => Ok(zset.score(member)),
+                _ => Err(DbError::WrongType),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Members between two ranks (inclusive, negative counting from the end) with their scores
+    pub async fn zrange(&self, key: &str, start: i64, stop: i64) -> Result<Vec<(Vec<u8>, f64)>> {
+        let data = self.data.read().await;
+
+        match data.get(key) {
+            Some(entry) if !entry.is_expired() => match &entry.value {
+                Value::SortedSet(zset) => {
+                    let len = zset.len() as i64;
+                    let start = normalize_index(start, len);
+                    let stop = normalize_index(stop, len);
+
+                    if start > stop || start >= len {
+                        return Ok(Vec::new());
+                    }
+
+                    Ok(zset
+                        .iter()
+                        .skip(start as usize)
+                        .take((stop - start + 1) as usize)
+                        .map(|(member, score)| (member.to_vec(), score))
+                        .collect())
+                }
+                _ => Err(DbError::WrongType),
+            },
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Zero-based rank of a member, lowest score first
+    pub async fn zrank(&self, key: &str, member: &[u8]) -> Result<Option<usize>> {
+        let data = self.data.read().await;
+
+        match data.get(key) {
+            Some(entry) if !entry.is_expired() => match &entry.value {
+                Value::SortedSet(zset) => Ok(zset.rank(member)),
+                _ => Err(DbError::WrongType),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    pub async fn zrem(&self, key: &str, members: Vec<Vec<u8>>) -> Result<usize> {
+        let mut data = self.data.write().await;
+
+        match data.get_mut(key) {
+            Some(entry) if !entry.is_expired() => match &mut entry.value {
+                Value::SortedSet(zset) => {
+                    let count = members.iter().filter(|m| zset.remove(m)).count();
+                    if zset.is_empty() {
+                        data.remove(key);
+                    } else {
+                        data.resize(key);
+                    }
+                    Ok(count)
+                }
+                _ => Err(DbError::WrongType),
+            },
+            _ => Ok(0),
+        }
+    }
+
+    // Persistence
+
+    /// Write every live key to a snapshot file, returning the key count
+    pub async fn save_snapshot(&self, path: &Path) -> Result<usize> {
+        let entries = self.snapshot_entries().await;
+        snapshot::write_file(path, &entries).await?;
+        Ok(entries.len())
+    }
+
+    /// Copy out every live key, with expiries as wall-clock times
+    pub async fn snapshot_entries(&self) -> Vec<SnapshotEntry> {
+        let data = self.data.read().await;
+        let now = Instant::now();
+        let wall_now = SystemTime::now();
+
+        data.iter()
+            .filter(|(_, entry)| !entry.is_expired())
+            .map(|(key, entry)| SnapshotEntry {
+                key: key.clone(),
+                value: entry.value.clone(),
+                expires_at_ms: entry.expires_at.map(|exp| {
+                    let at = wall_now + exp.saturating_duration_since(now);
+                    at.duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_millis() as u64
+                }),
+            })
+            .collect()
+    }
+
+    /// Remove every key
+    pub async fn clear(&self) {
+        self.data.write().await.clear();
+    }
+
+    /// Replace the database contents with a snapshot file
+    ///
+    /// Keys whose expiry passed while the server was down are skipped.
+    /// Returns the number of keys loaded.
+    pub async fn load_snapshot(&self, path: &Path) -> Result<usize> {
+        let entries = snapshot::read_file(path).await?;
+        let now = Instant::now();
+        let wall_now = SystemTime::now();
+
+        let mut loaded = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let expires_at = match entry.expires_at_ms {
+                Some(ms) => {
+                    let at = UNIX_EPOCH + Duration::from_millis(ms);
+                    match at.duration_since(wall_now) {
+                        Ok(remaining) if !remaining.is_zero() => Some(now + remaining),
+                        _ => continue,
+                    }
+                }
+                None => None,
+            };
+
+            loaded.push((entry.key, Entry::new(entry.value, expires_at)));
+        }
+
+        let mut data = self.data.write().await;
+        data.clear();
+        for (key, entry) in loaded {
+            data.insert(key, entry);
+        }
+        Ok(data.len())
+    }
+}
+
+/// Look up the sets stored at `keys`, treating missing keys as empty sets
+fn collect_sets(data: &HashMap<String, Entry>, keys: &[String]) -> Result<Vec<HashSet<Vec<u8>>>> {
+    keys.iter()
+        .map(|key| match data.get(key) {
+            Some(entry) if !entry.is_expired() => match &entry.value {
+                Value::Set(set) => Ok(set.clone()),
+                _ => Err(DbError::WrongType),
+            },
+            _ => Ok(HashSet::new()),
+        })
+        .collect()
 }
 
 /// Normalize a Redis-style index (supports negative indices)
@@ -470,103 +1517,671 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_expire() {
+    async fn test_append_new_key() {
         let db = Db::new();
-        db.set("key1".to_string(), b"value1".to_vec())
-            .await
-            .unwrap();
-        db.expire("key1", Duration::from_millis(100))
+
+        let len = db.append("greeting", b"hello").await.unwrap();
+        db.set("expected".to_string(), b"hello".to_vec())
             .await
             .unwrap();
 
-        tokio::time::sleep(Duration::from_millis(150)).await;
-
-        let value = db.get("key1").await.unwrap();
-        assert_eq!(value, None);
+        assert_eq!(len, db.strlen("expected").await.unwrap());
+        assert_eq!(
+            db.get("greeting").await.unwrap(),
+            db.get("expected").await.unwrap()
+        );
     }
 
     #[tokio::test]
-    async fn test_ttl() {
+    async fn test_append_existing_key() {
         let db = Db::new();
-        db.set("key1".to_string(), b"value1".to_vec())
+        db.set("greeting".to_string(), b"hello".to_vec())
             .await
             .unwrap();
-        db.expire("key1", Duration::from_secs(10)).await.unwrap();
 
-        let ttl = db.ttl("key1").await.unwrap();
-        assert!(ttl > 0 && ttl <= 10);
+        let len = db.append("greeting", b", world").await.unwrap();
+        assert_eq!(len, 12);
+        assert_eq!(
+            db.get("greeting").await.unwrap(),
+            Some(b"hello, world".to_vec())
+        );
+        assert_eq!(db.strlen("greeting").await.unwrap(), 12);
+        assert_eq!(db.strlen("missing").await.unwrap(), 0);
     }
 
     #[tokio::test]
-    async fn test_lpush_rpush() {
+    async fn test_strlen_wrong_type() {
         let db = Db::new();
-        db.lpush("mylist", vec![b"world".to_vec()]).await.unwrap();
-        db.lpush("mylist", vec![b"hello".to_vec()]).await.unwrap();
-        db.rpush("mylist", vec![b"!".to_vec()]).await.unwrap();
+        db.rpush("list", vec![b"item".to_vec()]).await.unwrap();
 
-        let range = db.lrange("mylist", 0, -1).await.unwrap();
-        assert_eq!(
-            range,
-            vec![b"hello".to_vec(), b"world".to_vec(), b"!".to_vec()]
-        );
+        let result = db.strlen("list").await;
+        assert!(matches!(result, Err(DbError::WrongType)));
+
+        let result = db.append("list", b"more").await;
+        assert!(matches!(result, Err(DbError::WrongType)));
     }
 
     #[tokio::test]
-    async fn test_lpop_rpop() {
+    async fn test_set_nx() {
         let db = Db::new();
-        db.rpush(
-            "mylist",
-            vec![b"one".to_vec(), b"two".to_vec(), b"three".to_vec()],
-        )
-        .await
-        .unwrap();
-
-        let popped = db.lpop("mylist", 1).await.unwrap();
-        assert_eq!(popped, Some(vec![b"one".to_vec()]));
 
-        let popped = db.rpop("mylist", 1).await.unwrap();
-        assert_eq!(popped, Some(vec![b"three".to_vec()]));
+        assert!(db
+            .set_nx("lock".to_string(), b"owner1".to_vec())
+            .await
+            .unwrap());
+        assert!(!db
+            .set_nx("lock".to_string(), b"owner2".to_vec())
+            .await
+            .unwrap());
+        assert_eq!(db.get("lock").await.unwrap(), Some(b"owner1".to_vec()));
     }
 
     #[tokio::test]
-    async fn test_lrange() {
+    async fn test_set_ex() {
         let db = Db::new();
-        db.rpush(
-            "mylist",
-            vec![
-                b"one".to_vec(),
-                b"two".to_vec(),
-                b"three".to_vec(),
-                b"four".to_vec(),
-            ],
+        db.set_ex(
+            "temp".to_string(),
+            b"value".to_vec(),
+            Duration::from_millis(100),
         )
         .await
         .unwrap();
 
-        let range = db.lrange("mylist", 1, 2).await.unwrap();
-        assert_eq!(range, vec![b"two".to_vec(), b"three".to_vec()]);
+        assert_eq!(db.get("temp").await.unwrap(), Some(b"value".to_vec()));
+        assert!(db.ttl("temp").await.unwrap() >= 0);
 
-        let range = db.lrange("mylist", 0, -1).await.unwrap();
-        assert_eq!(range.len(), 4);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(db.get("temp").await.unwrap(), None);
+
+        // An expired key no longer blocks SETNX
+        assert!(db
+            .set_nx("temp".to_string(), b"new".to_vec())
+            .await
+            .unwrap());
     }
 
     #[tokio::test]
-    async fn test_sadd_smembers() {
+    async fn test_getset() {
         let db = Db::new();
-        let count = db
-            .sadd("myset", vec![b"one".to_vec(), b"two".to_vec()])
+
+        let old = db
+            .getset("key".to_string(), b"first".to_vec())
             .await
             .unwrap();
-        assert_eq!(count, 2);
+        assert_eq!(old, None);
 
-        let members = db.smembers("myset").await.unwrap();
-        assert_eq!(members.len(), 2);
-        assert!(members.contains(&b"one".to_vec()));
-        assert!(members.contains(&b"two".to_vec()));
+        let old = db
+            .getset("key".to_string(), b"second".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(old, Some(b"first".to_vec()));
+        assert_eq!(db.get("key").await.unwrap(), Some(b"second".to_vec()));
+
+        db.lpush("list", vec![b"item".to_vec()]).await.unwrap();
+        let result = db.getset("list".to_string(), b"value".to_vec()).await;
+        assert!(matches!(result, Err(DbError::WrongType)));
     }
 
     #[tokio::test]
-    async fn test_sismember() {
+    async fn test_incr_by() {
+        let db = Db::new();
+
+        assert_eq!(db.incr_by("counter", 1).await.unwrap(), 1);
+        assert_eq!(db.incr_by("counter", 10).await.unwrap(), 11);
+        assert_eq!(db.incr_by("counter", -3).await.unwrap(), 8);
+        assert_eq!(db.get("counter").await.unwrap(), Some(b"8".to_vec()));
+
+        db.set("negative".to_string(), b"-5".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(db.incr_by("negative", -1).await.unwrap(), -6);
+    }
+
+    #[tokio::test]
+    async fn test_incr_by_not_integer() {
+        let db = Db::new();
+        db.set("name".to_string(), b"alice".to_vec()).await.unwrap();
+
+        let result = db.incr_by("name", 1).await;
+        assert!(matches!(result, Err(DbError::NotInteger)));
+
+        db.set("max".to_string(), i64::MAX.to_string().into_bytes())
+            .await
+            .unwrap();
+        let result = db.incr_by("max", 1).await;
+        assert!(matches!(result, Err(DbError::NotInteger)));
+
+        db.lpush("list", vec![b"1".to_vec()]).await.unwrap();
+        let result = db.incr_by("list", 1).await;
+        assert!(matches!(result, Err(DbError::WrongType)));
+    }
+
+    #[tokio::test]
+    async fn test_key_type() {
+        let db = Db::new();
+        db.set("str".to_string(), b"v".to_vec()).await.unwrap();
+        db.lpush("list", vec![b"v".to_vec()]).await.unwrap();
+        db.sadd("set", vec![b"v".to_vec()]).await.unwrap();
+        db.hset("hash", "f".to_string(), b"v".to_vec())
+            .await
+            .unwrap();
+
+        assert_eq!(db.key_type("str").await.unwrap(), "string");
+        assert_eq!(db.key_type("list").await.unwrap(), "list");
+        assert_eq!(db.key_type("set").await.unwrap(), "set");
+        assert_eq!(db.key_type("hash").await.unwrap(), "hash");
+        assert_eq!(db.key_type("missing").await.unwrap(), "none");
+    }
+
+    #[tokio::test]
+    async fn test_keys() {
+        let db = Db::new();
+        for key in ["user:1", "user:2", "user:10", "session:1"] {
+            db.set(key.to_string(), b"v".to_vec()).await.unwrap();
+        }
+        db.set_ex(
+            "user:3".to_string(),
+            b"v".to_vec(),
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let mut all = db.keys("*").await.unwrap();
+        all.sort();
+        assert_eq!(all, vec!["session:1", "user:1", "user:10", "user:2"]);
+
+        let mut users = db.keys("user:*").await.unwrap();
+        users.sort();
+        assert_eq!(users, vec!["user:1", "user:10", "user:2"]);
+
+        let mut single = db.keys("user:?").await.unwrap();
+        single.sort();
+        assert_eq!(single, vec!["user:1", "user:2"]);
+
+        assert!(db.keys("missing:*").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expire() {
+        let db = Db::new();
+        db.set("key1".to_string(), b"value1".to_vec())
+            .await
+            .unwrap();
+        db.expire("key1", Duration::from_millis(100))
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        let value = db.get("key1").await.unwrap();
+        assert_eq!(value, None);
+    }
+
+    #[tokio::test]
+    async fn test_ttl() {
+        let db = Db::new();
+        db.set("key1".to_string(), b"value1".to_vec())
+            .await
+            .unwrap();
+        db.expire("key1", Duration::from_secs(10)).await.unwrap();
+
+        let ttl = db.ttl("key1").await.unwrap();
+        assert!(ttl > 0 && ttl <= 10);
+    }
+
+    #[tokio::test]
+    async fn test_persist() {
+        let db = Db::new();
+        db.set("key1".to_string(), b"value1".to_vec())
+            .await
+            .unwrap();
+        db.expire("key1", Duration::from_millis(100)).await.unwrap();
+
+        assert!(db.persist("key1").await.unwrap());
+        assert!(!db.persist("key1").await.unwrap());
+        assert_eq!(db.ttl("key1").await.unwrap(), -1);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(db.get("key1").await.unwrap(), Some(b"value1".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_pttl() {
+        let db = Db::new();
+        db.set("key1".to_string(), b"value1".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(db.pttl("key1").await.unwrap(), -1);
+        assert_eq!(db.pttl("missing").await.unwrap(), -2);
+
+        db.expire("key1", Duration::from_millis(1500))
+            .await
+            .unwrap();
+        let pttl = db.pttl("key1").await.unwrap();
+        assert!(pttl > 1000 && pttl <= 1500, "pttl was {}", pttl);
+
+        // TTL rounds down to whole seconds
+        assert_eq!(db.ttl("key1").await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rename_preserves_list_and_ttl() {
+        let db = Db::new();
+        db.rpush("src", vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()])
+            .await
+            .unwrap();
+        db.expire("src", Duration::from_secs(100)).await.unwrap();
+        db.set("dst".to_string(), b"old".to_vec()).await.unwrap();
+
+        db.rename("src", "dst").await.unwrap();
+
+        assert!(!db.exists("src").await.unwrap());
+        assert_eq!(db.key_type("dst").await.unwrap(), "list");
+        assert_eq!(
+            db.lrange("dst", 0, -1).await.unwrap(),
+            vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]
+        );
+        let ttl = db.ttl("dst").await.unwrap();
+        assert!(ttl > 90 && ttl <= 100, "ttl was {}", ttl);
+
+        assert!(matches!(
+            db.rename("src", "dst").await,
+            Err(DbError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rename_nx_refuses_existing_key() {
+        let db = Db::new();
+        db.set("a".to_string(), b"1".to_vec()).await.unwrap();
+        db.set("b".to_string(), b"2".to_vec()).await.unwrap();
+
+        assert!(!db.rename_nx("a", "b").await.unwrap());
+        assert_eq!(db.get("a").await.unwrap(), Some(b"1".to_vec()));
+        assert_eq!(db.get("b").await.unwrap(), Some(b"2".to_vec()));
+
+        assert!(db.rename_nx("a", "c").await.unwrap());
+        assert!(!db.exists("a").await.unwrap());
+        assert_eq!(db.get("c").await.unwrap(), Some(b"1".to_vec()));
+
+        assert!(matches!(
+            db.rename_nx("a", "d").await,
+            Err(DbError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_copy_hash_is_independent() {
+        let db = Db::new();
+        db.hset("src", "name".to_string(), b"alice".to_vec())
+            .await
+            .unwrap();
+        db.expire("src", Duration::from_secs(100)).await.unwrap();
+
+        assert!(db.copy("src", "dst", false).await.unwrap());
+        let ttl = db.ttl("dst").await.unwrap();
+        assert!(ttl > 90 && ttl <= 100, "ttl was {}", ttl);
+
+        db.hset("dst", "name".to_string(), b"bob".to_vec())
+            .await
+            .unwrap();
+        db.hset("src", "age".to_string(), b"30".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            db.hget("src", "name").await.unwrap(),
+            Some(b"alice".to_vec())
+        );
+        assert_eq!(db.hget("dst", "name").await.unwrap(), Some(b"bob".to_vec()));
+        assert_eq!(db.hlen("dst").await.unwrap(), 1);
+
+        // An existing destination is only overwritten with REPLACE
+        assert!(!db.copy("src", "dst", false).await.unwrap());
+        assert_eq!(db.hget("dst", "name").await.unwrap(), Some(b"bob".to_vec()));
+        assert!(db.copy("src", "dst", true).await.unwrap());
+        assert_eq!(db.hlen("dst").await.unwrap(), 2);
+
+        assert!(!db.copy("missing", "other", false).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_dump_restore_round_trip() {
+        let db = Db::new();
+        db.rpush("list", vec![b"a".to_vec(), b"b".to_vec()])
+            .await
+            .unwrap();
+        assert_eq!(db.dump("missing").await.unwrap(), None);
+
+        let payload = db.dump("list").await.unwrap().unwrap();
+        db.restore("copy", None, &payload, false).await.unwrap();
+        assert_eq!(
+            db.lrange("copy", 0, -1).await.unwrap(),
+            vec![b"a".to_vec(), b"b".to_vec()]
+        );
+        assert_eq!(db.ttl("copy").await.unwrap(), -1);
+
+        assert!(matches!(
+            db.restore("copy", None, &payload, false).await,
+            Err(DbError::BusyKey)
+        ));
+        db.restore("copy", Some(Duration::from_secs(100)), &payload, true)
+            .await
+            .unwrap();
+        assert!(db.ttl("copy").await.unwrap() > 90);
+
+        let mut corrupt = payload.clone();
+        corrupt[0] ^= 0xff;
+        assert!(matches!(
+            db.restore("other", None, &corrupt, false).await,
+            Err(DbError::InvalidDump)
+        ));
+        assert!(matches!(
+            db.restore("other", None, b"short", false).await,
+            Err(DbError::InvalidDump)
+        ));
+        assert!(!db.exists("other").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_expire_at() {
+        let db = Db::new();
+        db.set("past".to_string(), b"v".to_vec()).await.unwrap();
+        db.set("future".to_string(), b"v".to_vec()).await.unwrap();
+
+        let past = SystemTime::now() - Duration::from_secs(10);
+        assert!(db.expire_at("past", past).await.unwrap());
+        assert!(!db.exists("past").await.unwrap());
+
+        let future = SystemTime::now() + Duration::from_secs(100);
+        assert!(db.expire_at("future", future).await.unwrap());
+        let ttl = db.ttl("future").await.unwrap();
+        assert!(ttl > 90 && ttl <= 100);
+
+        assert!(!db.expire_at("missing", future).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_lpush_rpush() {
+        let db = Db::new();
+        db.lpush("mylist", vec![b"world".to_vec()]).await.unwrap();
+        db.lpush("mylist", vec![b"hello".to_vec()]).await.unwrap();
+        db.rpush("mylist", vec![b"!".to_vec()]).await.unwrap();
+
+        let range = db.lrange("mylist", 0, -1).await.unwrap();
+        assert_eq!(
+            range,
+            vec![b"hello".to_vec(), b"world".to_vec(), b"!".to_vec()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lpop_rpop() {
+        let db = Db::new();
+        db.rpush(
+            "mylist",
+            vec![b"one".to_vec(), b"two".to_vec(), b"three".to_vec()],
+        )
+        .await
+        .unwrap();
+
+        let popped = db.lpop("mylist", 1).await.unwrap();
+        assert_eq!(popped, Some(vec![b"one".to_vec()]));
+
+        let popped = db.rpop("mylist", 1).await.unwrap();
+        assert_eq!(popped, Some(vec![b"three".to_vec()]));
+
+        // Popping the last element deletes the key
+        db.lpop("mylist", 5).await.unwrap();
+        assert!(!db.exists("mylist").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_lrange() {
+        let db = Db::new();
+        db.rpush(
+            "mylist",
+            vec![
+                b"one".to_vec(),
+                b"two".to_vec(),
+                b"three".to_vec(),
+                b"four".to_vec(),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let range = db.lrange("mylist", 1, 2).await.unwrap();
+        assert_eq!(range, vec![b"two".to_vec(), b"three".to_vec()]);
+
+        let range = db.lrange("mylist", 0, -1).await.unwrap();
+        assert_eq!(range.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_lmove() {
+        let db = Db::new();
+        db.rpush("src", vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()])
+            .await
+            .unwrap();
+
+        // RPOPLPUSH into a missing list creates it
+        let moved = db
+            .lmove("src", "dst", ListEnd::Right, ListEnd::Left)
+            .await
+            .unwrap();
+        assert_eq!(moved, Some(b"c".to_vec()));
+        let moved = db
+            .lmove("src", "dst", ListEnd::Left, ListEnd::Right)
+            .await
+            .unwrap();
+        assert_eq!(moved, Some(b"a".to_vec()));
+        assert_eq!(db.lrange("src", 0, -1).await.unwrap(), vec![b"b".to_vec()]);
+        assert_eq!(
+            db.lrange("dst", 0, -1).await.unwrap(),
+            vec![b"c".to_vec(), b"a".to_vec()]
+        );
+
+        // Emptying the source deletes it
+        db.lmove("src", "dst", ListEnd::Left, ListEnd::Left)
+            .await
+            .unwrap();
+        assert!(!db.exists("src").await.unwrap());
+        assert_eq!(
+            db.lmove("src", "dst", ListEnd::Left, ListEnd::Left)
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(db.llen("dst").await.unwrap(), 3);
+
+        // Neither list changes when the destination has the wrong type
+        db.set("string".to_string(), b"x".to_vec()).await.unwrap();
+        assert!(matches!(
+            db.lmove("dst", "string", ListEnd::Left, ListEnd::Left)
+                .await,
+            Err(DbError::WrongType)
+        ));
+        assert_eq!(db.llen("dst").await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_lmove_rotates_same_list() {
+        let db = Db::new();
+        db.rpush("ring", vec![b"1".to_vec(), b"2".to_vec(), b"3".to_vec()])
+            .await
+            .unwrap();
+
+        let moved = db
+            .lmove("ring", "ring", ListEnd::Right, ListEnd::Left)
+            .await
+            .unwrap();
+        assert_eq!(moved, Some(b"3".to_vec()));
+        assert_eq!(
+            db.lrange("ring", 0, -1).await.unwrap(),
+            vec![b"3".to_vec(), b"1".to_vec(), b"2".to_vec()]
+        );
+
+        let moved = db
+            .lmove("ring", "ring", ListEnd::Left, ListEnd::Right)
+            .await
+            .unwrap();
+        assert_eq!(moved, Some(b"3".to_vec()));
+        assert_eq!(
+            db.lrange("ring", 0, -1).await.unwrap(),
+            vec![b"1".to_vec(), b"2".to_vec(), b"3".to_vec()]
+        );
+
+        // A single element rotated onto itself keeps its list and expiry
+        db.rpush("single", vec![b"only".to_vec()]).await.unwrap();
+        db.expire("single", Duration::from_secs(100)).await.unwrap();
+        let moved = db
+            .lmove("single", "single", ListEnd::Left, ListEnd::Right)
+            .await
+            .unwrap();
+        assert_eq!(moved, Some(b"only".to_vec()));
+        assert_eq!(
+            db.lrange("single", 0, -1).await.unwrap(),
+            vec![b"only".to_vec()]
+        );
+        assert!(db.ttl("single").await.unwrap() > 90);
+    }
+
+    #[tokio::test]
+    async fn test_zadd_updates_scores() {
+        let db = Db::new();
+        let added = db
+            .zadd(
+                "board",
+                vec![(10.0, b"alice".to_vec()), (5.0, b"bob".to_vec())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(added, 2);
+        assert_eq!(db.key_type("board").await.unwrap(), "zset");
+
+        // Updating an existing member doesn't count as an addition
+        let added = db
+            .zadd(
+                "board",
+                vec![(1.0, b"alice".to_vec()), (7.5, b"carol".to_vec())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(db.zscore("board", b"alice").await.unwrap(), Some(1.0));
+        assert_eq!(db.zscore("board", b"nobody").await.unwrap(), None);
+        assert_eq!(db.zscore("missing", b"alice").await.unwrap(), None);
+
+        assert_eq!(
+            db.zrem("board", vec![b"bob".to_vec(), b"nobody".to_vec()])
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            db.zrem("board", vec![b"alice".to_vec(), b"carol".to_vec()])
+                .await
+                .unwrap(),
+            2
+        );
+        assert!(!db.exists("board").await.unwrap());
+
+        db.set("string".to_string(), b"x".to_vec()).await.unwrap();
+        assert!(matches!(
+            db.zadd("string", vec![(1.0, b"a".to_vec())]).await,
+            Err(DbError::WrongType)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_zrange_with_scores() {
+        let db = Db::new();
+        db.zadd(
+            "board",
+            vec![
+                (3.0, b"c".to_vec()),
+                (1.0, b"a".to_vec()),
+                (f64::INFINITY, b"top".to_vec()),
+                (2.0, b"b".to_vec()),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            db.zrange("board", 0, -1).await.unwrap(),
+            vec![
+                (b"a".to_vec(), 1.0),
+                (b"b".to_vec(), 2.0),
+                (b"c".to_vec(), 3.0),
+                (b"top".to_vec(), f64::INFINITY),
+            ]
+        );
+        assert_eq!(
+            db.zrange("board", -2, -1).await.unwrap(),
+            vec![(b"c".to_vec(), 3.0), (b"top".to_vec(), f64::INFINITY)]
+        );
+        assert_eq!(
+            db.zrange("board", 1, 1).await.unwrap(),
+            vec![(b"b".to_vec(), 2.0)]
+        );
+        assert!(db.zrange("board", 3, 1).await.unwrap().is_empty());
+        assert!(db.zrange("missing", 0, -1).await.unwrap().is_empty());
+
+        // Scores and order survive a DUMP/RESTORE round trip
+        let payload = db.dump("board").await.unwrap().unwrap();
+        db.restore("copy", None, &payload, false).await.unwrap();
+        assert_eq!(
+            db.zrange("copy", 0, -1).await.unwrap(),
+            db.zrange("board", 0, -1).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_zrank_ordering() {
+        let db = Db::new();
+        db.zadd(
+            "board",
+            vec![
+                (20.0, b"x".to_vec()),
+                (10.0, b"y".to_vec()),
+                // Ties are ordered by member
+                (10.0, b"a".to_vec()),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.zrank("board", b"a").await.unwrap(), Some(0));
+        assert_eq!(db.zrank("board", b"y").await.unwrap(), Some(1));
+        assert_eq!(db.zrank("board", b"x").await.unwrap(), Some(2));
+        assert_eq!(db.zrank("board", b"nobody").await.unwrap(), None);
+
+        // Moving a member's score moves its rank
+        db.zadd("board", vec![(-1.0, b"x".to_vec())]).await.unwrap();
+        assert_eq!(db.zrank("board", b"x").await.unwrap(), Some(0));
+        assert_eq!(db.zrank("board", b"y").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_sadd_smembers() {
+        let db = Db::new();
+        let count = db
+            .sadd("myset", vec![b"one".to_vec(), b"two".to_vec()])
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let members = db.smembers("myset").await.unwrap();
+        assert_eq!(members.len(), 2);
+        assert!(members.contains(&b"one".to_vec()));
+        assert!(members.contains(&b"two".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_sismember() {
         let db = Db::new();
         db.sadd("myset", vec![b"hello".to_vec()]).await.unwrap();
 
@@ -577,6 +2192,71 @@ mod tests {
         assert!(!is_member);
     }
 
+    #[tokio::test]
+    async fn test_srem() {
+        let db = Db::new();
+        db.sadd("myset", vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()])
+            .await
+            .unwrap();
+
+        let removed = db
+            .srem("myset", vec![b"a".to_vec(), b"b".to_vec(), b"z".to_vec()])
+            .await
+            .unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(db.smembers("myset").await.unwrap(), vec![b"c".to_vec()]);
+
+        // Removing the last member deletes the key
+        db.srem("myset", vec![b"c".to_vec()]).await.unwrap();
+        assert!(!db.exists("myset").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_set_algebra() {
+        let db = Db::new();
+        db.sadd(
+            "s1",
+            vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), b"d".to_vec()],
+        )
+        .await
+        .unwrap();
+        db.sadd("s2", vec![b"c".to_vec(), b"d".to_vec(), b"e".to_vec()])
+            .await
+            .unwrap();
+        db.sadd("s3", vec![b"a".to_vec(), b"c".to_vec(), b"e".to_vec()])
+            .await
+            .unwrap();
+
+        let keys = |names: &[&str]| names.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        let sorted = |mut members: Vec<Vec<u8>>| {
+            members.sort();
+            members
+        };
+
+        let inter = db.sinter(&keys(&["s1", "s2", "s3"])).await.unwrap();
+        assert_eq!(sorted(inter), vec![b"c".to_vec()]);
+
+        let union = db.sunion(&keys(&["s1", "s2", "s3"])).await.unwrap();
+        assert_eq!(union.len(), 5);
+
+        let diff = db.sdiff(&keys(&["s1", "s2", "s3"])).await.unwrap();
+        assert_eq!(sorted(diff), vec![b"b".to_vec()]);
+
+        // Missing keys act as empty sets
+        assert!(db
+            .sinter(&keys(&["s1", "missing"]))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(db.sunion(&keys(&["s2", "missing"])).await.unwrap().len(), 3);
+        assert_eq!(db.sdiff(&keys(&["s2", "missing"])).await.unwrap().len(), 3);
+
+        // Wrong-typed keys error
+        db.set("str".to_string(), b"v".to_vec()).await.unwrap();
+        let result = db.sunion(&keys(&["s1", "str"])).await;
+        assert!(matches!(result, Err(DbError::WrongType)));
+    }
+
     #[tokio::test]
     async fn test_hset_hget() {
         let db = Db::new();
@@ -604,6 +2284,412 @@ mod tests {
         assert_eq!(hash.get("field2"), Some(&b"value2".to_vec()));
     }
 
+    #[tokio::test]
+    async fn test_hmset_hmget() {
+        let db = Db::new();
+        db.hmset(
+            "user:1",
+            vec![
+                ("name".to_string(), b"alice".to_vec()),
+                ("age".to_string(), b"30".to_vec()),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let fields = vec!["name".to_string(), "missing".to_string(), "age".to_string()];
+        let values = db.hmget("user:1", &fields).await.unwrap();
+        assert_eq!(
+            values,
+            vec![Some(b"alice".to_vec()), None, Some(b"30".to_vec())]
+        );
+
+        assert!(db.hexists("user:1", "name").await.unwrap());
+        assert!(!db.hexists("user:1", "missing").await.unwrap());
+
+        let values = db.hmget("nokey", &fields).await.unwrap();
+        assert_eq!(values, vec![None, None, None]);
+    }
+
+    #[tokio::test]
+    async fn test_hdel_empties_hash() {
+        let db = Db::new();
+        db.hmset(
+            "myhash",
+            vec![
+                ("f1".to_string(), b"v1".to_vec()),
+                ("f2".to_string(), b"v2".to_vec()),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let deleted = db
+            .hdel("myhash", &["f1".to_string(), "nope".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert_eq!(db.hlen("myhash").await.unwrap(), 1);
+
+        let deleted = db.hdel("myhash", &["f2".to_string()]).await.unwrap();
+        assert_eq!(deleted, 1);
+        assert!(!db.exists("myhash").await.unwrap());
+    }
+
+    async fn scan_all(db: &Db, pattern: Option<&str>, count: usize) -> (Vec<String>, usize) {
+        let mut cursor = 0;
+        let mut keys = Vec::new();
+        let mut calls = 0;
+        loop {
+            let (next, batch) = db.scan(cursor, pattern, count).await.unwrap();
+            assert!(batch.len() <= count);
+            keys.extend(batch);
+            calls += 1;
+            if next == 0 {
+                return (keys, calls);
+            }
+            cursor = next;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_covers_keyspace() {
+        let db = Db::new();
+        for i in 0..50 {
+            db.set(format!("key:{}", i), b"v".to_vec()).await.unwrap();
+        }
+
+        let (mut keys, calls) = scan_all(&db, None, 7).await;
+        assert!(calls > 1);
+        keys.sort();
+        let mut expected: Vec<String> = (0..50).map(|i| format!("key:{}", i)).collect();
+        expected.sort();
+        assert_eq!(keys, expected);
+
+        let (mut matched, _) = scan_all(&db, Some("key:1?"), 7).await;
+        matched.sort();
+        let expected: Vec<String> = (10..20).map(|i| format!("key:{}", i)).collect();
+        assert_eq!(matched, expected);
+
+        let (keys, calls) = scan_all(&db, None, 100).await;
+        assert_eq!((keys.len(), calls), (50, 1));
+    }
+
+    #[tokio::test]
+    async fn test_scan_with_concurrent_changes() {
+        let db = Db::new();
+        for i in 0..50 {
+            db.set(format!("stable:{}", i), b"v".to_vec())
+                .await
+                .unwrap();
+            db.set(format!("doomed:{}", i), b"v".to_vec())
+                .await
+                .unwrap();
+        }
+
+        let mut cursor = 0;
+        let mut seen = Vec::new();
+        let mut round = 0;
+        loop {
+            let (next, batch) = db.scan(cursor, Some("stable:*"), 5).await.unwrap();
+            seen.extend(batch);
+
+            // Churn the keyspace between calls
+            db.del(&format!("doomed:{}", round)).await.unwrap();
+            db.set(format!("added:{}", round), b"v".to_vec())
+                .await
+                .unwrap();
+            round += 1;
+
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+
+        seen.sort();
+        let mut expected: Vec<String> = (0..50).map(|i| format!("stable:{}", i)).collect();
+        expected.sort();
+        assert_eq!(seen, expected);
+    }
+
+    #[tokio::test]
+    async fn test_scan_order_tracks_keyspace() {
+        let db = Db::new();
+        for i in 0..20 {
+            db.set(format!("key:{}", i), b"v".to_vec()).await.unwrap();
+        }
+        for i in 0..10 {
+            db.del(&format!("key:{}", i)).await.unwrap();
+        }
+        db.rename("key:10", "renamed").await.unwrap();
+
+        let (mut keys, _) = scan_all(&db, None, 3).await;
+        keys.sort();
+        let mut expected: Vec<String> = (11..20).map(|i| format!("key:{}", i)).collect();
+        expected.push("renamed".to_string());
+        expected.sort();
+        assert_eq!(keys, expected);
+        assert_eq!(db.data.read().await.scan_order.len(), 10);
+
+        // A cursor past every hash ends the scan instead of overflowing
+        let (next, _) = db.scan(u64::MAX, None, 10).await.unwrap();
+        assert_eq!(next, 0);
+    }
+
+    #[tokio::test]
+    async fn test_maxmemory_lru_eviction() {
+        let db = Db::new();
+        let value = vec![b'x'; 100];
+        let entry_size = db_entry_size("key00", &value);
+        db.set_maxmemory(entry_size * 5, EvictionPolicy::AllKeysLru)
+            .await;
+
+        for i in 0..5 {
+            db.set(format!("key{:02}", i), value.clone()).await.unwrap();
+        }
+        assert_eq!(db.used_memory().await, entry_size * 5);
+
+        // Reading key00 makes key01 the least recently used
+        db.get("key00").await.unwrap();
+        db.set("key05".to_string(), value.clone()).await.unwrap();
+        assert!(db.exists("key00").await.unwrap());
+        assert!(!db.exists("key01").await.unwrap());
+
+        for i in 6..20 {
+            db.set(format!("key{:02}", i), value.clone()).await.unwrap();
+            assert!(db.used_memory().await <= entry_size * 5);
+        }
+
+        assert_eq!(db.keys("*").await.unwrap().len(), 5);
+        for i in 15..20 {
+            assert!(db.exists(&format!("key{:02}", i)).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_maxmemory_evicts_on_growth_in_place() {
+        let db = Db::new();
+        db.set_maxmemory(1000, EvictionPolicy::AllKeysLru).await;
+
+        db.set("old".to_string(), vec![b'x'; 300]).await.unwrap();
+        db.rpush("list", vec![vec![b'x'; 100]]).await.unwrap();
+        assert!(db.exists("old").await.unwrap());
+
+        // Growing the list pushes the total over the limit
+        db.rpush("list", vec![vec![b'x'; 100]; 5]).await.unwrap();
+        assert!(!db.exists("old").await.unwrap());
+        assert_eq!(db.llen("list").await.unwrap(), 6);
+        assert!(db.used_memory().await <= 1000);
+
+        // Shrinking gives the memory back
+        let before = db.used_memory().await;
+        db.lpop("list", 3).await.unwrap();
+        assert!(db.used_memory().await < before);
+    }
+
+    #[tokio::test]
+    async fn test_maxmemory_volatile_ttl() {
+        let db = Db::new();
+        let value = vec![b'x'; 100];
+        let entry_size = db_entry_size("forever", &value);
+        db.set_maxmemory(entry_size * 3, EvictionPolicy::VolatileTtl)
+            .await;
+
+        db.set("forever".to_string(), value.clone()).await.unwrap();
+        db.set_ex("later".to_string(), value.clone(), Duration::from_secs(600))
+            .await
+            .unwrap();
+        db.set_ex("soon".to_string(), value.clone(), Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        // Only keys with an expiry are candidates, nearest expiry first
+        db.set("new".to_string(), value.clone()).await.unwrap();
+        assert!(!db.exists("soon").await.unwrap());
+        assert!(db.exists("later").await.unwrap());
+        assert!(db.exists("forever").await.unwrap());
+
+        db.set("newer".to_string(), value.clone()).await.unwrap();
+        assert!(!db.exists("later").await.unwrap());
+
+        // With nothing left to evict the limit overshoots rather than failing
+        db.set("newest".to_string(), value.clone()).await.unwrap();
+        assert_eq!(db.keys("*").await.unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_eviction_policy_from_name() {
+        assert_eq!(
+            EvictionPolicy::from_name("allkeys-lru"),
+            Some(EvictionPolicy::AllKeysLru)
+        );
+        assert_eq!(
+            EvictionPolicy::from_name("VOLATILE-TTL"),
+            Some(EvictionPolicy::VolatileTtl)
+        );
+        assert_eq!(EvictionPolicy::from_name("noeviction"), None);
+    }
+
+    fn db_entry_size(key: &str, value: &[u8]) -> usize {
+        ENTRY_OVERHEAD + key.len() + value.len()
+    }
+
+    #[tokio::test]
+    async fn test_sort_numeric() {
+        let db = Db::new();
+        let values = ["10", "2", "-3.5", "2", "7"];
+        db.rpush(
+            "list",
+            values.iter().map(|v| v.as_bytes().to_vec()).collect(),
+        )
+        .await
+        .unwrap();
+
+        let sorted = db.sort("list", SortOptions::default()).await.unwrap();
+        assert_eq!(
+            sorted,
+            vec![
+                b"-3.5".to_vec(),
+                b"2".to_vec(),
+                b"2".to_vec(),
+                b"7".to_vec(),
+                b"10".to_vec()
+            ]
+        );
+
+        // The list itself is left in insertion order
+        assert_eq!(db.lrange("list", 0, 0).await.unwrap(), vec![b"10".to_vec()]);
+
+        db.rpush("list", vec![b"abc".to_vec()]).await.unwrap();
+        assert!(matches!(
+            db.sort("list", SortOptions::default()).await,
+            Err(DbError::NotFloat)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sort_alpha_and_desc() {
+        let db = Db::new();
+        let members = ["pear", "apple", "10", "banana", "9"];
+        db.sadd(
+            "fruit",
+            members.iter().map(|m| m.as_bytes().to_vec()).collect(),
+        )
+        .await
+        .unwrap();
+
+        let alpha = SortOptions {
+            alpha: true,
+            ..Default::default()
+        };
+        let sorted = db.sort("fruit", alpha).await.unwrap();
+        assert_eq!(
+            sorted,
+            vec![
+                b"10".to_vec(),
+                b"9".to_vec(),
+                b"apple".to_vec(),
+                b"banana".to_vec(),
+                b"pear".to_vec()
+            ]
+        );
+
+        let desc = SortOptions {
+            alpha: true,
+            descending: true,
+            ..Default::default()
+        };
+        let sorted = db.sort("fruit", desc).await.unwrap();
+        assert_eq!(sorted.first().unwrap(), b"pear");
+        assert_eq!(sorted.last().unwrap(), b"10");
+
+        db.sadd("nums", vec![b"3".to_vec(), b"1".to_vec(), b"2".to_vec()])
+            .await
+            .unwrap();
+        let desc = SortOptions {
+            descending: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            db.sort("nums", desc).await.unwrap(),
+            vec![b"3".to_vec(), b"2".to_vec(), b"1".to_vec()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sort_limit() {
+        let db = Db::new();
+        db.rpush(
+            "list",
+            (1..=10).rev().map(|i| i.to_string().into_bytes()).collect(),
+        )
+        .await
+        .unwrap();
+
+        let window = |offset, count| SortOptions {
+            limit: Some((offset, count)),
+            ..Default::default()
+        };
+        assert_eq!(
+            db.sort("list", window(2, 3)).await.unwrap(),
+            vec![b"3".to_vec(), b"4".to_vec(), b"5".to_vec()]
+        );
+        assert_eq!(
+            db.sort("list", window(8, 5)).await.unwrap(),
+            vec![b"9".to_vec(), b"10".to_vec()]
+        );
+        assert_eq!(
+            db.sort("list", window(-1, 1)).await.unwrap(),
+            vec![b"1".to_vec()]
+        );
+        assert_eq!(db.sort("list", window(7, -1)).await.unwrap().len(), 3);
+        assert!(db.sort("list", window(20, 5)).await.unwrap().is_empty());
+
+        let desc = SortOptions {
+            descending: true,
+            limit: Some((0, 2)),
+            ..Default::default()
+        };
+        assert_eq!(
+            db.sort("list", desc).await.unwrap(),
+            vec![b"10".to_vec(), b"9".to_vec()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sort_missing_and_wrong_type() {
+        let db = Db::new();
+        assert!(db
+            .sort("missing", SortOptions::default())
+            .await
+            .unwrap()
+            .is_empty());
+
+        db.set("string".to_string(), b"1".to_vec()).await.unwrap();
+        assert!(matches!(
+            db.sort("string", SortOptions::default()).await,
+            Err(DbError::WrongType)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_key_counts() {
+        let db = Db::new();
+        assert_eq!(db.key_counts().await, (0, 0));
+
+        db.set("a".to_string(), b"1".to_vec()).await.unwrap();
+        db.set_ex("b".to_string(), b"2".to_vec(), Duration::from_secs(60))
+            .await
+            .unwrap();
+        db.set_ex("gone".to_string(), b"3".to_vec(), Duration::from_millis(10))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert_eq!(db.key_counts().await, (2, 1));
+    }
+
     #[tokio::test]
     async fn test_wrong_type_error() {
         let db = Db::new();
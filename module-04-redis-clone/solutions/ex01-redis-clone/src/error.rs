@@ -41,10 +41,21 @@ pub enum DbError {
     #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
     WrongType,
 
-    #[allow(dead_code)]
-    #[error("Key not found")]
+    #[error("ERR value is not an integer or out of range")]
+    NotInteger,
+
+    #[error("ERR One or more scores can't be converted into double")]
+    NotFloat,
+
+    #[error("no such key")]
     NotFound,
 
+    #[error("BUSYKEY Target key name already exists.")]
+    BusyKey,
+
+    #[error("ERR DUMP payload version or checksum are wrong")]
+    InvalidDump,
+
     #[error("Command error: {0}")]
     CommandError(#[from] CommandError),
 
@@ -53,6 +64,12 @@ pub enum DbError {
 
     #[error("Serialization error: {0}")]
     Serialization(#[from] bincode::Error),
+
+    #[error("Invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+
+    #[error("Invalid AOF: {0}")]
+    InvalidAof(String),
 }
 
 pub type Result<T> = std::result::Result<T, DbError>;
@@ -1,12 +1,27 @@
+mod aof;
+mod clients;
 mod command;
 mod db;
 mod error;
+mod glob;
+mod pubsub;
 mod resp;
 mod server;
+mod snapshot;
+mod sorted_set;
 
+use db::EvictionPolicy;
 use server::Server;
+use std::time::Duration;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+const SNAPSHOT_PATH: &str = "dump.rdb";
+const AOF_PATH: &str = "appendonly.aof";
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+const MAX_MEMORY: usize = 256 * 1024 * 1024;
+/// How long a client may stall partway through sending a command
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logging
@@ -18,10 +33,54 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    let policy = std::env::var("MAXMEMORY_POLICY")
+        .ok()
+        .and_then(|name| EvictionPolicy::from_name(&name))
+        .unwrap_or(EvictionPolicy::AllKeysLru);
+    let appendonly = std::env::var("APPENDONLY").is_ok_and(|value| value == "yes");
+    let unixsocket = std::env::var("UNIXSOCKET").ok();
+    // Seconds a client may sit idle, as `timeout` in redis.conf; 0 disables it
+    let idle_timeout = std::env::var("TIMEOUT")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs);
+
     // Start server
-    let server = Server::bind("127.0.0.1:6379").await?;
-    tracing::info!("Server listening on 127.0.0.1:6379");
-    tracing::info!("Compatible with redis-cli - try: redis-cli -p 6379");
+    let server = match &unixsocket {
+        Some(path) => Server::bind_unix(path).await?,
+        None => Server::bind("127.0.0.1:6379").await?,
+    };
+    let mut server = server
+        .with_read_timeout(READ_TIMEOUT)
+        .with_snapshot(SNAPSHOT_PATH, Some(SAVE_INTERVAL))
+        .await?;
+    if let Some(timeout) = idle_timeout {
+        server = server.with_idle_timeout(timeout);
+    }
+    if appendonly {
+        server = server.with_aof(AOF_PATH).await?;
+    }
+    let server = server.with_maxmemory(MAX_MEMORY, policy).await;
+    match &unixsocket {
+        Some(path) => {
+            tracing::info!("Server listening on {}", path);
+            tracing::info!("Compatible with redis-cli - try: redis-cli -s {}", path);
+        }
+        None => {
+            tracing::info!("Server listening on 127.0.0.1:6379");
+            tracing::info!("Compatible with redis-cli - try: redis-cli -p 6379");
+        }
+    }
+
+    // Stop cleanly on Ctrl+C, saving a final snapshot
+    let shutdown = server.shutdown_handle();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            tracing::info!("Received Ctrl+C");
+            shutdown.shutdown();
+        }
+    });
 
     server.run().await?;
 
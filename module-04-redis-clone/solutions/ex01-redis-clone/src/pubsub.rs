@@ -0,0 +1,187 @@
+use crate::glob::glob_match;
+use crate::resp::RespValue;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{mpsc, RwLock};
+
+/// Sender used to push messages to a subscribed connection
+pub type PushSender = mpsc::UnboundedSender<RespValue>;
+
+/// Subscribers keyed by channel name or pattern, then by subscriber id
+type Subscriptions = HashMap<String, HashMap<u64, PushSender>>;
+
+/// Registry of channel and pattern subscriptions shared by all connections
+#[derive(Clone, Default)]
+pub struct PubSub {
+    channels: Arc<RwLock<Subscriptions>>,
+    patterns: Arc<RwLock<Subscriptions>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl PubSub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocate a unique id for a new subscriber
+    pub fn next_subscriber_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub async fn subscribe(&self, channel: &str, id: u64, tx: PushSender) {
+        add_subscriber(&self.channels, channel, id, tx).await;
+    }
+
+    pub async fn unsubscribe(&self, channel: &str, id: u64) {
+        remove_subscriber(&self.channels, channel, id).await;
+    }
+
+    /// Subscribe to every channel matching a glob-style pattern
+    pub async fn psubscribe(&self, pattern: &str, id: u64, tx: PushSender) {
+        add_subscriber(&self.patterns, pattern, id, tx).await;
+    }
+
+    pub async fn punsubscribe(&self, pattern: &str, id: u64) {
+        remove_subscriber(&self.patterns, pattern, id).await;
+    }
+
+    /// Push a message to every subscriber of `channel` and of each pattern matching it
+    ///
+    /// Pattern subscribers get a `pmessage` naming the pattern that matched.
+    /// Returns the number of messages delivered, so a connection subscribed
+    /// to both the channel and a matching pattern counts twice.
+    pub async fn publish(&self, channel: &str, message: Vec<u8>) -> usize {
+        let mut delivered = 0;
+
+        if let Some(subscribers) = self.channels.read().await.get(channel) {
+            let push = RespValue::Push(vec![
+                RespValue::BulkString(Some(b"message".to_vec())),
+                RespValue::BulkString(Some(channel.as_bytes().to_vec())),
+                RespValue::BulkString(Some(message.clone())),
+            ]);
+            delivered += send_to_all(subscribers, &push);
+        }
+
+        let patterns = self.patterns.read().await;
+        for (pattern, subscribers) in patterns.iter() {
+            if !glob_match(pattern.as_bytes(), channel.as_bytes()) {
+                continue;
+            }
+
+            let push = RespValue::Push(vec![
+                RespValue::BulkString(Some(b"pmessage".to_vec())),
+                RespValue::BulkString(Some(pattern.as_bytes().to_vec())),
+                RespValue::BulkString(Some(channel.as_bytes().to_vec())),
+                RespValue::BulkString(Some(message.clone())),
+            ]);
+            delivered += send_to_all(subscribers, &push);
+        }
+
+        delivered
+    }
+}
+
+async fn add_subscriber(
+    subscriptions: &RwLock<Subscriptions>,
+    name: &str,
+    id: u64,
+    tx: PushSender,
+) {
+    let mut subscriptions = subscriptions.write().await;
+    subscriptions
+        .entry(name.to_string())
+        .or_default()
+        .insert(id, tx);
+}
+
+async fn remove_subscriber(subscriptions: &RwLock<Subscriptions>, name: &str, id: u64) {
+    let mut subscriptions = subscriptions.write().await;
+
+    if let Some(subscribers) = subscriptions.get_mut(name) {
+        subscribers.remove(&id);
+        if subscribers.is_empty() {
+            subscriptions.remove(name);
+        }
+    }
+}
+
+/// Send `push` to each subscriber, returning how many are still connected
+fn send_to_all(subscribers: &HashMap<u64, PushSender>, push: &RespValue) -> usize {
+    subscribers
+        .values()
+        .filter(|tx| tx.send(push.clone()).is_ok())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_to_subscribers() {
+        let pubsub = PubSub::new();
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (tx2, mut rx2) = mpsc::unbounded_channel();
+
+        pubsub
+            .subscribe("news", pubsub.next_subscriber_id(), tx1)
+            .await;
+        pubsub
+            .subscribe("news", pubsub.next_subscriber_id(), tx2)
+            .await;
+
+        let received = pubsub.publish("news", b"hello".to_vec()).await;
+        assert_eq!(received, 2);
+        assert!(rx1.try_recv().is_ok());
+        assert!(rx2.try_recv().is_ok());
+
+        assert_eq!(pubsub.publish("other", b"hello".to_vec()).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_publish_to_patterns() {
+        let pubsub = PubSub::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let id = pubsub.next_subscriber_id();
+
+        pubsub.psubscribe("news.*", id, tx.clone()).await;
+        pubsub.subscribe("news.sports", id, tx).await;
+
+        assert_eq!(pubsub.publish("news.sports", b"goal".to_vec()).await, 2);
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            RespValue::Push(vec![
+                RespValue::BulkString(Some(b"message".to_vec())),
+                RespValue::BulkString(Some(b"news.sports".to_vec())),
+                RespValue::BulkString(Some(b"goal".to_vec())),
+            ])
+        );
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            RespValue::Push(vec![
+                RespValue::BulkString(Some(b"pmessage".to_vec())),
+                RespValue::BulkString(Some(b"news.*".to_vec())),
+                RespValue::BulkString(Some(b"news.sports".to_vec())),
+                RespValue::BulkString(Some(b"goal".to_vec())),
+            ])
+        );
+
+        assert_eq!(pubsub.publish("weather", b"rain".to_vec()).await, 0);
+
+        pubsub.punsubscribe("news.*", id).await;
+        assert_eq!(pubsub.publish("news.tech", b"chips".to_vec()).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe() {
+        let pubsub = PubSub::new();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let id = pubsub.next_subscriber_id();
+
+        pubsub.subscribe("news", id, tx).await;
+        pubsub.unsubscribe("news", id).await;
+
+        assert_eq!(pubsub.publish("news", b"hello".to_vec()).await, 0);
+    }
+}
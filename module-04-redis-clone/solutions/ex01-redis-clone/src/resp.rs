@@ -2,6 +2,27 @@ use crate::error::RespError;
 use bytes::Buf;
 use std::io::Cursor;
 
+/// Longest inline command or protocol line accepted, as in Redis
+const MAX_INLINE_LEN: usize = 64 * 1024;
+
+/// Most elements an array, map or push may declare
+const MAX_AGGREGATE_LEN: usize = 1024 * 1024;
+
+/// Largest bulk string accepted, Redis's default `proto-max-bulk-len`
+const MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+
+/// Elements reserved for an aggregate up front; the declared length is
+/// only a claim until that many elements have actually arrived
+const PREALLOC_LEN: usize = 1024;
+
+/// Protocol version spoken on a connection, negotiated with HELLO
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Protocol {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
 /// RESP (REdis Serialization Protocol) value types
 #[derive(Debug, Clone, PartialEq)]
 pub enum RespValue {
@@ -15,6 +36,16 @@ pub enum RespValue {
     BulkString(Option<Vec<u8>>),
     /// Arrays: *2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n (or *-1\r\n for null)
     Array(Option<Vec<RespValue>>),
+    /// RESP3 null: _\r\n
+    Null,
+    /// RESP3 booleans: #t\r\n or #f\r\n
+    Boolean(bool),
+    /// RESP3 doubles: ,1.23\r\n
+    Double(f64),
+    /// RESP3 maps: %1\r\n+key\r\n+value\r\n
+    Map(Vec<(RespValue, RespValue)>),
+    /// RESP3 out-of-band pushes: >3\r\n...
+    Push(Vec<RespValue>),
 }
 
 impl RespValue {
@@ -51,7 +82,12 @@ impl RespValue {
                     return Ok(RespValue::BulkString(None));
                 }
 
-                let len = len as usize;
+                let len = usize::try_from(len)
+                    .ok()
+                    .filter(|&len| len <= MAX_BULK_LEN)
+                    .ok_or_else(|| {
+                        RespError::InvalidFormat(format!("invalid bulk length {}", len))
+                    })?;
                 if src.remaining() < len + 2 {
                     return Err(RespError::Incomplete);
                 }
@@ -78,8 +114,8 @@ impl RespValue {
                     return Ok(RespValue::Array(None));
                 }
 
-                let len = len as usize;
-                let mut array = Vec::with_capacity(len);
+                let len = aggregate_len(len)?;
+                let mut array = Vec::with_capacity(len.min(PREALLOC_LEN));
 
                 for _ in 0..len {
                     array.push(RespValue::parse(src)?);
@@ -87,12 +123,118 @@ impl RespValue {
 
                 Ok(RespValue::Array(Some(array)))
             }
+            b'_' => {
+                src.advance(1);
+                read_line(src)?;
+                Ok(RespValue::Null)
+            }
+            b'#' => {
+                src.advance(1);
+                match read_line(src)?.as_str() {
+                    "t" => Ok(RespValue::Boolean(true)),
+                    "f" => Ok(RespValue::Boolean(false)),
+                    other => Err(RespError::InvalidFormat(format!(
+                        "Invalid boolean: {}",
+                        other
+                    ))),
+                }
+            }
+            b',' => {
+                src.advance(1);
+                let line = read_line(src)?;
+                let num = line
+                    .parse::<f64>()
+                    .map_err(|_| RespError::InvalidFormat(format!("Invalid double: {}", line)))?;
+                Ok(RespValue::Double(num))
+            }
+            b'%' => {
+                src.advance(1);
+                let len = aggregate_len(read_line(src)?.parse::<i64>()?)?;
+                let mut map = Vec::with_capacity(len.min(PREALLOC_LEN));
+
+                for _ in 0..len {
+                    let key = RespValue::parse(src)?;
+                    let value = RespValue::parse(src)?;
+                    map.push((key, value));
+                }
+
+                Ok(RespValue::Map(map))
+            }
+            b'>' => {
+                src.advance(1);
+                let len = aggregate_len(read_line(src)?.parse::<i64>()?)?;
+                let mut items = Vec::with_capacity(len.min(PREALLOC_LEN));
+
+                for _ in 0..len {
+                    items.push(RespValue::parse(src)?);
+                }
+
+                Ok(RespValue::Push(items))
+            }
             b => Err(RespError::InvalidType(b as char)),
         }
     }
 
-    /// Serialize a RESP value to bytes
+    /// Parse a command sent by a client, accepting inline commands as well as RESP
+    ///
+    /// A line that doesn't start with a RESP type marker, like `SET foo bar`
+    /// typed into telnet, is split on whitespace into the equivalent array
+    /// of bulk strings. Blank lines are skipped.
+    pub fn parse_command(src: &mut Cursor<&[u8]>) -> Result<RespValue, RespError> {
+        loop {
+            if !src.has_remaining() {
+                return Err(RespError::Incomplete);
+            }
+
+            if is_type_marker(src.chunk()[0]) {
+                return RespValue::parse(src);
+            }
+
+            let args = split_inline_args(read_inline_line(src)?)?;
+            if !args.is_empty() {
+                let array = args
+                    .into_iter()
+                    .map(|arg| RespValue::BulkString(Some(arg)))
+                    .collect();
+                return Ok(RespValue::Array(Some(array)));
+            }
+        }
+    }
+
+    /// Serialize a RESP value to bytes using RESP2
     pub fn serialize(&self) -> Vec<u8> {
+        self.encode(Protocol::Resp2)
+    }
+
+    /// Serialize a RESP value for the given protocol version
+    ///
+    /// RESP3-only types are downgraded to their RESP2 equivalents when needed.
+    pub fn encode(&self, protocol: Protocol) -> Vec<u8> {
+        if protocol == Protocol::Resp2 {
+            return self.encode_resp2();
+        }
+
+        match self {
+            RespValue::BulkString(None) | RespValue::Array(None) | RespValue::Null => {
+                b"_\r\n".to_vec()
+            }
+            RespValue::Boolean(b) => format!("#{}\r\n", if *b { 't' } else { 'f' }).into_bytes(),
+            RespValue::Double(d) => format!(",{}\r\n", format_double(*d)).into_bytes(),
+            RespValue::Array(Some(values)) => encode_aggregate(b'*', values, protocol),
+            RespValue::Push(values) => encode_aggregate(b'>', values, protocol),
+            RespValue::Map(pairs) => {
+                let mut result = format!("%{}\r\n", pairs.len()).into_bytes();
+                for (key, value) in pairs {
+                    result.extend_from_slice(&key.encode(protocol));
+                    result.extend_from_slice(&value.encode(protocol));
+                }
+                result
+            }
+            other => other.encode_resp2(),
+        }
+    }
+
+    fn encode_resp2(&self) -> Vec<u8> {
         match self {
             RespValue::SimpleString(s) => format!("+{}\r\n", s).into_bytes(),
             RespValue::Error(s) => format!("-{}\r\n", s).into_bytes(),
@@ -105,10 +247,20 @@ impl RespValue {
                 result
             }
             RespValue::Array(None) => b"*-1\r\n".to_vec(),
-            RespValue::Array(Some(values)) => {
-                let mut result = format!("*{}\r\n", values.len()).into_bytes();
-                for value in values {
-                    result.extend_from_slice(&value.serialize());
+            RespValue::Array(Some(values)) | RespValue::Push(values) => {
+                encode_aggregate(b'*', values, Protocol::Resp2)
+            }
+            RespValue::Null => b"$-1\r\n".to_vec(),
+            RespValue::Boolean(b) => format!(":{}\r\n", *b as i64).into_bytes(),
+            RespValue::Double(d) => {
+                RespValue::BulkString(Some(format_double(*d).into_bytes())).encode_resp2()
+            }
+            RespValue::Map(pairs) => {
+                // Maps flatten into [key, value, key, value, ...]
+                let mut result = format!("*{}\r\n", pairs.len() * 2).into_bytes();
+                for (key, value) in pairs {
+                    result.extend_from_slice(&key.encode_resp2());
+                    result.extend_from_slice(&value.encode_resp2());
                 }
                 result
             }
@@ -144,6 +296,27 @@ impl RespValue {
     }
 }
 
+/// Encode an array-like value with the given type marker
+fn encode_aggregate(marker: u8, values: &[RespValue], protocol: Protocol) -> Vec<u8> {
+    let mut result = vec![marker];
+    result.extend_from_slice(format!("{}\r\n", values.len()).as_bytes());
+    for value in values {
+        result.extend_from_slice(&value.encode(protocol));
+    }
+    result
+}
+
+/// Format a double the way RESP3 expects, including infinities
+fn format_double(d: f64) -> String {
+    if d.is_infinite() {
+        if d > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else if d.is_nan() {
+        "nan".to_string()
+    } else {
+        d.to_string()
+    }
+}
+
 /// Read a line from the cursor (until \r\n)
 fn read_line(src: &mut Cursor<&[u8]>) -> Result<String, RespError> {
     let start = src.position() as usize;
@@ -157,9 +330,127 @@ fn read_line(src: &mut Cursor<&[u8]>) -> Result<String, RespError> {
         }
     }
 
+    check_line_len(slice.len() - start)?;
     Err(RespError::Incomplete)
 }
 
+/// Fail once a line has grown past `MAX_INLINE_LEN` without ending, so a
+/// client can't make the read buffer grow without bound
+fn check_line_len(len: usize) -> Result<(), RespError> {
+    if len > MAX_INLINE_LEN {
+        return Err(RespError::InvalidFormat("too big inline request".into()));
+    }
+    Ok(())
+}
+
+/// Validate the element count of an array, map or push
+fn aggregate_len(len: i64) -> Result<usize, RespError> {
+    usize::try_from(len)
+        .ok()
+        .filter(|&len| len <= MAX_AGGREGATE_LEN)
+        .ok_or_else(|| RespError::InvalidFormat(format!("invalid multibulk length {}", len)))
+}
+
+fn is_type_marker(byte: u8) -> bool {
+    matches!(
+        byte,
+        b'+' | b'-' | b':' | b'$' | b'*' | b'_' | b'#' | b',' | b'%' | b'>'
+    )
+}
+
+/// Read an inline command line, terminated by \n with an optional \r
+fn read_inline_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], RespError> {
+    let start = src.position() as usize;
+    let slice: &'a [u8] = src.get_ref();
+
+    let Some(offset) = slice[start..].iter().position(|&b| b == b'\n') else {
+        check_line_len(slice.len() - start)?;
+        return Err(RespError::Incomplete);
+    };
+    check_line_len(offset)?;
+    src.set_position((start + offset + 1) as u64);
+
+    let line = &slice[start..start + offset];
+    Ok(line.strip_suffix(b"\r").unwrap_or(line))
+}
+
+/// Split an inline command into arguments the way redis-cli does
+///
+/// Arguments are separated by whitespace. Double-quoted arguments support
+/// `\n`, `\r`, `\t`, `\xHH` and backslash escapes; single-quoted ones
+/// only `\'`. A closing quote must be followed by whitespace.
+fn split_inline_args(line: &[u8]) -> Result<Vec<Vec<u8>>, RespError> {
+    let unbalanced = || RespError::InvalidFormat("unbalanced quotes in request".into());
+    let mut args = Vec::new();
+    let mut i = 0;
+
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == line.len() {
+            return Ok(args);
+        }
+
+        let mut arg = Vec::new();
+        match line[i] {
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                loop {
+                    let Some(&b) = line.get(i) else {
+                        return Err(unbalanced());
+                    };
+                    i += 1;
+
+                    if b == quote {
+                        break;
+                    }
+                    if b != b'\\' || i == line.len() {
+                        arg.push(b);
+                        continue;
+                    }
+
+                    let escaped = line[i];
+                    i += 1;
+                    match (quote, escaped) {
+                        (b'\'', b'\'') => arg.push(b'\''),
+                        (b'\'', _) => {
+                            arg.push(b'\\');
+                            i -= 1;
+                        }
+                        (_, b'n') => arg.push(b'\n'),
+                        (_, b'r') => arg.push(b'\r'),
+                        (_, b't') => arg.push(b'\t'),
+                        (_, b'x') => match line.get(i..i + 2).and_then(parse_hex_byte) {
+                            Some(byte) => {
+                                arg.push(byte);
+                                i += 2;
+                            }
+                            None => arg.push(b'x'),
+                        },
+                        (_, other) => arg.push(other),
+                    }
+                }
+
+                if line.get(i).is_some_and(|b| !b.is_ascii_whitespace()) {
+                    return Err(unbalanced());
+                }
+            }
+            _ => {
+                while i < line.len() && !line[i].is_ascii_whitespace() {
+                    arg.push(line[i]);
+                    i += 1;
+                }
+            }
+        }
+        args.push(arg);
+    }
+}
+
+fn parse_hex_byte(digits: &[u8]) -> Option<u8> {
+    u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -221,6 +512,130 @@ mod tests {
         );
     }
 
+    fn parse_inline(data: &[u8]) -> Result<RespValue, RespError> {
+        RespValue::parse_command(&mut Cursor::new(data))
+    }
+
+    fn command(args: &[&str]) -> RespValue {
+        RespValue::Array(Some(
+            args.iter()
+                .map(|arg| RespValue::BulkString(Some(arg.as_bytes().to_vec())))
+                .collect(),
+        ))
+    }
+
+    #[test]
+    fn test_parse_inline_command() {
+        assert_eq!(parse_inline(b"PING\r\n").unwrap(), command(&["PING"]));
+        assert_eq!(
+            parse_inline(b"SET key \"hello world\"\r\n").unwrap(),
+            command(&["SET", "key", "hello world"])
+        );
+
+        // Bare \n line endings and extra whitespace, as sent by nc
+        assert_eq!(
+            parse_inline(b"  get   key \n").unwrap(),
+            command(&["get", "key"])
+        );
+
+        // RESP input still goes through the regular parser
+        assert_eq!(
+            parse_inline(b"*1\r\n$4\r\nPING\r\n").unwrap(),
+            command(&["PING"])
+        );
+    }
+
+    #[test]
+    fn test_parse_inline_quoting() {
+        assert_eq!(
+            parse_inline(b"SET k \"a\\\"b\\n\\x41\"\r\n").unwrap(),
+            command(&["SET", "k", "a\"b\nA"])
+        );
+        assert_eq!(
+            parse_inline(b"SET k 'it\\'s \"raw\"\\n'\r\n").unwrap(),
+            command(&["SET", "k", "it's \"raw\"\\n"])
+        );
+        assert_eq!(
+            parse_inline(b"SET k \"\"\r\n").unwrap(),
+            command(&["SET", "k", ""])
+        );
+
+        assert!(matches!(
+            parse_inline(b"SET k \"open\r\n"),
+            Err(RespError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            parse_inline(b"SET k \"a\"b\r\n"),
+            Err(RespError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_inline_incomplete_and_blank() {
+        assert!(matches!(
+            parse_inline(b"SET key"),
+            Err(RespError::Incomplete)
+        ));
+
+        let data = b"\r\n\r\nPING\r\n";
+        let mut cursor = Cursor::new(&data[..]);
+        assert_eq!(
+            RespValue::parse_command(&mut cursor).unwrap(),
+            command(&["PING"])
+        );
+        assert_eq!(cursor.position() as usize, data.len());
+    }
+
+    #[test]
+    fn test_parse_inline_too_long() {
+        // Still waiting for the newline, but already past the limit
+        let unterminated = vec![b'a'; MAX_INLINE_LEN + 1];
+        assert!(matches!(
+            parse_inline(&unterminated),
+            Err(RespError::InvalidFormat(_))
+        ));
+
+        let mut line = vec![b'a'; MAX_INLINE_LEN + 1];
+        line.extend_from_slice(b"\r\n");
+        assert!(matches!(
+            parse_inline(&line),
+            Err(RespError::InvalidFormat(_))
+        ));
+
+        // Just under the limit is only incomplete
+        let pending = vec![b'a'; MAX_INLINE_LEN];
+        assert!(matches!(parse_inline(&pending), Err(RespError::Incomplete)));
+    }
+
+    #[test]
+    fn test_parse_rejects_absurd_lengths() {
+        for header in [
+            &b"%999999999999\r\n"[..],
+            b">999999999999\r\n",
+            b"*999999999999\r\n",
+            b"*-5\r\n",
+            b"$99999999999\r\n",
+            b"$-2\r\n",
+        ] {
+            let mut cursor = Cursor::new(header);
+            assert!(
+                matches!(
+                    RespValue::parse(&mut cursor),
+                    Err(RespError::InvalidFormat(_))
+                ),
+                "{:?} was accepted",
+                String::from_utf8_lossy(header)
+            );
+        }
+
+        // A plausible length is only incomplete until the elements arrive
+        let mut cursor = Cursor::new(&b"%100000\r\n"[..]);
+        assert!(matches!(
+            RespValue::parse(&mut cursor),
+            Err(RespError::Incomplete)
+        ));
+    }
+
     #[test]
     fn test_serialize_simple_string() {
         let value = RespValue::SimpleString("OK".to_string());
@@ -249,4 +664,63 @@ mod tests {
         let result = RespValue::parse(&mut cursor);
         assert!(matches!(result, Err(RespError::Incomplete)));
     }
+
+    #[test]
+    fn test_serialize_resp3_map() {
+        let value = RespValue::Map(vec![(
+            RespValue::BulkString(Some(b"proto".to_vec())),
+            RespValue::Integer(3),
+        )]);
+        assert_eq!(
+            value.encode(Protocol::Resp3),
+            b"%1\r\n$5\r\nproto\r\n:3\r\n"
+        );
+
+        // RESP2 flattens maps into arrays
+        assert_eq!(value.serialize(), b"*2\r\n$5\r\nproto\r\n:3\r\n");
+    }
+
+    #[test]
+    fn test_serialize_resp3_scalars() {
+        assert_eq!(RespValue::Boolean(true).encode(Protocol::Resp3), b"#t\r\n");
+        assert_eq!(RespValue::Boolean(false).encode(Protocol::Resp3), b"#f\r\n");
+        assert_eq!(RespValue::Boolean(true).serialize(), b":1\r\n");
+
+        assert_eq!(RespValue::Double(1.5).encode(Protocol::Resp3), b",1.5\r\n");
+        assert_eq!(
+            RespValue::Double(f64::INFINITY).encode(Protocol::Resp3),
+            b",inf\r\n"
+        );
+        assert_eq!(RespValue::Double(1.5).serialize(), b"$3\r\n1.5\r\n");
+
+        assert_eq!(
+            RespValue::BulkString(None).encode(Protocol::Resp3),
+            b"_\r\n"
+        );
+        assert_eq!(RespValue::Null.serialize(), b"$-1\r\n");
+    }
+
+    #[test]
+    fn test_resp3_roundtrip() {
+        let value = RespValue::Map(vec![
+            (
+                RespValue::SimpleString("ok".to_string()),
+                RespValue::Boolean(true),
+            ),
+            (
+                RespValue::SimpleString("score".to_string()),
+                RespValue::Double(2.25),
+            ),
+            (RespValue::SimpleString("none".to_string()), RespValue::Null),
+        ]);
+        let bytes = value.encode(Protocol::Resp3);
+        let mut cursor = Cursor::new(&bytes[..]);
+        assert_eq!(RespValue::parse(&mut cursor).unwrap(), value);
+
+        let push = RespValue::Push(vec![RespValue::BulkString(Some(b"message".to_vec()))]);
+        let bytes = push.encode(Protocol::Resp3);
+        assert_eq!(bytes[0], b'>');
+        let mut cursor = Cursor::new(&bytes[..]);
+        assert_eq!(RespValue::parse(&mut cursor).unwrap(), push);
+    }
 }
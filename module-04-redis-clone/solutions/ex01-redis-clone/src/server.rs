@@ -1,80 +1,424 @@
+use crate::aof::Aof;
+use crate::clients::ClientRegistry;
 use crate::command::Command;
-use crate::db::Db;
+use crate::db::{Db, EvictionPolicy};
 use crate::error::DbError;
-use crate::resp::RespValue;
+use crate::pubsub::{PubSub, PushSender};
+use crate::resp::{Protocol, RespValue};
+use crate::snapshot::Snapshotter;
 use bytes::BytesMut;
+use std::collections::HashSet;
 use std::io::Cursor;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::{TcpListener, TcpStream};
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, UnixListener, UnixStream};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinSet;
 use tracing::{debug, error, info, warn};
 
+/// How long open connections may keep sending commands once shutdown starts
+const SHUTDOWN_GRACE: Duration = Duration::from_millis(100);
+
 pub struct Server {
-    listener: TcpListener,
+    listener: Listener,
     db: Db,
+    pubsub: PubSub,
+    clients: ClientRegistry,
+    persistence: Persistence,
+    timeouts: Timeouts,
+    shutdown: Arc<watch::Sender<bool>>,
+}
+
+/// How long a connection may go without sending, both off by default
+#[derive(Debug, Clone, Copy, Default)]
+struct Timeouts {
+    /// Between commands; subscribed connections are exempt
+    idle: Option<Duration>,
+    /// Between reads while a command has only partly arrived
+    read: Option<Duration>,
+}
+
+/// Where the server accepts connections
+enum Listener {
+    Tcp(TcpListener),
+    /// Listening on the socket file at the path, which is removed on shutdown
+    Unix(UnixListener, PathBuf),
+}
+
+/// A client connection over either transport
+trait Socket: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Socket for T {}
+
+impl Listener {
+    /// Wait for a client, returning its connection and address for `CLIENT LIST`
+    async fn accept(&self) -> std::io::Result<(Box<dyn Socket>, String)> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (socket, addr) = listener.accept().await?;
+                Ok((Box::new(socket), addr.to_string()))
+            }
+            Listener::Unix(listener, path) => {
+                // Unix clients are unnamed, so Redis reports the server's path
+                let (socket, _) = listener.accept().await?;
+                Ok((Box::new(socket), format!("{}:0", path.display())))
+            }
+        }
+    }
+}
+
+/// Where the database is persisted, shared with every connection
+#[derive(Clone, Default)]
+struct Persistence {
+    snapshotter: Option<Snapshotter>,
+    aof: Option<Aof>,
+}
+
+/// Stops a running server from another task
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+impl ShutdownHandle {
+    /// Ask the server to stop; `run()` returns once open connections have wound down
+    pub fn shutdown(&self) {
+        self.tx.send_replace(true);
+    }
 }
 
 impl Server {
     pub async fn bind(addr: &str) -> Result<Self, std::io::Error> {
         let listener = TcpListener::bind(addr).await?;
+        Ok(Self::with_listener(Listener::Tcp(listener)))
+    }
+
+    /// Listen on a Unix domain socket instead of TCP, like `unixsocket`
+    ///
+    /// A socket file left behind by a server that is no longer running is
+    /// removed first. The file is removed again when `run()` returns.
+    pub async fn bind_unix(path: impl Into<PathBuf>) -> Result<Self, std::io::Error> {
+        let path = path.into();
+        remove_stale_socket(&path).await?;
+        let listener = UnixListener::bind(&path)?;
+        Ok(Self::with_listener(Listener::Unix(listener, path)))
+    }
+
+    fn with_listener(listener: Listener) -> Self {
         let db = Db::new();
 
         // Spawn expiration background task
         db.clone().spawn_expiration_task();
 
-        Ok(Server { listener, db })
+        Server {
+            listener,
+            db,
+            pubsub: PubSub::new(),
+            clients: ClientRegistry::new(),
+            persistence: Persistence::default(),
+            timeouts: Timeouts::default(),
+            shutdown: Arc::new(watch::channel(false).0),
+        }
     }
 
+    /// Persist the database to a snapshot file
+    ///
+    /// Loads the file if it exists, enables SAVE/BGSAVE, and saves every
+    /// `save_interval` when one is given.
+    pub async fn with_snapshot(
+        mut self,
+        path: impl Into<PathBuf>,
+        save_interval: Option<Duration>,
+    ) -> crate::error::Result<Self> {
+        let snapshotter = Snapshotter::new(path);
+
+        if tokio::fs::try_exists(snapshotter.path()).await? {
+            let keys = self.db.load_snapshot(snapshotter.path()).await?;
+            info!("Loaded {} keys from {}", keys, snapshotter.path().display());
+        }
+
+        if let Some(interval) = save_interval {
+            snapshotter
+                .clone()
+                .spawn_auto_save(self.db.clone(), interval);
+        }
+
+        self.persistence.snapshotter = Some(snapshotter);
+        Ok(self)
+    }
+
+    /// Log every write command to an append-only file
+    ///
+    /// If the file exists it's replayed, replacing anything loaded from a
+    /// snapshot since the log is the more up to date of the two. Enables
+    /// BGREWRITEAOF.
+    pub async fn with_aof(mut self, path: impl Into<PathBuf>) -> crate::error::Result<Self> {
+        let aof = Aof::open(path).await?;
+
+        let commands = aof.replay(&self.db).await?;
+        info!(
+            "Replayed {} commands from {}",
+            commands,
+            aof.path().display()
+        );
+
+        self.persistence.aof = Some(aof);
+        Ok(self)
+    }
+
+    /// Keep memory use under roughly `maxmemory` bytes by evicting keys
+    pub async fn with_maxmemory(self, maxmemory: usize, policy: EvictionPolicy) -> Self {
+        self.db.set_maxmemory(maxmemory, policy).await;
+        info!(
+            "maxmemory set to {} bytes ({:?}), {} bytes in use",
+            maxmemory,
+            policy,
+            self.db.used_memory().await
+        );
+        self
+    }
+
+    /// Close connections that send no command for `timeout`, like `timeout`
+    /// in redis.conf
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.idle = Some(timeout);
+        self
+    }
+
+    /// Close connections that stall for `timeout` partway through a command
+    ///
+    /// The timer restarts whenever more of the command arrives.
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.read = Some(timeout);
+        self
+    }
+
+    /// Handle for stopping `run()` from elsewhere, e.g. on Ctrl+C
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            tx: self.shutdown.clone(),
+        }
+    }
+
+    /// Accept connections until shut down via a `ShutdownHandle`
+    ///
+    /// On shutdown no new connections are accepted, open connections get a
+    /// short grace period to finish the requests they have sent, and the
+    /// database is saved one last time if snapshotting is configured.
     pub async fn run(&self) -> Result<(), std::io::Error> {
         info!("Redis clone server started");
 
+        let mut shutdown = self.shutdown.subscribe();
+        let mut connections = JoinSet::new();
+
         loop {
-            let (socket, addr) = self.listener.accept().await?;
-            info!("New connection from {}", addr);
+            tokio::select! {
+                result = self.listener.accept() => {
+                    let (socket, addr) = result?;
+                    info!("New connection from {}", addr);
 
-            let db = self.db.clone();
-            tokio::spawn(async move {
-                if let Err(e) = handle_connection(socket, db).await {
-                    error!("Error handling connection from {}: {}", addr, e);
+                    let db = self.db.clone();
+                    let pubsub = self.pubsub.clone();
+                    let clients = self.clients.clone();
+                    let persistence = self.persistence.clone();
+                    let timeouts = self.timeouts;
+                    let shutdown = self.shutdown.subscribe();
+                    connections.spawn(async move {
+                        if let Err(e) =
+                            handle_connection(socket, addr.clone(), db, pubsub, clients, persistence, timeouts, shutdown).await
+                        {
+                            error!("Error handling connection from {}: {}", addr, e);
+                        }
+                        info!("Connection closed: {}", addr);
+                    });
                 }
-                info!("Connection closed: {}", addr);
-            });
+
+                // Reap finished connections so the set doesn't grow forever
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+
+                _ = shutdown_requested(&mut shutdown) => break,
+            }
         }
+
+        info!(
+            "Shutting down, waiting for {} connection(s) to finish",
+            connections.len()
+        );
+        while connections.join_next().await.is_some() {}
+
+        if let Listener::Unix(_, path) = &self.listener {
+            if let Err(e) = tokio::fs::remove_file(path).await {
+                warn!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+
+        if let Some(snapshotter) = &self.persistence.snapshotter {
+            match snapshotter.save(&self.db).await {
+                Ok(keys) => info!("Saved {} keys before exiting", keys),
+                Err(e) => error!("Final save failed: {}", e),
+            }
+        }
+
+        info!("Server stopped");
+        Ok(())
     }
 }
 
-async fn handle_connection(mut socket: TcpStream, db: Db) -> Result<(), std::io::Error> {
+/// State for a single client connection
+struct ClientState {
+    id: u64,
+    protocol: Protocol,
+    /// Sender for messages pushed to this connection
+    tx: PushSender,
+    /// Channels this connection is subscribed to
+    channels: HashSet<String>,
+    /// Channel patterns this connection is subscribed to
+    patterns: HashSet<String>,
+    timeouts: Timeouts,
+    /// Flips to true when the server starts shutting down
+    shutdown: watch::Receiver<bool>,
+}
+
+impl ClientState {
+    /// Channels plus patterns, as reported in (un)subscribe replies
+    fn subscription_count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_connection(
+    socket: impl Socket,
+    addr: String,
+    db: Db,
+    pubsub: PubSub,
+    clients: ClientRegistry,
+    persistence: Persistence,
+    timeouts: Timeouts,
+    shutdown: watch::Receiver<bool>,
+) -> Result<(), std::io::Error> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut client = ClientState {
+        id: pubsub.next_subscriber_id(),
+        protocol: Protocol::default(),
+        tx,
+        channels: HashSet::new(),
+        patterns: HashSet::new(),
+        timeouts,
+        shutdown,
+    };
+    clients.register(client.id, addr).await;
+
+    let result = serve_connection(
+        socket,
+        &db,
+        &pubsub,
+        &clients,
+        &persistence,
+        &mut client,
+        rx,
+    )
+    .await;
+
+    clients.unregister(client.id).await;
+
+    // Drop any subscriptions left behind by this connection
+    for channel in &client.channels {
+        pubsub.unsubscribe(channel, client.id).await;
+    }
+    for pattern in &client.patterns {
+        pubsub.punsubscribe(pattern, client.id).await;
+    }
+
+    result
+}
+
+async fn serve_connection(
+    mut socket: impl Socket,
+    db: &Db,
+    pubsub: &PubSub,
+    clients: &ClientRegistry,
+    persistence: &Persistence,
+    client: &mut ClientState,
+    mut push_rx: mpsc::UnboundedReceiver<RespValue>,
+) -> Result<(), std::io::Error> {
     let mut buffer = BytesMut::with_capacity(4096);
+    // Deadline for finishing up once the server is shutting down
+    let mut closing_at = None;
+    let mut last_read = tokio::time::Instant::now();
 
     loop {
-        // Read data from socket
-        let n = socket.read_buf(&mut buffer).await?;
+        // Leftover bytes are a command still arriving
+        let timeout = if !buffer.is_empty() {
+            client.timeouts.read
+        } else if client.subscription_count() == 0 {
+            client.timeouts.idle
+        } else {
+            None
+        };
+        let timed_out_at = timeout.map(|timeout| last_read + timeout);
+
+        tokio::select! {
+            // Read data from socket
+            result = socket.read_buf(&mut buffer) => {
+                let n = result?;
 
-        if n == 0 {
-            // Connection closed
-            return Ok(());
+                if n == 0 {
+                    // Connection closed
+                    return Ok(());
+                }
+                last_read = tokio::time::Instant::now();
+            }
+
+            _ = tokio::time::sleep_until(timed_out_at.unwrap_or_else(tokio::time::Instant::now)),
+                if timed_out_at.is_some() => {
+                info!(
+                    "Closing connection {}: nothing received for {:?}",
+                    client.id,
+                    timeout.unwrap_or_default()
+                );
+                return Ok(());
+            }
+
+            // Deliver published messages
+            Some(message) = push_rx.recv() => {
+                socket.write_all(&message.encode(client.protocol)).await?;
+                socket.flush().await?;
+                continue;
+            }
+
+            // Keep reading briefly so requests already sent still get a reply
+            _ = shutdown_requested(&mut client.shutdown), if closing_at.is_none() => {
+                closing_at = Some(tokio::time::Instant::now() + SHUTDOWN_GRACE);
+                continue;
+            }
+
+            _ = tokio::time::sleep_until(closing_at.unwrap_or_else(tokio::time::Instant::now)),
+                if closing_at.is_some() => {
+                return Ok(());
+            }
         }
 
         // Process all complete commands in the buffer
         while !buffer.is_empty() {
             let mut cursor = Cursor::new(&buffer[..]);
 
-            match RespValue::parse(&mut cursor) {
+            match RespValue::parse_command(&mut cursor) {
                 Ok(value) => {
                     let consumed = cursor.position() as usize;
                     debug!("Parsed RESP value: {:?}", value);
 
                     // Process command
-                    let response = match process_command(value, &db).await {
-                        Ok(resp) => resp,
-                        Err(e) => {
-                            warn!("Command error: {}", e);
-                            RespValue::Error(e.to_string())
-                        }
-                    };
+                    let responses =
+                        process_command(value, db, pubsub, clients, persistence, client).await;
 
-                    // Send response
-                    let response_bytes = response.serialize();
-                    socket.write_all(&response_bytes).await?;
+                    // Send responses
+                    for response in responses {
+                        socket.write_all(&response.encode(client.protocol)).await?;
+                    }
                     socket.flush().await?;
 
                     // Remove consumed bytes from buffer
@@ -96,10 +440,373 @@ async fn handle_connection(mut socket: TcpStream, db: Db) -> Result<(), std::io:
     }
 }
 
-async fn process_command(value: RespValue, db: &Db) -> Result<RespValue, DbError> {
-    let command = Command::from_resp(value)?;
+/// Remove a socket file left behind by a server that didn't shut down cleanly
+///
+/// Fails if a server is still accepting connections on it, or if the path
+/// is something other than a socket.
+async fn remove_stale_socket(path: &Path) -> std::io::Result<()> {
+    match tokio::fs::symlink_metadata(path).await {
+        Ok(metadata) if metadata.file_type().is_socket() => {}
+        Ok(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    }
+
+    if UnixStream::connect(path).await.is_ok() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AddrInUse,
+            format!("a server is already listening on {}", path.display()),
+        ));
+    }
+
+    info!("Removing stale socket {}", path.display());
+    tokio::fs::remove_file(path).await
+}
+
+/// Resolve once the server has been asked to shut down
+async fn shutdown_requested(shutdown: &mut watch::Receiver<bool>) {
+    // An error means the server itself is gone, which is as good as a shutdown
+    let _ = shutdown.wait_for(|&stop| stop).await;
+}
+
+/// Run a command for a connection, returning the replies to send back
+async fn process_command(
+    value: RespValue,
+    db: &Db,
+    pubsub: &PubSub,
+    clients: &ClientRegistry,
+    persistence: &Persistence,
+    client: &mut ClientState,
+) -> Vec<RespValue> {
+    let Persistence { snapshotter, aof } = persistence;
+    let name = command_name(&value);
+    // Kept to be logged as sent if the command turns out to be a write
+    let request = aof.as_ref().map(|_| value.clone());
+    let command = match Command::from_resp(value) {
+        Ok(command) => command,
+        Err(e) => {
+            warn!("Command error: {}", e);
+            return vec![error_response(&e.into())];
+        }
+    };
     debug!("Executing command: {:?}", command);
-    command.execute(db).await
+    clients.record_command(client.id, &name).await;
+
+    if client.subscription_count() > 0 && !command.allowed_while_subscribed() {
+        return vec![RespValue::Error(
+            "ERR only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING are allowed in this context".to_string(),
+        )];
+    }
+
+    match command {
+        Command::Subscribe { channels } => {
+            let mut replies = Vec::new();
+            for channel in channels {
+                if client.channels.insert(channel.clone()) {
+                    pubsub
+                        .subscribe(&channel, client.id, client.tx.clone())
+                        .await;
+                }
+                replies.push(subscription_reply(
+                    "subscribe",
+                    Some(channel),
+                    client.subscription_count(),
+                ));
+            }
+            replies
+        }
+
+        Command::Unsubscribe { channels } => {
+            // No channels means unsubscribe from everything
+            let channels = if channels.is_empty() {
+                client.channels.iter().cloned().collect()
+            } else {
+                channels
+            };
+
+            if channels.is_empty() {
+                return vec![subscription_reply(
+                    "unsubscribe",
+                    None,
+                    client.subscription_count(),
+                )];
+            }
+
+            let mut replies = Vec::new();
+            for channel in channels {
+                if client.channels.remove(&channel) {
+                    pubsub.unsubscribe(&channel, client.id).await;
+                }
+                replies.push(subscription_reply(
+                    "unsubscribe",
+                    Some(channel),
+                    client.subscription_count(),
+                ));
+            }
+            replies
+        }
+
+        Command::PSubscribe { patterns } => {
+            let mut replies = Vec::new();
+            for pattern in patterns {
+                if client.patterns.insert(pattern.clone()) {
+                    pubsub
+                        .psubscribe(&pattern, client.id, client.tx.clone())
+                        .await;
+                }
+                replies.push(subscription_reply(
+                    "psubscribe",
+                    Some(pattern),
+                    client.subscription_count(),
+                ));
+            }
+            replies
+        }
+
+        Command::PUnsubscribe { patterns } => {
+            // No patterns means unsubscribe from every pattern
+            let patterns = if patterns.is_empty() {
+                client.patterns.iter().cloned().collect()
+            } else {
+                patterns
+            };
+
+            if patterns.is_empty() {
+                return vec![subscription_reply(
+                    "punsubscribe",
+                    None,
+                    client.subscription_count(),
+                )];
+            }
+
+            let mut replies = Vec::new();
+            for pattern in patterns {
+                if client.patterns.remove(&pattern) {
+                    pubsub.punsubscribe(&pattern, client.id).await;
+                }
+                replies.push(subscription_reply(
+                    "punsubscribe",
+                    Some(pattern),
+                    client.subscription_count(),
+                ));
+            }
+            replies
+        }
+
+        Command::Hello { protover } => {
+            match protover {
+                None => {}
+                Some(2) => client.protocol = Protocol::Resp2,
+                Some(3) => client.protocol = Protocol::Resp3,
+                Some(_) => {
+                    return vec![RespValue::Error(
+                        "NOPROTO unsupported protocol version".to_string(),
+                    )]
+                }
+            }
+            vec![hello_reply(client)]
+        }
+
+        Command::ClientList => {
+            let lines: String = clients
+                .list()
+                .await
+                .iter()
+                .map(|info| info.to_line() + "\n")
+                .collect();
+            vec![RespValue::BulkString(Some(lines.into_bytes()))]
+        }
+
+        Command::Info { section } => {
+            let info = info_reply(db, clients, section.as_deref()).await;
+            vec![RespValue::BulkString(Some(info.into_bytes()))]
+        }
+
+        Command::Save | Command::BgSave => {
+            let Some(snapshotter) = snapshotter else {
+                return vec![RespValue::Error(
+                    "ERR snapshotting is not configured".to_string(),
+                )];
+            };
+
+            if matches!(command, Command::BgSave) {
+                if snapshotter.start_background_save(db.clone()) {
+                    return vec![RespValue::SimpleString(
+                        "Background saving started".to_string(),
+                    )];
+                }
+                return vec![RespValue::Error(
+                    "ERR Background save already in progress".to_string(),
+                )];
+            }
+
+            match snapshotter.save(db).await {
+                Ok(_) => vec![RespValue::SimpleString("OK".to_string())],
+                Err(e) => {
+                    error!("SAVE failed: {}", e);
+                    vec![error_response(&e)]
+                }
+            }
+        }
+
+        Command::BgRewriteAof => {
+            let Some(aof) = aof else {
+                return vec![RespValue::Error(
+                    "ERR append only file is not enabled".to_string(),
+                )];
+            };
+
+            if aof.start_background_rewrite(db.clone()) {
+                vec![RespValue::SimpleString(
+                    "Background append only file rewriting started".to_string(),
+                )]
+            } else {
+                vec![RespValue::Error(
+                    "ERR Background append only file rewriting already in progress".to_string(),
+                )]
+            }
+        }
+
+        Command::Publish { channel, message } => {
+            let receivers = pubsub.publish(&channel, message).await;
+            vec![RespValue::Integer(receivers as i64)]
+        }
+
+        command => {
+            let logged = aof.as_ref().zip(request).filter(|_| command.is_write());
+            let expiry_key = command.relative_expiry_key().map(str::to_string);
+            // Applying and logging happen under one lock so concurrent writes
+            // are logged in the order they were applied, and a rewrite can't
+            // start in between
+            let _writing = match &logged {
+                Some((aof, _)) => Some(aof.begin_write().await),
+                None => None,
+            };
+
+            match command.execute(db).await {
+                Ok(resp) => {
+                    if let Some((aof, request)) = logged {
+                        if let Err(e) = aof.log_write(db, request, expiry_key.as_deref()).await {
+                            error!("Failed to append to the AOF: {}", e);
+                        }
+                    }
+                    vec![resp]
+                }
+                Err(e) => {
+                    warn!("Command error: {}", e);
+                    vec![error_response(&e)]
+                }
+            }
+        }
+    }
+}
+
+/// Name of the command in a request, for CLIENT LIST
+fn command_name(value: &RespValue) -> String {
+    match value {
+        RespValue::Array(Some(items)) => items
+            .first()
+            .and_then(|name| name.as_str().ok())
+            .unwrap_or_default()
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Build the INFO report, limited to one section when `section` is given
+async fn info_reply(db: &Db, clients: &ClientRegistry, section: Option<&str>) -> String {
+    let (keys, expires) = db.key_counts().await;
+    let sections = [
+        (
+            "server",
+            format!(
+                "redis_version:{}\r\nuptime_in_seconds:{}\r\n",
+                env!("CARGO_PKG_VERSION"),
+                clients.uptime_secs()
+            ),
+        ),
+        (
+            "clients",
+            format!("connected_clients:{}\r\n", clients.connected().await),
+        ),
+        (
+            "memory",
+            format!("used_memory:{}\r\n", db.used_memory().await),
+        ),
+        (
+            "stats",
+            format!(
+                "total_connections_received:{}\r\ntotal_commands_processed:{}\r\n",
+                clients.total_connections(),
+                clients.total_commands()
+            ),
+        ),
+        (
+            "keyspace",
+            format!("db0:keys={},expires={}\r\n", keys, expires),
+        ),
+    ];
+
+    let everything = matches!(
+        section,
+        None | Some("all") | Some("default") | Some("everything")
+    );
+    sections
+        .iter()
+        .filter(|(name, _)| everything || section == Some(*name))
+        .map(|(name, body)| {
+            let mut title = name.to_string();
+            title[..1].make_ascii_uppercase();
+            format!("# {}\r\n{}", title, body)
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// Build a (un)subscribe confirmation: [kind, channel or pattern, subscription count]
+fn subscription_reply(kind: &str, channel: Option<String>, count: usize) -> RespValue {
+    RespValue::Push(vec![
+        RespValue::BulkString(Some(kind.as_bytes().to_vec())),
+        RespValue::BulkString(channel.map(String::into_bytes)),
+        RespValue::Integer(count as i64),
+    ])
+}
+
+/// Build the server info map returned by HELLO
+fn hello_reply(client: &ClientState) -> RespValue {
+    let field = |name: &str| RespValue::BulkString(Some(name.as_bytes().to_vec()));
+    let proto = match client.protocol {
+        Protocol::Resp2 => 2,
+        Protocol::Resp3 => 3,
+    };
+
+    RespValue::Map(vec![
+        (field("server"), field("redis")),
+        (field("version"), field(env!("CARGO_PKG_VERSION"))),
+        (field("proto"), RespValue::Integer(proto)),
+        (field("id"), RespValue::Integer(client.id as i64)),
+        (field("mode"), field("standalone")),
+        (field("role"), field("master")),
+        (field("modules"), RespValue::Array(Some(Vec::new()))),
+    ])
+}
+
+/// Translate a database error into the error reply a Redis client expects
+fn error_response(err: &DbError) -> RespValue {
+    match err {
+        DbError::WrongType => RespValue::Error(
+            "WRONGTYPE Operation against a key holding the wrong kind of value".to_string(),
+        ),
+        DbError::NotInteger | DbError::NotFloat | DbError::BusyKey | DbError::InvalidDump => {
+            RespValue::Error(err.to_string())
+        }
+        other => RespValue::Error(format!("ERR {}", other)),
+    }
 }
 
 use bytes::Buf;
@@ -107,12 +814,21 @@ use bytes::Buf;
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::net::SocketAddr;
     use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpStream;
+
+    fn tcp_addr(server: &Server) -> SocketAddr {
+        match &server.listener {
+            Listener::Tcp(listener) => listener.local_addr().unwrap(),
+            Listener::Unix(..) => panic!("server isn't listening on TCP"),
+        }
+    }
 
     #[tokio::test]
     async fn test_server_ping() {
         let server = Server::bind("127.0.0.1:0").await.unwrap();
-        let addr = server.listener.local_addr().unwrap();
+        let addr = tcp_addr(&server);
 
         tokio::spawn(async move {
             server.run().await.unwrap();
@@ -137,10 +853,197 @@ mod tests {
         assert_eq!(response, RespValue::SimpleString("PONG".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_server_inline_commands() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = tcp_addr(&server);
+
+        tokio::spawn(async move {
+            server.run().await.unwrap();
+        });
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+
+        // Typed by hand, the way telnet or nc would send it
+        client
+            .write_all(b"SET greeting \"hello world\"\r\nGET greeting\n")
+            .await
+            .unwrap();
+        client.flush().await.unwrap();
+
+        let mut buffer = BytesMut::with_capacity(1024);
+        let expected = b"+OK\r\n$11\r\nhello world\r\n";
+        while buffer.len() < expected.len() {
+            client.read_buf(&mut buffer).await.unwrap();
+        }
+        assert_eq!(&buffer[..], expected);
+    }
+
+    #[tokio::test]
+    async fn test_server_graceful_shutdown() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = tcp_addr(&server);
+        let handle = server.shutdown_handle();
+
+        let running = tokio::spawn(async move { server.run().await });
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let mut idle = TcpStream::connect(addr).await.unwrap();
+        assert_eq!(
+            send_and_read(&mut idle, &[b"PING"]).await,
+            RespValue::SimpleString("PONG".to_string())
+        );
+
+        // Shut down while a request is on its way to the server
+        let request = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"SET".to_vec())),
+            RespValue::BulkString(Some(b"key".to_vec())),
+            RespValue::BulkString(Some(b"value".to_vec())),
+        ]));
+        client.write_all(&request.serialize()).await.unwrap();
+        handle.shutdown();
+
+        assert_eq!(
+            read_value(&mut client).await,
+            RespValue::SimpleString("OK".to_string())
+        );
+
+        let result = tokio::time::timeout(tokio::time::Duration::from_secs(5), running)
+            .await
+            .expect("run() should return after shutdown")
+            .unwrap();
+        assert!(result.is_ok());
+
+        // Idle connections are closed rather than left hanging
+        let mut buffer = BytesMut::new();
+        assert_eq!(idle.read_buf(&mut buffer).await.unwrap(), 0);
+
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_saves_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dump.rdb");
+
+        let server = Server::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .with_snapshot(&path, None)
+            .await
+            .unwrap();
+        server
+            .db
+            .set("key".to_string(), b"value".to_vec())
+            .await
+            .unwrap();
+
+        let handle = server.shutdown_handle();
+        let running = tokio::spawn(async move { server.run().await });
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+
+        let db = Db::new();
+        assert_eq!(db.load_snapshot(&path).await.unwrap(), 1);
+        assert_eq!(db.get("key").await.unwrap(), Some(b"value".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_aof_replay_rebuilds_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("appendonly.aof");
+
+        let server = Server::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .with_aof(&path)
+            .await
+            .unwrap();
+        let addr = tcp_addr(&server);
+        let db = server.db.clone();
+        let aof = server.persistence.aof.clone().unwrap();
+        tokio::spawn(async move { server.run().await });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let commands: &[&[&[u8]]] = &[
+            &[b"SET", b"greeting", b"hello"],
+            &[b"APPEND", b"greeting", b" world"],
+            &[b"INCR", b"counter"],
+            &[b"INCRBY", b"counter", b"41"],
+            &[b"RPUSH", b"list", b"a", b"b", b"c"],
+            &[b"LPOP", b"list"],
+            &[b"SADD", b"set", b"x", b"y"],
+            &[b"HSET", b"hash", b"field", b"value"],
+            &[b"ZADD", b"board", b"10", b"alice", b"2.5", b"bob"],
+            &[b"SET", b"volatile", b"soon", b"EX", b"100"],
+            &[b"SET", b"gone", b"bye"],
+            &[b"DEL", b"gone"],
+            &[b"RENAME", b"set", b"members"],
+            // Reads and failed writes leave no trace
+            &[b"GET", b"greeting"],
+            &[b"INCR", b"greeting"],
+        ];
+        for command in commands {
+            send_and_read(&mut client, command).await;
+        }
+
+        let replayed = Server::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .with_aof(&path)
+            .await
+            .unwrap();
+        assert_same_entries(&db, &replayed.db).await;
+
+        // Compacting the log rebuilds the same state from fewer commands
+        let before = std::fs::metadata(&path).unwrap().len();
+        aof.rewrite(&db).await.unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() < before);
+
+        let rewritten = Server::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .with_aof(&path)
+            .await
+            .unwrap();
+        assert_same_entries(&db, &rewritten.db).await;
+
+        // Writes after a rewrite are appended to the new log
+        send_and_read(&mut client, &[b"SET", b"after", b"rewrite"]).await;
+        let latest = Db::new();
+        aof.replay(&latest).await.unwrap();
+        assert_eq!(
+            latest.get("after").await.unwrap(),
+            Some(b"rewrite".to_vec())
+        );
+    }
+
+    /// Assert two databases hold the same keys, values and (nearly) the same expiries
+    async fn assert_same_entries(expected: &Db, actual: &Db) {
+        let mut expected = expected.snapshot_entries().await;
+        let mut actual = actual.snapshot_entries().await;
+        expected.sort_by(|a, b| a.key.cmp(&b.key));
+        actual.sort_by(|a, b| a.key.cmp(&b.key));
+
+        assert_eq!(expected.len(), actual.len());
+        for (expected, actual) in expected.iter().zip(&actual) {
+            assert_eq!(expected.key, actual.key);
+            assert_eq!(expected.value, actual.value, "value of {}", expected.key);
+            match (expected.expires_at_ms, actual.expires_at_ms) {
+                (Some(a), Some(b)) => assert!(a.abs_diff(b) < 50, "expiry of {}", expected.key),
+                (a, b) => assert_eq!(a, b, "expiry of {}", expected.key),
+            }
+        }
+    }
+
     #[tokio::test]
     async fn test_server_set_get() {
         let server = Server::bind("127.0.0.1:0").await.unwrap();
-        let addr = server.listener.local_addr().unwrap();
+        let addr = tcp_addr(&server);
 
         tokio::spawn(async move {
             server.run().await.unwrap();
@@ -184,4 +1087,404 @@ mod tests {
             RespValue::BulkString(Some(b"myvalue".to_vec()))
         );
     }
+
+    #[tokio::test]
+    async fn test_server_wrongtype_error() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = tcp_addr(&server);
+
+        tokio::spawn(async move {
+            server.run().await.unwrap();
+        });
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let mut buffer = BytesMut::with_capacity(1024);
+
+        let set = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"SET".to_vec())),
+            RespValue::BulkString(Some(b"mykey".to_vec())),
+            RespValue::BulkString(Some(b"myvalue".to_vec())),
+        ]));
+        client.write_all(&set.serialize()).await.unwrap();
+        client.read_buf(&mut buffer).await.unwrap();
+        buffer.clear();
+
+        // TYPE reports the string
+        let type_cmd = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"TYPE".to_vec())),
+            RespValue::BulkString(Some(b"mykey".to_vec())),
+        ]));
+        client.write_all(&type_cmd.serialize()).await.unwrap();
+        client.read_buf(&mut buffer).await.unwrap();
+        let mut cursor = Cursor::new(&buffer[..]);
+        let response = RespValue::parse(&mut cursor).unwrap();
+        assert_eq!(response, RespValue::SimpleString("string".to_string()));
+        buffer.clear();
+
+        // A list operation on it fails with WRONGTYPE
+        let lpush = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"LPUSH".to_vec())),
+            RespValue::BulkString(Some(b"mykey".to_vec())),
+            RespValue::BulkString(Some(b"item".to_vec())),
+        ]));
+        client.write_all(&lpush.serialize()).await.unwrap();
+        client.read_buf(&mut buffer).await.unwrap();
+        let mut cursor = Cursor::new(&buffer[..]);
+        let response = RespValue::parse(&mut cursor).unwrap();
+        assert_eq!(
+            response,
+            RespValue::Error(
+                "WRONGTYPE Operation against a key holding the wrong kind of value".to_string()
+            )
+        );
+    }
+
+    async fn send_and_read(client: &mut (impl Socket + ?Sized), command: &[&[u8]]) -> RespValue {
+        let request = RespValue::Array(Some(
+            command
+                .iter()
+                .map(|part| RespValue::BulkString(Some(part.to_vec())))
+                .collect(),
+        ));
+        client.write_all(&request.serialize()).await.unwrap();
+        read_value(client).await
+    }
+
+    async fn read_value(client: &mut (impl Socket + ?Sized)) -> RespValue {
+        let mut buffer = BytesMut::with_capacity(1024);
+        loop {
+            client.read_buf(&mut buffer).await.unwrap();
+            let mut cursor = Cursor::new(&buffer[..]);
+            match RespValue::parse(&mut cursor) {
+                Ok(value) => return value,
+                Err(crate::error::RespError::Incomplete) => continue,
+                Err(e) => panic!("parse error: {}", e),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_client_list_and_info() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = tcp_addr(&server);
+
+        tokio::spawn(async move {
+            server.run().await.unwrap();
+        });
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let mut first = TcpStream::connect(addr).await.unwrap();
+        let mut second = TcpStream::connect(addr).await.unwrap();
+
+        for key in [&b"a"[..], b"b", b"c"] {
+            let reply = send_and_read(&mut first, &[b"SET", key, b"v"]).await;
+            assert_eq!(reply, RespValue::SimpleString("OK".to_string()));
+        }
+
+        let reply = send_and_read(&mut second, &[b"CLIENT", b"LIST"]).await;
+        let RespValue::BulkString(Some(list)) = reply else {
+            panic!("Expected bulk string, got {:?}", reply);
+        };
+        let list = String::from_utf8(list).unwrap();
+        let lines: Vec<&str> = list.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("cmd=set"));
+        assert!(lines[1].ends_with("cmd=client"));
+        for (line, stream) in lines.iter().zip([&first, &second]) {
+            let local = stream.local_addr().unwrap();
+            assert!(line.contains(&format!("addr={} ", local)));
+        }
+
+        let reply = send_and_read(&mut second, &[b"INFO"]).await;
+        let RespValue::BulkString(Some(info)) = reply else {
+            panic!("Expected bulk string, got {:?}", reply);
+        };
+        let info = String::from_utf8(info).unwrap();
+        assert!(info.contains("connected_clients:2\r\n"));
+        assert!(info.contains("total_commands_processed:5\r\n"));
+        assert!(info.contains("# Keyspace\r\ndb0:keys=3,expires=0\r\n"));
+
+        let reply = send_and_read(&mut first, &[b"INFO", b"keyspace"]).await;
+        assert_eq!(
+            reply,
+            RespValue::BulkString(Some(b"# Keyspace\r\ndb0:keys=3,expires=0\r\n".to_vec()))
+        );
+
+        // Closed connections drop out of the list
+        drop(first);
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let reply = send_and_read(&mut second, &[b"INFO", b"clients"]).await;
+        assert_eq!(
+            reply,
+            RespValue::BulkString(Some(b"# Clients\r\nconnected_clients:1\r\n".to_vec()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("redis.sock");
+
+        // A socket file left over from a server that's gone
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let server = Server::bind_unix(&path).await.unwrap();
+        let shutdown = server.shutdown_handle();
+        let handle = tokio::spawn(async move { server.run().await });
+
+        let mut client = UnixStream::connect(&path).await.unwrap();
+        assert_eq!(
+            send_and_read(&mut client, &[b"PING"]).await,
+            RespValue::SimpleString("PONG".to_string())
+        );
+        assert_eq!(
+            send_and_read(&mut client, &[b"SET", b"key", b"value"]).await,
+            RespValue::SimpleString("OK".to_string())
+        );
+        assert_eq!(
+            send_and_read(&mut client, &[b"GET", b"key"]).await,
+            RespValue::BulkString(Some(b"value".to_vec()))
+        );
+
+        // Clients are listed under the socket path
+        let reply = send_and_read(&mut client, &[b"CLIENT", b"LIST"]).await;
+        let RespValue::BulkString(Some(list)) = reply else {
+            panic!("Expected bulk string, got {:?}", reply);
+        };
+        let list = String::from_utf8(list).unwrap();
+        assert!(list.contains(&format!("addr={}:0 ", path.display())));
+
+        // A second server can't take over a live socket
+        assert!(Server::bind_unix(&path).await.is_err());
+
+        drop(client);
+        shutdown.shutdown();
+        handle.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    /// Wait for the server to close `client`, failing if it takes over `limit`
+    async fn assert_closed_within(client: &mut TcpStream, limit: Duration) {
+        let mut buf = [0u8; 64];
+        let read = tokio::time::timeout(limit, client.read(&mut buf))
+            .await
+            .expect("connection was never closed");
+        assert_eq!(read.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_server_closes_stalled_command() {
+        let server = Server::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .with_read_timeout(Duration::from_millis(200));
+        let addr = tcp_addr(&server);
+        tokio::spawn(async move { server.run().await });
+
+        // Half of `SET key value`, then nothing more
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let start = tokio::time::Instant::now();
+        client
+            .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nke")
+            .await
+            .unwrap();
+        assert_closed_within(&mut client, Duration::from_secs(5)).await;
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // Each partial read restarts the timer
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let request = b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n";
+        for chunk in request.chunks(8) {
+            client.write_all(chunk).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(
+            read_value(&mut client).await,
+            RespValue::SimpleString("OK".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_closes_idle_connection() {
+        let server = Server::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .with_idle_timeout(Duration::from_millis(200));
+        let addr = tcp_addr(&server);
+        tokio::spawn(async move { server.run().await });
+
+        let mut idle = TcpStream::connect(addr).await.unwrap();
+        assert_eq!(
+            send_and_read(&mut idle, &[b"PING"]).await,
+            RespValue::SimpleString("PONG".to_string())
+        );
+        assert_closed_within(&mut idle, Duration::from_secs(5)).await;
+
+        // Subscribers wait on the server, so they're never idle
+        let mut subscriber = TcpStream::connect(addr).await.unwrap();
+        send_and_read(&mut subscriber, &[b"SUBSCRIBE", b"news"]).await;
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(
+            send_and_read(&mut subscriber, &[b"PING"]).await,
+            RespValue::SimpleString("PONG".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_pubsub() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = tcp_addr(&server);
+
+        tokio::spawn(async move {
+            server.run().await.unwrap();
+        });
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let mut sub1 = TcpStream::connect(addr).await.unwrap();
+        let mut sub2 = TcpStream::connect(addr).await.unwrap();
+        let mut publisher = TcpStream::connect(addr).await.unwrap();
+
+        for sub in [&mut sub1, &mut sub2] {
+            let reply = send_and_read(sub, &[b"SUBSCRIBE", b"news"]).await;
+            assert_eq!(
+                reply,
+                RespValue::Array(Some(vec![
+                    RespValue::BulkString(Some(b"subscribe".to_vec())),
+                    RespValue::BulkString(Some(b"news".to_vec())),
+                    RespValue::Integer(1),
+                ]))
+            );
+        }
+
+        let reply = send_and_read(&mut publisher, &[b"PUBLISH", b"news", b"hello"]).await;
+        assert_eq!(reply, RespValue::Integer(2));
+
+        let expected = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"message".to_vec())),
+            RespValue::BulkString(Some(b"news".to_vec())),
+            RespValue::BulkString(Some(b"hello".to_vec())),
+        ]));
+        assert_eq!(read_value(&mut sub1).await, expected);
+        assert_eq!(read_value(&mut sub2).await, expected);
+
+        // Subscribed connections can't run regular commands
+        let reply = send_and_read(&mut sub1, &[b"GET", b"key"]).await;
+        assert!(matches!(reply, RespValue::Error(_)));
+
+        // Unsubscribing leaves subscribed mode
+        let reply = send_and_read(&mut sub1, &[b"UNSUBSCRIBE"]).await;
+        assert_eq!(
+            reply,
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"unsubscribe".to_vec())),
+                RespValue::BulkString(Some(b"news".to_vec())),
+                RespValue::Integer(0),
+            ]))
+        );
+        let reply = send_and_read(&mut sub1, &[b"GET", b"key"]).await;
+        assert_eq!(reply, RespValue::BulkString(None));
+
+        let reply = send_and_read(&mut publisher, &[b"PUBLISH", b"news", b"again"]).await;
+        assert_eq!(reply, RespValue::Integer(1));
+    }
+
+    #[tokio::test]
+    async fn test_server_psubscribe() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = tcp_addr(&server);
+
+        tokio::spawn(async move {
+            server.run().await.unwrap();
+        });
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let mut sub = TcpStream::connect(addr).await.unwrap();
+        let mut publisher = TcpStream::connect(addr).await.unwrap();
+
+        let reply = send_and_read(&mut sub, &[b"PSUBSCRIBE", b"news.*"]).await;
+        assert_eq!(
+            reply,
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"psubscribe".to_vec())),
+                RespValue::BulkString(Some(b"news.*".to_vec())),
+                RespValue::Integer(1),
+            ]))
+        );
+
+        let reply = send_and_read(&mut publisher, &[b"PUBLISH", b"weather", b"rain"]).await;
+        assert_eq!(reply, RespValue::Integer(0));
+        let reply = send_and_read(&mut publisher, &[b"PUBLISH", b"news.sports", b"goal"]).await;
+        assert_eq!(reply, RespValue::Integer(1));
+
+        assert_eq!(
+            read_value(&mut sub).await,
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"pmessage".to_vec())),
+                RespValue::BulkString(Some(b"news.*".to_vec())),
+                RespValue::BulkString(Some(b"news.sports".to_vec())),
+                RespValue::BulkString(Some(b"goal".to_vec())),
+            ]))
+        );
+
+        // Pattern subscriptions alone keep the connection in subscribed mode
+        let reply = send_and_read(&mut sub, &[b"GET", b"key"]).await;
+        assert!(matches!(reply, RespValue::Error(_)));
+
+        let reply = send_and_read(&mut sub, &[b"PUNSUBSCRIBE"]).await;
+        assert_eq!(
+            reply,
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"punsubscribe".to_vec())),
+                RespValue::BulkString(Some(b"news.*".to_vec())),
+                RespValue::Integer(0),
+            ]))
+        );
+        let reply = send_and_read(&mut publisher, &[b"PUBLISH", b"news.sports", b"again"]).await;
+        assert_eq!(reply, RespValue::Integer(0));
+    }
+
+    #[tokio::test]
+    async fn test_server_hello_handshake() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = tcp_addr(&server);
+
+        tokio::spawn(async move {
+            server.run().await.unwrap();
+        });
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+
+        // RESP2 by default: nulls are $-1
+        let reply = send_and_read(&mut client, &[b"GET", b"missing"]).await;
+        assert_eq!(reply, RespValue::BulkString(None));
+
+        let reply = send_and_read(&mut client, &[b"HELLO", b"3"]).await;
+        let fields = match reply {
+            RespValue::Map(fields) => fields,
+            other => panic!("Expected map, got {:?}", other),
+        };
+        assert!(fields.contains(&(
+            RespValue::BulkString(Some(b"proto".to_vec())),
+            RespValue::Integer(3)
+        )));
+
+        // RESP3 encodes nulls as _
+        let reply = send_and_read(&mut client, &[b"GET", b"missing"]).await;
+        assert_eq!(reply, RespValue::Null);
+
+        let reply = send_and_read(&mut client, &[b"HELLO", b"4"]).await;
+        assert!(matches!(reply, RespValue::Error(msg) if msg.starts_with("NOPROTO")));
+
+        // Switching back to RESP2 returns a flattened map
+        let reply = send_and_read(&mut client, &[b"HELLO", b"2"]).await;
+        assert!(matches!(reply, RespValue::Array(Some(items)) if items.len() == 14));
+    }
 }
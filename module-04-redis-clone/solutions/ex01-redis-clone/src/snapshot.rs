@@ -0,0 +1,232 @@
+use crate::db::{Db, Value};
+use crate::error::{DbError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Magic bytes at the start of every snapshot file
+const MAGIC: &[u8; 8] = b"RCLNSNAP";
+
+/// Current snapshot format version
+const VERSION: u32 = 1;
+
+/// A single key as stored in a snapshot
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub key: String,
+    pub value: Value,
+    /// Absolute expiry in milliseconds since the Unix epoch
+    pub expires_at_ms: Option<u64>,
+}
+
+/// Encode entries as `MAGIC | version (u32 LE) | bincode(entries)`
+pub fn encode(entries: &[SnapshotEntry]) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(MAGIC.len() + 4);
+    buf.extend_from_slice(MAGIC);
+    buf.extend_from_slice(&VERSION.to_le_bytes());
+    bincode::serialize_into(&mut buf, entries)?;
+    Ok(buf)
+}
+
+/// Decode a snapshot produced by [`encode`]
+pub fn decode(bytes: &[u8]) -> Result<Vec<SnapshotEntry>> {
+    let header_len = MAGIC.len() + 4;
+    if bytes.len() < header_len || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(DbError::InvalidSnapshot("missing magic header".into()));
+    }
+
+    let version = u32::from_le_bytes(bytes[MAGIC.len()..header_len].try_into().unwrap());
+    if version != VERSION {
+        return Err(DbError::InvalidSnapshot(format!(
+            "unsupported version {}",
+            version
+        )));
+    }
+
+    Ok(bincode::deserialize(&bytes[header_len..])?)
+}
+
+/// Write a snapshot file, replacing any previous one atomically
+pub async fn write_file(path: &Path, entries: &[SnapshotEntry]) -> Result<()> {
+    let bytes = encode(entries)?;
+
+    // Write to a temporary file first so a crash never leaves a torn snapshot
+    let tmp_path = path.with_extension("tmp");
+    tokio::fs::write(&tmp_path, bytes).await?;
+    tokio::fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
+
+/// Read the entries stored in a snapshot file
+pub async fn read_file(path: &Path) -> Result<Vec<SnapshotEntry>> {
+    let bytes = tokio::fs::read(path).await?;
+    decode(&bytes)
+}
+
+/// Handles SAVE, BGSAVE and periodic snapshots to a single file
+#[derive(Clone)]
+pub struct Snapshotter {
+    path: Arc<PathBuf>,
+    /// Set while a background save is running
+    saving: Arc<AtomicBool>,
+}
+
+impl Snapshotter {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Snapshotter {
+            path: Arc::new(path.into()),
+            saving: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Save the database in the foreground
+    pub async fn save(&self, db: &Db) -> Result<usize> {
+        db.save_snapshot(&self.path).await
+    }
+
+    /// Start a save in a background task
+    ///
+    /// Returns `false` if a background save is already running.
+    pub fn start_background_save(&self, db: Db) -> bool {
+        if self.saving.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+
+        let snapshotter = self.clone();
+        tokio::spawn(async move {
+            match snapshotter.save(&db).await {
+                Ok(keys) => info!("Background save finished: {} keys", keys),
+                Err(e) => error!("Background save failed: {}", e),
+            }
+            snapshotter.saving.store(false, Ordering::Release);
+        });
+
+        true
+    }
+
+    /// Spawn a task that saves the database every `interval`
+    pub fn spawn_auto_save(self, db: Db, interval: Duration) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick completes immediately
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                self.start_background_save(db.clone());
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_rejects_bad_header() {
+        assert!(matches!(
+            decode(b"not a snapshot"),
+            Err(DbError::InvalidSnapshot(_))
+        ));
+
+        let mut bytes = encode(&[]).unwrap();
+        bytes[MAGIC.len()] = 99;
+        assert!(matches!(decode(&bytes), Err(DbError::InvalidSnapshot(_))));
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_all_types() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("dump.rdb");
+
+        let db = Db::new();
+        db.set("string".to_string(), b"value".to_vec())
+            .await
+            .unwrap();
+        db.rpush("list", vec![b"a".to_vec(), b"b".to_vec()])
+            .await
+            .unwrap();
+        db.sadd("set", vec![b"x".to_vec(), b"y".to_vec()])
+            .await
+            .unwrap();
+        db.hset("hash", "field".to_string(), b"v".to_vec())
+            .await
+            .unwrap();
+        db.set_ex(
+            "volatile".to_string(),
+            b"soon".to_vec(),
+            Duration::from_secs(100),
+        )
+        .await
+        .unwrap();
+
+        let saved = Snapshotter::new(&path).save(&db).await.unwrap();
+        assert_eq!(saved, 5);
+
+        let restored = Db::new();
+        assert_eq!(restored.load_snapshot(&path).await.unwrap(), 5);
+
+        assert_eq!(
+            restored.get("string").await.unwrap(),
+            Some(b"value".to_vec())
+        );
+        assert_eq!(
+            restored.lrange("list", 0, -1).await.unwrap(),
+            vec![b"a".to_vec(), b"b".to_vec()]
+        );
+        let mut members = restored.smembers("set").await.unwrap();
+        members.sort();
+        assert_eq!(members, vec![b"x".to_vec(), b"y".to_vec()]);
+        assert_eq!(
+            restored.hgetall("hash").await.unwrap(),
+            db.hgetall("hash").await.unwrap()
+        );
+        assert_eq!(
+            restored.get("volatile").await.unwrap(),
+            Some(b"soon".to_vec())
+        );
+
+        let ttl = restored.ttl("volatile").await.unwrap();
+        assert!(ttl > 90 && ttl <= 100);
+        assert_eq!(restored.ttl("string").await.unwrap(), -1);
+    }
+
+    #[tokio::test]
+    async fn test_load_skips_expired_keys() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("dump.rdb");
+
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let entries = vec![
+            SnapshotEntry {
+                key: "expired".to_string(),
+                value: Value::String(b"old".to_vec()),
+                expires_at_ms: Some(now_ms - 1000),
+            },
+            SnapshotEntry {
+                key: "live".to_string(),
+                value: Value::String(b"new".to_vec()),
+                expires_at_ms: None,
+            },
+        ];
+        write_file(&path, &entries).await.unwrap();
+
+        let db = Db::new();
+        assert_eq!(db.load_snapshot(&path).await.unwrap(), 1);
+        assert_eq!(db.get("expired").await.unwrap(), None);
+        assert_eq!(db.get("live").await.unwrap(), Some(b"new".to_vec()));
+    }
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+
+/// A score that can be ordered; sorted sets never hold NaN
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Members ordered by score, with ties broken by comparing the member bytes
+///
+/// Scores are looked up through a map and ordered through a B-tree of
+/// `(score, member)` pairs, so both stay in step on every change. Only the
+/// map is serialized; the order is rebuilt on load.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "HashMap<Vec<u8>, f64>", into = "HashMap<Vec<u8>, f64>")]
+pub struct SortedSet {
+    scores: HashMap<Vec<u8>, f64>,
+    ordered: BTreeSet<(Score, Vec<u8>)>,
+}
+
+impl SortedSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a member or update its score, returning whether it was new
+    ///
+    /// The score must not be NaN.
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) -> bool {
+        debug_assert!(!score.is_nan(), "sorted set scores can't be NaN");
+
+        let old = self.scores.insert(member.clone(), score);
+        if let Some(old) = old {
+            self.ordered.remove(&(Score(old), member.clone()));
+        }
+        self.ordered.insert((Score(score), member));
+        old.is_none()
+    }
+
+    /// Remove a member, returning whether it was present
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => {
+                self.ordered.remove(&(Score(score), member.to_vec()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Zero-based position of `member` in score order
+    ///
+    /// Counts the members before it, so this is linear in the rank.
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(
+            self.ordered
+                .range(..(Score(score), member.to_vec()))
+                .count(),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Members and their scores, lowest score first
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], f64)> {
+        self.ordered
+            .iter()
+            .map(|(score, member)| (member.as_slice(), score.0))
+    }
+}
+
+impl From<HashMap<Vec<u8>, f64>> for SortedSet {
+    fn from(scores: HashMap<Vec<u8>, f64>) -> Self {
+        let ordered = scores
+            .iter()
+            .map(|(member, &score)| (Score(score), member.clone()))
+            .collect();
+        SortedSet { scores, ordered }
+    }
+}
+
+impl From<SortedSet> for HashMap<Vec<u8>, f64> {
+    fn from(set: SortedSet) -> Self {
+        set.scores
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Idempotency keys published recently, used to drop retried publishes
+///
+/// A key is forgotten once it is older than the TTL, or, oldest first, when
+/// the window already holds `capacity` keys.
+pub struct DedupWindow {
+    ttl: Duration,
+    capacity: usize,
+    keys: HashSet<String>,
+    /// Keys in the order they were recorded, with when
+    order: VecDeque<(String, Instant)>,
+}
+
+impl DedupWindow {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        DedupWindow {
+            ttl,
+            capacity,
+            keys: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Whether `key` was recorded less than a TTL before `now`
+    pub fn is_duplicate(&mut self, key: &str, now: Instant) -> bool {
+        self.expire(now);
+        self.keys.contains(key)
+    }
+
+    /// Remember `key` as published at `now`
+    pub fn record(&mut self, key: &str, now: Instant) {
+        if self.capacity == 0 || self.keys.contains(key) {
+            return;
+        }
+
+        while self.keys.len() >= self.capacity {
+            self.evict_oldest();
+        }
+
+        self.keys.insert(key.to_string());
+        self.order.push_back((key.to_string(), now));
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while let Some((_, recorded_at)) = self.order.front() {
+            if now.duration_since(*recorded_at) < self.ttl {
+                break;
+            }
+            self.evict_oldest();
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        if let Some((key, _)) = self.order.pop_front() {
+            self.keys.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_expire_after_ttl() {
+        let mut window = DedupWindow::new(Duration::from_secs(10), 100);
+        let start = Instant::now();
+
+        window.record("a", start);
+        assert!(window.is_duplicate("a", start + Duration::from_secs(9)));
+        assert!(!window.is_duplicate("b", start + Duration::from_secs(9)));
+        assert!(!window.is_duplicate("a", start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_window_is_bounded() {
+        let mut window = DedupWindow::new(Duration::from_secs(60), 2);
+        let now = Instant::now();
+
+        for key in ["a", "b", "c"] {
+            window.record(key, now);
+        }
+
+        // The oldest key made room for the newest
+        assert!(!window.is_duplicate("a", now));
+        assert!(window.is_duplicate("b", now));
+        assert!(window.is_duplicate("c", now));
+        assert_eq!(window.keys.len(), 2);
+    }
+}
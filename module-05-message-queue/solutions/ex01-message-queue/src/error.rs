@@ -25,6 +25,21 @@ pub enum QueueError {
 
     #[error("Max retries exceeded for message: {0}")]
     MaxRetriesExceeded(String),
+
+    #[error("Payload too large: {size} bytes exceeds limit of {max}")]
+    PayloadTooLarge { size: usize, max: usize },
+
+    #[error("Corrupt log entry at offset {offset}: {reason}")]
+    CorruptLog { offset: u64, reason: String },
+
+    #[error("Unsupported log format version {0}")]
+    UnsupportedLogVersion(u16),
+
+    #[error("Queue full: {queue} already holds {max} unacked messages")]
+    QueueFull { queue: String, max: usize },
+
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
 }
 
 pub type Result<T> = std::result::Result<T, QueueError>;
@@ -1,11 +1,23 @@
 use crate::error::{QueueError, Result};
-use crate::message::{LogEntry, Message, MessageStatus};
+use crate::message::{LogEntry, LogEntryV0, LogEntryV1, Message, MessageStatus};
 use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
-use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use tracing::{debug, info};
 
+/// Start of every log file, followed by the format version
+const MAGIC: &[u8; 4] = b"MQLG";
+
+/// Layout of the entries; bump it whenever `LogEntry` or `Message` changes
+///
+/// Files without a header are version 0, from before content types were
+/// added. Version 1 is from before idempotency keys.
+const FORMAT_VERSION: u16 = 2;
+
+/// Size of the magic and version at the start of the file
+const HEADER_LEN: u64 = 6;
+
 /// Persistent log store for messages
 pub struct LogStore {
     path: PathBuf,
@@ -14,6 +26,8 @@ pub struct LogStore {
     index: HashMap<String, u64>,
     /// Current write offset
     offset: u64,
+    /// Format the file's entries are in
+    version: u16,
 }
 
 impl LogStore {
@@ -26,8 +40,6 @@ impl LogStore {
             std::fs::create_dir_all(parent)?;
         }
 
-        let file_exists = path.exists();
-
         let file = OpenOptions::new()
             .create(true)
             .read(true)
@@ -35,16 +47,31 @@ impl LogStore {
             .append(true)
             .open(&path)?;
 
+        let version = read_version(&path)?;
         let mut store = LogStore {
             path: path.clone(),
             writer: BufWriter::new(file),
             index: HashMap::new(),
             offset: 0,
+            version: version.unwrap_or(FORMAT_VERSION),
         };
 
-        if file_exists {
-            info!("Recovering log from {:?}", path);
-            store.recover()?;
+        match version {
+            None => {
+                write_header(&mut store.writer)?;
+                store.writer.flush()?;
+                store.offset = HEADER_LEN;
+            }
+            Some(version) => {
+                info!("Recovering log from {:?}", path);
+                store.recover()?;
+
+                // New entries can't be appended in an older format
+                if version < FORMAT_VERSION {
+                    info!("Upgrading log {:?} from format version {}", path, version);
+                    store.compact()?;
+                }
+            }
         }
 
         Ok(store)
@@ -120,7 +147,7 @@ impl LogStore {
             let mut data = vec![0u8; len as usize];
             reader.read_exact(&mut data)?;
 
-            let entry: LogEntry = bincode::deserialize(&data)?;
+            let entry = decode_entry(self.version, &data)?;
             Ok(Some(entry.message))
         } else {
             Ok(None)
@@ -129,10 +156,14 @@ impl LogStore {
 
     /// Recover the log by scanning all entries
     /// Returns all pending messages that need to be redelivered
+    ///
+    /// Fails with `CorruptLog` on an entry that can't be decoded, rather
+    /// than losing track of the message it held.
     pub fn recover(&mut self) -> Result<Vec<Message>> {
         let mut reader = BufReader::new(File::open(&self.path)?);
         let mut pending = HashMap::new();
-        let mut offset = 0u64;
+        let mut offset = data_start(self.version);
+        reader.seek(SeekFrom::Start(offset))?;
 
         loop {
             // Try to read length prefix
@@ -152,37 +183,32 @@ impl LogStore {
             let mut data = vec![0u8; len as usize];
             reader.read_exact(&mut data)?;
 
-            match bincode::deserialize::<LogEntry>(&data) {
-                Ok(entry) => {
-                    let msg_id = entry.message.id.clone();
-
-                    // Update index
-                    self.index.insert(msg_id.clone(), offset);
-
-                    // Update message status
-                    match entry.status {
-                        MessageStatus::Pending | MessageStatus::Delivered => {
-                            // Message needs redelivery
-                            pending.insert(msg_id, entry.message);
-                        }
-                        MessageStatus::Acknowledged | MessageStatus::DeadLettered => {
-                            // Message is done, remove from pending
-                            pending.remove(&msg_id);
-                        }
-                        MessageStatus::Failed => {
-                            // Keep in pending for retry
-                            pending.insert(msg_id, entry.message);
-                        }
-                    }
-
-                    offset += 4 + len as u64;
+            let entry = decode_entry(self.version, &data).map_err(|e| QueueError::CorruptLog {
+                offset,
+                reason: e.to_string(),
+            })?;
+            let msg_id = entry.message.id.clone();
+
+            // Update index
+            self.index.insert(msg_id.clone(), offset);
+
+            // Update message status
+            match entry.status {
+                MessageStatus::Pending | MessageStatus::Delivered => {
+                    // Message needs redelivery
+                    pending.insert(msg_id, entry.message);
                 }
-                Err(e) => {
-                    // Corrupted entry, skip
-                    debug!("Skipping corrupted entry at offset {}: {}", offset, e);
-                    offset += 4 + len as u64;
+                MessageStatus::Acknowledged | MessageStatus::DeadLettered => {
+                    // Message is done, remove from pending
+                    pending.remove(&msg_id);
+                }
+                MessageStatus::Failed => {
+                    // Keep in pending for retry
+                    pending.insert(msg_id, entry.message);
                 }
             }
+
+            offset += 4 + len as u64;
         }
 
         self.offset = offset;
@@ -206,10 +232,13 @@ impl LogStore {
             .open(&temp_path)?;
 
         let mut new_index = HashMap::new();
-        let mut new_offset = 0u64;
+        write_header(&mut temp_file)?;
+        let mut new_offset = HEADER_LEN;
 
         // Read all entries and write only pending/failed ones to temp file
         let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut offset = data_start(self.version);
+        reader.seek(SeekFrom::Start(offset))?;
         let mut seen_messages: HashMap<String, LogEntry> = HashMap::new();
 
         loop {
@@ -224,9 +253,12 @@ impl LogStore {
             let mut data = vec![0u8; len as usize];
             reader.read_exact(&mut data)?;
 
-            if let Ok(entry) = bincode::deserialize::<LogEntry>(&data) {
-                seen_messages.insert(entry.message.id.clone(), entry);
-            }
+            let entry = decode_entry(self.version, &data).map_err(|e| QueueError::CorruptLog {
+                offset,
+                reason: e.to_string(),
+            })?;
+            seen_messages.insert(entry.message.id.clone(), entry);
+            offset += 4 + len as u64;
         }
 
         // Write only pending/failed messages to new file
@@ -262,6 +294,7 @@ impl LogStore {
         self.writer = BufWriter::new(file);
         self.index = new_index;
         self.offset = new_offset;
+        self.version = FORMAT_VERSION;
 
         info!("Compaction complete. New offset: {}", new_offset);
 
@@ -269,6 +302,51 @@ impl LogStore {
     }
 }
 
+/// Format version of the log at `path`, or `None` if it is still empty
+fn read_version(path: &Path) -> Result<Option<u16>> {
+    let mut file = File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(None);
+    }
+
+    let mut header = [0u8; HEADER_LEN as usize];
+    match file.read_exact(&mut header) {
+        Ok(()) if &header[..4] == MAGIC => {}
+        Ok(()) => return Ok(Some(0)),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(Some(0)),
+        Err(e) => return Err(QueueError::from(e)),
+    }
+
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version > FORMAT_VERSION {
+        return Err(QueueError::UnsupportedLogVersion(version));
+    }
+    Ok(Some(version))
+}
+
+fn write_header(writer: &mut impl Write) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&FORMAT_VERSION.to_le_bytes())
+}
+
+/// Offset of the first entry in a file of the given version
+fn data_start(version: u16) -> u64 {
+    if version == 0 {
+        0
+    } else {
+        HEADER_LEN
+    }
+}
+
+/// Decode an entry written in format `version`
+fn decode_entry(version: u16, data: &[u8]) -> bincode::Result<LogEntry> {
+    match version {
+        0 => bincode::deserialize::<LogEntryV0>(data).map(LogEntry::from),
+        1 => bincode::deserialize::<LogEntryV1>(data).map(LogEntry::from),
+        _ => bincode::deserialize(data),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -320,6 +398,133 @@ mod tests {
         assert_eq!(pending.len(), 0);
     }
 
+    /// Write `entries` as a length-prefixed log in an older format
+    ///
+    /// Version 0 files have no header.
+    fn write_old_log<T: serde::Serialize>(path: &Path, version: u16, entries: &[T]) {
+        let mut file = File::create(path).unwrap();
+        if version > 0 {
+            file.write_all(MAGIC).unwrap();
+            file.write_all(&version.to_le_bytes()).unwrap();
+        }
+        for entry in entries {
+            let data = bincode::serialize(entry).unwrap();
+            file.write_all(&(data.len() as u32).to_le_bytes()).unwrap();
+            file.write_all(&data).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_v0_log_is_upgraded() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.log");
+
+        let message = |id: &str| crate::message::MessageV0 {
+            id: id.to_string(),
+            queue: "test".to_string(),
+            payload: id.as_bytes().to_vec(),
+            created_at: 1,
+            attempts: 0,
+            metadata: HashMap::new(),
+        };
+        let entry = |id: &str, status| LogEntryV0 {
+            message: message(id),
+            status,
+            updated_at: 1,
+        };
+        write_old_log(
+            &path,
+            0,
+            &[
+                entry("a", MessageStatus::Pending),
+                entry("b", MessageStatus::Pending),
+                entry("a", MessageStatus::Acknowledged),
+            ],
+        );
+
+        let mut log = LogStore::open(&path).unwrap();
+        let pending = log.recover().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, "b");
+        assert_eq!(pending[0].content_type, None);
+
+        // Rewritten in the current format, so new entries can follow
+        assert_eq!(&std::fs::read(&path).unwrap()[..4], MAGIC);
+        log.mark_acked("b").unwrap();
+        drop(log);
+
+        let mut log = LogStore::open(&path).unwrap();
+        assert!(log.recover().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_v1_log_is_upgraded() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.log");
+
+        let entry = LogEntryV1 {
+            message: crate::message::MessageV1 {
+                id: "a".to_string(),
+                queue: "test".to_string(),
+                payload: b"{}".to_vec(),
+                created_at: 1,
+                attempts: 0,
+                metadata: HashMap::new(),
+                content_type: Some("application/json".to_string()),
+            },
+            status: MessageStatus::Pending,
+            updated_at: 1,
+        };
+        write_old_log(&path, 1, &[entry]);
+
+        let mut log = LogStore::open(&path).unwrap();
+        let pending = log.recover().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].content_type.as_deref(), Some("application/json"));
+        assert_eq!(pending[0].idempotency_key, None);
+
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(&data[4..6], &FORMAT_VERSION.to_le_bytes());
+    }
+
+    #[test]
+    fn test_corrupt_entry_fails_recovery() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.log");
+
+        {
+            let mut log = LogStore::open(&path).unwrap();
+            log.append(&Message::new("test", b"ok".to_vec()), MessageStatus::Pending)
+                .unwrap();
+        }
+        let good_len = std::fs::metadata(&path).unwrap().len();
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&8u32.to_le_bytes()).unwrap();
+        file.write_all(&[0xff; 8]).unwrap();
+        drop(file);
+
+        match LogStore::open(&path) {
+            Err(QueueError::CorruptLog { offset, .. }) => assert_eq!(offset, good_len),
+            other => panic!("expected CorruptLog, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_newer_format_is_rejected() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.log");
+
+        let mut header = MAGIC.to_vec();
+        header.extend_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        std::fs::write(&path, header).unwrap();
+
+        assert!(matches!(
+            LogStore::open(&path),
+            Err(QueueError::UnsupportedLogVersion(v)) if v == FORMAT_VERSION + 1
+        ));
+    }
+
     #[test]
     fn test_compact() {
         let dir = tempdir().unwrap();
@@ -1,9 +1,10 @@
+mod dedup;
 mod error;
 mod log;
 mod message;
 mod queue;
 
-use queue::Queue;
+use queue::{DeliveryMode, Queue};
 use message::Message;
 use std::sync::Arc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -28,15 +29,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let queue = Arc::new(Queue::open("orders", &data_dir).await?);
     tracing::info!("Queue 'orders' opened");
 
+    // AT_MOST_ONCE=1 trades redelivery on a crash for never seeing a message twice
+    let at_most_once = std::env::var("AT_MOST_ONCE").is_ok();
+
     // Spawn a consumer
     let consumer_queue = Arc::clone(&queue);
     let consumer_handle = tokio::spawn(async move {
-        let mut consumer = consumer_queue.subscribe("worker-1").await.unwrap();
-        tracing::info!("Consumer 'worker-1' started");
+        let mut consumer = if at_most_once {
+            consumer_queue
+                .subscribe_with_mode("worker-1", DeliveryMode::AtMostOnce)
+                .await
+                .unwrap()
+        } else {
+            consumer_queue.subscribe("worker-1").await.unwrap()
+        };
+        tracing::info!("Consumer 'worker-1' started ({:?})", consumer.mode());
 
         while let Ok(Some(msg)) = consumer.receive().await {
             let payload = String::from_utf8_lossy(msg.payload());
-            tracing::info!("Consumer received message: {}", payload);
+            tracing::info!(
+                "Consumer received message: {} ({})",
+                payload,
+                msg.content_type().unwrap_or("untyped")
+            );
 
             // Simulate processing
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
@@ -52,11 +67,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Publish some messages
     for i in 0..5 {
-        let msg = Message::new("orders", format!("Order #{}", i + 1).into_bytes());
+        let msg = Message::new("orders", format!("Order #{}", i + 1).into_bytes())
+            .with_content_type("text/plain");
         queue.publish(msg).await?;
         tracing::info!("Published order #{}", i + 1);
     }
 
+    // A publisher retrying after a timeout sends the same order again
+    let retry = Message::new("orders", b"Order #5".to_vec())
+        .with_content_type("text/plain")
+        .with_idempotency_key("order-5");
+    queue.publish(retry.clone()).await?;
+    if queue.publish(retry).await? {
+        tracing::info!("Dropped a retried publish of order #5");
+    }
+
+    if let Some(next) = queue.peek().await {
+        tracing::info!("Next buffered message: {}", String::from_utf8_lossy(&next.payload));
+    }
+
     // Wait for processing
     tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
 
@@ -23,6 +23,12 @@ pub struct Message {
 
     /// Optional metadata for routing, filtering, etc.
     pub metadata: HashMap<String, String>,
+
+    /// MIME type of the payload, if known
+    pub content_type: Option<String>,
+
+    /// Publisher-chosen key; a retry with the same key is dropped as a duplicate
+    pub idempotency_key: Option<String>,
 }
 
 impl Message {
@@ -35,6 +41,8 @@ impl Message {
             created_at: current_timestamp(),
             attempts: 0,
             metadata: HashMap::new(),
+            content_type: None,
+            idempotency_key: None,
         }
     }
 
@@ -51,9 +59,23 @@ impl Message {
             created_at: current_timestamp(),
             attempts: 0,
             metadata,
+            content_type: None,
+            idempotency_key: None,
         }
     }
 
+    /// Set the payload's content type
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Set the key that identifies retries of this message
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
     /// Increment the attempt counter
     pub fn increment_attempts(&mut self) {
         self.attempts += 1;
@@ -103,6 +125,88 @@ impl LogEntry {
     }
 }
 
+/// A message as logged before content types existed
+///
+/// Bincode fields are positional, so logs from that format (version 0) are
+/// decoded through these and converted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageV0 {
+    pub id: String,
+    pub queue: String,
+    pub payload: Vec<u8>,
+    pub created_at: u64,
+    pub attempts: u32,
+    pub metadata: HashMap<String, String>,
+}
+
+/// A log entry in format version 0
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntryV0 {
+    pub message: MessageV0,
+    pub status: MessageStatus,
+    pub updated_at: u64,
+}
+
+impl From<LogEntryV0> for LogEntry {
+    fn from(entry: LogEntryV0) -> Self {
+        let message = entry.message;
+        LogEntry {
+            message: Message {
+                id: message.id,
+                queue: message.queue,
+                payload: message.payload,
+                created_at: message.created_at,
+                attempts: message.attempts,
+                metadata: message.metadata,
+                content_type: None,
+                idempotency_key: None,
+            },
+            status: entry.status,
+            updated_at: entry.updated_at,
+        }
+    }
+}
+
+/// A message as logged before idempotency keys existed (version 1)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageV1 {
+    pub id: String,
+    pub queue: String,
+    pub payload: Vec<u8>,
+    pub created_at: u64,
+    pub attempts: u32,
+    pub metadata: HashMap<String, String>,
+    pub content_type: Option<String>,
+}
+
+/// A log entry in format version 1
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntryV1 {
+    pub message: MessageV1,
+    pub status: MessageStatus,
+    pub updated_at: u64,
+}
+
+impl From<LogEntryV1> for LogEntry {
+    fn from(entry: LogEntryV1) -> Self {
+        let message = entry.message;
+        LogEntry {
+            message: Message {
+                id: message.id,
+                queue: message.queue,
+                payload: message.payload,
+                created_at: message.created_at,
+                attempts: message.attempts,
+                metadata: message.metadata,
+                content_type: message.content_type,
+                idempotency_key: None,
+            },
+            status: entry.status,
+            updated_at: entry.updated_at,
+        }
+    }
+}
+
 /// Get current Unix timestamp in milliseconds
 pub fn current_timestamp() -> u64 {
     SystemTime::now()
@@ -136,6 +240,21 @@ mod tests {
         assert_eq!(msg.metadata.get("priority"), Some(&"high".to_string()));
     }
 
+    #[test]
+    fn test_message_with_content_type() {
+        let msg = Message::new("test-queue", b"{}".to_vec());
+        assert_eq!(msg.content_type, None);
+
+        let msg = msg.with_content_type("application/json");
+        assert_eq!(msg.content_type.as_deref(), Some("application/json"));
+    }
+
+    #[test]
+    fn test_message_with_idempotency_key() {
+        let msg = Message::new("test-queue", b"data".to_vec()).with_idempotency_key("order-1");
+        assert_eq!(msg.idempotency_key.as_deref(), Some("order-1"));
+    }
+
     #[test]
     fn test_increment_attempts() {
         let mut msg = Message::new("test", b"data".to_vec());
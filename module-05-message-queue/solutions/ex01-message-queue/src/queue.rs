@@ -1,11 +1,28 @@
-use crate::error::Result;
+use crate::dedup::DedupWindow;
+use crate::error::{QueueError, Result};
 use crate::log::LogStore;
 use crate::message::{Message, MessageStatus};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, Mutex, RwLock};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+/// Messages a consumer has received but not yet acked or nacked
+///
+/// A std mutex so `Consumer::drop` can hand them back without awaiting.
+type InFlight = Arc<std::sync::Mutex<HashMap<String, Message>>>;
+
+/// When a consumer's messages stop being the queue's responsibility
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeliveryMode {
+    /// Done once the consumer acks; redelivered if it goes away first
+    #[default]
+    AtLeastOnce,
+    /// Done as soon as the consumer receives it; never redelivered
+    AtMostOnce,
+}
 
 /// Configuration for a queue
 #[derive(Debug, Clone)]
@@ -16,6 +33,17 @@ pub struct QueueConfig {
     pub max_retries: u32,
     /// Enable dead letter queue
     pub enable_dlq: bool,
+    /// Largest payload `publish` accepts
+    pub max_payload_bytes: usize,
+    /// Most unacked messages the buffer holds before `publish` is refused
+    ///
+    /// Can't exceed `buffer_size`, so a new subscriber's channel always has
+    /// room for the whole backlog.
+    pub max_depth: usize,
+    /// How long an idempotency key is remembered after its message is published
+    pub dedup_ttl: Duration,
+    /// Most idempotency keys remembered at once; the oldest are forgotten first
+    pub dedup_capacity: usize,
 }
 
 impl Default for QueueConfig {
@@ -24,6 +52,10 @@ impl Default for QueueConfig {
             buffer_size: 1000,
             max_retries: 3,
             enable_dlq: true,
+            max_payload_bytes: 1024 * 1024,
+            max_depth: 1000,
+            dedup_ttl: Duration::from_secs(300),
+            dedup_capacity: 10_000,
         }
     }
 }
@@ -36,6 +68,10 @@ pub struct Queue {
     subscribers: Arc<RwLock<Vec<Subscriber>>>,
     config: QueueConfig,
     dlq: Option<Arc<Mutex<VecDeque<Message>>>>,
+    /// Recently published idempotency keys; kept in memory only
+    dedup: std::sync::Mutex<DedupWindow>,
+    /// Messages released by closed consumers, redelivered by a background task
+    redeliver: mpsc::UnboundedSender<Message>,
 }
 
 impl Queue {
@@ -55,60 +91,138 @@ impl Queue {
         );
 
         let buffer = VecDeque::from(pending);
+        let log = Arc::new(Mutex::new(log));
+        let subscribers = Arc::new(RwLock::new(Vec::new()));
+
+        let (redeliver, redeliver_rx) = mpsc::unbounded_channel();
+        spawn_redelivery(
+            name.clone(),
+            Arc::clone(&log),
+            Arc::clone(&subscribers),
+            redeliver_rx,
+        );
+
+        let config = QueueConfig::default();
+        let dedup = DedupWindow::new(config.dedup_ttl, config.dedup_capacity);
 
         Ok(Queue {
             name,
-            log: Arc::new(Mutex::new(log)),
+            log,
             buffer: Arc::new(Mutex::new(buffer)),
-            subscribers: Arc::new(RwLock::new(Vec::new())),
-            config: QueueConfig::default(),
+            subscribers,
+            config,
             dlq: Some(Arc::new(Mutex::new(VecDeque::new()))),
+            dedup: std::sync::Mutex::new(dedup),
+            redeliver,
         })
     }
 
     /// Create a queue with custom configuration
+    ///
+    /// Fails with `InvalidConfig` if `max_depth` exceeds `buffer_size`.
     pub async fn with_config(
         name: impl Into<String>,
         data_dir: impl AsRef<Path>,
         config: QueueConfig,
     ) -> Result<Self> {
+        if config.max_depth > config.buffer_size {
+            return Err(QueueError::InvalidConfig(format!(
+                "max_depth {} exceeds buffer_size {}",
+                config.max_depth, config.buffer_size
+            )));
+        }
+
         let mut queue = Self::open(name, data_dir).await?;
+        queue.dedup = std::sync::Mutex::new(DedupWindow::new(
+            config.dedup_ttl,
+            config.dedup_capacity,
+        ));
         queue.config = config;
         Ok(queue)
     }
 
     /// Publish a message to the queue
-    pub async fn publish(&self, mut message: Message) -> Result<()> {
+    ///
+    /// Fails with `QueueFull` while `max_depth` messages are waiting to be
+    /// acked, so a publisher can't outrun its consumers without bound.
+    ///
+    /// Returns `true` if the message was dropped because its idempotency key
+    /// was already published within the dedup window.
+    pub async fn publish(&self, mut message: Message) -> Result<bool> {
+        if message.payload.len() > self.config.max_payload_bytes {
+            return Err(QueueError::PayloadTooLarge {
+                size: message.payload.len(),
+                max: self.config.max_payload_bytes,
+            });
+        }
+
         message.queue = self.name.clone();
 
-        // Write to persistent log
+        // Hold the buffer across the log write so the depth check can't race
         {
+            let mut buffer = self.buffer.lock().await;
+
+            if let Some(key) = &message.idempotency_key {
+                if self.dedup.lock().unwrap().is_duplicate(key, Instant::now()) {
+                    debug!(
+                        "Dropping message {} with duplicate key '{}' in queue '{}'",
+                        message.id, key, self.name
+                    );
+                    return Ok(true);
+                }
+            }
+
+            if buffer.len() >= self.config.max_depth {
+                return Err(QueueError::QueueFull {
+                    queue: self.name.clone(),
+                    max: self.config.max_depth,
+                });
+            }
+
+            // Write to persistent log
             let mut log = self.log.lock().await;
             log.append(&message, MessageStatus::Pending)?;
-        }
 
-        // Add to in-memory buffer
-        {
-            let mut buffer = self.buffer.lock().await;
+            if let Some(key) = &message.idempotency_key {
+                self.dedup.lock().unwrap().record(key, Instant::now());
+            }
             buffer.push_back(message.clone());
         }
 
         // Notify subscribers
-        self.notify_subscribers(message).await;
+        dispatch(&self.name, &self.subscribers, message).await;
 
-        Ok(())
+        Ok(false)
     }
 
-    /// Subscribe to the queue
+    /// Subscribe to the queue with at-least-once delivery
     pub async fn subscribe(&self, consumer_id: impl Into<String>) -> Result<Consumer> {
+        self.subscribe_with_mode(consumer_id, DeliveryMode::default())
+            .await
+    }
+
+    /// Subscribe to the queue with the given delivery mode
+    pub async fn subscribe_with_mode(
+        &self,
+        consumer_id: impl Into<String>,
+        mode: DeliveryMode,
+    ) -> Result<Consumer> {
         let consumer_id = consumer_id.into();
         let (tx, rx) = mpsc::channel(self.config.buffer_size);
 
-        // Send all buffered messages to new subscriber
+        // Send all buffered messages to new subscriber. Nothing reads the
+        // channel yet, so never wait on it: a backlog recovered from the log
+        // can still outgrow it, and the rest stays buffered.
         {
             let buffer = self.buffer.lock().await;
             for msg in buffer.iter() {
-                let _ = tx.send(msg.clone()).await;
+                if tx.try_send(msg.clone()).is_err() {
+                    warn!(
+                        "Backlog of queue '{}' exceeds the channel of consumer '{}'",
+                        self.name, consumer_id
+                    );
+                    break;
+                }
             }
         }
 
@@ -119,39 +233,35 @@ impl Queue {
 
         self.subscribers.write().await.push(subscriber);
 
-        info!("Consumer '{}' subscribed to queue '{}'", consumer_id, self.name);
+        info!(
+            "Consumer '{}' subscribed to queue '{}' ({:?})",
+            consumer_id, self.name, mode
+        );
 
         Ok(Consumer {
             id: consumer_id,
             queue: self.name.clone(),
             receiver: rx,
             log: Arc::clone(&self.log),
+            buffer: Arc::clone(&self.buffer),
+            mode,
             max_retries: self.config.max_retries,
+            in_flight: InFlight::default(),
+            redeliver: self.redeliver.clone(),
         })
     }
 
-    /// Notify all subscribers of a new message
-    async fn notify_subscribers(&self, message: Message) {
-        let subscribers = self.subscribers.read().await;
-
-        // Round-robin distribution: send to first available subscriber
-        for subscriber in subscribers.iter() {
-            if let Ok(()) = subscriber.sender.try_send(message.clone()) {
-                debug!(
-                    "Delivered message {} to subscriber {}",
-                    message.id, subscriber.id
-                );
-                return;
-            }
-        }
+    /// Look at the next buffered message without consuming it
+    pub async fn peek(&self) -> Option<Message> {
+        self.buffer.lock().await.front().cloned()
+    }
 
-        debug!(
-            "No subscribers available for message {} in queue '{}'",
-            message.id, self.name
-        );
+    /// Look at up to `n` buffered messages, front first, without consuming them
+    pub async fn peek_n(&self, n: usize) -> Vec<Message> {
+        self.buffer.lock().await.iter().take(n).cloned().collect()
     }
 
-    /// Get the current queue depth (messages in buffer)
+    /// Get the current queue depth (published messages not yet acked)
     pub async fn depth(&self) -> usize {
         self.buffer.lock().await.len()
     }
@@ -178,17 +288,78 @@ struct Subscriber {
     sender: mpsc::Sender<Message>,
 }
 
+/// Send a message to the first subscriber with room for it
+async fn dispatch(queue: &str, subscribers: &RwLock<Vec<Subscriber>>, message: Message) {
+    let subscribers = subscribers.read().await;
+
+    // Round-robin distribution: send to first available subscriber
+    for subscriber in subscribers.iter() {
+        if let Ok(()) = subscriber.sender.try_send(message.clone()) {
+            debug!(
+                "Delivered message {} to subscriber {}",
+                message.id, subscriber.id
+            );
+            return;
+        }
+    }
+
+    debug!(
+        "No subscribers available for message {} in queue '{}'",
+        message.id, queue
+    );
+}
+
+/// Stop counting a finished message towards the queue depth
+///
+/// Called without the log lock held; `publish` takes the buffer first.
+async fn remove_from_buffer(buffer: &Mutex<VecDeque<Message>>, msg_id: &str) {
+    buffer.lock().await.retain(|message| message.id != msg_id);
+}
+
+/// Hand messages released by closed consumers to the remaining subscribers
+///
+/// Runs until the queue and all of its consumers are dropped.
+fn spawn_redelivery(
+    queue: String,
+    log: Arc<Mutex<LogStore>>,
+    subscribers: Arc<RwLock<Vec<Subscriber>>>,
+    mut rx: mpsc::UnboundedReceiver<Message>,
+) {
+    tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            // Closed consumers can no longer receive anything
+            subscribers
+                .write()
+                .await
+                .retain(|subscriber| !subscriber.sender.is_closed());
+
+            if let Err(e) = log.lock().await.append(&message, MessageStatus::Pending) {
+                warn!("Failed to log redelivery of message {}: {}", message.id, e);
+            }
+
+            dispatch(&queue, &subscribers, message).await;
+        }
+    });
+}
+
 /// A consumer that receives messages from a queue
 pub struct Consumer {
     id: String,
     queue: String,
     receiver: mpsc::Receiver<Message>,
     log: Arc<Mutex<LogStore>>,
+    buffer: Arc<Mutex<VecDeque<Message>>>,
+    mode: DeliveryMode,
     max_retries: u32,
+    in_flight: InFlight,
+    redeliver: mpsc::UnboundedSender<Message>,
 }
 
 impl Consumer {
     /// Receive the next message
+    ///
+    /// In at-most-once mode the message is acked in the log before it is
+    /// returned, so it is lost if the consumer fails to process it.
     pub async fn receive(&mut self) -> Result<Option<AckMessage>> {
         match self.receiver.recv().await {
             Some(message) => {
@@ -197,16 +368,36 @@ impl Consumer {
                     self.id, message.id, self.queue
                 );
 
-                // Mark as delivered in log
-                {
-                    let mut log = self.log.lock().await;
-                    log.mark_delivered(&message.id)?;
+                match self.mode {
+                    DeliveryMode::AtLeastOnce => {
+                        // Mark as delivered in log
+                        {
+                            let mut log = self.log.lock().await;
+                            log.mark_delivered(&message.id)?;
+                        }
+
+                        self.in_flight
+                            .lock()
+                            .unwrap()
+                            .insert(message.id.clone(), message.clone());
+                    }
+                    DeliveryMode::AtMostOnce => {
+                        {
+                            let mut log = self.log.lock().await;
+                            log.mark_acked(&message.id)?;
+                        }
+
+                        remove_from_buffer(&self.buffer, &message.id).await;
+                    }
                 }
 
                 Ok(Some(AckMessage {
                     message,
                     log: Arc::clone(&self.log),
+                    buffer: Arc::clone(&self.buffer),
+                    mode: self.mode,
                     max_retries: self.max_retries,
+                    in_flight: Arc::clone(&self.in_flight),
                 }))
             }
             None => Ok(None),
@@ -217,26 +408,106 @@ impl Consumer {
     pub fn id(&self) -> &str {
         &self.id
     }
+
+    /// Get the consumer's delivery mode
+    pub fn mode(&self) -> DeliveryMode {
+        self.mode
+    }
+
+    /// Number of received messages not yet acked or nacked
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    /// Stop consuming and hand unfinished messages to other subscribers
+    ///
+    /// Covers both unacked messages and ones queued for this consumer but
+    /// never received. Dropping the consumer does the same. Returns the
+    /// number of messages released.
+    pub fn close(mut self) -> usize {
+        self.release()
+    }
+
+    fn release(&mut self) -> usize {
+        self.receiver.close();
+
+        let mut released: Vec<Message> = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, message)| message)
+            .collect();
+        while let Ok(message) = self.receiver.try_recv() {
+            released.push(message);
+        }
+
+        if !released.is_empty() {
+            info!(
+                "Consumer '{}' released {} messages from queue '{}' for redelivery",
+                self.id,
+                released.len(),
+                self.queue
+            );
+        }
+
+        let count = released.len();
+        for message in released {
+            // Only fails once the queue and its redelivery task are gone;
+            // recovery on the next open picks the message up instead
+            let _ = self.redeliver.send(message);
+        }
+
+        count
+    }
+}
+
+impl Drop for Consumer {
+    fn drop(&mut self) {
+        self.release();
+    }
 }
 
 /// A message that can be acknowledged or rejected
 pub struct AckMessage {
     message: Message,
     log: Arc<Mutex<LogStore>>,
+    buffer: Arc<Mutex<VecDeque<Message>>>,
+    mode: DeliveryMode,
     max_retries: u32,
+    in_flight: InFlight,
 }
 
 impl AckMessage {
     /// Acknowledge successful processing
+    ///
+    /// A no-op in at-most-once mode, where receiving already acked it.
     pub async fn ack(self) -> Result<()> {
+        if self.mode == DeliveryMode::AtMostOnce {
+            return Ok(());
+        }
+
         debug!("Acknowledging message {}", self.message.id);
 
-        let mut log = self.log.lock().await;
-        log.mark_acked(&self.message.id)
+        {
+            let mut log = self.log.lock().await;
+            log.mark_acked(&self.message.id)?;
+        }
+
+        remove_from_buffer(&self.buffer, &self.message.id).await;
+        self.in_flight.lock().unwrap().remove(&self.message.id);
+        Ok(())
     }
 
     /// Negative acknowledge - message failed processing
+    ///
+    /// A no-op in at-most-once mode: the message was acked on receipt and
+    /// is not retried.
     pub async fn nack(mut self) -> Result<()> {
+        if self.mode == DeliveryMode::AtMostOnce {
+            return Ok(());
+        }
+
         self.message.increment_attempts();
 
         debug!(
@@ -244,19 +515,26 @@ impl AckMessage {
             self.message.id, self.message.attempts
         );
 
-        let mut log = self.log.lock().await;
+        let dead_lettered = self.message.attempts >= self.max_retries;
+        {
+            let mut log = self.log.lock().await;
 
-        if self.message.attempts >= self.max_retries {
-            info!(
-                "Message {} exceeded max retries, moving to DLQ",
-                self.message.id
-            );
-            log.append(&self.message, MessageStatus::DeadLettered)?;
-        } else {
-            // Requeue for retry
-            log.mark_failed(&self.message.id)?;
+            if dead_lettered {
+                info!(
+                    "Message {} exceeded max retries, moving to DLQ",
+                    self.message.id
+                );
+                log.append(&self.message, MessageStatus::DeadLettered)?;
+            } else {
+                // Requeue for retry
+                log.mark_failed(&self.message.id)?;
+            }
         }
 
+        if dead_lettered {
+            remove_from_buffer(&self.buffer, &self.message.id).await;
+        }
+        self.in_flight.lock().unwrap().remove(&self.message.id);
         Ok(())
     }
 
@@ -270,6 +548,11 @@ impl AckMessage {
         &self.message.id
     }
 
+    /// Get the payload's content type, if one was set
+    pub fn content_type(&self) -> Option<&str> {
+        self.message.content_type.as_deref()
+    }
+
     /// Get the full message
     pub fn message(&self) -> &Message {
         &self.message
@@ -314,6 +597,264 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_peek() {
+        let dir = tempdir().unwrap();
+        let queue = Queue::open("test", dir.path()).await.unwrap();
+
+        assert!(queue.peek().await.is_none());
+        assert!(queue.peek_n(3).await.is_empty());
+
+        for i in 0..3 {
+            let msg = Message::new("test", format!("msg{}", i).into_bytes());
+            queue.publish(msg).await.unwrap();
+        }
+
+        let head = queue.peek().await.unwrap();
+        assert_eq!(head.payload, b"msg0");
+        assert_eq!(queue.peek().await.unwrap().id, head.id);
+        assert_eq!(queue.depth().await, 3);
+
+        let payloads: Vec<_> = queue
+            .peek_n(2)
+            .await
+            .into_iter()
+            .map(|m| m.payload)
+            .collect();
+        assert_eq!(payloads, vec![b"msg0".to_vec(), b"msg1".to_vec()]);
+        assert_eq!(queue.peek_n(10).await.len(), 3);
+        assert_eq!(queue.depth().await, 3);
+    }
+
+    async fn receive_within(consumer: &mut Consumer) -> Option<AckMessage> {
+        tokio::time::timeout(std::time::Duration::from_secs(1), consumer.receive())
+            .await
+            .ok()
+            .and_then(|r| r.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_dropped_consumer_redelivers() {
+        let dir = tempdir().unwrap();
+        let queue = Queue::open("test", dir.path()).await.unwrap();
+        let mut first = queue.subscribe("c1").await.unwrap();
+        let mut second = queue.subscribe("c2").await.unwrap();
+
+        let msg = Message::new("test", b"work".to_vec());
+        queue.publish(msg.clone()).await.unwrap();
+
+        // The first subscriber gets it, then goes away without acking
+        let received = receive_within(&mut first).await.unwrap();
+        assert_eq!(received.id(), msg.id);
+        assert_eq!(first.in_flight(), 1);
+        drop(received);
+        drop(first);
+
+        let redelivered = receive_within(&mut second).await.unwrap();
+        assert_eq!(redelivered.id(), msg.id);
+        redelivered.ack().await.unwrap();
+        assert_eq!(second.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_close_releases_unreceived_messages() {
+        let dir = tempdir().unwrap();
+        let queue = Queue::open("test", dir.path()).await.unwrap();
+        let mut first = queue.subscribe("c1").await.unwrap();
+        let mut second = queue.subscribe("c2").await.unwrap();
+
+        for i in 0..3 {
+            let msg = Message::new("test", format!("msg{}", i).into_bytes());
+            queue.publish(msg).await.unwrap();
+        }
+
+        // One acked, one in flight, one never received
+        receive_within(&mut first).await.unwrap().ack().await.unwrap();
+        let _unacked = receive_within(&mut first).await.unwrap();
+        assert_eq!(first.close(), 2);
+
+        let mut payloads = Vec::new();
+        for _ in 0..2 {
+            let msg = receive_within(&mut second).await.unwrap();
+            payloads.push(msg.payload().to_vec());
+            msg.ack().await.unwrap();
+        }
+        payloads.sort();
+        assert_eq!(payloads, vec![b"msg1".to_vec(), b"msg2".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn test_acked_messages_are_not_released() {
+        let dir = tempdir().unwrap();
+        let queue = Queue::open("test", dir.path()).await.unwrap();
+        let mut consumer = queue.subscribe("c1").await.unwrap();
+
+        queue.publish(Message::new("test", b"done".to_vec())).await.unwrap();
+        receive_within(&mut consumer).await.unwrap().ack().await.unwrap();
+
+        assert_eq!(consumer.in_flight(), 0);
+        assert_eq!(consumer.close(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_payload_too_large() {
+        let dir = tempdir().unwrap();
+        let queue = Queue::with_config(
+            "test",
+            dir.path(),
+            QueueConfig {
+                max_payload_bytes: 8,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let result = queue.publish(Message::new("test", vec![0; 9])).await;
+        assert!(matches!(
+            result,
+            Err(QueueError::PayloadTooLarge { size: 9, max: 8 })
+        ));
+        assert_eq!(queue.depth().await, 0);
+
+        queue.publish(Message::new("test", vec![0; 8])).await.unwrap();
+        assert_eq!(queue.depth().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_publish_refused_when_full() {
+        let dir = tempdir().unwrap();
+        let queue = Queue::with_config(
+            "test",
+            dir.path(),
+            QueueConfig {
+                max_depth: 2,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        for i in 0..2 {
+            let msg = Message::new("test", format!("msg{}", i).into_bytes());
+            queue.publish(msg).await.unwrap();
+        }
+
+        let result = queue.publish(Message::new("test", b"overflow".to_vec())).await;
+        assert!(matches!(result, Err(QueueError::QueueFull { max: 2, .. })));
+        assert_eq!(queue.depth().await, 2);
+
+        // Delivery alone doesn't make room, an ack does
+        let mut consumer = queue.subscribe("c1").await.unwrap();
+        let received = receive_within(&mut consumer).await.unwrap();
+        assert!(queue.publish(Message::new("test", b"overflow".to_vec())).await.is_err());
+
+        received.ack().await.unwrap();
+        assert_eq!(queue.depth().await, 1);
+        queue.publish(Message::new("test", b"overflow".to_vec())).await.unwrap();
+        assert_eq!(queue.depth().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_max_depth_cannot_exceed_buffer_size() {
+        let dir = tempdir().unwrap();
+        let result = Queue::with_config(
+            "test",
+            dir.path(),
+            QueueConfig {
+                buffer_size: 2,
+                max_depth: 3,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(QueueError::InvalidConfig(_))));
+
+        let defaults = QueueConfig::default();
+        assert!(defaults.max_depth <= defaults.buffer_size);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_to_full_queue() {
+        let dir = tempdir().unwrap();
+        let queue = Queue::with_config(
+            "test",
+            dir.path(),
+            QueueConfig {
+                buffer_size: 2,
+                max_depth: 2,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        for i in 0..2 {
+            let msg = Message::new("test", format!("msg{}", i).into_bytes());
+            queue.publish(msg).await.unwrap();
+        }
+
+        let mut consumer = tokio::time::timeout(Duration::from_secs(1), queue.subscribe("c1"))
+            .await
+            .expect("subscribe blocked on a full channel")
+            .unwrap();
+        for _ in 0..2 {
+            receive_within(&mut consumer).await.unwrap().ack().await.unwrap();
+        }
+        assert_eq!(queue.depth().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_key_is_dropped() {
+        let dir = tempdir().unwrap();
+        let queue = Queue::open("test", dir.path()).await.unwrap();
+
+        let first = Message::new("test", b"order".to_vec()).with_idempotency_key("order-1");
+        let retry = Message::new("test", b"order".to_vec()).with_idempotency_key("order-1");
+        assert!(!queue.publish(first.clone()).await.unwrap());
+        assert!(queue.publish(retry).await.unwrap());
+
+        assert_eq!(queue.depth().await, 1);
+        assert_eq!(queue.peek().await.unwrap().id, first.id);
+    }
+
+    #[tokio::test]
+    async fn test_different_keys_are_accepted() {
+        let dir = tempdir().unwrap();
+        let queue = Queue::open("test", dir.path()).await.unwrap();
+
+        for key in ["order-1", "order-2"] {
+            let msg = Message::new("test", b"order".to_vec()).with_idempotency_key(key);
+            assert!(!queue.publish(msg).await.unwrap());
+        }
+        // Messages without a key are never deduplicated
+        for _ in 0..2 {
+            let msg = Message::new("test", b"order".to_vec());
+            assert!(!queue.publish(msg).await.unwrap());
+        }
+
+        assert_eq!(queue.depth().await, 4);
+    }
+
+    #[tokio::test]
+    async fn test_content_type_survives_restart() {
+        let dir = tempdir().unwrap();
+
+        {
+            let queue = Queue::open("test", dir.path()).await.unwrap();
+            let msg = Message::new("test", b"{}".to_vec()).with_content_type("application/json");
+            queue.publish(msg).await.unwrap();
+        }
+
+        let queue = Queue::open("test", dir.path()).await.unwrap();
+        let mut consumer = queue.subscribe("c1").await.unwrap();
+
+        let received = receive_within(&mut consumer).await.unwrap();
+        assert_eq!(received.payload(), b"{}");
+        assert_eq!(received.content_type(), Some("application/json"));
+        received.ack().await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_ack_prevents_redelivery() {
         let dir = tempdir().unwrap();
@@ -340,6 +881,58 @@ mod tests {
     }
 
 
+    /// Receive one message and drop it unacked along with the consumer and
+    /// queue, then report how many messages a reopened queue recovers
+    async fn depth_after_crash(mode: DeliveryMode) -> usize {
+        let dir = tempdir().unwrap();
+
+        {
+            let queue = Queue::open("test", dir.path()).await.unwrap();
+            let mut consumer = queue.subscribe_with_mode("c1", mode).await.unwrap();
+            assert_eq!(consumer.mode(), mode);
+
+            queue.publish(Message::new("test", b"work".to_vec())).await.unwrap();
+            let received = receive_within(&mut consumer).await.unwrap();
+            assert_eq!(received.payload(), b"work");
+
+            // A crash runs no destructors, so nothing gets released
+            std::mem::forget(received);
+            std::mem::forget(consumer);
+        }
+
+        // Only what receive logged survives the restart
+        let queue = Queue::open("test", dir.path()).await.unwrap();
+        queue.depth().await
+    }
+
+    #[tokio::test]
+    async fn test_at_least_once_redelivers_after_restart() {
+        assert_eq!(depth_after_crash(DeliveryMode::AtLeastOnce).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_at_most_once_does_not_redeliver_after_restart() {
+        assert_eq!(depth_after_crash(DeliveryMode::AtMostOnce).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_at_most_once_close_releases_nothing_received() {
+        let dir = tempdir().unwrap();
+        let queue = Queue::open("test", dir.path()).await.unwrap();
+        let mut consumer = queue
+            .subscribe_with_mode("c1", DeliveryMode::AtMostOnce)
+            .await
+            .unwrap();
+
+        queue.publish(Message::new("test", b"work".to_vec())).await.unwrap();
+        let received = receive_within(&mut consumer).await.unwrap();
+        assert_eq!(queue.depth().await, 0);
+        assert_eq!(consumer.in_flight(), 0);
+
+        received.nack().await.unwrap();
+        assert_eq!(consumer.close(), 0);
+    }
+
     #[tokio::test]
     async fn test_persistence_across_restarts() {
         let dir = tempdir().unwrap();
@@ -1,16 +1,22 @@
 use crate::error::Result;
 use bytes::Bytes;
 use lru::LruCache;
+use std::collections::BTreeSet;
 use std::num::NonZeroUsize;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 
 /// Entry in the cache with optional TTL
 #[derive(Clone, Debug)]
 struct CacheEntry {
     value: Bytes,
     expires_at: Option<Instant>,
+    /// Number of reads, used by LFU eviction
+    hits: u64,
+    /// When the entry was last read or written, for LFU ties
+    last_used: u64,
 }
 
 impl CacheEntry {
@@ -20,6 +26,16 @@ impl CacheEntry {
     }
 }
 
+/// Which entry to evict when a node is full
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the least recently used entry
+    #[default]
+    Lru,
+    /// Evict the least frequently read entry, oldest first on ties
+    Lfu,
+}
+
 /// Configuration for a cache node
 #[derive(Clone, Debug)]
 pub struct CacheConfig {
@@ -27,6 +43,62 @@ pub struct CacheConfig {
     pub max_entries: usize,
     /// Default TTL for entries (None = no expiration)
     pub default_ttl: Option<Duration>,
+    /// Eviction policy once `max_entries` is reached
+    pub eviction_policy: EvictionPolicy,
+    /// How often the background sweep removes expired entries
+    /// (None = only expire lazily on access)
+    pub expiration_interval: Option<Duration>,
+}
+
+/// Entries ordered coldest first, for LFU eviction
+///
+/// Reads and writes move an entry in O(log n). Entries removed by delete or
+/// expiry aren't taken out eagerly: eviction skips them, and the index is
+/// rebuilt once they outnumber the live ones.
+#[derive(Debug, Default)]
+struct FrequencyIndex {
+    /// (hits, last use, key)
+    order: BTreeSet<(u64, u64, String)>,
+    clock: u64,
+}
+
+impl FrequencyIndex {
+    /// Count a read of `entry`
+    fn record_hit(&mut self, key: &str, entry: &mut CacheEntry) {
+        self.order.remove(&(entry.hits, entry.last_used, key.to_string()));
+        entry.hits += 1;
+        self.insert(key, entry);
+    }
+
+    /// Place `entry` as the most recently used at its read count
+    fn insert(&mut self, key: &str, entry: &mut CacheEntry) {
+        self.clock += 1;
+        entry.last_used = self.clock;
+        self.order.insert((entry.hits, entry.last_used, key.to_string()));
+    }
+
+    /// Take the least read key still in `cache`, oldest first on ties
+    fn pop_coldest(&mut self, cache: &LruCache<String, CacheEntry>) -> Option<String> {
+        while let Some((hits, last_used, key)) = self.order.pop_first() {
+            let live = cache
+                .peek(&key)
+                .is_some_and(|entry| entry.hits == hits && entry.last_used == last_used);
+            if live {
+                return Some(key);
+            }
+        }
+        None
+    }
+
+    /// Drop stale positions once they outnumber live entries
+    fn compact(&mut self, cache: &LruCache<String, CacheEntry>) {
+        if self.order.len() > 2 * cache.len() + 16 {
+            self.order = cache
+                .iter()
+                .map(|(key, entry)| (entry.hits, entry.last_used, key.clone()))
+                .collect();
+        }
+    }
 }
 
 impl Default for CacheConfig {
@@ -34,13 +106,17 @@ impl Default for CacheConfig {
         CacheConfig {
             max_entries: 10000,
             default_ttl: None,
+            eviction_policy: EvictionPolicy::Lru,
+            expiration_interval: None,
         }
     }
 }
 
-/// An individual cache node with LRU eviction
+/// An individual cache node with LRU or LFU eviction
 pub struct CacheNode {
     cache: Arc<RwLock<LruCache<String, CacheEntry>>>,
+    /// Only used by LFU, and only locked while holding the cache's write lock
+    frequencies: Mutex<FrequencyIndex>,
     config: CacheConfig,
 }
 
@@ -51,6 +127,7 @@ impl CacheNode {
 
         CacheNode {
             cache: Arc::new(RwLock::new(LruCache::new(capacity))),
+            frequencies: Mutex::default(),
             config,
         }
     }
@@ -67,8 +144,11 @@ impl CacheNode {
     pub async fn get(&self, key: &str) -> Result<Option<Bytes>> {
         let mut cache = self.cache.write().await;
 
-        match cache.get(key) {
-            Some(entry) if !entry.is_expired() => Ok(Some(entry.value.clone())),
+        match cache.get_mut(key) {
+            Some(entry) if !entry.is_expired() => {
+                self.record_hit(key, entry);
+                Ok(Some(entry.value.clone()))
+            }
             Some(_) => {
                 // Entry expired, remove it
                 cache.pop(key);
@@ -91,14 +171,113 @@ impl CacheNode {
         ttl: Option<Duration>,
     ) -> Result<()> {
         let mut cache = self.cache.write().await;
+        self.put_entry(&mut cache, key, value, ttl.map(|d| Instant::now() + d));
+        Ok(())
+    }
+
+    /// Set `key` to `new` only if its current value equals `expected`
+    ///
+    /// `None` means the key must be absent (or expired). Returns whether the
+    /// write happened; the check and write share one lock acquisition.
+    pub async fn cas(&self, key: &str, expected: Option<Bytes>, new: Bytes) -> Result<bool> {
+        let mut cache = self.cache.write().await;
+
+        let current = cache
+            .peek(key)
+            .filter(|entry| !entry.is_expired())
+            .map(|entry| &entry.value);
+
+        if current != expected.as_ref() {
+            return Ok(false);
+        }
+
+        let expires_at = self.config.default_ttl.map(|d| Instant::now() + d);
+        self.put_entry(&mut cache, key.to_string(), new, expires_at);
+        Ok(true)
+    }
+
+    /// Get multiple values under a single lock acquisition
+    pub async fn get_many(&self, keys: &[&str]) -> Result<Vec<Option<Bytes>>> {
+        let mut cache = self.cache.write().await;
+        let mut values = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            match cache.get_mut(*key) {
+                Some(entry) if !entry.is_expired() => {
+                    self.record_hit(key, entry);
+                    values.push(Some(entry.value.clone()));
+                }
+                Some(_) => {
+                    cache.pop(*key);
+                    values.push(None);
+                }
+                None => values.push(None),
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Set multiple values under a single lock acquisition
+    pub async fn set_many(&self, entries: Vec<(String, Bytes)>, ttl: Option<Duration>) -> Result<()> {
+        let mut cache = self.cache.write().await;
+        let expires_at = ttl.map(|d| Instant::now() + d);
+
+        for (key, value) in entries {
+            self.put_entry(&mut cache, key, value, expires_at);
+        }
 
-        let entry = CacheEntry {
+        Ok(())
+    }
+
+    fn record_hit(&self, key: &str, entry: &mut CacheEntry) {
+        if self.config.eviction_policy == EvictionPolicy::Lfu {
+            self.frequencies.lock().unwrap().record_hit(key, entry);
+        } else {
+            entry.hits += 1;
+        }
+    }
+
+    /// Insert an entry, making room according to the eviction policy
+    ///
+    /// Overwriting a key keeps its read count. The LRU policy relies on
+    /// `LruCache` evicting on `put`; LFU takes the coldest entry from the
+    /// frequency index first.
+    fn put_entry(
+        &self,
+        cache: &mut LruCache<String, CacheEntry>,
+        key: String,
+        value: Bytes,
+        expires_at: Option<Instant>,
+    ) {
+        let mut entry = CacheEntry {
             value,
-            expires_at: ttl.map(|d| Instant::now() + d),
+            expires_at,
+            hits: 0,
+            last_used: 0,
         };
 
+        if self.config.eviction_policy == EvictionPolicy::Lfu {
+            let mut frequencies = self.frequencies.lock().unwrap();
+
+            if let Some(existing) = cache.peek(&key) {
+                entry.hits = existing.hits;
+                frequencies
+                    .order
+                    .remove(&(existing.hits, existing.last_used, key.clone()));
+            } else if cache.len() >= cache.cap().get() {
+                if let Some(victim) = frequencies.pop_coldest(cache) {
+                    cache.pop(&victim);
+                }
+            }
+
+            frequencies.compact(cache);
+            frequencies.insert(&key, &mut entry);
+        } else {
+            entry.hits = cache.peek(&key).map_or(0, |existing| existing.hits);
+        }
+
         cache.put(key, entry);
-        Ok(())
     }
 
     /// Delete a value from the cache
@@ -121,14 +300,28 @@ impl CacheNode {
         }
     }
 
-    /// Get current cache size
+    /// Number of live entries, not counting expired ones awaiting removal
     pub async fn len(&self) -> usize {
-        self.cache.read().await.len()
+        let cache = self.cache.read().await;
+        cache.iter().filter(|(_, entry)| !entry.is_expired()).count()
     }
 
-    /// Check if cache is empty
+    /// Keys of all live entries, most recently used first
+    ///
+    /// Meant for debugging and tests; takes a read lock over the whole node.
+    pub async fn keys(&self) -> Vec<String> {
+        let cache = self.cache.read().await;
+        cache
+            .iter()
+            .filter(|(_, entry)| !entry.is_expired())
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Check if cache has no live entries
     pub async fn is_empty(&self) -> bool {
-        self.cache.read().await.is_empty()
+        let cache = self.cache.read().await;
+        cache.iter().all(|(_, entry)| entry.is_expired())
     }
 
     /// Clear all entries
@@ -139,6 +332,35 @@ impl CacheNode {
     /// Clean up expired entries
     pub async fn cleanup_expired(&self) -> usize {
         let mut cache = self.cache.write().await;
+        Self::remove_expired(&mut cache)
+    }
+
+    /// Spawn a background task that periodically removes expired entries
+    ///
+    /// Returns `None` when `expiration_interval` isn't configured. The task
+    /// stops on its own once the node is dropped.
+    pub fn spawn_expiration_task(&self) -> Option<JoinHandle<()>> {
+        let period = self.config.expiration_interval.filter(|p| !p.is_zero())?;
+        let cache = Arc::downgrade(&self.cache);
+
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+
+            loop {
+                interval.tick().await;
+
+                let Some(cache) = cache.upgrade() else {
+                    break;
+                };
+                let removed = Self::remove_expired(&mut *cache.write().await);
+                if removed > 0 {
+                    tracing::debug!("Expired {} entries", removed);
+                }
+            }
+        }))
+    }
+
+    fn remove_expired(cache: &mut LruCache<String, CacheEntry>) -> usize {
         let mut expired_keys = Vec::new();
 
         // Find expired keys (can't remove during iteration)
@@ -157,6 +379,18 @@ impl CacheNode {
     }
 }
 
+#[cfg(test)]
+impl CacheNode {
+    /// Block every operation on this node for `duration`, simulating a hung node
+    pub(crate) async fn stall(&self, duration: Duration) {
+        let guard = Arc::clone(&self.cache).write_owned().await;
+        tokio::spawn(async move {
+            tokio::time::sleep(duration).await;
+            drop(guard);
+        });
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -228,6 +462,138 @@ mod tests {
         assert_eq!(cache.get("key4").await.unwrap(), Some(Bytes::from("value4")));
     }
 
+    async fn fill_hot_and_cold(cache: &CacheNode) {
+        cache.set("hot".to_string(), Bytes::from("h")).await.unwrap();
+        cache.set("cold".to_string(), Bytes::from("c")).await.unwrap();
+
+        for _ in 0..10 {
+            cache.get("hot").await.unwrap();
+        }
+        // Read the cold key last so it is the most recently used
+        cache.get("cold").await.unwrap();
+
+        cache.set("new".to_string(), Bytes::from("n")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lfu_evicts_cold_key() {
+        let cache = CacheNode::new(CacheConfig {
+            max_entries: 2,
+            eviction_policy: EvictionPolicy::Lfu,
+            ..Default::default()
+        });
+
+        fill_hot_and_cold(&cache).await;
+
+        assert!(!cache.exists("cold").await.unwrap());
+        assert!(cache.exists("hot").await.unwrap());
+        assert!(cache.exists("new").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_lfu_skips_removed_keys() {
+        let cache = CacheNode::new(CacheConfig {
+            max_entries: 2,
+            eviction_policy: EvictionPolicy::Lfu,
+            ..Default::default()
+        });
+
+        cache.set("a".to_string(), Bytes::from("1")).await.unwrap();
+        cache.get("a").await.unwrap();
+        for i in 0..100 {
+            let key = format!("tmp{}", i);
+            cache.set(key.clone(), Bytes::from("t")).await.unwrap();
+            cache.delete(&key).await.unwrap();
+        }
+        // Positions of deleted keys are dropped, not kept forever
+        assert!(cache.frequencies.lock().unwrap().order.len() < 25);
+
+        cache.set("b".to_string(), Bytes::from("2")).await.unwrap();
+        cache.set("c".to_string(), Bytes::from("3")).await.unwrap();
+        assert!(cache.exists("a").await.unwrap());
+        assert!(!cache.exists("b").await.unwrap());
+        assert!(cache.exists("c").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_lru_evicts_least_recent_key() {
+        let cache = CacheNode::with_capacity(2);
+
+        fill_hot_and_cold(&cache).await;
+
+        assert!(!cache.exists("hot").await.unwrap());
+        assert!(cache.exists("cold").await.unwrap());
+        assert!(cache.exists("new").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_lfu_overwrite_keeps_frequency() {
+        let cache = CacheNode::new(CacheConfig {
+            max_entries: 2,
+            eviction_policy: EvictionPolicy::Lfu,
+            ..Default::default()
+        });
+
+        cache.set("a".to_string(), Bytes::from("1")).await.unwrap();
+        cache.set("b".to_string(), Bytes::from("1")).await.unwrap();
+        cache.get("a").await.unwrap();
+        cache.get("a").await.unwrap();
+        cache.get("b").await.unwrap();
+
+        // Overwriting does not reset "a" or evict anything
+        cache.set("a".to_string(), Bytes::from("2")).await.unwrap();
+        assert_eq!(cache.len().await, 2);
+
+        cache.set("c".to_string(), Bytes::from("1")).await.unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), Some(Bytes::from("2")));
+        assert!(!cache.exists("b").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cas() {
+        let cache = CacheNode::with_capacity(100);
+        cache.set("lock".to_string(), Bytes::from("a")).await.unwrap();
+
+        // Wrong expectation leaves the value alone
+        let swapped = cache
+            .cas("lock", Some(Bytes::from("b")), Bytes::from("c"))
+            .await
+            .unwrap();
+        assert!(!swapped);
+        assert_eq!(cache.get("lock").await.unwrap(), Some(Bytes::from("a")));
+
+        let swapped = cache
+            .cas("lock", Some(Bytes::from("a")), Bytes::from("c"))
+            .await
+            .unwrap();
+        assert!(swapped);
+        assert_eq!(cache.get("lock").await.unwrap(), Some(Bytes::from("c")));
+
+        // Expecting absence fails while the key is present
+        assert!(!cache.cas("lock", None, Bytes::from("d")).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cas_set_if_not_exists() {
+        let cache = CacheNode::with_capacity(100);
+
+        assert!(cache.cas("lock", None, Bytes::from("owner1")).await.unwrap());
+        assert!(!cache.cas("lock", None, Bytes::from("owner2")).await.unwrap());
+        assert_eq!(cache.get("lock").await.unwrap(), Some(Bytes::from("owner1")));
+
+        // Expired keys count as absent
+        cache
+            .set_with_ttl(
+                "lease".to_string(),
+                Bytes::from("old"),
+                Some(Duration::from_millis(20)),
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(cache.cas("lease", None, Bytes::from("new")).await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_ttl_expiration() {
         let cache = CacheNode::with_capacity(100);
@@ -264,6 +630,69 @@ mod tests {
         assert!(!cache.exists("key2").await.unwrap());
     }
 
+    #[tokio::test]
+    async fn test_keys_and_len_skip_expired() {
+        let cache = CacheNode::with_capacity(100);
+
+        cache.set("a".to_string(), Bytes::from("1")).await.unwrap();
+        cache.set("b".to_string(), Bytes::from("2")).await.unwrap();
+        cache
+            .set_with_ttl("short".to_string(), Bytes::from("3"), Some(Duration::from_millis(20)))
+            .await
+            .unwrap();
+
+        let mut keys = cache.keys().await;
+        keys.sort();
+        assert_eq!(keys, vec!["a", "b", "short"]);
+        assert_eq!(cache.len().await, 3);
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+
+        let mut keys = cache.keys().await;
+        keys.sort();
+        assert_eq!(keys, vec!["a", "b"]);
+        assert_eq!(cache.len().await, 2);
+
+        // Only the expired entry is left
+        cache.delete("a").await.unwrap();
+        cache.delete("b").await.unwrap();
+        assert_eq!(cache.len().await, 0);
+        assert!(cache.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_expiration_task_sweeps_unread_keys() {
+        let cache = CacheNode::new(CacheConfig {
+            expiration_interval: Some(Duration::from_millis(20)),
+            ..Default::default()
+        });
+        let task = cache.spawn_expiration_task().unwrap();
+
+        cache
+            .set_with_ttl("short".to_string(), Bytes::from("v"), Some(Duration::from_millis(30)))
+            .await
+            .unwrap();
+        cache.set("long".to_string(), Bytes::from("v")).await.unwrap();
+        assert_eq!(cache.cache.read().await.len(), 2);
+
+        // Never read the key; only the sweep can remove it
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(cache.cache.read().await.len(), 1);
+        assert_eq!(cache.len().await, 1);
+
+        drop(cache);
+        tokio::time::timeout(Duration::from_secs(1), task)
+            .await
+            .expect("sweep should stop once the node is dropped")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_no_expiration_task_by_default() {
+        let cache = CacheNode::with_capacity(10);
+        assert!(cache.spawn_expiration_task().is_none());
+    }
+
     #[tokio::test]
     async fn test_cleanup_expired() {
         let cache = CacheNode::with_capacity(100);
@@ -3,9 +3,16 @@ use crate::error::{CacheError, Result};
 use crate::hash_ring::{HashRing, NodeId};
 use bytes::Bytes;
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, OwnedMutexGuard, RwLock};
+
+/// Per-key locks held while a loader runs for that key
+///
+/// A std mutex so `LoadGuard::drop` can clean up without awaiting; it is
+/// never held across an await.
+type LoadingLocks = std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>;
 
 /// Configuration for the cache client
 #[derive(Clone, Debug)]
@@ -16,6 +23,8 @@ pub struct ClientConfig {
     pub write_quorum: usize,
     /// Number of virtual nodes per physical node
     pub virtual_nodes: usize,
+    /// How long to wait on a single node before counting it as failed
+    pub operation_timeout: Duration,
 }
 
 impl Default for ClientConfig {
@@ -24,6 +33,7 @@ impl Default for ClientConfig {
             replication_factor: 3,
             write_quorum: 2,
             virtual_nodes: 150,
+            operation_timeout: Duration::from_secs(1),
         }
     }
 }
@@ -32,6 +42,7 @@ impl Default for ClientConfig {
 pub struct CacheClient {
     ring: Arc<RwLock<HashRing>>,
     nodes: Arc<RwLock<HashMap<NodeId, Arc<CacheNode>>>>,
+    loading: Arc<LoadingLocks>,
     config: ClientConfig,
 }
 
@@ -41,6 +52,7 @@ impl CacheClient {
         CacheClient {
             ring: Arc::new(RwLock::new(HashRing::new(config.virtual_nodes))),
             nodes: Arc::new(RwLock::new(HashMap::new())),
+            loading: Arc::default(),
             config,
         }
     }
@@ -69,18 +81,79 @@ impl CacheClient {
     }
 
     /// Get a value from the cache
+    ///
+    /// Reads from the primary, falling back to the other replicas in ring
+    /// order if it fails or doesn't answer within `operation_timeout`.
     pub async fn get(&self, key: &str) -> Result<Option<Bytes>> {
         let ring = self.ring.read().await;
-        let node_id = ring
-            .get_node(key)
-            .ok_or(CacheError::NoNodesAvailable)?;
+        let replica_nodes = ring.get_replicas(key, self.config.replication_factor);
 
         let nodes = self.nodes.read().await;
-        let node = nodes
-            .get(node_id)
-            .ok_or_else(|| CacheError::NodeNotFound(node_id.0.clone()))?;
+        self.read_replicas(&nodes, key, &replica_nodes, CacheError::NoNodesAvailable)
+            .await
+    }
+
+    /// Read `key` from the first of `replica_nodes` that answers in time
+    ///
+    /// Fails with the last node's error, or `last_error` if there are none.
+    async fn read_replicas(
+        &self,
+        nodes: &HashMap<NodeId, Arc<CacheNode>>,
+        key: &str,
+        replica_nodes: &[NodeId],
+        mut last_error: CacheError,
+    ) -> Result<Option<Bytes>> {
+        for node_id in replica_nodes {
+            let Some(node) = nodes.get(node_id) else {
+                last_error = CacheError::NodeNotFound(node_id.0.clone());
+                continue;
+            };
+
+            match with_timeout(self.config.operation_timeout, node_id, node.get(key)).await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    tracing::warn!("Read of {} from {} failed: {}", key, node_id.0, e);
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// Get a value, calling `loader` to fill the cache on a miss
+    ///
+    /// Concurrent misses for the same key share a single loader call; the
+    /// other callers wait for it and then read the stored value.
+    pub async fn get_or_load<F, Fut>(&self, key: &str, loader: F) -> Result<Bytes>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Bytes>>,
+    {
+        if let Some(value) = self.get(key).await? {
+            return Ok(value);
+        }
+
+        let lock = {
+            let mut loading = self.loading.lock().unwrap();
+            Arc::clone(loading.entry(key.to_string()).or_default())
+        };
+        let _guard = LoadGuard {
+            loading: &self.loading,
+            key,
+            _held: Arc::clone(&lock).lock_owned().await,
+            lock,
+        };
 
-        node.get(key).await
+        // Another caller may have loaded it while we waited
+        if let Some(value) = self.get(key).await? {
+            return Ok(value);
+        }
+
+        match loader().await {
+            Ok(value) => self.set(key, value.clone()).await.map(|_| value),
+            Err(e) => Err(e),
+        }
     }
 
     /// Set a value in the cache with replication
@@ -99,14 +172,18 @@ impl CacheClient {
 
         let nodes = self.nodes.read().await;
 
-        // Write to all replicas concurrently
+        // Write to all replicas concurrently; a replica that times out
+        // counts as a failed write
+        let deadline = self.config.operation_timeout;
         let mut futures = Vec::new();
         for node_id in &replica_nodes {
             if let Some(node) = nodes.get(node_id) {
                 let node = Arc::clone(node);
                 let key = key.to_string();
                 let value = value.clone();
-                futures.push(async move { node.set_with_ttl(key, value, ttl).await });
+                futures.push(async move {
+                    with_timeout(deadline, node_id, node.set_with_ttl(key, value, ttl)).await
+                });
             }
         }
 
@@ -126,7 +203,188 @@ impl CacheClient {
         }
     }
 
+    /// Compare-and-swap `key` on its primary node, then replicate
+    ///
+    /// The primary owns the decision, so concurrent callers cannot both
+    /// succeed. On success the new value is copied to the other replicas and
+    /// the write quorum (counting the primary) must be reached.
+    pub async fn cas(&self, key: &str, expected: Option<Bytes>, new: Bytes) -> Result<bool> {
+        let ring = self.ring.read().await;
+        let replica_nodes = ring.get_replicas(key, self.config.replication_factor);
+
+        let (primary_id, others) = replica_nodes
+            .split_first()
+            .ok_or(CacheError::NoNodesAvailable)?;
+
+        let nodes = self.nodes.read().await;
+        let primary = nodes
+            .get(primary_id)
+            .ok_or_else(|| CacheError::NodeNotFound(primary_id.0.clone()))?;
+
+        let deadline = self.config.operation_timeout;
+        if !with_timeout(deadline, primary_id, primary.cas(key, expected, new.clone())).await? {
+            return Ok(false);
+        }
+
+        let mut futures = Vec::new();
+        for node_id in others {
+            if let Some(node) = nodes.get(node_id) {
+                let node = Arc::clone(node);
+                let key = key.to_string();
+                let value = new.clone();
+                futures.push(async move { with_timeout(deadline, node_id, node.set(key, value)).await });
+            }
+        }
+
+        let results = futures::future::join_all(futures).await;
+        let successes = 1 + results.iter().filter(|r| r.is_ok()).count();
+
+        if successes >= self.config.write_quorum {
+            Ok(true)
+        } else {
+            Err(CacheError::QuorumNotReached(
+                successes,
+                self.config.write_quorum,
+            ))
+        }
+    }
+
+    /// Get multiple values, issuing one request per owning node
+    ///
+    /// Results are returned in the same order as `keys`. Keys whose owning
+    /// node fails or times out are read from their other replicas, as `get`
+    /// does.
+    pub async fn mget(&self, keys: &[&str]) -> Result<Vec<Option<Bytes>>> {
+        let ring = self.ring.read().await;
+        let nodes = self.nodes.read().await;
+
+        // Group key positions by the node that owns them
+        let mut batches: HashMap<NodeId, Vec<usize>> = HashMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            let node_id = ring.get_node(key).ok_or(CacheError::NoNodesAvailable)?;
+            batches.entry(node_id.clone()).or_default().push(i);
+        }
+
+        // Fetch from all nodes concurrently
+        let deadline = self.config.operation_timeout;
+        let mut futures = Vec::new();
+        for (node_id, positions) in batches {
+            let node = nodes.get(&node_id).cloned();
+            let batch: Vec<&str> = positions.iter().map(|&i| keys[i]).collect();
+            futures.push(async move {
+                let result = match node {
+                    Some(node) => with_timeout(deadline, &node_id, node.get_many(&batch)).await,
+                    None => Err(CacheError::NodeNotFound(node_id.0.clone())),
+                };
+                (node_id, positions, result)
+            });
+        }
+
+        // Reassemble in input order, setting aside keys from failed batches
+        let mut results = vec![None; keys.len()];
+        let mut fallbacks = Vec::new();
+        for (node_id, positions, batch) in futures::future::join_all(futures).await {
+            let e = match batch {
+                Ok(values) => {
+                    for (i, value) in positions.into_iter().zip(values) {
+                        results[i] = value;
+                    }
+                    continue;
+                }
+                Err(e) => e,
+            };
+
+            tracing::warn!("Batch read from {} failed: {}", node_id.0, e);
+            for i in positions {
+                let others: Vec<NodeId> = ring
+                    .get_replicas(keys[i], self.config.replication_factor)
+                    .into_iter()
+                    .filter(|replica| *replica != node_id)
+                    .collect();
+                if others.is_empty() {
+                    return Err(e);
+                }
+                fallbacks.push((i, others));
+            }
+        }
+
+        let reads = fallbacks.iter().map(|(i, others)| {
+            self.read_replicas(&nodes, keys[*i], others, CacheError::NoNodesAvailable)
+        });
+        let values = futures::future::join_all(reads).await;
+        for ((i, _), value) in fallbacks.iter().zip(values) {
+            results[*i] = value?;
+        }
+
+        Ok(results)
+    }
+
+    /// Set multiple values with replication, issuing one request per node
+    ///
+    /// Every key must reach the write quorum for the call to succeed.
+    pub async fn mset(&self, entries: Vec<(String, Bytes)>) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let ring = self.ring.read().await;
+        let nodes = self.nodes.read().await;
+
+        // Group entries by every replica that should hold them
+        let mut batches = HashMap::new();
+        for (i, (key, value)) in entries.iter().enumerate() {
+            let replica_nodes = ring.get_replicas(key, self.config.replication_factor);
+            if replica_nodes.is_empty() {
+                return Err(CacheError::NoNodesAvailable);
+            }
+
+            for node_id in replica_nodes {
+                let (positions, batch): &mut (Vec<usize>, Vec<(String, Bytes)>) =
+                    batches.entry(node_id).or_default();
+                positions.push(i);
+                batch.push((key.clone(), value.clone()));
+            }
+        }
+
+        // Write to all nodes concurrently
+        let deadline = self.config.operation_timeout;
+        let mut futures = Vec::new();
+        for (node_id, (positions, batch)) in batches {
+            if let Some(node) = nodes.get(&node_id) {
+                let node = Arc::clone(node);
+                futures.push(async move {
+                    let result = with_timeout(deadline, &node_id, node.set_many(batch, None)).await;
+                    (positions, result)
+                });
+            }
+        }
+
+        // Count successful replica writes per key
+        let mut successes = vec![0; entries.len()];
+        for (positions, result) in futures::future::join_all(futures).await {
+            if result.is_ok() {
+                for i in positions {
+                    successes[i] += 1;
+                }
+            }
+        }
+
+        // Check that every key reached quorum
+        let min_successes = successes.into_iter().min().unwrap_or(0);
+        if min_successes >= self.config.write_quorum {
+            Ok(())
+        } else {
+            Err(CacheError::QuorumNotReached(
+                min_successes,
+                self.config.write_quorum,
+            ))
+        }
+    }
+
     /// Delete a value from the cache
+    ///
+    /// Deletes from every replica concurrently. A replica that fails or
+    /// times out is skipped; the call only fails if none of them answered.
     pub async fn delete(&self, key: &str) -> Result<bool> {
         let ring = self.ring.read().await;
         let replica_nodes = ring.get_replicas(key, self.config.replication_factor);
@@ -138,16 +396,35 @@ impl CacheClient {
         let nodes = self.nodes.read().await;
 
         // Delete from all replicas
-        let mut any_deleted = false;
+        let deadline = self.config.operation_timeout;
+        let mut futures = Vec::new();
         for node_id in &replica_nodes {
             if let Some(node) = nodes.get(node_id) {
-                if node.delete(key).await? {
-                    any_deleted = true;
+                let node = Arc::clone(node);
+                futures.push(async move { (node_id, with_timeout(deadline, node_id, node.delete(key)).await) });
+            }
+        }
+
+        let mut any_deleted = false;
+        let mut last_error = None;
+        let mut successes = 0;
+        for (node_id, result) in futures::future::join_all(futures).await {
+            match result {
+                Ok(deleted) => {
+                    successes += 1;
+                    any_deleted |= deleted;
+                }
+                Err(e) => {
+                    tracing::warn!("Delete of {} from {} failed: {}", key, node_id.0, e);
+                    last_error = Some(e);
                 }
             }
         }
 
-        Ok(any_deleted)
+        match last_error {
+            Some(e) if successes == 0 => Err(e),
+            _ => Ok(any_deleted),
+        }
     }
 
     /// Check if a key exists
@@ -162,7 +439,7 @@ impl CacheClient {
             .get(node_id)
             .ok_or_else(|| CacheError::NodeNotFound(node_id.0.clone()))?;
 
-        node.exists(key).await
+        with_timeout(self.config.operation_timeout, node_id, node.exists(key)).await
     }
 
     /// Get number of nodes
@@ -174,6 +451,54 @@ impl CacheClient {
     pub async fn nodes(&self) -> Vec<NodeId> {
         self.ring.read().await.nodes()
     }
+
+    /// Number of live entries held by each node
+    ///
+    /// Replicated keys are counted once per node holding them.
+    pub async fn key_distribution(&self) -> HashMap<NodeId, usize> {
+        let nodes = self.nodes.read().await;
+        let mut distribution = HashMap::with_capacity(nodes.len());
+
+        for (node_id, node) in nodes.iter() {
+            distribution.insert(node_id.clone(), node.len().await);
+        }
+
+        distribution
+    }
+}
+
+/// Holds a key's loader lock, removing it from the map when dropped
+///
+/// Also runs if the caller is cancelled mid-load. Only removes the entry if
+/// it is still this lock, since a later caller may have replaced it.
+struct LoadGuard<'a> {
+    loading: &'a LoadingLocks,
+    key: &'a str,
+    lock: Arc<Mutex<()>>,
+    _held: OwnedMutexGuard<()>,
+}
+
+impl Drop for LoadGuard<'_> {
+    fn drop(&mut self) {
+        let mut loading = self.loading.lock().unwrap();
+        if loading
+            .get(self.key)
+            .is_some_and(|current| Arc::ptr_eq(current, &self.lock))
+        {
+            loading.remove(self.key);
+        }
+    }
+}
+
+/// Run an operation against `node_id`, failing with `Timeout` after `deadline`
+async fn with_timeout<T>(
+    deadline: Duration,
+    node_id: &NodeId,
+    operation: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::time::timeout(deadline, operation)
+        .await
+        .unwrap_or_else(|_| Err(CacheError::Timeout(node_id.0.clone())))
 }
 
 #[cfg(test)]
@@ -187,6 +512,7 @@ mod tests {
             replication_factor: 1,
             write_quorum: 1,
             virtual_nodes: 150,
+            ..Default::default()
         });
 
         let node = Arc::new(CacheNode::new(CacheConfig::default()));
@@ -207,6 +533,7 @@ mod tests {
             replication_factor: 1,
             write_quorum: 1,
             virtual_nodes: 150,
+            ..Default::default()
         });
 
         // Add 3 nodes
@@ -230,12 +557,59 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_key_distribution() {
+        let client = CacheClient::new(ClientConfig {
+            replication_factor: 2,
+            write_quorum: 2,
+            virtual_nodes: 150,
+            ..Default::default()
+        });
+
+        let mut nodes = Vec::new();
+        for i in 1..=3 {
+            let node = Arc::new(CacheNode::new(CacheConfig::default()));
+            client.add_node(format!("node{}", i).into(), Arc::clone(&node)).await;
+            nodes.push(node);
+        }
+
+        for i in 0..50 {
+            client
+                .set(&format!("key{}", i), Bytes::from("v"))
+                .await
+                .unwrap();
+        }
+        // Overwrites land on the same replicas
+        for i in 0..10 {
+            client
+                .set(&format!("key{}", i), Bytes::from("v2"))
+                .await
+                .unwrap();
+        }
+
+        let distribution = client.key_distribution().await;
+        assert_eq!(distribution.len(), 3);
+        assert_eq!(distribution.values().sum::<usize>(), 50 * 2);
+        assert!(distribution.values().all(|&count| count > 0));
+
+        // Every key lives on exactly `replication_factor` nodes
+        let mut placements: HashMap<String, usize> = HashMap::new();
+        for node in &nodes {
+            for key in node.keys().await {
+                *placements.entry(key).or_default() += 1;
+            }
+        }
+        assert_eq!(placements.len(), 50);
+        assert!(placements.values().all(|&count| count == 2));
+    }
+
     #[tokio::test]
     async fn test_replication() {
         let client = CacheClient::new(ClientConfig {
             replication_factor: 3,
             write_quorum: 2,
             virtual_nodes: 150,
+            ..Default::default()
         });
 
         // Add 3 nodes
@@ -273,6 +647,7 @@ mod tests {
             replication_factor: 1,
             write_quorum: 1,
             virtual_nodes: 150,
+            ..Default::default()
         });
 
         let node = Arc::new(CacheNode::new(CacheConfig::default()));
@@ -290,6 +665,403 @@ mod tests {
         assert_eq!(value, None);
     }
 
+    #[tokio::test]
+    async fn test_mget_mset_across_nodes() {
+        let client = CacheClient::new(ClientConfig {
+            replication_factor: 1,
+            write_quorum: 1,
+            virtual_nodes: 150,
+            ..Default::default()
+        });
+
+        for i in 1..=3 {
+            let node = Arc::new(CacheNode::new(CacheConfig::default()));
+            client.add_node(format!("node{}", i).into(), node).await;
+        }
+
+        let keys: Vec<String> = (0..30).map(|i| format!("key{}", i)).collect();
+
+        // Keys should be spread over more than one node
+        {
+            let ring = client.ring.read().await;
+            let owners: std::collections::HashSet<_> =
+                keys.iter().map(|k| ring.get_node(k).unwrap().clone()).collect();
+            assert_eq!(owners.len(), 3);
+        }
+
+        let entries = keys
+            .iter()
+            .map(|k| (k.clone(), Bytes::from(format!("value-{}", k))))
+            .collect();
+        client.mset(entries).await.unwrap();
+
+        // Interleave a missing key to check positions are preserved
+        let mut lookup: Vec<&str> = keys.iter().rev().map(|k| k.as_str()).collect();
+        lookup.insert(5, "missing");
+
+        let values = client.mget(&lookup).await.unwrap();
+        assert_eq!(values.len(), lookup.len());
+        for (key, value) in lookup.iter().zip(values) {
+            if *key == "missing" {
+                assert_eq!(value, None);
+            } else {
+                assert_eq!(value, Some(Bytes::from(format!("value-{}", key))));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mset_replication() {
+        let client = CacheClient::new(ClientConfig {
+            replication_factor: 3,
+            write_quorum: 2,
+            virtual_nodes: 150,
+            ..Default::default()
+        });
+
+        let nodes: Vec<_> = (1..=3)
+            .map(|_| Arc::new(CacheNode::new(CacheConfig::default())))
+            .collect();
+
+        for (i, node) in nodes.iter().enumerate() {
+            client
+                .add_node(format!("node{}", i + 1).into(), Arc::clone(node))
+                .await;
+        }
+
+        client
+            .mset(vec![
+                ("a".to_string(), Bytes::from("1")),
+                ("b".to_string(), Bytes::from("2")),
+            ])
+            .await
+            .unwrap();
+
+        // Every key should be on all three replicas
+        for node in &nodes {
+            assert!(node.exists("a").await.unwrap());
+            assert!(node.exists("b").await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mset_no_nodes() {
+        let client = CacheClient::new_default();
+
+        let result = client
+            .mset(vec![("a".to_string(), Bytes::from("1"))])
+            .await;
+        assert!(matches!(result, Err(CacheError::NoNodesAvailable)));
+    }
+
+    #[tokio::test]
+    async fn test_get_or_load_single_flight() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let client = CacheClient::new(ClientConfig {
+            replication_factor: 3,
+            write_quorum: 2,
+            virtual_nodes: 150,
+            ..Default::default()
+        });
+
+        for i in 1..=3 {
+            let node = Arc::new(CacheNode::new(CacheConfig::default()));
+            client.add_node(format!("node{}", i).into(), node).await;
+        }
+
+        let calls = AtomicUsize::new(0);
+        let load = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(Bytes::from("loaded"))
+        };
+
+        let gets = (0..10).map(|_| client.get_or_load("slow-key", load));
+        for value in futures::future::join_all(gets).await {
+            assert_eq!(value.unwrap(), Bytes::from("loaded"));
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(client.get("slow-key").await.unwrap(), Some(Bytes::from("loaded")));
+
+        // A cached value skips the loader entirely
+        client.get_or_load("slow-key", load).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(client.loading.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_or_load_cancelled() {
+        let client = CacheClient::new(ClientConfig {
+            replication_factor: 1,
+            write_quorum: 1,
+            virtual_nodes: 150,
+            ..Default::default()
+        });
+
+        let node = Arc::new(CacheNode::new(CacheConfig::default()));
+        client.add_node("node1".into(), node).await;
+
+        let stuck = client.get_or_load("key1", std::future::pending);
+        assert!(tokio::time::timeout(Duration::from_millis(20), stuck).await.is_err());
+        assert!(client.loading.lock().unwrap().is_empty());
+
+        let value = client
+            .get_or_load("key1", || async { Ok(Bytes::from("loaded")) })
+            .await
+            .unwrap();
+        assert_eq!(value, Bytes::from("loaded"));
+    }
+
+    #[tokio::test]
+    async fn test_get_or_load_keeps_newer_lock() {
+        let client = CacheClient::new(ClientConfig {
+            replication_factor: 1,
+            write_quorum: 1,
+            virtual_nodes: 150,
+            ..Default::default()
+        });
+
+        let node = Arc::new(CacheNode::new(CacheConfig::default()));
+        client.add_node("node1".into(), node).await;
+
+        // Stand in for a later loader whose lock replaced ours mid-load
+        let newer = Arc::new(Mutex::new(()));
+        client
+            .get_or_load("key1", || async {
+                client
+                    .loading
+                    .lock()
+                    .unwrap()
+                    .insert("key1".to_string(), Arc::clone(&newer));
+                Ok(Bytes::from("loaded"))
+            })
+            .await
+            .unwrap();
+
+        let loading = client.loading.lock().unwrap();
+        assert!(Arc::ptr_eq(&loading["key1"], &newer));
+    }
+
+    #[tokio::test]
+    async fn test_get_or_load_error() {
+        let client = CacheClient::new(ClientConfig {
+            replication_factor: 1,
+            write_quorum: 1,
+            virtual_nodes: 150,
+            ..Default::default()
+        });
+
+        let node = Arc::new(CacheNode::new(CacheConfig::default()));
+        client.add_node("node1".into(), node).await;
+
+        let result = client
+            .get_or_load("key1", || async {
+                Err(CacheError::KeyNotFound("key1".to_string()))
+            })
+            .await;
+        assert!(matches!(result, Err(CacheError::KeyNotFound(_))));
+        assert_eq!(client.get("key1").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_cas_replicates() {
+        let client = CacheClient::new(ClientConfig {
+            replication_factor: 3,
+            write_quorum: 2,
+            virtual_nodes: 150,
+            ..Default::default()
+        });
+
+        let nodes: Vec<_> = (1..=3)
+            .map(|_| Arc::new(CacheNode::new(CacheConfig::default())))
+            .collect();
+
+        for (i, node) in nodes.iter().enumerate() {
+            client
+                .add_node(format!("node{}", i + 1).into(), Arc::clone(node))
+                .await;
+        }
+
+        // Absent expectation acts as set-if-not-exists
+        assert!(client.cas("lock", None, Bytes::from("owner1")).await.unwrap());
+        assert!(!client.cas("lock", None, Bytes::from("owner2")).await.unwrap());
+
+        assert!(!client
+            .cas("lock", Some(Bytes::from("owner2")), Bytes::from("owner3"))
+            .await
+            .unwrap());
+        assert!(client
+            .cas("lock", Some(Bytes::from("owner1")), Bytes::from("owner3"))
+            .await
+            .unwrap());
+
+        for node in &nodes {
+            assert_eq!(node.get("lock").await.unwrap(), Some(Bytes::from("owner3")));
+        }
+    }
+
+    async fn client_with_nodes(
+        config: ClientConfig,
+        count: usize,
+    ) -> (CacheClient, HashMap<NodeId, Arc<CacheNode>>) {
+        let client = CacheClient::new(config);
+        let mut nodes = HashMap::new();
+
+        for i in 1..=count {
+            let node_id: NodeId = format!("node{}", i).into();
+            let node = Arc::new(CacheNode::new(CacheConfig::default()));
+            client.add_node(node_id.clone(), Arc::clone(&node)).await;
+            nodes.insert(node_id, node);
+        }
+
+        (client, nodes)
+    }
+
+    #[tokio::test]
+    async fn test_hung_primary_is_skipped() {
+        let (client, nodes) = client_with_nodes(
+            ClientConfig {
+                replication_factor: 3,
+                write_quorum: 2,
+                operation_timeout: Duration::from_millis(50),
+                ..Default::default()
+            },
+            3,
+        )
+        .await;
+
+        client.set("key", Bytes::from("v1")).await.unwrap();
+
+        let primary = client.ring.read().await.get_replicas("key", 3)[0].clone();
+        nodes[&primary].stall(Duration::from_secs(5)).await;
+
+        let start = std::time::Instant::now();
+        assert_eq!(client.get("key").await.unwrap(), Some(Bytes::from("v1")));
+
+        // Two of three replicas still make the quorum
+        client.set("key", Bytes::from("v2")).await.unwrap();
+        assert_eq!(client.get("key").await.unwrap(), Some(Bytes::from("v2")));
+        assert!(start.elapsed() < Duration::from_secs(1), "took {:?}", start.elapsed());
+    }
+
+    #[tokio::test]
+    async fn test_hung_node_times_out() {
+        let (client, nodes) = client_with_nodes(
+            ClientConfig {
+                replication_factor: 2,
+                write_quorum: 2,
+                operation_timeout: Duration::from_millis(50),
+                ..Default::default()
+            },
+            2,
+        )
+        .await;
+
+        let stalled: NodeId = "node1".into();
+        nodes[&stalled].stall(Duration::from_secs(5)).await;
+
+        let start = std::time::Instant::now();
+        let result = client.set("key", Bytes::from("value")).await;
+        assert!(matches!(result, Err(CacheError::QuorumNotReached(1, 2))));
+
+        // With every replica hung, reads report the timeout
+        nodes[&"node2".into()].stall(Duration::from_secs(5)).await;
+        let result = client.get("key").await;
+        assert!(matches!(result, Err(CacheError::Timeout(_))), "{:?}", result);
+        assert!(start.elapsed() < Duration::from_secs(1), "took {:?}", start.elapsed());
+    }
+
+    #[tokio::test]
+    async fn test_mget_falls_back_to_replicas() {
+        let (client, nodes) = client_with_nodes(
+            ClientConfig {
+                replication_factor: 3,
+                write_quorum: 2,
+                operation_timeout: Duration::from_millis(50),
+                ..Default::default()
+            },
+            3,
+        )
+        .await;
+
+        let keys: Vec<String> = (0..30).map(|i| format!("key{}", i)).collect();
+        let entries = keys
+            .iter()
+            .map(|k| (k.clone(), Bytes::from(format!("value-{}", k))))
+            .collect();
+        client.mset(entries).await.unwrap();
+
+        nodes[&"node1".into()].stall(Duration::from_secs(5)).await;
+
+        let start = std::time::Instant::now();
+        let lookup: Vec<&str> = keys.iter().map(|k| k.as_str()).collect();
+        let values = client.mget(&lookup).await.unwrap();
+        for (key, value) in lookup.iter().zip(values) {
+            assert_eq!(value, Some(Bytes::from(format!("value-{}", key))));
+        }
+        assert!(start.elapsed() < Duration::from_secs(1), "took {:?}", start.elapsed());
+
+        // With no other replica to ask, the timeout is reported
+        let (client, nodes) = client_with_nodes(
+            ClientConfig {
+                replication_factor: 1,
+                write_quorum: 1,
+                operation_timeout: Duration::from_millis(50),
+                ..Default::default()
+            },
+            1,
+        )
+        .await;
+        nodes[&"node1".into()].stall(Duration::from_secs(5)).await;
+        let result = client.mget(&["key"]).await;
+        assert!(matches!(result, Err(CacheError::Timeout(_))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_delete_skips_hung_replica() {
+        let (client, nodes) = client_with_nodes(
+            ClientConfig {
+                replication_factor: 3,
+                write_quorum: 2,
+                operation_timeout: Duration::from_millis(50),
+                ..Default::default()
+            },
+            3,
+        )
+        .await;
+
+        client.set("key", Bytes::from("value")).await.unwrap();
+
+        let stalled: NodeId = "node1".into();
+        nodes[&stalled].stall(Duration::from_secs(5)).await;
+
+        assert!(client.delete("key").await.unwrap());
+        for (node_id, node) in &nodes {
+            if *node_id != stalled {
+                assert_eq!(node.get("key").await.unwrap(), None);
+            }
+        }
+
+        // Only a delete that reaches no replica at all fails
+        for (node_id, node) in &nodes {
+            if *node_id != stalled {
+                node.stall(Duration::from_secs(5)).await;
+            }
+        }
+        let result = client.delete("key").await;
+        assert!(matches!(result, Err(CacheError::Timeout(_))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_cas_no_nodes() {
+        let client = CacheClient::new_default();
+
+        let result = client.cas("lock", None, Bytes::from("owner")).await;
+        assert!(matches!(result, Err(CacheError::NoNodesAvailable)));
+    }
+
     // Note: test_node_addition removed - requires data migration on topology change
     // which is not implemented in this basic version
 
@@ -299,6 +1071,7 @@ mod tests {
             replication_factor: 1,
             write_quorum: 1,
             virtual_nodes: 150,
+            ..Default::default()
         });
 
         let node = Arc::new(CacheNode::new(CacheConfig::default()));
@@ -17,6 +17,9 @@ pub enum CacheError {
     #[error("Quorum not reached: {0}/{1}")]
     QuorumNotReached(usize, usize),
 
+    #[error("Operation timed out on node {0}")]
+    Timeout(String),
+
     #[error("Node unhealthy: {0}")]
     NodeUnhealthy(String),
 
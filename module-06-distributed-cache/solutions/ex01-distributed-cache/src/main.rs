@@ -4,9 +4,10 @@ mod error;
 mod hash_ring;
 
 use bytes::Bytes;
-use cache_node::{CacheConfig, CacheNode};
+use cache_node::{CacheConfig, CacheNode, EvictionPolicy};
 use client::{CacheClient, ClientConfig};
 use std::sync::Arc;
+use std::time::Duration;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
@@ -27,6 +28,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         replication_factor: 3,
         write_quorum: 2,
         virtual_nodes: 150,
+        operation_timeout: Duration::from_millis(500),
     });
 
     // Add 4 cache nodes
@@ -34,7 +36,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let node = Arc::new(CacheNode::new(CacheConfig {
             max_entries: 1000,
             default_ttl: None,
+            eviction_policy: EvictionPolicy::Lfu,
+            expiration_interval: Some(Duration::from_secs(1)),
         }));
+        node.spawn_expiration_task();
         client.add_node(format!("node{}", i).into(), node).await;
         tracing::info!("Added node{}", i);
     }
@@ -55,8 +60,42 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    // Batch operations
+    let entries = (10..20)
+        .map(|i| (format!("user:{}", i), Bytes::from(format!("User data {}", i))))
+        .collect();
+    client.mset(entries).await?;
+
+    let keys: Vec<String> = (10..20).map(|i| format!("user:{}", i)).collect();
+    let keys: Vec<&str> = keys.iter().map(|k| k.as_str()).collect();
+    let values = client.mget(&keys).await?;
+    tracing::info!(
+        "Batch fetched {} of {} keys",
+        values.iter().filter(|v| v.is_some()).count(),
+        keys.len()
+    );
+
+    // Read-through: the loader only runs on a miss
+    let profile = client
+        .get_or_load("profile:42", || async {
+            tracing::info!("Loading profile:42 from the backing store");
+            Ok(Bytes::from("Profile data 42"))
+        })
+        .await?;
+    tracing::info!("Read-through got {}", String::from_utf8_lossy(&profile));
+
+    // Compare-and-swap as a simple lock
+    let acquired = client.cas("lock:report", None, Bytes::from("worker-1")).await?;
+    let contended = client.cas("lock:report", None, Bytes::from("worker-2")).await?;
+    tracing::info!("Lock acquired by worker-1: {}, by worker-2: {}", acquired, contended);
+
     // Show distribution
     tracing::info!("Total nodes: {}", client.node_count().await);
+    let mut distribution: Vec<_> = client.key_distribution().await.into_iter().collect();
+    distribution.sort();
+    for (node_id, count) in distribution {
+        tracing::info!("{} holds {} entries", node_id.0, count);
+    }
 
     tracing::info!("Demo completed successfully");
 
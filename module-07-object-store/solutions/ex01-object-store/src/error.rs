@@ -23,6 +23,9 @@ pub enum ObjectStoreError {
     #[error("Invalid object key: {0}")]
     InvalidObjectKey(String),
 
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+
     #[error("Checksum mismatch: expected {expected}, got {actual}")]
     ChecksumMismatch { expected: String, actual: String },
 }
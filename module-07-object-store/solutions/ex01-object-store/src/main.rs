@@ -1,9 +1,11 @@
 mod error;
 mod metadata;
+mod sniff;
 mod storage;
 mod store;
 
-use store::ObjectStore;
+use storage::Codec;
+use store::{ConditionalGet, ObjectStore};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
@@ -50,6 +52,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .await?;
     tracing::info!("Stored guide.md");
 
+    let log = "GET /index.html 200\n".repeat(100);
+    let meta = store
+        .put_object_with_codec(
+            "documents",
+            "access.log",
+            log.as_bytes(),
+            Some("text/plain".to_string()),
+            Codec::Gzip,
+        )
+        .await?;
+    tracing::info!("Stored access.log ({} bytes, codec {:?})", meta.size, meta.codec);
+
     // List buckets
     let buckets = store.list_buckets().await?;
     tracing::info!("Buckets:");
@@ -82,6 +96,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .await?;
     tracing::info!("Copied readme.txt to readme-copy.txt");
 
+    // Move object (metadata only, the blob stays put)
+    store
+        .move_object("documents", "readme-copy.txt", "images", "readme-moved.txt")
+        .await?;
+    tracing::info!("Moved readme-copy.txt to images/readme-moved.txt");
+
     // Demonstrate deduplication
     let doc3 = b"This is a text document"; // Same as doc1
     let meta = store
@@ -94,6 +114,40 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         meta.content_hash
     );
 
+    // Conditional requests using the ETag
+    let etag = original_meta.etag().to_string();
+    if let ConditionalGet::NotModified(_) = store
+        .get_object_if_none_match("documents", "readme.txt", &etag)
+        .await?
+    {
+        tracing::info!("readme.txt not modified since ETag {}", etag);
+    }
+    let updated = store
+        .put_object_if_match(
+            "documents",
+            "readme.txt",
+            &b"This is an updated text document"[..],
+            Some("text/plain".to_string()),
+            &etag,
+        )
+        .await?;
+    tracing::info!("Updated readme.txt, new ETag {}", updated.etag());
+    let result = store
+        .put_object_if_none_match("documents", "readme.txt", &b"clobber"[..], None, "*")
+        .await;
+    tracing::info!("Create-only put over readme.txt rejected: {}", result.is_err());
+
+    // Check metadata and content agree
+    let report = store.verify().await?;
+    tracing::info!(
+        "Verified {} objects (clean: {}): {} dangling, {} corrupt, {} orphaned blobs",
+        report.objects_checked,
+        report.is_clean(),
+        report.dangling.len(),
+        report.corrupt.len(),
+        report.orphaned_blobs.len()
+    );
+
     tracing::info!("Demo completed successfully");
 
     Ok(())
@@ -1,16 +1,70 @@
 use crate::error::{ObjectStoreError, Result};
+use crate::storage::Codec;
 use serde::{Deserialize, Serialize};
 use sqlx::{sqlite::SqlitePool, FromRow};
 
 /// Object metadata
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
 pub struct ObjectMetadata {
     pub bucket: String,
     pub key: String,
     pub content_hash: String,
+    /// Size of the original, uncompressed content
     pub size: i64,
     pub content_type: Option<String>,
     pub created_at: i64,
+    /// Encoding of the blob at rest
+    pub codec: Codec,
+}
+
+impl ObjectMetadata {
+    /// Entity tag for HTTP caching, which is the content hash
+    pub fn etag(&self) -> &str {
+        &self.content_hash
+    }
+
+    /// Whether `etag` refers to this object's current content
+    ///
+    /// Accepts the quoted and weak (`W/"..."`) forms HTTP clients send.
+    pub fn etag_matches(&self, etag: &str) -> bool {
+        normalize_etag(etag) == self.content_hash
+    }
+}
+
+/// Strip HTTP quoting from an entity tag
+pub fn normalize_etag(etag: &str) -> &str {
+    let etag = etag.trim();
+    let etag = etag.strip_prefix("W/").unwrap_or(etag);
+    etag.strip_prefix('"')
+        .and_then(|etag| etag.strip_suffix('"'))
+        .unwrap_or(etag)
+}
+
+/// Condition a write must satisfy against the current object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precondition<'a> {
+    /// The object exists with this ETag; `*` means it exists at all
+    IfMatch(&'a str),
+    /// The object doesn't have this ETag; `*` means it must not exist at all
+    IfNoneMatch(&'a str),
+}
+
+impl Precondition<'_> {
+    /// Whether the condition holds for `current`, `None` if there's no object
+    pub fn holds(&self, current: Option<&ObjectMetadata>) -> bool {
+        match (*self, current) {
+            (Precondition::IfMatch("*"), current) => current.is_some(),
+            (Precondition::IfMatch(etag), Some(current)) => current.etag_matches(etag),
+            (Precondition::IfMatch(_), None) => false,
+            (Precondition::IfNoneMatch("*"), current) => current.is_none(),
+            (Precondition::IfNoneMatch(etag), Some(current)) => !current.etag_matches(etag),
+            (Precondition::IfNoneMatch(_), None) => true,
+        }
+    }
+}
+
+fn precondition_failed(bucket: &str, key: &str, precondition: Precondition<'_>) -> ObjectStoreError {
+    ObjectStoreError::PreconditionFailed(format!("{}/{} ({:?})", bucket, key, precondition))
 }
 
 /// Bucket metadata
@@ -51,6 +105,7 @@ impl MetadataStore {
                 size INTEGER NOT NULL,
                 content_type TEXT,
                 created_at INTEGER NOT NULL,
+                codec TEXT NOT NULL DEFAULT 'none',
                 PRIMARY KEY (bucket, key),
                 FOREIGN KEY (bucket) REFERENCES buckets(name) ON DELETE CASCADE
             )
@@ -59,6 +114,19 @@ impl MetadataStore {
         .execute(&pool)
         .await?;
 
+        // Databases created before compression support lack the codec column
+        let (has_codec,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM pragma_table_info('objects') WHERE name = 'codec'",
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        if has_codec == 0 {
+            sqlx::query("ALTER TABLE objects ADD COLUMN codec TEXT NOT NULL DEFAULT 'none'")
+                .execute(&pool)
+                .await?;
+        }
+
         Ok(MetadataStore { pool })
     }
 
@@ -123,6 +191,7 @@ impl MetadataStore {
         content_hash: &str,
         size: i64,
         content_type: Option<String>,
+        codec: Codec,
     ) -> Result<()> {
         // Verify bucket exists
         if !self.bucket_exists(bucket).await? {
@@ -133,13 +202,14 @@ impl MetadataStore {
 
         sqlx::query(
             r#"
-            INSERT INTO objects (bucket, key, content_hash, size, content_type, created_at)
-            VALUES (?, ?, ?, ?, ?, ?)
+            INSERT INTO objects (bucket, key, content_hash, size, content_type, created_at, codec)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(bucket, key) DO UPDATE SET
                 content_hash = excluded.content_hash,
                 size = excluded.size,
                 content_type = excluded.content_type,
-                created_at = excluded.created_at
+                created_at = excluded.created_at,
+                codec = excluded.codec
             "#,
         )
         .bind(bucket)
@@ -148,12 +218,155 @@ impl MetadataStore {
         .bind(size)
         .bind(content_type)
         .bind(now)
+        .bind(codec)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
+    /// Put object metadata only if `precondition` holds
+    ///
+    /// The check is part of the write statement itself, so a concurrent
+    /// writer can't slip in between. Fails with `PreconditionFailed`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn put_object_if(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_hash: &str,
+        size: i64,
+        content_type: Option<String>,
+        codec: Codec,
+        precondition: Precondition<'_>,
+    ) -> Result<()> {
+        if !self.bucket_exists(bucket).await? {
+            return Err(ObjectStoreError::BucketNotFound(bucket.to_string()));
+        }
+
+        let now = chrono::Utc::now().timestamp();
+
+        let result = match precondition {
+            Precondition::IfMatch("*") => {
+                sqlx::query(
+                    r#"
+                    UPDATE objects SET
+                        content_hash = ?,
+                        size = ?,
+                        content_type = ?,
+                        created_at = ?,
+                        codec = ?
+                    WHERE bucket = ? AND key = ?
+                    "#,
+                )
+                .bind(content_hash)
+                .bind(size)
+                .bind(content_type)
+                .bind(now)
+                .bind(codec)
+                .bind(bucket)
+                .bind(key)
+                .execute(&self.pool)
+                .await?
+            }
+            Precondition::IfMatch(etag) => {
+                sqlx::query(
+                    r#"
+                    UPDATE objects SET
+                        content_hash = ?,
+                        size = ?,
+                        content_type = ?,
+                        created_at = ?,
+                        codec = ?
+                    WHERE bucket = ? AND key = ? AND content_hash = ?
+                    "#,
+                )
+                .bind(content_hash)
+                .bind(size)
+                .bind(content_type)
+                .bind(now)
+                .bind(codec)
+                .bind(bucket)
+                .bind(key)
+                .bind(normalize_etag(etag))
+                .execute(&self.pool)
+                .await?
+            }
+            Precondition::IfNoneMatch("*") => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO objects (bucket, key, content_hash, size, content_type, created_at, codec)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                    ON CONFLICT(bucket, key) DO NOTHING
+                    "#,
+                )
+                .bind(bucket)
+                .bind(key)
+                .bind(content_hash)
+                .bind(size)
+                .bind(content_type)
+                .bind(now)
+                .bind(codec)
+                .execute(&self.pool)
+                .await?
+            }
+            Precondition::IfNoneMatch(etag) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO objects (bucket, key, content_hash, size, content_type, created_at, codec)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                    ON CONFLICT(bucket, key) DO UPDATE SET
+                        content_hash = excluded.content_hash,
+                        size = excluded.size,
+                        content_type = excluded.content_type,
+                        created_at = excluded.created_at,
+                        codec = excluded.codec
+                    WHERE objects.content_hash != ?
+                    "#,
+                )
+                .bind(bucket)
+                .bind(key)
+                .bind(content_hash)
+                .bind(size)
+                .bind(content_type)
+                .bind(now)
+                .bind(codec)
+                .bind(normalize_etag(etag))
+                .execute(&self.pool)
+                .await?
+            }
+        };
+
+        if result.rows_affected() == 0 {
+            return Err(precondition_failed(bucket, key, precondition));
+        }
+
+        Ok(())
+    }
+
+    /// Fail with `PreconditionFailed` unless `precondition` holds right now
+    ///
+    /// Lets callers give up before doing any work; the write itself still
+    /// has to go through [`put_object_if`](Self::put_object_if).
+    pub async fn check_precondition(
+        &self,
+        bucket: &str,
+        key: &str,
+        precondition: Precondition<'_>,
+    ) -> Result<()> {
+        let current = match self.get_object(bucket, key).await {
+            Ok(current) => Some(current),
+            Err(ObjectStoreError::ObjectNotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+
+        if !precondition.holds(current.as_ref()) {
+            return Err(precondition_failed(bucket, key, precondition));
+        }
+
+        Ok(())
+    }
+
     /// Get object metadata
     pub async fn get_object(&self, bucket: &str, key: &str) -> Result<ObjectMetadata> {
         let obj = sqlx::query_as::<_, ObjectMetadata>(
@@ -170,6 +383,79 @@ impl MetadataStore {
         Ok(obj)
     }
 
+    /// Re-point `src` at `dst` and remove `src` in one transaction
+    ///
+    /// Any existing object at `dst` is replaced. The content hash is carried
+    /// over unchanged, so the blob itself is never touched.
+    pub async fn move_object(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dst_bucket: &str,
+        dst_key: &str,
+    ) -> Result<ObjectMetadata> {
+        let mut tx = self.pool.begin().await?;
+
+        let source = sqlx::query_as::<_, ObjectMetadata>(
+            "SELECT * FROM objects WHERE bucket = ? AND key = ?",
+        )
+        .bind(src_bucket)
+        .bind(src_key)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            ObjectStoreError::ObjectNotFound(format!("{}/{}", src_bucket, src_key))
+        })?;
+
+        if (src_bucket, src_key) == (dst_bucket, dst_key) {
+            return Ok(source);
+        }
+
+        let (dst_exists,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM buckets WHERE name = ?")
+            .bind(dst_bucket)
+            .fetch_one(&mut *tx)
+            .await?;
+        if dst_exists == 0 {
+            return Err(ObjectStoreError::BucketNotFound(dst_bucket.to_string()));
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO objects (bucket, key, content_hash, size, content_type, created_at, codec)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(bucket, key) DO UPDATE SET
+                content_hash = excluded.content_hash,
+                size = excluded.size,
+                content_type = excluded.content_type,
+                created_at = excluded.created_at,
+                codec = excluded.codec
+            "#,
+        )
+        .bind(dst_bucket)
+        .bind(dst_key)
+        .bind(&source.content_hash)
+        .bind(source.size)
+        .bind(&source.content_type)
+        .bind(source.created_at)
+        .bind(source.codec)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM objects WHERE bucket = ? AND key = ?")
+            .bind(src_bucket)
+            .bind(src_key)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(ObjectMetadata {
+            bucket: dst_bucket.to_string(),
+            key: dst_key.to_string(),
+            ..source
+        })
+    }
+
     /// Delete object metadata
     pub async fn delete_object(&self, bucket: &str, key: &str) -> Result<bool> {
         let result = sqlx::query("DELETE FROM objects WHERE bucket = ? AND key = ?")
@@ -181,6 +467,17 @@ impl MetadataStore {
         Ok(result.rows_affected() > 0)
     }
 
+    /// List every object across all buckets
+    pub async fn list_all_objects(&self) -> Result<Vec<ObjectMetadata>> {
+        let objects = sqlx::query_as::<_, ObjectMetadata>(
+            "SELECT * FROM objects ORDER BY bucket, key",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(objects)
+    }
+
     /// List objects in a bucket
     pub async fn list_objects(&self, bucket: &str, prefix: Option<&str>) -> Result<Vec<ObjectMetadata>> {
         let objects = if let Some(prefix) = prefix {
@@ -237,7 +534,14 @@ mod tests {
 
         store.create_bucket("bucket1").await.unwrap();
         store
-            .put_object("bucket1", "file.txt", "hash123", 1024, Some("text/plain".to_string()))
+            .put_object(
+                "bucket1",
+                "file.txt",
+                "hash123",
+                1024,
+                Some("text/plain".to_string()),
+                Codec::None,
+            )
             .await
             .unwrap();
 
@@ -245,6 +549,7 @@ mod tests {
         assert_eq!(obj.key, "file.txt");
         assert_eq!(obj.content_hash, "hash123");
         assert_eq!(obj.size, 1024);
+        assert_eq!(obj.codec, Codec::None);
     }
 
     #[tokio::test]
@@ -252,9 +557,9 @@ mod tests {
         let store = MetadataStore::new("sqlite::memory:").await.unwrap();
 
         store.create_bucket("bucket1").await.unwrap();
-        store.put_object("bucket1", "docs/a.txt", "hash1", 100, None).await.unwrap();
-        store.put_object("bucket1", "docs/b.txt", "hash2", 200, None).await.unwrap();
-        store.put_object("bucket1", "images/c.jpg", "hash3", 300, None).await.unwrap();
+        store.put_object("bucket1", "docs/a.txt", "hash1", 100, None, Codec::None).await.unwrap();
+        store.put_object("bucket1", "docs/b.txt", "hash2", 200, None, Codec::None).await.unwrap();
+        store.put_object("bucket1", "images/c.jpg", "hash3", 300, None, Codec::None).await.unwrap();
 
         let docs = store.list_objects("bucket1", Some("docs/")).await.unwrap();
         assert_eq!(docs.len(), 2);
@@ -263,12 +568,76 @@ mod tests {
         assert_eq!(all.len(), 3);
     }
 
+    #[test]
+    fn test_normalize_etag() {
+        assert_eq!(normalize_etag("abc"), "abc");
+        assert_eq!(normalize_etag("\"abc\""), "abc");
+        assert_eq!(normalize_etag("W/\"abc\""), "abc");
+        assert_eq!(normalize_etag("\"abc"), "\"abc");
+    }
+
+    #[tokio::test]
+    async fn test_put_object_if_none_match_star() {
+        let store = MetadataStore::new("sqlite::memory:").await.unwrap();
+
+        store.create_bucket("bucket1").await.unwrap();
+        let create_only = Precondition::IfNoneMatch("*");
+
+        store
+            .put_object_if("bucket1", "file.txt", "hash1", 1, None, Codec::None, create_only)
+            .await
+            .unwrap();
+        let result = store
+            .put_object_if("bucket1", "file.txt", "hash2", 1, None, Codec::None, create_only)
+            .await;
+
+        assert!(matches!(result, Err(ObjectStoreError::PreconditionFailed(_))));
+        assert_eq!(store.get_object("bucket1", "file.txt").await.unwrap().content_hash, "hash1");
+    }
+
+    #[tokio::test]
+    async fn test_put_object_if_match_star() {
+        let store = MetadataStore::new("sqlite::memory:").await.unwrap();
+
+        store.create_bucket("bucket1").await.unwrap();
+        let must_exist = Precondition::IfMatch("*");
+
+        let result = store
+            .put_object_if("bucket1", "file.txt", "hash1", 1, None, Codec::None, must_exist)
+            .await;
+        assert!(matches!(result, Err(ObjectStoreError::PreconditionFailed(_))));
+        assert!(store.check_precondition("bucket1", "file.txt", must_exist).await.is_err());
+
+        store.put_object("bucket1", "file.txt", "hash1", 1, None, Codec::None).await.unwrap();
+        store.check_precondition("bucket1", "file.txt", must_exist).await.unwrap();
+        store
+            .put_object_if("bucket1", "file.txt", "hash2", 1, None, Codec::None, must_exist)
+            .await
+            .unwrap();
+        assert_eq!(store.get_object("bucket1", "file.txt").await.unwrap().content_hash, "hash2");
+    }
+
+    #[tokio::test]
+    async fn test_move_object_missing_destination_bucket() {
+        let store = MetadataStore::new("sqlite::memory:").await.unwrap();
+
+        store.create_bucket("bucket1").await.unwrap();
+        store.put_object("bucket1", "file.txt", "hash123", 1024, None, Codec::None).await.unwrap();
+
+        let result = store.move_object("bucket1", "file.txt", "nope", "file.txt").await;
+        assert!(matches!(result, Err(ObjectStoreError::BucketNotFound(_))));
+
+        // The rolled-back transaction leaves the source in place
+        let obj = store.get_object("bucket1", "file.txt").await.unwrap();
+        assert_eq!(obj.content_hash, "hash123");
+    }
+
     #[tokio::test]
     async fn test_delete_object() {
         let store = MetadataStore::new("sqlite::memory:").await.unwrap();
 
         store.create_bucket("bucket1").await.unwrap();
-        store.put_object("bucket1", "file.txt", "hash123", 1024, None).await.unwrap();
+        store.put_object("bucket1", "file.txt", "hash123", 1024, None, Codec::None).await.unwrap();
 
         assert!(store.delete_object("bucket1", "file.txt").await.unwrap());
         assert!(!store.delete_object("bucket1", "file.txt").await.unwrap());
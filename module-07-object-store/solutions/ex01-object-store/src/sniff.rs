@@ -0,0 +1,78 @@
+/// How many leading bytes are inspected to guess a content type
+pub const SNIFF_LEN: u64 = 512;
+
+/// Content type for data that is neither a known format nor text
+const BINARY: &str = "application/octet-stream";
+
+/// Known formats and the bytes their files start with
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"\x1f\x8b", "application/gzip"),
+];
+
+/// Guess a content type from the first bytes of some content
+///
+/// Magic numbers are checked first, then the bytes are treated as text if
+/// they are UTF-8 without control characters. Anything else is binary.
+/// Returns `None` for empty content.
+pub fn sniff_content_type(head: &[u8]) -> Option<&'static str> {
+    if head.is_empty() {
+        return None;
+    }
+
+    let known = SIGNATURES
+        .iter()
+        .find(|(magic, _)| head.starts_with(magic))
+        .map(|&(_, content_type)| content_type);
+
+    Some(known.unwrap_or_else(|| {
+        if looks_like_text(head) {
+            "text/plain"
+        } else {
+            BINARY
+        }
+    }))
+}
+
+fn looks_like_text(head: &[u8]) -> bool {
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        // The sample may end partway through a multi-byte character
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&head[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return false,
+    };
+
+    !text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x0c'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_magic_numbers() {
+        assert_eq!(sniff_content_type(b"GIF89a\x01\x00"), Some("image/gif"));
+        assert_eq!(sniff_content_type(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(sniff_content_type(b"\x1f\x8b\x08\x00"), Some("application/gzip"));
+    }
+
+    #[test]
+    fn test_sniff_text_and_binary() {
+        assert_eq!(sniff_content_type(b"line one\r\n\tline two"), Some("text/plain"));
+        assert_eq!(sniff_content_type("caf\u{e9}".as_bytes()), Some("text/plain"));
+        // A sample cut off inside a multi-byte character is still text
+        assert_eq!(sniff_content_type(&"caf\u{e9}".as_bytes()[..4]), Some("text/plain"));
+
+        assert_eq!(sniff_content_type(b"\x00\x01\x02binary"), Some(BINARY));
+        assert_eq!(sniff_content_type(b"\xc3\x28"), Some(BINARY));
+        assert_eq!(sniff_content_type(b""), None);
+    }
+}
@@ -1,9 +1,34 @@
 use crate::error::{ObjectStoreError, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
+/// How a blob is encoded on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    None,
+    Gzip,
+}
+
+/// Where a blob ended up after `put_with_codec`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredBlob {
+    /// SHA-256 of the original content
+    pub hash: String,
+    /// Encoding of the blob on disk, which may differ from the requested
+    /// one when identical content was already stored
+    pub codec: Codec,
+    /// Size of the original content
+    pub size: u64,
+}
+
 /// Content-addressed storage backend
 pub struct ContentStore {
     root: PathBuf,
@@ -19,52 +44,99 @@ impl ContentStore {
     }
 
     /// Store content and return its SHA-256 hash
-    pub async fn put<R: AsyncRead + Unpin>(&self, mut reader: R) -> Result<String> {
-        let mut hasher = Sha256::new();
+    pub async fn put<R: AsyncRead + Unpin>(&self, reader: R) -> Result<String> {
+        Ok(self.put_with_codec(reader, Codec::None).await?.hash)
+    }
+
+    /// Store content, encoding it with `codec` unless it is already stored
+    ///
+    /// Blobs are addressed by the hash of the original content, so identical
+    /// data is kept once whichever codec it was first written with.
+    pub async fn put_with_codec<R: AsyncRead + Unpin>(
+        &self,
+        mut reader: R,
+        codec: Codec,
+    ) -> Result<StoredBlob> {
         let mut buffer = Vec::new();
 
         // Read all content and compute hash
         reader.read_to_end(&mut buffer).await?;
-        hasher.update(&buffer);
-        let hash = hex::encode(hasher.finalize());
+        let hash = hash_content(&buffer);
+        let size = buffer.len() as u64;
+
+        if let Some(existing) = self.codec_of(&hash) {
+            return Ok(StoredBlob {
+                hash,
+                codec: existing,
+                size,
+            });
+        }
 
         // Create nested directory structure (first 2 chars / next 2 chars / hash)
-        let dir = self.hash_to_path(&hash);
-        if let Some(parent) = dir.parent() {
+        let path = self.blob_path(&hash, codec);
+        if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).await?;
         }
 
+        let encoded = match codec {
+            Codec::None => buffer,
+            Codec::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&buffer)?;
+                encoder.finish()?
+            }
+        };
+
         // Write content to disk
-        fs::write(&dir, &buffer).await?;
+        fs::write(&path, &encoded).await?;
 
-        Ok(hash)
+        Ok(StoredBlob {
+            hash,
+            codec,
+            size,
+        })
     }
 
-    /// Retrieve content by hash
+    /// Retrieve content by hash, decoding it if stored compressed
     pub async fn get(&self, hash: &str) -> Result<Vec<u8>> {
-        let path = self.hash_to_path(hash);
-
-        if !path.exists() {
-            return Err(ObjectStoreError::ObjectNotFound(hash.to_string()));
+        let codec = self
+            .codec_of(hash)
+            .ok_or_else(|| ObjectStoreError::ObjectNotFound(hash.to_string()))?;
+
+        let data = fs::read(self.blob_path(hash, codec)).await?;
+
+        match codec {
+            Codec::None => Ok(data),
+            Codec::Gzip => {
+                let mut decoded = Vec::new();
+                GzDecoder::new(&data[..]).read_to_end(&mut decoded)?;
+                Ok(decoded)
+            }
         }
+    }
+
+    /// Size of the blob as stored on disk
+    pub async fn stored_size(&self, hash: &str) -> Result<u64> {
+        let codec = self
+            .codec_of(hash)
+            .ok_or_else(|| ObjectStoreError::ObjectNotFound(hash.to_string()))?;
 
-        Ok(fs::read(&path).await?)
+        Ok(fs::metadata(self.blob_path(hash, codec)).await?.len())
     }
 
     /// Check if content exists
     pub async fn exists(&self, hash: &str) -> bool {
-        self.hash_to_path(hash).exists()
+        self.codec_of(hash).is_some()
     }
 
     /// Delete content by hash
     pub async fn delete(&self, hash: &str) -> Result<bool> {
-        let path = self.hash_to_path(hash);
+        let codec = match self.codec_of(hash) {
+            Some(codec) => codec,
+            None => return Ok(false),
+        };
 
-        if !path.exists() {
-            return Ok(false);
-        }
-
-        fs::remove_file(&path).await?;
+        fs::remove_file(self.blob_path(hash, codec)).await?;
         Ok(true)
     }
 
@@ -74,26 +146,82 @@ impl ContentStore {
         hash: &str,
         mut writer: W,
     ) -> Result<u64> {
-        let path = self.hash_to_path(hash);
+        let content = self.get(hash).await?;
+        writer.write_all(&content).await?;
+        Ok(content.len() as u64)
+    }
 
-        if !path.exists() {
-            return Err(ObjectStoreError::ObjectNotFound(hash.to_string()));
+    /// List the hashes of every stored blob
+    pub async fn list_hashes(&self) -> Result<Vec<String>> {
+        let mut hashes = Vec::new();
+
+        // Blobs live exactly two directories below the root
+        let mut level1 = fs::read_dir(&self.root).await?;
+        while let Some(dir1) = level1.next_entry().await? {
+            if !dir1.file_type().await?.is_dir() {
+                continue;
+            }
+
+            let mut level2 = fs::read_dir(dir1.path()).await?;
+            while let Some(dir2) = level2.next_entry().await? {
+                if !dir2.file_type().await?.is_dir() {
+                    continue;
+                }
+
+                let mut blobs = fs::read_dir(dir2.path()).await?;
+                while let Some(blob) = blobs.next_entry().await? {
+                    if blob.file_type().await?.is_file() {
+                        let name = blob.file_name().to_string_lossy().into_owned();
+                        let hash = name.strip_suffix(".gz").unwrap_or(&name);
+                        hashes.push(hash.to_string());
+                    }
+                }
+            }
         }
 
-        let content = fs::read(&path).await?;
-        writer.write_all(&content).await?;
-        Ok(content.len() as u64)
+        hashes.sort();
+        Ok(hashes)
+    }
+
+    /// Re-hash a stored blob and check it matches its address
+    ///
+    /// Returns `ObjectNotFound` if the blob is missing.
+    pub async fn verify(&self, hash: &str) -> Result<bool> {
+        let content = self.get(hash).await?;
+        Ok(hash_content(&content) == hash)
+    }
+
+    /// Codec of the stored blob, or `None` if it is missing
+    fn codec_of(&self, hash: &str) -> Option<Codec> {
+        [Codec::None, Codec::Gzip]
+            .into_iter()
+            .find(|&codec| self.blob_path(hash, codec).exists())
+    }
+
+    /// Path of a blob encoded with `codec`
+    /// Compressed blobs carry a `.gz` suffix
+    pub(crate) fn blob_path(&self, hash: &str, codec: Codec) -> PathBuf {
+        let path = self.hash_to_path(hash);
+        match codec {
+            Codec::None => path,
+            Codec::Gzip => path.with_extension("gz"),
+        }
     }
 
     /// Convert hash to filesystem path
     /// Format: root/ab/cd/abcd1234...
-    fn hash_to_path(&self, hash: &str) -> PathBuf {
+    pub(crate) fn hash_to_path(&self, hash: &str) -> PathBuf {
         let prefix1 = &hash[0..2];
         let prefix2 = &hash[2..4];
         self.root.join(prefix1).join(prefix2).join(hash)
     }
 }
 
+/// SHA-256 of `content` as lowercase hex
+fn hash_content(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,4 +278,60 @@ mod tests {
         assert!(!store.exists(&hash).await);
         assert!(!store.delete(&hash).await.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_list_and_verify() {
+        let dir = tempdir().unwrap();
+        let store = ContentStore::new(dir.path()).await.unwrap();
+
+        let hash1 = store.put(&b"first"[..]).await.unwrap();
+        let hash2 = store.put(&b"second"[..]).await.unwrap();
+
+        let mut expected = vec![hash1.clone(), hash2.clone()];
+        expected.sort();
+        assert_eq!(store.list_hashes().await.unwrap(), expected);
+
+        assert!(store.verify(&hash1).await.unwrap());
+
+        // Corrupt the blob on disk
+        fs::write(store.hash_to_path(&hash1), b"tampered").await.unwrap();
+        assert!(!store.verify(&hash1).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_gzip_round_trip() {
+        let dir = tempdir().unwrap();
+        let store = ContentStore::new(dir.path()).await.unwrap();
+
+        let data = "all work and no play makes jack a dull boy\n".repeat(200);
+        let blob = store
+            .put_with_codec(data.as_bytes(), Codec::Gzip)
+            .await
+            .unwrap();
+
+        assert_eq!(blob.codec, Codec::Gzip);
+        assert_eq!(blob.size, data.len() as u64);
+        assert!(store.stored_size(&blob.hash).await.unwrap() < blob.size / 10);
+        assert_eq!(store.get(&blob.hash).await.unwrap(), data.as_bytes());
+        assert!(store.verify(&blob.hash).await.unwrap());
+        assert_eq!(store.list_hashes().await.unwrap(), vec![blob.hash.clone()]);
+
+        assert!(store.delete(&blob.hash).await.unwrap());
+        assert!(!store.exists(&blob.hash).await);
+    }
+
+    #[tokio::test]
+    async fn test_dedupe_across_codecs() {
+        let dir = tempdir().unwrap();
+        let store = ContentStore::new(dir.path()).await.unwrap();
+
+        let data = b"same bytes either way";
+        let gzip = store.put_with_codec(&data[..], Codec::Gzip).await.unwrap();
+        let plain = store.put_with_codec(&data[..], Codec::None).await.unwrap();
+
+        // The existing compressed blob is reused
+        assert_eq!(plain.hash, gzip.hash);
+        assert_eq!(plain.codec, Codec::Gzip);
+        assert_eq!(store.list_hashes().await.unwrap().len(), 1);
+    }
 }
@@ -1,8 +1,43 @@
 use crate::error::{ObjectStoreError, Result};
-use crate::metadata::{BucketMetadata, MetadataStore, ObjectMetadata};
-use crate::storage::ContentStore;
+use crate::metadata::{BucketMetadata, MetadataStore, ObjectMetadata, Precondition};
+use crate::sniff::{sniff_content_type, SNIFF_LEN};
+use crate::storage::{Codec, ContentStore};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Cursor;
 use std::path::Path;
-use tokio::io::AsyncRead;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Result of checking metadata against stored content
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Number of object metadata entries checked
+    pub objects_checked: usize,
+    /// Objects (as `bucket/key`) whose blob is missing
+    pub dangling: Vec<String>,
+    /// Objects (as `bucket/key`) whose blob no longer matches its hash
+    pub corrupt: Vec<String>,
+    /// Blob hashes not referenced by any object
+    pub orphaned_blobs: Vec<String>,
+}
+
+impl VerifyReport {
+    /// True if no problems were found
+    pub fn is_clean(&self) -> bool {
+        self.dangling.is_empty() && self.corrupt.is_empty() && self.orphaned_blobs.is_empty()
+    }
+}
+
+/// Outcome of a conditional GET
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConditionalGet {
+    /// The caller's ETag is current; no content was read
+    NotModified(ObjectMetadata),
+    /// The object differs from the caller's copy
+    Modified {
+        metadata: ObjectMetadata,
+        content: Vec<u8>,
+    },
+}
 
 /// Main object store combining content storage and metadata
 pub struct ObjectStore {
@@ -49,25 +84,48 @@ impl ObjectStore {
     // Object operations
 
     /// Put an object
+    ///
+    /// Without a `content_type`, one is guessed from the first bytes of the
+    /// content and recorded instead.
     pub async fn put_object<R: AsyncRead + Unpin>(
         &self,
         bucket: &str,
         key: &str,
-        mut content: R,
+        content: R,
         content_type: Option<String>,
+    ) -> Result<ObjectMetadata> {
+        self.put_object_with_codec(bucket, key, content, content_type, Codec::None)
+            .await
+    }
+
+    /// Put an object, compressing it at rest with `codec`
+    ///
+    /// Reads decompress transparently. If identical content is already
+    /// stored it is reused as-is, and the metadata records its actual codec.
+    pub async fn put_object_with_codec<R: AsyncRead + Unpin>(
+        &self,
+        bucket: &str,
+        key: &str,
+        content: R,
+        content_type: Option<String>,
+        codec: Codec,
     ) -> Result<ObjectMetadata> {
         validate_object_key(key)?;
+        let (mut content, content_type) = sniff_if_missing(content, content_type).await?;
 
         // Store content and get hash
-        let content_hash = self.content.put(&mut content).await?;
-
-        // Get size
-        let data = self.content.get(&content_hash).await?;
-        let size = data.len() as i64;
+        let blob = self.content.put_with_codec(&mut content, codec).await?;
 
         // Store metadata
         self.metadata
-            .put_object(bucket, key, &content_hash, size, content_type.clone())
+            .put_object(
+                bucket,
+                key,
+                &blob.hash,
+                blob.size as i64,
+                content_type,
+                blob.codec,
+            )
             .await?;
 
         // Return metadata
@@ -80,6 +138,89 @@ impl ObjectStore {
         self.content.get(&metadata.content_hash).await
     }
 
+    /// Get an object unless the caller already has it
+    ///
+    /// Returns `NotModified` without touching the blob when `etag` matches
+    /// the current content, like HTTP `If-None-Match`.
+    pub async fn get_object_if_none_match(
+        &self,
+        bucket: &str,
+        key: &str,
+        etag: &str,
+    ) -> Result<ConditionalGet> {
+        let metadata = self.metadata.get_object(bucket, key).await?;
+        if metadata.etag_matches(etag) {
+            return Ok(ConditionalGet::NotModified(metadata));
+        }
+
+        let content = self.content.get(&metadata.content_hash).await?;
+        Ok(ConditionalGet::Modified { metadata, content })
+    }
+
+    /// Put an object only if its current ETag is `etag`
+    ///
+    /// For optimistic concurrency: read, modify, then write back with the
+    /// ETag that was read. Fails with `PreconditionFailed` if the object
+    /// changed or no longer exists.
+    pub async fn put_object_if_match<R: AsyncRead + Unpin>(
+        &self,
+        bucket: &str,
+        key: &str,
+        content: R,
+        content_type: Option<String>,
+        etag: &str,
+    ) -> Result<ObjectMetadata> {
+        self.put_object_if(bucket, key, content, content_type, Precondition::IfMatch(etag))
+            .await
+    }
+
+    /// Put an object only if its current ETag isn't `etag`
+    ///
+    /// Pass `*` to only create the object if it doesn't exist yet.
+    pub async fn put_object_if_none_match<R: AsyncRead + Unpin>(
+        &self,
+        bucket: &str,
+        key: &str,
+        content: R,
+        content_type: Option<String>,
+        etag: &str,
+    ) -> Result<ObjectMetadata> {
+        self.put_object_if(bucket, key, content, content_type, Precondition::IfNoneMatch(etag))
+            .await
+    }
+
+    async fn put_object_if<R: AsyncRead + Unpin>(
+        &self,
+        bucket: &str,
+        key: &str,
+        content: R,
+        content_type: Option<String>,
+        precondition: Precondition<'_>,
+    ) -> Result<ObjectMetadata> {
+        validate_object_key(key)?;
+        self.metadata.check_precondition(bucket, key, precondition).await?;
+        let (mut content, content_type) = sniff_if_missing(content, content_type).await?;
+
+        let blob = self.content.put_with_codec(&mut content, Codec::None).await?;
+
+        // If another writer got in after the check, the blob stays behind:
+        // a concurrent put of the same bytes may already share it, so it's
+        // left for verify to report as orphaned rather than deleted here
+        self.metadata
+            .put_object_if(
+                bucket,
+                key,
+                &blob.hash,
+                blob.size as i64,
+                content_type,
+                blob.codec,
+                precondition,
+            )
+            .await?;
+
+        self.metadata.get_object(bucket, key).await
+    }
+
     /// Get object metadata
     pub async fn head_object(&self, bucket: &str, key: &str) -> Result<ObjectMetadata> {
         self.metadata.get_object(bucket, key).await
@@ -127,11 +268,93 @@ impl ObjectStore {
                 &source.content_hash,
                 source.size,
                 source.content_type,
+                source.codec,
             )
             .await?;
 
         self.metadata.get_object(dest_bucket, dest_key).await
     }
+
+    /// Move an object, atomically with respect to metadata
+    ///
+    /// Unlike `copy_object` followed by `delete_object`, a failure part way
+    /// leaves either the source or the destination, never both. The blob is
+    /// shared as-is.
+    pub async fn move_object(
+        &self,
+        source_bucket: &str,
+        source_key: &str,
+        dest_bucket: &str,
+        dest_key: &str,
+    ) -> Result<ObjectMetadata> {
+        validate_object_key(dest_key)?;
+        self.metadata
+            .move_object(source_bucket, source_key, dest_bucket, dest_key)
+            .await
+    }
+
+    /// Check every object's blob exists and matches its recorded hash
+    ///
+    /// Also reports blobs that no object points at. Nothing is repaired.
+    pub async fn verify(&self) -> Result<VerifyReport> {
+        let objects = self.metadata.list_all_objects().await?;
+
+        // Group objects by blob so shared content is only hashed once
+        let mut by_hash: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for obj in &objects {
+            by_hash
+                .entry(&obj.content_hash)
+                .or_default()
+                .push(format!("{}/{}", obj.bucket, obj.key));
+        }
+
+        let mut report = VerifyReport {
+            objects_checked: objects.len(),
+            ..Default::default()
+        };
+
+        for (hash, names) in &by_hash {
+            match self.content.verify(hash).await {
+                Ok(true) => {}
+                Ok(false) => report.corrupt.extend(names.iter().cloned()),
+                Err(ObjectStoreError::ObjectNotFound(_)) => {
+                    report.dangling.extend(names.iter().cloned())
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let referenced: HashSet<&str> = by_hash.keys().copied().collect();
+        report.orphaned_blobs = self
+            .content
+            .list_hashes()
+            .await?
+            .into_iter()
+            .filter(|hash| !referenced.contains(hash.as_str()))
+            .collect();
+
+        report.dangling.sort();
+        report.corrupt.sort();
+
+        Ok(report)
+    }
+}
+
+/// Fill in a missing content type by sniffing the start of `content`
+///
+/// An explicit content type is kept as given. The returned reader still
+/// yields all of the content, including the bytes that were sniffed.
+async fn sniff_if_missing<R: AsyncRead + Unpin>(
+    mut content: R,
+    content_type: Option<String>,
+) -> Result<(impl AsyncRead + Unpin, Option<String>)> {
+    let mut head = Vec::new();
+    if content_type.is_none() {
+        (&mut content).take(SNIFF_LEN).read_to_end(&mut head).await?;
+    }
+
+    let content_type = content_type.or_else(|| sniff_content_type(&head).map(String::from));
+    Ok((Cursor::new(head).chain(content), content_type))
 }
 
 /// Validate bucket name (simplified S3 rules)
@@ -270,6 +493,154 @@ mod tests {
         assert_eq!(copied, data);
     }
 
+    #[tokio::test]
+    async fn test_move_object() {
+        let dir = tempdir().unwrap();
+        let store = ObjectStore::new(dir.path(), "sqlite::memory:")
+            .await
+            .unwrap();
+
+        store.create_bucket("bucket1").await.unwrap();
+        store.create_bucket("bucket2").await.unwrap();
+
+        let data = b"Hello, World!";
+        let original = store
+            .put_object("bucket1", "original.txt", &data[..], Some("text/plain".to_string()))
+            .await
+            .unwrap();
+
+        let moved = store
+            .move_object("bucket1", "original.txt", "bucket2", "moved.txt")
+            .await
+            .unwrap();
+        assert_eq!(moved.bucket, "bucket2");
+        assert_eq!(moved.key, "moved.txt");
+        assert_eq!(moved.content_hash, original.content_hash);
+        assert_eq!(moved.content_type.as_deref(), Some("text/plain"));
+
+        assert!(matches!(
+            store.head_object("bucket1", "original.txt").await,
+            Err(ObjectStoreError::ObjectNotFound(_))
+        ));
+        assert_eq!(store.get_object("bucket2", "moved.txt").await.unwrap(), data);
+
+        // Still a single blob, referenced once
+        assert_eq!(store.content.list_hashes().await.unwrap().len(), 1);
+        assert!(store.verify().await.unwrap().is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_conditional_get() {
+        let dir = tempdir().unwrap();
+        let store = ObjectStore::new(dir.path(), "sqlite::memory:")
+            .await
+            .unwrap();
+
+        store.create_bucket("my-bucket").await.unwrap();
+        store.put_object("my-bucket", "page.html", &b"v1"[..], None).await.unwrap();
+        let head = store.head_object("my-bucket", "page.html").await.unwrap();
+        assert_eq!(head.etag(), head.content_hash);
+
+        // Quoted, as it would arrive in an If-None-Match header
+        let quoted = format!("\"{}\"", head.etag());
+        let result = store
+            .get_object_if_none_match("my-bucket", "page.html", &quoted)
+            .await
+            .unwrap();
+        assert_eq!(result, ConditionalGet::NotModified(head.clone()));
+
+        store.put_object("my-bucket", "page.html", &b"v2"[..], None).await.unwrap();
+        match store
+            .get_object_if_none_match("my-bucket", "page.html", head.etag())
+            .await
+            .unwrap()
+        {
+            ConditionalGet::Modified { metadata, content } => {
+                assert_ne!(metadata.etag(), head.etag());
+                assert_eq!(content, b"v2");
+            }
+            other => panic!("expected Modified, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conditional_put() {
+        let dir = tempdir().unwrap();
+        let store = ObjectStore::new(dir.path(), "sqlite::memory:")
+            .await
+            .unwrap();
+
+        store.create_bucket("my-bucket").await.unwrap();
+
+        let v1 = store
+            .put_object_if_none_match("my-bucket", "doc.txt", &b"v1"[..], None, "*")
+            .await
+            .unwrap();
+        assert!(matches!(
+            store
+                .put_object_if_none_match("my-bucket", "doc.txt", &b"other"[..], None, "*")
+                .await,
+            Err(ObjectStoreError::PreconditionFailed(_))
+        ));
+
+        // Two writers read v1; the first write wins, the second is rejected
+        let v2 = store
+            .put_object_if_match("my-bucket", "doc.txt", &b"v2"[..], None, v1.etag())
+            .await
+            .unwrap();
+        assert!(matches!(
+            store
+                .put_object_if_match("my-bucket", "doc.txt", &b"v2 from a stale reader"[..], None, v1.etag())
+                .await,
+            Err(ObjectStoreError::PreconditionFailed(_))
+        ));
+        assert_eq!(store.get_object("my-bucket", "doc.txt").await.unwrap(), b"v2");
+
+        // If-Match never creates
+        assert!(matches!(
+            store
+                .put_object_if_match("my-bucket", "new.txt", &b"data"[..], None, v2.etag())
+                .await,
+            Err(ObjectStoreError::PreconditionFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_conditional_put_leaves_no_blob() {
+        let dir = tempdir().unwrap();
+        let store = ObjectStore::new(dir.path(), "sqlite::memory:")
+            .await
+            .unwrap();
+
+        store.create_bucket("my-bucket").await.unwrap();
+
+        // If-Match: * only updates an object that exists
+        assert!(matches!(
+            store
+                .put_object_if_match("my-bucket", "doc.txt", &b"v1"[..], None, "*")
+                .await,
+            Err(ObjectStoreError::PreconditionFailed(_))
+        ));
+        store.put_object("my-bucket", "doc.txt", &b"v1"[..], None).await.unwrap();
+        store
+            .put_object_if_match("my-bucket", "doc.txt", &b"v2"[..], None, "*")
+            .await
+            .unwrap();
+        assert_eq!(store.get_object("my-bucket", "doc.txt").await.unwrap(), b"v2");
+
+        assert!(store
+            .put_object_if_none_match("my-bucket", "doc.txt", &b"v3"[..], None, "*")
+            .await
+            .is_err());
+        assert!(store
+            .put_object_if_match("my-bucket", "doc.txt", &b"v4"[..], None, "stale")
+            .await
+            .is_err());
+
+        // Only the blobs for v1 and v2 were ever written
+        assert_eq!(store.content.list_hashes().await.unwrap().len(), 2);
+    }
+
     #[tokio::test]
     async fn test_content_deduplication() {
         let dir = tempdir().unwrap();
@@ -304,4 +675,153 @@ mod tests {
         // Valid
         assert!(store.create_bucket("my-bucket-123").await.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_gzip_object_round_trip() {
+        let dir = tempdir().unwrap();
+        let store = ObjectStore::new(dir.path(), "sqlite::memory:")
+            .await
+            .unwrap();
+
+        store.create_bucket("logs").await.unwrap();
+
+        let data = "GET /index.html 200\n".repeat(500);
+        let metadata = store
+            .put_object_with_codec(
+                "logs",
+                "access.log",
+                data.as_bytes(),
+                Some("text/plain".to_string()),
+                Codec::Gzip,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.codec, Codec::Gzip);
+        assert_eq!(metadata.size, data.len() as i64);
+
+        let stored = store.content.stored_size(&metadata.content_hash).await.unwrap();
+        assert!(stored < data.len() as u64 / 10, "stored {} bytes", stored);
+
+        let retrieved = store.get_object("logs", "access.log").await.unwrap();
+        assert_eq!(retrieved, data.as_bytes());
+        assert!(store.verify().await.unwrap().is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_compressed_and_plain_dedupe() {
+        let dir = tempdir().unwrap();
+        let store = ObjectStore::new(dir.path(), "sqlite::memory:")
+            .await
+            .unwrap();
+
+        store.create_bucket("my-bucket").await.unwrap();
+
+        let data = "repeated text ".repeat(100);
+        let gzip = store
+            .put_object_with_codec("my-bucket", "a.txt", data.as_bytes(), None, Codec::Gzip)
+            .await
+            .unwrap();
+        let plain = store
+            .put_object("my-bucket", "b.txt", data.as_bytes(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(gzip.content_hash, plain.content_hash);
+        assert_eq!(plain.codec, Codec::Gzip);
+        assert_eq!(store.content.list_hashes().await.unwrap().len(), 1);
+        assert_eq!(store.get_object("my-bucket", "b.txt").await.unwrap(), data.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_verify_clean_store() {
+        let dir = tempdir().unwrap();
+        let store = ObjectStore::new(dir.path(), "sqlite::memory:")
+            .await
+            .unwrap();
+
+        store.create_bucket("my-bucket").await.unwrap();
+        store.put_object("my-bucket", "a.txt", &b"shared"[..], None).await.unwrap();
+        store.put_object("my-bucket", "b.txt", &b"shared"[..], None).await.unwrap();
+
+        let report = store.verify().await.unwrap();
+        assert_eq!(report.objects_checked, 2);
+        assert!(report.is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_verify_reports_problems() {
+        let dir = tempdir().unwrap();
+        let store = ObjectStore::new(dir.path(), "sqlite::memory:")
+            .await
+            .unwrap();
+
+        store.create_bucket("my-bucket").await.unwrap();
+        let missing = store
+            .put_object("my-bucket", "missing.txt", &b"missing"[..], None)
+            .await
+            .unwrap();
+        let corrupt = store
+            .put_object("my-bucket", "corrupt.txt", &b"corrupt"[..], None)
+            .await
+            .unwrap();
+        store.put_object("my-bucket", "ok.txt", &b"ok"[..], None).await.unwrap();
+
+        // Pull the blob out from under the metadata, tamper with another,
+        // and leave a blob nothing refers to
+        store.content.delete(&missing.content_hash).await.unwrap();
+        tokio::fs::write(store.content.hash_to_path(&corrupt.content_hash), b"garbage")
+            .await
+            .unwrap();
+        let orphan = store.content.put(&b"orphan"[..]).await.unwrap();
+
+        let report = store.verify().await.unwrap();
+        assert_eq!(report.objects_checked, 3);
+        assert_eq!(report.dangling, vec!["my-bucket/missing.txt"]);
+        assert_eq!(report.corrupt, vec!["my-bucket/corrupt.txt"]);
+        assert_eq!(report.orphaned_blobs, vec![orphan]);
+        assert!(!report.is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_put_object_sniffs_content_type() {
+        let dir = tempdir().unwrap();
+        let store = ObjectStore::new(dir.path(), "sqlite::memory:")
+            .await
+            .unwrap();
+        store.create_bucket("my-bucket").await.unwrap();
+
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+        let jpeg = b"\xff\xd8\xff\xe0\x00\x10JFIF\x00";
+        let cases: [(&str, &[u8], &str); 3] = [
+            ("image.png", png, "image/png"),
+            ("photo.jpg", jpeg, "image/jpeg"),
+            ("notes", b"plain old text\n", "text/plain"),
+        ];
+
+        for (key, data, expected) in cases {
+            let metadata = store.put_object("my-bucket", key, data, None).await.unwrap();
+            assert_eq!(metadata.content_type.as_deref(), Some(expected), "{}", key);
+
+            // Sniffing must not eat the start of the content
+            assert_eq!(store.get_object("my-bucket", key).await.unwrap(), data);
+        }
+
+        // Content longer than the sniffed prefix is stored whole
+        let long = "x".repeat(SNIFF_LEN as usize * 3);
+        let metadata = store
+            .put_object_if_none_match("my-bucket", "long.txt", long.as_bytes(), None, "*")
+            .await
+            .unwrap();
+        assert_eq!(metadata.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(metadata.size, long.len() as i64);
+        assert_eq!(store.get_object("my-bucket", "long.txt").await.unwrap(), long.as_bytes());
+
+        // An explicit content type wins over the sniffed one
+        let metadata = store
+            .put_object("my-bucket", "data.bin", &png[..], Some("application/x-custom".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(metadata.content_type.as_deref(), Some("application/x-custom"));
+    }
 }
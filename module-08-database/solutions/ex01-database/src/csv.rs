@@ -0,0 +1,162 @@
+use crate::error::{DbError, Result};
+
+/// One CSV field; `None` for an empty unquoted field
+///
+/// An empty quoted field (`""`) is an empty string, so NULL and empty
+/// text survive a round trip.
+pub type Field = Option<String>;
+
+/// Split CSV text into records of fields
+///
+/// Each record is paired with the line it starts on, which differs from its
+/// index when quoted fields span lines. Quoted fields may hold commas, line
+/// breaks and doubled quotes. Both `\n` and `\r\n` end a record, and a
+/// trailing line break is optional.
+pub fn parse(text: &str) -> Result<Vec<(usize, Vec<Field>)>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if in_quotes {
+            match ch {
+                '"' if chars.peek() == Some(&'"') => {
+                    // A doubled quote inside a quoted field is a literal quote
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(ch);
+                }
+                _ => field.push(ch),
+            }
+            continue;
+        }
+
+        match ch {
+            '"' if field.is_empty() && !quoted => {
+                in_quotes = true;
+                quoted = true;
+            }
+            '"' => {
+                return Err(DbError::ParseError(format!(
+                    "CSV line {}: unexpected quote",
+                    line
+                )))
+            }
+            ',' => record.push(finish_field(&mut field, &mut quoted)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(finish_field(&mut field, &mut quoted));
+                records.push((start, std::mem::take(&mut record)));
+                line += 1;
+                start = line;
+            }
+            _ if quoted => {
+                return Err(DbError::ParseError(format!(
+                    "CSV line {}: text after closing quote",
+                    line
+                )))
+            }
+            _ => field.push(ch),
+        }
+    }
+
+    if in_quotes {
+        return Err(DbError::ParseError(format!(
+            "CSV line {}: unterminated quoted field",
+            line
+        )));
+    }
+
+    // The last record has no line break after it
+    if !field.is_empty() || quoted || !record.is_empty() {
+        record.push(finish_field(&mut field, &mut quoted));
+        records.push((start, record));
+    }
+
+    Ok(records)
+}
+
+fn finish_field(field: &mut String, quoted: &mut bool) -> Field {
+    let value = std::mem::take(field);
+    let was_quoted = std::mem::replace(quoted, false);
+    if value.is_empty() && !was_quoted {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Append one record to `out`, quoting fields only where needed
+pub fn write_record(out: &mut String, fields: &[Field]) {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+
+        match field {
+            None => {}
+            Some(value) if needs_quotes(value) => {
+                out.push('"');
+                out.push_str(&value.replace('"', "\"\""));
+                out.push('"');
+            }
+            Some(value) => out.push_str(value),
+        }
+    }
+    out.push('\n');
+}
+
+fn needs_quotes(value: &str) -> bool {
+    value.is_empty() || value.contains([',', '"', '\n', '\r'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Field {
+        Some(s.to_string())
+    }
+
+    #[test]
+    fn test_parse_quoted_fields() {
+        let records = parse("id,name\r\n1,\"Smith, \"\"Jo\"\"\"\n2,\"two\nlines\"\n3,").unwrap();
+
+        assert_eq!(
+            records,
+            vec![
+                (1, vec![text("id"), text("name")]),
+                (2, vec![text("1"), text("Smith, \"Jo\"")]),
+                (3, vec![text("2"), text("two\nlines")]),
+                (5, vec![text("3"), None]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_quotes() {
+        assert!(matches!(parse("a,\"open\n"), Err(DbError::ParseError(_))));
+        assert!(matches!(parse("a,b\"c\n"), Err(DbError::ParseError(_))));
+        assert!(matches!(parse("\"a\"b\n"), Err(DbError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let record = vec![None, text(""), text("plain"), text("a,b"), text("say \"hi\"")];
+
+        let mut out = String::new();
+        write_record(&mut out, &record);
+        assert_eq!(out, ",\"\",plain,\"a,b\",\"say \"\"hi\"\"\"\n");
+
+        assert_eq!(parse(&out).unwrap(), vec![(1, record)]);
+    }
+}
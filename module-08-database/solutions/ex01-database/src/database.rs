@@ -1,8 +1,10 @@
+use crate::csv;
 use crate::error::{DbError, Result};
-use crate::parser::{Operator, Parser, Statement};
+use crate::parser::{Operator, Parser, Statement, WhereClause, COUNT_STAR};
 use crate::table::Table;
-use crate::types::{Column, Row, Schema, Value};
-use std::collections::HashMap;
+use crate::types::{Column, DataType, Row, Schema, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
 
 /// Query result
 #[derive(Debug)]
@@ -45,18 +47,41 @@ impl Database {
         let statement = parser.parse()?;
 
         match statement {
-            Statement::CreateTable { name, columns } => self.create_table(name, columns),
-            Statement::Insert { table, values } => self.insert(table, values),
+            Statement::CreateTable {
+                name,
+                columns,
+                if_not_exists,
+            } => self.create_table(name, columns, if_not_exists),
+            Statement::DropTable { name, if_exists } => self.drop_table(name, if_exists),
+            Statement::Insert { table, rows } => self.insert(table, rows),
             Statement::Select {
                 table,
                 columns,
+                distinct,
                 where_clause,
-            } => self.select(table, columns, where_clause),
+                group_by,
+            } => self.select(table, columns, distinct, where_clause, group_by),
+            Statement::Explain(inner) => match *inner {
+                Statement::Select {
+                    table,
+                    where_clause,
+                    ..
+                } => self.explain(table, where_clause),
+                other => Err(DbError::InvalidQuery(format!("Cannot explain {:?}", other))),
+            },
         }
     }
 
-    fn create_table(&mut self, name: String, column_defs: Vec<crate::parser::ColumnDef>) -> Result<QueryResult> {
+    fn create_table(
+        &mut self,
+        name: String,
+        column_defs: Vec<crate::parser::ColumnDef>,
+        if_not_exists: bool,
+    ) -> Result<QueryResult> {
         if self.tables.contains_key(&name) {
+            if if_not_exists {
+                return Ok(QueryResult::empty());
+            }
             return Err(DbError::TableAlreadyExists(name));
         }
 
@@ -78,77 +103,143 @@ impl Database {
         Ok(QueryResult::empty())
     }
 
-    fn insert(&mut self, table_name: String, values: Vec<Value>) -> Result<QueryResult> {
+    fn drop_table(&mut self, name: String, if_exists: bool) -> Result<QueryResult> {
+        if self.tables.remove(&name).is_none() && !if_exists {
+            return Err(DbError::TableNotFound(name));
+        }
+
+        Ok(QueryResult::empty())
+    }
+
+    fn insert(&mut self, table_name: String, rows: Vec<Vec<Value>>) -> Result<QueryResult> {
         let table = self
             .tables
             .get_mut(&table_name)
             .ok_or_else(|| DbError::TableNotFound(table_name.clone()))?;
 
-        let row = Row::new(values);
-        table.insert(row)?;
+        let rows = rows.into_iter().map(Row::new).collect();
+        let count = table.insert_many(rows)?;
 
-        Ok(QueryResult::rows_affected(1))
+        Ok(QueryResult::rows_affected(count))
     }
 
     fn select(
         &self,
         table_name: String,
         column_names: Vec<String>,
-        where_clause: Option<crate::parser::WhereClause>,
+        distinct: bool,
+        where_clause: Option<WhereClause>,
+        group_by: Option<String>,
     ) -> Result<QueryResult> {
         let table = self
             .tables
             .get(&table_name)
             .ok_or_else(|| DbError::TableNotFound(table_name.clone()))?;
 
-        // Get rows (with optional filter)
-        let rows: Vec<&Row> = if let Some(clause) = where_clause {
-            let col_index = table
+        // Resolve the projection against the schema before touching any rows
+        let mut columns = Vec::new();
+        let mut projection = Vec::new();
+        for name in &column_names {
+            if name == "*" {
+                columns.extend(table.schema.columns.iter().map(|c| c.name.clone()));
+                projection.extend((0..table.schema.columns.len()).map(Projection::Column));
+                continue;
+            }
+
+            if name == COUNT_STAR {
+                columns.push(name.clone());
+                projection.push(Projection::Count);
+                continue;
+            }
+
+            let idx = table
                 .schema
-                .column_index(&clause.column)
-                .ok_or_else(|| DbError::ColumnNotFound(clause.column.clone()))?;
+                .column_index(name)
+                .ok_or_else(|| DbError::ColumnNotFound(name.clone()))?;
+            columns.push(name.clone());
+            projection.push(Projection::Column(idx));
+        }
 
-            table.scan_where(|row| {
+        let group_index = match &group_by {
+            Some(name) => Some(
+                table
+                    .schema
+                    .column_index(name)
+                    .ok_or_else(|| DbError::ColumnNotFound(name.clone()))?,
+            ),
+            None => None,
+        };
+        let has_count = projection.contains(&Projection::Count);
+
+        // With aggregates, plain columns are only allowed if they are grouped on
+        if group_index.is_some() || has_count {
+            for (name, item) in columns.iter().zip(&projection) {
+                if let Projection::Column(idx) = item {
+                    if Some(*idx) != group_index {
+                        return Err(DbError::InvalidQuery(format!(
+                            "Column '{}' must appear in GROUP BY",
+                            name
+                        )));
+                    }
+                }
+            }
+        }
+
+        // Get rows (with optional filter)
+        let rows: Vec<&Row> = match plan_access(table, where_clause.as_ref())? {
+            AccessPath::IndexLookup(clause) => table.get_by_pk(&clause.value).into_iter().collect(),
+            AccessPath::FullScan(Some((col_index, clause))) => table.scan_where(|row| {
                 if let Some(value) = row.get(col_index) {
                     matches_predicate(value, &clause.operator, &clause.value)
                 } else {
                     false
                 }
-            })
-        } else {
-            table.scan()
+            }),
+            AccessPath::FullScan(None) => table.scan(),
         };
 
-        // Determine columns to return
-        let columns = if column_names.len() == 1 && column_names[0] == "*" {
-            table.schema.columns.iter().map(|c| c.name.clone()).collect()
+        // Build result
+        let mut result_rows: Vec<Vec<Value>> = if let Some(group_index) = group_index {
+            // One output row per distinct group value, in value order
+            let mut groups: BTreeMap<&Value, i64> = BTreeMap::new();
+            for row in &rows {
+                *groups.entry(&row.values[group_index]).or_insert(0) += 1;
+            }
+
+            groups
+                .into_iter()
+                .map(|(key, count)| {
+                    projection
+                        .iter()
+                        .map(|item| match item {
+                            Projection::Column(_) => key.clone(),
+                            Projection::Count => Value::Integer(count),
+                        })
+                        .collect()
+                })
+                .collect()
+        } else if has_count {
+            // Aggregate over the whole table
+            vec![vec![Value::Integer(rows.len() as i64); projection.len()]]
         } else {
-            column_names.clone()
+            rows.into_iter()
+                .map(|row| {
+                    projection
+                        .iter()
+                        .map(|item| match item {
+                            Projection::Column(idx) => row.values[*idx].clone(),
+                            Projection::Count => unreachable!("handled above"),
+                        })
+                        .collect()
+                })
+                .collect()
         };
 
-        // Extract column indices
-        let column_indices: Result<Vec<usize>> = columns
-            .iter()
-            .map(|name| {
-                table
-                    .schema
-                    .column_index(name)
-                    .ok_or_else(|| DbError::ColumnNotFound(name.clone()))
-            })
-            .collect();
-
-        let column_indices = column_indices?;
-
-        // Build result
-        let result_rows: Vec<Vec<Value>> = rows
-            .into_iter()
-            .map(|row| {
-                column_indices
-                    .iter()
-                    .map(|&idx| row.values[idx].clone())
-                    .collect()
-            })
-            .collect();
+        if distinct {
+            // Keep the first occurrence of each row
+            let mut seen = BTreeSet::new();
+            result_rows.retain(|row| seen.insert(row.clone()));
+        }
 
         Ok(QueryResult {
             columns,
@@ -156,6 +247,137 @@ impl Database {
         })
     }
 
+    /// Describe how a SELECT would fetch its rows, one line per plan step
+    fn explain(
+        &self,
+        table_name: String,
+        where_clause: Option<WhereClause>,
+    ) -> Result<QueryResult> {
+        let table = self
+            .tables
+            .get(&table_name)
+            .ok_or_else(|| DbError::TableNotFound(table_name.clone()))?;
+        let total_rows = table.stats().total_rows;
+
+        let (access, estimated_rows, condition) = match plan_access(table, where_clause.as_ref())? {
+            AccessPath::IndexLookup(clause) => (
+                format!("index lookup on {} using primary key", table_name),
+                total_rows.min(1),
+                format!("{} (index)", describe_clause(clause)),
+            ),
+            AccessPath::FullScan(Some((_, clause))) => (
+                format!("full scan of {}", table_name),
+                total_rows,
+                format!("{} (filter)", describe_clause(clause)),
+            ),
+            AccessPath::FullScan(None) => (
+                format!("full scan of {}", table_name),
+                total_rows,
+                "none".to_string(),
+            ),
+        };
+
+        let plan = [
+            format!("Access: {}", access),
+            format!("Estimated rows: {}", estimated_rows),
+            format!("Condition: {}", condition),
+        ];
+
+        Ok(QueryResult {
+            columns: vec!["plan".to_string()],
+            rows: plan.into_iter().map(|line| vec![Value::Text(line)]).collect(),
+        })
+    }
+
+    /// Load rows into an existing table from a CSV file with a header row
+    ///
+    /// Header names are matched to columns, so they may come in any order.
+    /// Fields are converted to the column types and an empty unquoted field
+    /// is NULL. Nothing is inserted unless every row is valid. Returns the
+    /// number of rows inserted.
+    pub fn import_csv(&mut self, table_name: &str, path: impl AsRef<Path>) -> Result<usize> {
+        let table = self
+            .tables
+            .get_mut(table_name)
+            .ok_or_else(|| DbError::TableNotFound(table_name.to_string()))?;
+
+        let text = std::fs::read_to_string(path)?;
+        let mut records = csv::parse(&text)?.into_iter();
+        let (_, header) = records
+            .next()
+            .ok_or_else(|| DbError::ParseError("CSV file is empty".to_string()))?;
+
+        // Position in the header of each schema column
+        let mut positions = Vec::new();
+        for column in &table.schema.columns {
+            let position = header
+                .iter()
+                .position(|name| name.as_deref() == Some(column.name.as_str()))
+                .ok_or_else(|| DbError::ColumnNotFound(column.name.clone()))?;
+            positions.push(position);
+        }
+        if header.len() != positions.len() {
+            return Err(DbError::InvalidQuery(format!(
+                "CSV header has {} columns, table '{}' has {}",
+                header.len(),
+                table_name,
+                positions.len()
+            )));
+        }
+
+        let mut rows = Vec::new();
+        // Skip blank lines; a lone NULL field can't be a valid row anyway
+        for (line, record) in records.filter(|(_, r)| r != &[None]) {
+            if record.len() != header.len() {
+                return Err(DbError::ParseError(format!(
+                    "CSV line {}: expected {} fields, got {}",
+                    line,
+                    header.len(),
+                    record.len()
+                )));
+            }
+
+            let values = table
+                .schema
+                .columns
+                .iter()
+                .zip(&positions)
+                .map(|(column, &position)| csv_to_value(&record[position], &column.data_type))
+                .collect::<Result<Vec<_>>>()?;
+            rows.push(Row::new(values));
+        }
+
+        table.insert_many(rows)
+    }
+
+    /// Write every row of a table to a CSV file with a header row
+    ///
+    /// NULL is written as an empty field and empty text as `""`.
+    pub fn export_csv(&self, table_name: &str, path: impl AsRef<Path>) -> Result<usize> {
+        let table = self
+            .tables
+            .get(table_name)
+            .ok_or_else(|| DbError::TableNotFound(table_name.to_string()))?;
+
+        let mut out = String::new();
+        let header: Vec<csv::Field> = table
+            .schema
+            .columns
+            .iter()
+            .map(|c| Some(c.name.clone()))
+            .collect();
+        csv::write_record(&mut out, &header);
+
+        let rows = table.scan();
+        for row in &rows {
+            let fields: Vec<csv::Field> = row.values.iter().map(value_to_csv).collect();
+            csv::write_record(&mut out, &fields);
+        }
+
+        std::fs::write(path, out)?;
+        Ok(rows.len())
+    }
+
     /// List all tables
     pub fn list_tables(&self) -> Vec<&str> {
         self.tables.keys().map(|s| s.as_str()).collect()
@@ -167,6 +389,85 @@ impl Database {
     }
 }
 
+/// One output column of a SELECT
+#[derive(Debug, PartialEq)]
+enum Projection {
+    Column(usize),
+    Count,
+}
+
+/// How a SELECT reaches its rows
+#[derive(Debug)]
+enum AccessPath<'a> {
+    /// Equality on the primary key, answered from the index
+    IndexLookup(&'a WhereClause),
+    /// Every row, filtered on a column index when there is a WHERE clause
+    FullScan(Option<(usize, &'a WhereClause)>),
+}
+
+/// Pick the access path for a WHERE clause
+///
+/// Only equality on the primary key can use the index; anything else
+/// scans the table.
+fn plan_access<'a>(table: &Table, where_clause: Option<&'a WhereClause>) -> Result<AccessPath<'a>> {
+    let Some(clause) = where_clause else {
+        return Ok(AccessPath::FullScan(None));
+    };
+
+    let col_index = table
+        .schema
+        .column_index(&clause.column)
+        .ok_or_else(|| DbError::ColumnNotFound(clause.column.clone()))?;
+
+    if clause.operator == Operator::Equals && table.schema.primary_key_index() == Some(col_index) {
+        Ok(AccessPath::IndexLookup(clause))
+    } else {
+        Ok(AccessPath::FullScan(Some((col_index, clause))))
+    }
+}
+
+/// A WHERE clause written back out as SQL
+fn describe_clause(clause: &WhereClause) -> String {
+    let value = match &clause.value {
+        Value::Null => "NULL".to_string(),
+        Value::Boolean(b) => b.to_string().to_uppercase(),
+        Value::Integer(i) => i.to_string(),
+        Value::Text(s) => format!("'{}'", s.replace('\'', "''")),
+    };
+    format!("{} {} {}", clause.column, clause.operator.symbol(), value)
+}
+
+/// Convert a CSV field to a value of the column's type
+fn csv_to_value(field: &csv::Field, data_type: &DataType) -> Result<Value> {
+    let Some(text) = field else {
+        return Ok(Value::Null);
+    };
+
+    let mismatch = || DbError::TypeMismatch {
+        expected: format!("{:?}", data_type),
+        actual: format!("'{}'", text),
+    };
+
+    match data_type {
+        DataType::Integer => text.trim().parse().map(Value::Integer).map_err(|_| mismatch()),
+        DataType::Boolean => match text.trim().to_uppercase().as_str() {
+            "TRUE" => Ok(Value::Boolean(true)),
+            "FALSE" => Ok(Value::Boolean(false)),
+            _ => Err(mismatch()),
+        },
+        DataType::Text => Ok(Value::Text(text.clone())),
+    }
+}
+
+fn value_to_csv(value: &Value) -> csv::Field {
+    match value {
+        Value::Null => None,
+        Value::Boolean(b) => Some(b.to_string().to_uppercase()),
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Text(s) => Some(s.clone()),
+    }
+}
+
 fn matches_predicate(value: &Value, operator: &Operator, target: &Value) -> bool {
     match operator {
         Operator::Equals => value == target,
@@ -185,6 +486,20 @@ fn matches_predicate(value: &Value, operator: &Operator, target: &Value) -> bool
                 false
             }
         }
+        Operator::GreaterThanOrEqual => {
+            if let (Value::Integer(a), Value::Integer(b)) = (value, target) {
+                a >= b
+            } else {
+                false
+            }
+        }
+        Operator::LessThanOrEqual => {
+            if let (Value::Integer(a), Value::Integer(b)) = (value, target) {
+                a <= b
+            } else {
+                false
+            }
+        }
     }
 }
 
@@ -203,6 +518,55 @@ mod tests {
         assert!(db.get_table("users").is_some());
     }
 
+    #[test]
+    fn test_create_duplicate_table() {
+        let mut db = Database::new();
+
+        db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY)")
+            .unwrap();
+        db.execute("INSERT INTO users VALUES (1)")
+            .unwrap();
+
+        let result = db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)");
+        assert!(matches!(result, Err(DbError::TableAlreadyExists(name)) if name == "users"));
+
+        // IF NOT EXISTS leaves the existing table and its rows alone
+        db.execute("CREATE TABLE IF NOT EXISTS users (id INTEGER PRIMARY KEY, name TEXT)")
+            .unwrap();
+        let result = db.execute("SELECT * FROM users").unwrap();
+        assert_eq!(result.columns, vec!["id"]);
+        assert_eq!(result.rows.len(), 1);
+    }
+
+    #[test]
+    fn test_drop_table() {
+        let mut db = Database::new();
+
+        db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY)")
+            .unwrap();
+        db.execute("DROP TABLE users").unwrap();
+
+        assert!(db.get_table("users").is_none());
+        assert!(matches!(
+            db.execute("SELECT * FROM users"),
+            Err(DbError::TableNotFound(_))
+        ));
+
+        // The name can be reused once dropped
+        db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY)")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_drop_missing_table() {
+        let mut db = Database::new();
+
+        let result = db.execute("DROP TABLE missing");
+        assert!(matches!(result, Err(DbError::TableNotFound(name)) if name == "missing"));
+
+        db.execute("DROP TABLE IF EXISTS missing").unwrap();
+    }
+
     #[test]
     fn test_insert_and_select() {
         let mut db = Database::new();
@@ -221,6 +585,27 @@ mod tests {
         assert_eq!(result.columns, vec!["id", "name", "active"]);
     }
 
+    #[test]
+    fn test_multi_row_insert() {
+        let mut db = Database::new();
+
+        db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, age INTEGER)")
+            .unwrap();
+
+        let result = db
+            .execute("INSERT INTO users VALUES (1, 'Alice', 25), (2, 'Bob', 30), (3, 'Charlie', 35)")
+            .unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Integer(3)]]);
+
+        let result = db.execute("SELECT name FROM users WHERE age > 28").unwrap();
+        assert_eq!(result.rows.len(), 2);
+
+        // A bad row rejects the whole statement
+        let result = db.execute("INSERT INTO users VALUES (4, 'Dave', 40), (5, 'Erin', 'old')");
+        assert!(matches!(result, Err(DbError::TypeMismatch { .. })));
+        assert_eq!(db.execute("SELECT * FROM users").unwrap().rows.len(), 3);
+    }
+
     #[test]
     fn test_select_with_where() {
         let mut db = Database::new();
@@ -241,6 +626,201 @@ mod tests {
         assert_eq!(result.columns, vec!["name"]);
     }
 
+    #[test]
+    fn test_select_with_inclusive_comparisons() {
+        let mut db = Database::new();
+
+        db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, age INTEGER)")
+            .unwrap();
+
+        db.execute("INSERT INTO users VALUES (1, 'Alice', 25)")
+            .unwrap();
+        db.execute("INSERT INTO users VALUES (2, 'Bob', 30)")
+            .unwrap();
+        db.execute("INSERT INTO users VALUES (3, 'Charlie', 35)")
+            .unwrap();
+
+        let result = db.execute("SELECT name FROM users WHERE age >= 30").unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Text("Bob".to_string())],
+                vec![Value::Text("Charlie".to_string())],
+            ]
+        );
+
+        let result = db.execute("SELECT name FROM users WHERE age <= 30").unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Text("Alice".to_string())],
+                vec![Value::Text("Bob".to_string())],
+            ]
+        );
+
+        let result = db.execute("SELECT id FROM users WHERE id<=1").unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Integer(1)]]);
+    }
+
+    #[test]
+    fn test_escaped_quotes_round_trip() {
+        let mut db = Database::new();
+
+        db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)")
+            .unwrap();
+
+        db.execute("INSERT INTO users VALUES (1, 'O''Brien')")
+            .unwrap();
+        db.execute("INSERT INTO users VALUES (2, 'Smith')")
+            .unwrap();
+
+        let result = db
+            .execute("SELECT id, name FROM users WHERE name = 'O''Brien'")
+            .unwrap();
+
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(1), Value::Text("O'Brien".to_string())]]
+        );
+    }
+
+    #[test]
+    fn test_select_unknown_column() {
+        let mut db = Database::new();
+
+        db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)")
+            .unwrap();
+
+        // Rejected even when there are no rows to project
+        let result = db.execute("SELECT nickname FROM users");
+        assert!(matches!(result, Err(DbError::ColumnNotFound(name)) if name == "nickname"));
+
+        db.execute("INSERT INTO users VALUES (1, 'Alice')")
+            .unwrap();
+
+        let result = db.execute("SELECT id, nickname FROM users");
+        assert!(matches!(result, Err(DbError::ColumnNotFound(name)) if name == "nickname"));
+
+        let result = db.execute("SELECT name FROM users WHERE age > 1");
+        assert!(matches!(result, Err(DbError::ColumnNotFound(name)) if name == "age"));
+
+        let result = db.execute("SELECT name FROM users").unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Text("Alice".to_string())]]);
+    }
+
+    #[test]
+    fn test_select_star_with_columns() {
+        let mut db = Database::new();
+
+        db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)")
+            .unwrap();
+        db.execute("INSERT INTO users VALUES (1, 'Alice')")
+            .unwrap();
+
+        let result = db.execute("SELECT name, * FROM users").unwrap();
+        assert_eq!(result.columns, vec!["name", "id", "name"]);
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Text("Alice".to_string()),
+                Value::Integer(1),
+                Value::Text("Alice".to_string()),
+            ]]
+        );
+    }
+
+    fn setup_employees() -> Database {
+        let mut db = Database::new();
+
+        db.execute("CREATE TABLE employees (id INTEGER PRIMARY KEY, name TEXT, dept TEXT)")
+            .unwrap();
+        db.execute(
+            "INSERT INTO employees VALUES (1, 'Alice', 'eng'), (2, 'Bob', 'sales'), \
+             (3, 'Carol', 'eng'), (4, 'Dave', 'eng'), (5, 'Erin', 'sales'), (6, 'Frank', 'ops')",
+        )
+        .unwrap();
+
+        db
+    }
+
+    #[test]
+    fn test_select_distinct() {
+        let mut db = setup_employees();
+
+        let result = db.execute("SELECT DISTINCT dept FROM employees").unwrap();
+        assert_eq!(result.columns, vec!["dept"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Text("eng".to_string())],
+                vec![Value::Text("sales".to_string())],
+                vec![Value::Text("ops".to_string())],
+            ]
+        );
+
+        // Whole rows are compared, not just the first column
+        let result = db.execute("SELECT DISTINCT id, dept FROM employees").unwrap();
+        assert_eq!(result.rows.len(), 6);
+    }
+
+    #[test]
+    fn test_group_by_count() {
+        let mut db = setup_employees();
+
+        let result = db
+            .execute("SELECT dept, COUNT(*) FROM employees GROUP BY dept")
+            .unwrap();
+        assert_eq!(result.columns, vec!["dept", "COUNT(*)"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Text("eng".to_string()), Value::Integer(3)],
+                vec![Value::Text("ops".to_string()), Value::Integer(1)],
+                vec![Value::Text("sales".to_string()), Value::Integer(2)],
+            ]
+        );
+
+        // WHERE filters before grouping
+        let result = db
+            .execute("SELECT dept, COUNT(*) FROM employees WHERE id > 2 GROUP BY dept")
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Text("eng".to_string()), Value::Integer(2)],
+                vec![Value::Text("ops".to_string()), Value::Integer(1)],
+                vec![Value::Text("sales".to_string()), Value::Integer(1)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_without_group_by() {
+        let mut db = setup_employees();
+
+        let result = db.execute("SELECT COUNT(*) FROM employees").unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Integer(6)]]);
+
+        let result = db
+            .execute("SELECT COUNT(*) FROM employees WHERE dept = 'nobody'")
+            .unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Integer(0)]]);
+    }
+
+    #[test]
+    fn test_group_by_rejects_ungrouped_columns() {
+        let mut db = setup_employees();
+
+        let result = db.execute("SELECT name, COUNT(*) FROM employees GROUP BY dept");
+        assert!(matches!(result, Err(DbError::InvalidQuery(_))));
+
+        let result = db.execute("SELECT name, COUNT(*) FROM employees");
+        assert!(matches!(result, Err(DbError::InvalidQuery(_))));
+
+        let result = db.execute("SELECT dept FROM employees GROUP BY team");
+        assert!(matches!(result, Err(DbError::ColumnNotFound(name)) if name == "team"));
+    }
+
     #[test]
     fn test_select_specific_columns() {
         let mut db = Database::new();
@@ -271,4 +851,152 @@ mod tests {
 
         assert!(matches!(result, Err(DbError::ConstraintViolation(_))));
     }
+
+    #[test]
+    fn test_explain_select() {
+        let mut db = Database::new();
+
+        db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, age INTEGER)")
+            .unwrap();
+        db.execute("INSERT INTO users VALUES (1, 'Alice', 25), (2, 'Bob', 30), (3, 'Carol', 35)")
+            .unwrap();
+
+        let plan = |db: &mut Database, sql: &str| -> Vec<String> {
+            let result = db.execute(sql).unwrap();
+            assert_eq!(result.columns, vec!["plan"]);
+            result
+                .rows
+                .into_iter()
+                .map(|row| row[0].as_text().unwrap().to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            plan(&mut db, "EXPLAIN SELECT * FROM users WHERE id = 1"),
+            vec![
+                "Access: index lookup on users using primary key",
+                "Estimated rows: 1",
+                "Condition: id = 1 (index)",
+            ]
+        );
+        assert_eq!(
+            plan(&mut db, "EXPLAIN SELECT name FROM users WHERE name = 'Bob'"),
+            vec![
+                "Access: full scan of users",
+                "Estimated rows: 3",
+                "Condition: name = 'Bob' (filter)",
+            ]
+        );
+        // Only equality can use the primary key index
+        assert_eq!(
+            plan(&mut db, "EXPLAIN SELECT * FROM users WHERE id > 1")[0],
+            "Access: full scan of users"
+        );
+        assert_eq!(
+            plan(&mut db, "EXPLAIN SELECT * FROM users")[2],
+            "Condition: none"
+        );
+
+        assert!(matches!(
+            db.execute("EXPLAIN SELECT * FROM users WHERE missing = 1"),
+            Err(DbError::ColumnNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_select_by_primary_key() {
+        let mut db = Database::new();
+
+        db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)")
+            .unwrap();
+        db.execute("INSERT INTO users VALUES (1, 'Alice'), (2, 'Bob')")
+            .unwrap();
+
+        let result = db.execute("SELECT name FROM users WHERE id = 2").unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Text("Bob".to_string())]]);
+
+        let result = db.execute("SELECT name FROM users WHERE id = 3").unwrap();
+        assert!(result.rows.is_empty());
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.csv");
+
+        let mut db = Database::new();
+        db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, age INTEGER, active BOOLEAN)")
+            .unwrap();
+        db.execute(
+            "INSERT INTO users VALUES (1, 'Smith, ''Jo''', 25, TRUE), (2, '', NULL, FALSE), (3, NULL, -4, NULL)",
+        )
+        .unwrap();
+
+        assert_eq!(db.export_csv("users", &path).unwrap(), 3);
+
+        db.execute("CREATE TABLE copy (id INTEGER PRIMARY KEY, name TEXT, age INTEGER, active BOOLEAN)")
+            .unwrap();
+        assert_eq!(db.import_csv("copy", &path).unwrap(), 3);
+
+        let original = db.execute("SELECT * FROM users").unwrap();
+        let copy = db.execute("SELECT * FROM copy").unwrap();
+        assert_eq!(copy.rows, original.rows);
+        assert_eq!(copy.rows[1][1], Value::Text(String::new()));
+        assert_eq!(copy.rows[2][1], Value::Null);
+    }
+
+    #[test]
+    fn test_import_csv_coerces_types() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.csv");
+
+        let mut db = Database::new();
+        db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, active BOOLEAN)")
+            .unwrap();
+
+        // Header columns may be in any order
+        std::fs::write(&path, "name,active,id\n\"Alice\",true,1\nBob,FALSE,2\n").unwrap();
+        assert_eq!(db.import_csv("users", &path).unwrap(), 2);
+        let result = db.execute("SELECT * FROM users WHERE id = 1").unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Integer(1),
+                Value::Text("Alice".to_string()),
+                Value::Boolean(true),
+            ]]
+        );
+
+        // A bad value rejects the whole file
+        std::fs::write(&path, "id,name,active\n3,Carol,TRUE\nfour,Dave,TRUE\n").unwrap();
+        assert!(matches!(
+            db.import_csv("users", &path),
+            Err(DbError::TypeMismatch { .. })
+        ));
+        assert_eq!(db.execute("SELECT * FROM users").unwrap().rows.len(), 2);
+
+        std::fs::write(&path, "id,name\n3,Carol\n").unwrap();
+        assert!(matches!(
+            db.import_csv("users", &path),
+            Err(DbError::ColumnNotFound(name)) if name == "active"
+        ));
+
+        std::fs::write(&path, "id,name,active\n3,Carol\n").unwrap();
+        assert!(matches!(
+            db.import_csv("users", &path),
+            Err(DbError::ParseError(_))
+        ));
+
+        // Errors name the line in the file, counting blank lines and
+        // line breaks inside quoted fields
+        std::fs::write(
+            &path,
+            "id,name,active\n\n3,\"Carol\nSmith\",TRUE\n\n4,Dave\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            db.import_csv("users", &path),
+            Err(DbError::ParseError(message)) if message.starts_with("CSV line 6:")
+        ));
+    }
 }
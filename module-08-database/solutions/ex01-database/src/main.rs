@@ -1,3 +1,4 @@
+mod csv;
 mod database;
 mod error;
 mod parser;
@@ -47,8 +48,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Inserting employees...");
     db.execute("INSERT INTO employees VALUES (1, 'Alice', 75000, TRUE)")?;
     db.execute("INSERT INTO employees VALUES (2, 'Bob', 65000, TRUE)")?;
-    db.execute("INSERT INTO employees VALUES (3, 'Charlie', 80000, FALSE)")?;
-    db.execute("INSERT INTO employees VALUES (4, 'Diana', 90000, TRUE)")?;
+    db.execute(
+        "INSERT INTO employees VALUES (3, 'Charlie', 80000, FALSE), (4, 'Diana', 90000, TRUE)",
+    )?;
 
     // Query all employees
     println!("\n--- All Employees ---");
@@ -65,6 +67,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let result = db.execute("SELECT name, salary FROM employees WHERE salary > 70000")?;
     print_results(&result);
 
+    // Show how a primary key lookup is planned
+    println!("--- EXPLAIN lookup by id ---");
+    let result = db.execute("EXPLAIN SELECT name FROM employees WHERE id = 2")?;
+    print_results(&result);
+
+    // Count employees by status
+    println!("--- Employees per status ---");
+    let result = db.execute("SELECT active, COUNT(*) FROM employees GROUP BY active")?;
+    print_results(&result);
+
     // Create another table
     println!("Creating table 'departments'...");
     db.execute("CREATE TABLE departments (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")?;
@@ -76,6 +88,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let result = db.execute("SELECT * FROM departments")?;
     print_results(&result);
 
+    // Copy a table through a CSV file
+    let csv_path = std::env::temp_dir().join("simple_db_employees.csv");
+    let exported = db.export_csv("employees", &csv_path)?;
+    println!("Exported {} employees to {}", exported, csv_path.display());
+
+    db.execute("CREATE TABLE former_employees (id INTEGER PRIMARY KEY, name TEXT NOT NULL, salary INTEGER, active BOOLEAN)")?;
+    let imported = db.import_csv("former_employees", &csv_path)?;
+    println!("Imported {} rows into 'former_employees'\n", imported);
+    std::fs::remove_file(&csv_path)?;
+
     // List all tables
     println!("Tables in database: {:?}", db.list_tables());
 
@@ -6,18 +6,30 @@ pub enum Statement {
     CreateTable {
         name: String,
         columns: Vec<ColumnDef>,
+        if_not_exists: bool,
+    },
+    DropTable {
+        name: String,
+        if_exists: bool,
     },
     Insert {
         table: String,
-        values: Vec<Value>,
+        rows: Vec<Vec<Value>>,
     },
     Select {
         table: String,
-        columns: Vec<String>, // "*" for all
+        columns: Vec<String>, // "*" for all, COUNT_STAR for a row count
+        distinct: bool,
         where_clause: Option<WhereClause>,
+        group_by: Option<String>,
     },
+    /// Describe how the inner statement would run instead of running it
+    Explain(Box<Statement>),
 }
 
+/// Column name used for a `COUNT(*)` aggregate in a select list
+pub const COUNT_STAR: &str = "COUNT(*)";
+
 #[derive(Debug, PartialEq)]
 pub struct ColumnDef {
     pub name: String,
@@ -39,6 +51,22 @@ pub enum Operator {
     NotEquals,
     GreaterThan,
     LessThan,
+    GreaterThanOrEqual,
+    LessThanOrEqual,
+}
+
+impl Operator {
+    /// The operator as written in SQL
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Equals => "=",
+            Operator::NotEquals => "!=",
+            Operator::GreaterThan => ">",
+            Operator::LessThan => "<",
+            Operator::GreaterThanOrEqual => ">=",
+            Operator::LessThanOrEqual => "<=",
+        }
+    }
 }
 
 /// Simple SQL parser (hand-written, no parser generator)
@@ -58,8 +86,10 @@ impl Parser {
 
         match first.to_uppercase().as_str() {
             "CREATE" => self.parse_create_table(),
+            "DROP" => self.parse_drop_table(),
             "INSERT" => self.parse_insert(),
             "SELECT" => self.parse_select(),
+            "EXPLAIN" => self.parse_explain(),
             _ => Err(DbError::ParseError(format!("Unknown statement: {}", first))),
         }
     }
@@ -68,6 +98,7 @@ impl Parser {
         self.expect("CREATE")?;
         self.expect("TABLE")?;
 
+        let if_not_exists = self.accept_keywords(&["IF", "NOT", "EXISTS"])?;
         let name = self.consume()?.to_string();
 
         self.expect("(")?;
@@ -123,7 +154,25 @@ impl Parser {
 
         self.expect(")")?;
 
-        Ok(Statement::CreateTable { name, columns })
+        Ok(Statement::CreateTable {
+            name,
+            columns,
+            if_not_exists,
+        })
+    }
+
+    fn parse_drop_table(&mut self) -> Result<Statement> {
+        self.expect("DROP")?;
+        self.expect("TABLE")?;
+
+        let if_exists = self.accept_keywords(&["IF", "EXISTS"])?;
+        let name = self.consume()?.to_string();
+
+        if let Some(token) = self.peek() {
+            return Err(DbError::ParseError(format!("Unexpected token: {}", token)));
+        }
+
+        Ok(Statement::DropTable { name, if_exists })
     }
 
     fn parse_insert(&mut self) -> Result<Statement> {
@@ -133,6 +182,24 @@ impl Parser {
         let table = self.consume()?.to_string();
 
         self.expect("VALUES")?;
+
+        // One or more value tuples: VALUES (...), (...)
+        let mut rows = Vec::new();
+
+        loop {
+            rows.push(self.parse_value_tuple()?);
+
+            if let Some(",") = self.peek().map(|s| s.as_str()) {
+                self.consume()?;
+            } else {
+                break;
+            }
+        }
+
+        Ok(Statement::Insert { table, rows })
+    }
+
+    fn parse_value_tuple(&mut self) -> Result<Vec<Value>> {
         self.expect("(")?;
 
         let mut values = Vec::new();
@@ -151,18 +218,44 @@ impl Parser {
 
         self.expect(")")?;
 
-        Ok(Statement::Insert { table, values })
+        Ok(values)
+    }
+
+    fn parse_explain(&mut self) -> Result<Statement> {
+        self.expect("EXPLAIN")?;
+
+        match self.peek() {
+            Some(token) if token.eq_ignore_ascii_case("SELECT") => {}
+            Some(token) => {
+                return Err(DbError::ParseError(format!(
+                    "EXPLAIN only supports SELECT, got '{}'",
+                    token
+                )))
+            }
+            None => return Err(DbError::ParseError("Unexpected end of input".to_string())),
+        }
+
+        Ok(Statement::Explain(Box::new(self.parse_select()?)))
     }
 
     fn parse_select(&mut self) -> Result<Statement> {
         self.expect("SELECT")?;
 
+        let distinct = self.accept_keywords(&["DISTINCT"])?;
+
         let mut columns = Vec::new();
 
         // Parse column list
         loop {
             let col = self.consume()?.to_string();
-            columns.push(col);
+            if col.eq_ignore_ascii_case("COUNT") {
+                self.expect("(")?;
+                self.expect("*")?;
+                self.expect(")")?;
+                columns.push(COUNT_STAR.to_string());
+            } else {
+                columns.push(col);
+            }
 
             if let Some(",") = self.peek().map(|s| s.as_str()) {
                 self.consume()?;
@@ -188,6 +281,8 @@ impl Parser {
                 "!=" | "<>" => Operator::NotEquals,
                 ">" => Operator::GreaterThan,
                 "<" => Operator::LessThan,
+                ">=" => Operator::GreaterThanOrEqual,
+                "<=" => Operator::LessThanOrEqual,
                 _ => return Err(DbError::ParseError(format!("Unknown operator: {}", op_str))),
             };
 
@@ -202,10 +297,19 @@ impl Parser {
             None
         };
 
+        // Optional GROUP BY clause
+        let group_by = if self.accept_keywords(&["GROUP", "BY"])? {
+            Some(self.consume()?.to_string())
+        } else {
+            None
+        };
+
         Ok(Statement::Select {
             table,
             columns,
+            distinct,
             where_clause,
+            group_by,
         })
     }
 
@@ -226,6 +330,21 @@ impl Parser {
         Ok(token)
     }
 
+    /// Consume `keywords` if the next token starts the sequence
+    ///
+    /// Once the first keyword matches, the rest are required.
+    fn accept_keywords(&mut self, keywords: &[&str]) -> Result<bool> {
+        match (self.peek(), keywords.first()) {
+            (Some(token), Some(first)) if token.eq_ignore_ascii_case(first) => {}
+            _ => return Ok(false),
+        }
+
+        for keyword in keywords {
+            self.expect(keyword)?;
+        }
+        Ok(true)
+    }
+
     fn expect(&mut self, expected: &str) -> Result<()> {
         let token = self.consume()?;
         if token.to_uppercase() != expected.to_uppercase() {
@@ -242,12 +361,18 @@ fn tokenize(sql: &str) -> Vec<String> {
     let mut tokens = Vec::new();
     let mut current = String::new();
     let mut in_string = false;
+    let mut chars = sql.chars().peekable();
 
-    for ch in sql.chars() {
+    while let Some(ch) = chars.next() {
         match ch {
             '\'' if !in_string => {
                 in_string = true;
             }
+            '\'' if in_string && chars.peek() == Some(&'\'') => {
+                // A doubled quote inside a string is a literal quote
+                chars.next();
+                current.push('\'');
+            }
             '\'' if in_string => {
                 in_string = false;
                 tokens.push(format!("'{}'", current));
@@ -266,6 +391,21 @@ fn tokenize(sql: &str) -> Vec<String> {
                 }
                 tokens.push(ch.to_string());
             }
+            '=' | '!' | '<' | '>' if !in_string => {
+                if !current.is_empty() {
+                    tokens.push(current.clone());
+                    current.clear();
+                }
+                // Keep two-character operators (>=, <=, !=, <>) as one token
+                let mut op = ch.to_string();
+                if let Some(&next) = chars.peek() {
+                    if next == '=' || (ch == '<' && next == '>') {
+                        op.push(next);
+                        chars.next();
+                    }
+                }
+                tokens.push(op);
+            }
             _ => {
                 current.push(ch);
             }
@@ -308,8 +448,13 @@ mod tests {
         let stmt = parser.parse().unwrap();
 
         match stmt {
-            Statement::CreateTable { name, columns } => {
+            Statement::CreateTable {
+                name,
+                columns,
+                if_not_exists,
+            } => {
                 assert_eq!(name, "users");
+                assert!(!if_not_exists);
                 assert_eq!(columns.len(), 3);
                 assert_eq!(columns[0].name, "id");
                 assert!(columns[0].primary_key);
@@ -327,8 +472,10 @@ mod tests {
         let stmt = parser.parse().unwrap();
 
         match stmt {
-            Statement::Insert { table, values } => {
+            Statement::Insert { table, rows } => {
                 assert_eq!(table, "users");
+                assert_eq!(rows.len(), 1);
+                let values = &rows[0];
                 assert_eq!(values.len(), 3);
                 assert_eq!(values[0], Value::Integer(1));
                 assert_eq!(values[1], Value::Text("Alice".to_string()));
@@ -338,6 +485,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_multi_row_insert() {
+        let sql = "INSERT INTO users VALUES (1, 'Alice', TRUE), (2, 'Bob', FALSE),(3, 'Carol', TRUE)";
+        let mut parser = Parser::new(sql);
+
+        match parser.parse().unwrap() {
+            Statement::Insert { table, rows } => {
+                assert_eq!(table, "users");
+                assert_eq!(rows.len(), 3);
+                assert_eq!(
+                    rows[1],
+                    vec![
+                        Value::Integer(2),
+                        Value::Text("Bob".to_string()),
+                        Value::Boolean(false),
+                    ]
+                );
+                assert_eq!(rows[2][1], Value::Text("Carol".to_string()));
+            }
+            _ => panic!("Wrong statement type"),
+        }
+
+        // A trailing comma needs another tuple
+        let mut parser = Parser::new("INSERT INTO users VALUES (1, 'Alice', TRUE),");
+        assert!(parser.parse().is_err());
+    }
+
     #[test]
     fn test_parse_select() {
         let sql = "SELECT * FROM users";
@@ -348,16 +522,61 @@ mod tests {
             Statement::Select {
                 table,
                 columns,
+                distinct,
                 where_clause,
+                group_by,
             } => {
                 assert_eq!(table, "users");
                 assert_eq!(columns, vec!["*"]);
+                assert!(!distinct);
                 assert!(where_clause.is_none());
+                assert!(group_by.is_none());
             }
             _ => panic!("Wrong statement type"),
         }
     }
 
+    #[test]
+    fn test_parse_select_distinct() {
+        let mut parser = Parser::new("SELECT DISTINCT dept FROM employees");
+
+        match parser.parse().unwrap() {
+            Statement::Select {
+                columns, distinct, ..
+            } => {
+                assert_eq!(columns, vec!["dept"]);
+                assert!(distinct);
+            }
+            _ => panic!("Wrong statement type"),
+        }
+    }
+
+    #[test]
+    fn test_parse_group_by() {
+        let sql = "SELECT dept, count(*) FROM employees WHERE active = TRUE GROUP BY dept";
+        let mut parser = Parser::new(sql);
+
+        match parser.parse().unwrap() {
+            Statement::Select {
+                columns,
+                where_clause,
+                group_by,
+                ..
+            } => {
+                assert_eq!(columns, vec!["dept", COUNT_STAR]);
+                assert_eq!(where_clause.unwrap().column, "active");
+                assert_eq!(group_by, Some("dept".to_string()));
+            }
+            _ => panic!("Wrong statement type"),
+        }
+
+        let mut parser = Parser::new("SELECT COUNT(id) FROM employees");
+        assert!(parser.parse().is_err());
+
+        let mut parser = Parser::new("SELECT dept FROM employees GROUP dept");
+        assert!(parser.parse().is_err());
+    }
+
     #[test]
     fn test_parse_select_with_where() {
         let sql = "SELECT name FROM users WHERE id = 1";
@@ -376,4 +595,120 @@ mod tests {
             _ => panic!("Wrong statement"),
         }
     }
+
+    #[test]
+    fn test_parse_explain() {
+        let mut parser = Parser::new("EXPLAIN SELECT name FROM users WHERE id = 1");
+        match parser.parse().unwrap() {
+            Statement::Explain(inner) => match *inner {
+                Statement::Select {
+                    table,
+                    where_clause: Some(clause),
+                    ..
+                } => {
+                    assert_eq!(table, "users");
+                    assert_eq!(clause.column, "id");
+                }
+                _ => panic!("Wrong inner statement"),
+            },
+            _ => panic!("Wrong statement"),
+        }
+
+        let mut parser = Parser::new("EXPLAIN DROP TABLE users");
+        assert!(matches!(parser.parse(), Err(DbError::ParseError(_))));
+
+        let mut parser = Parser::new("EXPLAIN");
+        assert!(parser.parse().is_err());
+    }
+
+    #[test]
+    fn test_parse_comparison_operators() {
+        let cases = [
+            ("SELECT * FROM users WHERE age >= 30", Operator::GreaterThanOrEqual),
+            ("SELECT * FROM users WHERE age <= 30", Operator::LessThanOrEqual),
+            ("SELECT * FROM users WHERE age>=30", Operator::GreaterThanOrEqual),
+            ("SELECT * FROM users WHERE age<=30", Operator::LessThanOrEqual),
+            ("SELECT * FROM users WHERE age<>30", Operator::NotEquals),
+            ("SELECT * FROM users WHERE age>30", Operator::GreaterThan),
+        ];
+
+        for (sql, expected) in cases {
+            let mut parser = Parser::new(sql);
+            match parser.parse().unwrap() {
+                Statement::Select {
+                    where_clause: Some(clause),
+                    ..
+                } => {
+                    assert_eq!(clause.column, "age", "{}", sql);
+                    assert_eq!(clause.operator, expected, "{}", sql);
+                    assert_eq!(clause.value, Value::Integer(30), "{}", sql);
+                }
+                _ => panic!("Wrong statement for {}", sql),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokenize_keeps_operators_together() {
+        assert_eq!(tokenize("a >= 1"), vec!["a", ">=", "1"]);
+        assert_eq!(tokenize("a<=1"), vec!["a", "<=", "1"]);
+        assert_eq!(tokenize("a != 'x>=y'"), vec!["a", "!=", "'x>=y'"]);
+    }
+
+    #[test]
+    fn test_tokenize_escaped_quotes() {
+        assert_eq!(
+            tokenize("VALUES ('O''Brien', 'it''s', '')"),
+            vec!["VALUES", "(", "'O'Brien'", ",", "'it's'", ",", "''", ")"]
+        );
+
+        let mut parser = Parser::new("INSERT INTO users VALUES (1, 'O''Brien')");
+        match parser.parse().unwrap() {
+            Statement::Insert { rows, .. } => {
+                assert_eq!(rows[0][1], Value::Text("O'Brien".to_string()));
+            }
+            _ => panic!("Wrong statement type"),
+        }
+    }
+
+    #[test]
+    fn test_parse_create_table_if_not_exists() {
+        let mut parser = Parser::new("CREATE TABLE IF NOT EXISTS users (id INTEGER PRIMARY KEY)");
+
+        match parser.parse().unwrap() {
+            Statement::CreateTable {
+                name,
+                if_not_exists,
+                ..
+            } => {
+                assert_eq!(name, "users");
+                assert!(if_not_exists);
+            }
+            _ => panic!("Wrong statement type"),
+        }
+    }
+
+    #[test]
+    fn test_parse_drop_table() {
+        let mut parser = Parser::new("DROP TABLE users");
+        assert_eq!(
+            parser.parse().unwrap(),
+            Statement::DropTable {
+                name: "users".to_string(),
+                if_exists: false,
+            }
+        );
+
+        let mut parser = Parser::new("drop table if exists users");
+        assert_eq!(
+            parser.parse().unwrap(),
+            Statement::DropTable {
+                name: "users".to_string(),
+                if_exists: true,
+            }
+        );
+
+        assert!(Parser::new("DROP TABLE IF users").parse().is_err());
+        assert!(Parser::new("DROP TABLE users extra").parse().is_err());
+    }
 }
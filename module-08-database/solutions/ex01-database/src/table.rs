@@ -60,6 +60,37 @@ impl Table {
         Ok(row_id)
     }
 
+    /// Insert several rows, all or nothing
+    ///
+    /// Every row is checked against the schema and primary key index
+    /// before any of them is written.
+    pub fn insert_many(&mut self, rows: Vec<Row>) -> Result<usize> {
+        let pk_index = self
+            .schema
+            .primary_key_index()
+            .ok_or_else(|| DbError::ConstraintViolation("No primary key defined".to_string()))?;
+
+        let mut batch_keys = std::collections::BTreeSet::new();
+        for row in &rows {
+            self.schema.validate_row(row)?;
+
+            let pk_value = &row.values[pk_index];
+            if self.primary_index.contains_key(pk_value) || !batch_keys.insert(pk_value) {
+                return Err(DbError::ConstraintViolation(format!(
+                    "Duplicate primary key: {:?}",
+                    pk_value
+                )));
+            }
+        }
+
+        let count = rows.len();
+        for row in rows {
+            self.insert(row)?;
+        }
+
+        Ok(count)
+    }
+
     /// Get a row by primary key
     pub fn get_by_pk(&self, pk: &Value) -> Option<&Row> {
         self.primary_index
@@ -186,6 +217,33 @@ mod tests {
         assert!(matches!(result, Err(DbError::ConstraintViolation(_))));
     }
 
+    #[test]
+    fn test_insert_many_is_all_or_nothing() {
+        let mut table = Table::new(create_test_schema());
+
+        let row = |id: i64, name: &str| {
+            Row::new(vec![
+                Value::Integer(id),
+                Value::Text(name.to_string()),
+                Value::Boolean(true),
+            ])
+        };
+
+        assert_eq!(table.insert_many(vec![row(1, "Alice"), row(2, "Bob")]).unwrap(), 2);
+
+        // Duplicate within the batch
+        let result = table.insert_many(vec![row(3, "Carol"), row(3, "Dave")]);
+        assert!(matches!(result, Err(DbError::ConstraintViolation(_))));
+
+        // Duplicate of an existing row
+        let result = table.insert_many(vec![row(4, "Erin"), row(1, "Frank")]);
+        assert!(matches!(result, Err(DbError::ConstraintViolation(_))));
+
+        assert_eq!(table.scan().len(), 2);
+        assert!(table.get_by_pk(&Value::Integer(3)).is_none());
+        assert!(table.get_by_pk(&Value::Integer(4)).is_none());
+    }
+
     #[test]
     fn test_scan() {
         let mut table = Table::new(create_test_schema());
@@ -5,6 +5,13 @@ pub enum Expr {
     String(String),
     Identifier(String),
     Array(Vec<Expr>),
+    /// `[element for variable in iterable if condition]`
+    Comprehension {
+        element: Box<Expr>,
+        variable: String,
+        iterable: Box<Expr>,
+        condition: Option<Box<Expr>>,
+    },
     Hash(Vec<(Expr, Expr)>),
     Index {
         left: Box<Expr>,
@@ -1,6 +1,9 @@
 use crate::error::{EvalError, Result};
 use crate::value::Value;
 
+/// Most elements `range` builds, so a script can't exhaust memory with one call
+pub const MAX_RANGE_LEN: i64 = 1_000_000;
+
 pub fn builtin_print(args: Vec<Value>) -> Result<Value> {
     for arg in args {
         println!("{}", arg);
@@ -73,3 +76,114 @@ pub fn builtin_push(args: Vec<Value>) -> Result<Value> {
         _ => Err(EvalError::TypeMismatch),
     }
 }
+
+pub fn builtin_range(args: Vec<Value>) -> Result<Value> {
+    let (start, end) = match args.as_slice() {
+        [Value::Integer(end)] => (0, *end),
+        [Value::Integer(start), Value::Integer(end)] => (*start, *end),
+        [_] | [_, _] => return Err(EvalError::TypeMismatch),
+        _ => return Err(EvalError::WrongArgumentCount),
+    };
+
+    // Overflow means the bounds are further apart than any limit
+    let len = end.checked_sub(start).unwrap_or(i64::MAX);
+    if len > MAX_RANGE_LEN {
+        return Err(EvalError::RangeTooLarge(len));
+    }
+
+    Ok(Value::Array((start..end).map(Value::Integer).collect()))
+}
+
+pub fn builtin_split(args: Vec<Value>) -> Result<Value> {
+    if args.len() != 2 {
+        return Err(EvalError::WrongArgumentCount);
+    }
+
+    match (&args[0], &args[1]) {
+        // An empty separator splits into characters
+        (Value::String(s), Value::String(sep)) if sep.is_empty() => Ok(Value::Array(
+            s.chars().map(|c| Value::String(c.to_string())).collect(),
+        )),
+        (Value::String(s), Value::String(sep)) => Ok(Value::Array(
+            s.split(sep.as_str())
+                .map(|part| Value::String(part.to_string()))
+                .collect(),
+        )),
+        _ => Err(EvalError::TypeMismatch),
+    }
+}
+
+pub fn builtin_join(args: Vec<Value>) -> Result<Value> {
+    if args.len() != 2 {
+        return Err(EvalError::WrongArgumentCount);
+    }
+
+    match (&args[0], &args[1]) {
+        (Value::Array(arr), Value::String(sep)) => {
+            let parts = arr
+                .iter()
+                .map(|v| match v {
+                    Value::String(s) => Ok(s.as_str()),
+                    _ => Err(EvalError::TypeMismatch),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Value::String(parts.join(sep)))
+        }
+        _ => Err(EvalError::TypeMismatch),
+    }
+}
+
+fn map_string(args: Vec<Value>, f: fn(&str) -> String) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongArgumentCount);
+    }
+
+    match &args[0] {
+        Value::String(s) => Ok(Value::String(f(s))),
+        _ => Err(EvalError::TypeMismatch),
+    }
+}
+
+pub fn builtin_to_upper(args: Vec<Value>) -> Result<Value> {
+    map_string(args, str::to_uppercase)
+}
+
+pub fn builtin_to_lower(args: Vec<Value>) -> Result<Value> {
+    map_string(args, str::to_lowercase)
+}
+
+pub fn builtin_trim(args: Vec<Value>) -> Result<Value> {
+    map_string(args, |s| s.trim().to_string())
+}
+
+pub fn builtin_str(args: Vec<Value>) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongArgumentCount);
+    }
+
+    Ok(Value::String(args[0].to_string()))
+}
+
+pub fn builtin_int(args: Vec<Value>) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongArgumentCount);
+    }
+
+    match &args[0] {
+        Value::Integer(n) => Ok(Value::Integer(*n)),
+        Value::String(s) => s
+            .trim()
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| EvalError::InvalidNumber(s.clone())),
+        _ => Err(EvalError::TypeMismatch),
+    }
+}
+
+pub fn builtin_bool(args: Vec<Value>) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongArgumentCount);
+    }
+
+    Ok(Value::Boolean(args[0].is_truthy()))
+}
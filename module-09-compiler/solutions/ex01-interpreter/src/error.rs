@@ -37,6 +37,18 @@ pub enum EvalError {
 
     #[error("Division by zero")]
     DivisionByZero,
+
+    #[error("Invalid number: {0}")]
+    InvalidNumber(String),
+
+    #[error("Stack overflow: too many nested function calls")]
+    StackOverflow,
+
+    #[error("Timeout: evaluation step budget exhausted")]
+    Timeout,
+
+    #[error("Range too large: {0} elements")]
+    RangeTooLarge(i64),
 }
 
 pub type Result<T> = std::result::Result<T, EvalError>;
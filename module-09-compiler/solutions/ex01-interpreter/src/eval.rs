@@ -3,10 +3,59 @@ use crate::builtins;
 use crate::env::Environment;
 use crate::error::{EvalError, Result};
 use crate::value::Value;
+use std::cell::Cell;
 use std::collections::HashMap;
 
+/// Default limit on nested function calls
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+/// Native stack for evaluating up to `DEFAULT_MAX_DEPTH` nested calls
+///
+/// Each interpreted call takes tens of KB of Rust stack in debug builds, far
+/// more than a default thread has, so `eval_program` always runs on a thread
+/// with this much stack.
+pub const EVAL_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+thread_local! {
+    /// Whether this thread was started by `with_eval_stack`
+    static ON_EVAL_STACK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` on a thread with `EVAL_STACK_SIZE` of stack
+///
+/// Calls nested inside `f`, including `eval_program`, run in place, so a
+/// caller evaluating many programs can start the thread once up front.
+pub fn with_eval_stack<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R + Send,
+    R: Send,
+{
+    if ON_EVAL_STACK.with(Cell::get) {
+        return f();
+    }
+
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .name("evaluator".to_string())
+            .stack_size(EVAL_STACK_SIZE)
+            .spawn_scoped(scope, || {
+                ON_EVAL_STACK.with(|on_stack| on_stack.set(true));
+                f()
+            })
+            .expect("failed to spawn evaluator thread")
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
 pub struct Evaluator {
     env: Environment,
+    /// Number of function calls currently being evaluated
+    depth: usize,
+    max_depth: usize,
+    /// Statements and expressions evaluated by the current `eval_program`
+    steps: u64,
+    max_steps: Option<u64>,
 }
 
 impl Evaluator {
@@ -20,12 +69,55 @@ impl Evaluator {
         env.set("last".to_string(), Value::Builtin(builtins::builtin_last));
         env.set("rest".to_string(), Value::Builtin(builtins::builtin_rest));
         env.set("push".to_string(), Value::Builtin(builtins::builtin_push));
+        env.set("range".to_string(), Value::Builtin(builtins::builtin_range));
+        env.set("split".to_string(), Value::Builtin(builtins::builtin_split));
+        env.set("join".to_string(), Value::Builtin(builtins::builtin_join));
+        env.set("to_upper".to_string(), Value::Builtin(builtins::builtin_to_upper));
+        env.set("to_lower".to_string(), Value::Builtin(builtins::builtin_to_lower));
+        env.set("trim".to_string(), Value::Builtin(builtins::builtin_trim));
+        env.set("str".to_string(), Value::Builtin(builtins::builtin_str));
+        env.set("int".to_string(), Value::Builtin(builtins::builtin_int));
+        env.set("bool".to_string(), Value::Builtin(builtins::builtin_bool));
+
+        Evaluator {
+            env,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            steps: 0,
+            max_steps: None,
+        }
+    }
+
+    /// Fail with `Timeout` once a program evaluates more than `max_steps`
+    /// statements and expressions
+    ///
+    /// Unlimited by default. The budget applies to each `eval_program` call.
+    pub fn with_max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
 
-        Evaluator { env }
+    /// Fail with `StackOverflow` once calls nest deeper than `max_depth`
+    ///
+    /// `EVAL_STACK_SIZE` leaves room well beyond the default, but a limit
+    /// in the tens of thousands can exhaust the native stack first.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
     }
 
+    /// Evaluate a program, returning the value of its last statement
+    ///
+    /// Runs via `with_eval_stack`, so deep recursion ends in `StackOverflow`
+    /// whatever thread calls this. Called from a thread `with_eval_stack`
+    /// already started, it evaluates in place.
     pub fn eval_program(&mut self, program: Vec<Stmt>) -> Result<Value> {
+        with_eval_stack(|| self.run_program(program))
+    }
+
+    fn run_program(&mut self, program: Vec<Stmt>) -> Result<Value> {
         let mut result = Value::Null;
+        self.steps = 0;
 
         for stmt in program {
             result = self.eval_statement(stmt)?;
@@ -39,7 +131,32 @@ impl Evaluator {
         Ok(result)
     }
 
+    /// User-defined bindings in the global scope, sorted by name
+    pub fn bindings(&self) -> Vec<(String, Value)> {
+        let mut bindings: Vec<_> = self
+            .env
+            .store
+            .iter()
+            .filter(|(_, value)| !matches!(value, Value::Builtin(_)))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    /// Count one evaluation step against the budget
+    fn tick(&mut self) -> Result<()> {
+        self.steps += 1;
+
+        match self.max_steps {
+            Some(max_steps) if self.steps > max_steps => Err(EvalError::Timeout),
+            _ => Ok(()),
+        }
+    }
+
     fn eval_statement(&mut self, stmt: Stmt) -> Result<Value> {
+        self.tick()?;
+
         match stmt {
             Stmt::Let { name, value } => {
                 let val = self.eval_expression(value)?;
@@ -77,6 +194,8 @@ impl Evaluator {
     }
 
     fn eval_expression(&mut self, expr: Expr) -> Result<Value> {
+        self.tick()?;
+
         match expr {
             Expr::Integer(n) => Ok(Value::Integer(n)),
             Expr::Boolean(b) => Ok(Value::Boolean(b)),
@@ -92,6 +211,24 @@ impl Evaluator {
                     .collect();
                 Ok(Value::Array(values?))
             }
+            Expr::Comprehension {
+                element,
+                variable,
+                iterable,
+                condition,
+            } => {
+                let items = match self.eval_expression(*iterable)? {
+                    Value::Array(items) => items,
+                    _ => return Err(EvalError::TypeMismatch),
+                };
+
+                // The loop variable lives in a scope of its own, like a parameter
+                let scope = Environment::with_outer(self.env.clone());
+                let outer = std::mem::replace(&mut self.env, scope);
+                let result = self.eval_comprehension(*element, &variable, items, condition);
+                self.env = outer;
+                result
+            }
             Expr::Hash(pairs) => {
                 let mut map = HashMap::new();
                 for (key_expr, value_expr) in pairs {
@@ -154,6 +291,31 @@ impl Evaluator {
         }
     }
 
+    fn eval_comprehension(
+        &mut self,
+        element: Expr,
+        variable: &str,
+        items: Vec<Value>,
+        condition: Option<Box<Expr>>,
+    ) -> Result<Value> {
+        let mut values = Vec::new();
+
+        for item in items {
+            self.env.set(variable.to_string(), item);
+
+            if let Some(condition) = &condition {
+                let keep = self.eval_expression((**condition).clone())?;
+                if !self.is_truthy(&keep) {
+                    continue;
+                }
+            }
+
+            values.push(self.eval_expression(element.clone())?);
+        }
+
+        Ok(Value::Array(values))
+    }
+
     fn eval_prefix_expression(&self, operator: PrefixOp, right: Value) -> Result<Value> {
         match operator {
             PrefixOp::Bang => Ok(Value::Boolean(!self.is_truthy(&right))),
@@ -235,6 +397,9 @@ impl Evaluator {
                 if parameters.len() != args.len() {
                     return Err(EvalError::WrongArgumentCount);
                 }
+                if self.depth >= self.max_depth {
+                    return Err(EvalError::StackOverflow);
+                }
 
                 // Create environment chain: params -> closure -> calling env
                 // This allows recursive functions to find themselves in the calling environment
@@ -253,7 +418,9 @@ impl Evaluator {
 
                 // Evaluate function body with new environment
                 let prev_env = std::mem::replace(&mut self.env, extended_env);
+                self.depth += 1;
                 let result = self.eval_block_statement(body);
+                self.depth -= 1;
                 self.env = prev_env;
 
                 // Unwrap return value
@@ -268,11 +435,7 @@ impl Evaluator {
     }
 
     fn is_truthy(&self, value: &Value) -> bool {
-        match value {
-            Value::Null => false,
-            Value::Boolean(false) => false,
-            _ => true,
-        }
+        value.is_truthy()
     }
 
     fn eval_block_statement(&mut self, stmts: Vec<Stmt>) -> Result<Value> {
@@ -377,6 +540,116 @@ mod tests {
         assert_eq!(eval(input).unwrap(), Value::Integer(55));
     }
 
+    fn eval_with_max_depth(input: &str, max_depth: usize) -> Result<Value> {
+        let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+        let mut evaluator = Evaluator::new().with_max_depth(max_depth);
+        evaluator.eval_program(program)
+    }
+
+    #[test]
+    fn test_infinite_recursion_is_an_error() {
+        let result = eval_with_max_depth("let f = fn() { f() }; f()", 100);
+        assert_eq!(result, Err(EvalError::StackOverflow));
+    }
+
+    #[test]
+    fn test_default_limit_on_plain_thread() {
+        // A thread with the default 2MB stack, far too small for 1000 calls
+        let result = std::thread::spawn(|| eval("let f = fn() { f() }; f()"))
+            .join()
+            .unwrap();
+        assert_eq!(result, Err(EvalError::StackOverflow));
+    }
+
+    #[test]
+    fn test_recursion_up_to_max_depth() {
+        let count_down = "
+            let down = fn(n) {
+                if (n == 0) { 0 } else { 1 + down(n - 1) }
+            };
+        ";
+
+        // down(99) nests exactly 100 calls
+        let within = format!("{} down(99)", count_down);
+        let result = eval_with_max_depth(&within, 100);
+        assert_eq!(result, Ok(Value::Integer(99)));
+
+        let beyond = format!("{} down(100)", count_down);
+        let result = eval_with_max_depth(&beyond, 100);
+        assert_eq!(result, Err(EvalError::StackOverflow));
+    }
+
+    #[test]
+    fn test_depth_resets_after_overflow() {
+        let program = |input: &str| Parser::new(Lexer::new(input)).parse_program().unwrap();
+        let mut evaluator = Evaluator::new().with_max_depth(10);
+
+        let result = evaluator.eval_program(program("let f = fn() { f() }; f()"));
+        assert_eq!(result, Err(EvalError::StackOverflow));
+
+        let result = evaluator.eval_program(program(
+            "let g = fn(n) { if (n == 0) { 0 } else { g(n - 1) } }; g(9)",
+        ));
+        assert_eq!(result, Ok(Value::Integer(0)));
+    }
+
+    fn eval_with_max_steps(input: &str, max_steps: u64) -> Result<Value> {
+        let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+        let mut evaluator = Evaluator::new().with_max_steps(max_steps);
+        evaluator.eval_program(program)
+    }
+
+    #[test]
+    fn test_infinite_loop_times_out() {
+        assert_eq!(
+            eval_with_max_steps("while (true) { }", 10_000),
+            Err(EvalError::Timeout)
+        );
+        assert_eq!(
+            eval_with_max_steps("let i = 0; while (true) { i = i + 1; }", 10_000),
+            Err(EvalError::Timeout)
+        );
+    }
+
+    #[test]
+    fn test_step_budget() {
+        let input = "
+            let i = 0;
+            while (i < 10) { i = i + 1; }
+            i
+        ";
+        assert_eq!(eval_with_max_steps(input, 1_000), Ok(Value::Integer(10)));
+        assert_eq!(eval_with_max_steps(input, 50), Err(EvalError::Timeout));
+
+        // Each program gets the whole budget
+        let mut evaluator = Evaluator::new().with_max_steps(1_000);
+        for _ in 0..3 {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            assert_eq!(evaluator.eval_program(program), Ok(Value::Integer(10)));
+        }
+    }
+
+    #[test]
+    fn test_comprehension() {
+        fn ints(values: &[i64]) -> Value {
+            Value::Array(values.iter().copied().map(Value::Integer).collect())
+        }
+
+        assert_eq!(eval("[x * x for x in range(5)]").unwrap(), ints(&[0, 1, 4, 9, 16]));
+        assert_eq!(
+            eval("[x for x in range(10) if x > 6]").unwrap(),
+            ints(&[7, 8, 9])
+        );
+        assert_eq!(eval("[x for x in []]").unwrap(), ints(&[]));
+
+        // The loop variable doesn't leak, and shadows an outer binding inside
+        assert_eq!(
+            eval("let x = 100; let ys = [x + 1 for x in [1, 2]]; x + len(ys)").unwrap(),
+            Value::Integer(102)
+        );
+        assert_eq!(eval("[x for x in 5]"), Err(EvalError::TypeMismatch));
+    }
+
     #[test]
     fn test_array() {
         assert_eq!(
@@ -398,6 +671,102 @@ mod tests {
         assert_eq!(eval(input).unwrap(), Value::String("Alice".to_string()));
     }
 
+    #[test]
+    fn test_range() {
+        let ints = |ns: &[i64]| Value::Array(ns.iter().copied().map(Value::Integer).collect());
+
+        assert_eq!(eval("range(5)").unwrap(), ints(&[0, 1, 2, 3, 4]));
+        assert_eq!(eval("range(2, 5)").unwrap(), ints(&[2, 3, 4]));
+        assert_eq!(eval("range(5, 2)").unwrap(), ints(&[]));
+        assert_eq!(eval("len(range(0, 10))").unwrap(), Value::Integer(10));
+
+        assert_eq!(eval("range(\"5\")"), Err(EvalError::TypeMismatch));
+        assert_eq!(eval("range(1, true)"), Err(EvalError::TypeMismatch));
+        assert_eq!(eval("range()"), Err(EvalError::WrongArgumentCount));
+
+        assert_eq!(
+            eval("len(range(1000000))").unwrap(),
+            Value::Integer(builtins::MAX_RANGE_LEN)
+        );
+        assert_eq!(
+            eval_with_max_steps("range(100000000000000)", 1_000),
+            Err(EvalError::RangeTooLarge(100_000_000_000_000))
+        );
+        assert_eq!(
+            eval("range(-9223372036854775807, 9223372036854775807)"),
+            Err(EvalError::RangeTooLarge(i64::MAX))
+        );
+    }
+
+    #[test]
+    fn test_string_builtins() {
+        let strs = |ss: &[&str]| {
+            Value::Array(ss.iter().map(|s| Value::String(s.to_string())).collect())
+        };
+
+        assert_eq!(eval(r#"split("a,b,c", ",")"#).unwrap(), strs(&["a", "b", "c"]));
+        assert_eq!(eval(r#"split("abc", "")"#).unwrap(), strs(&["a", "b", "c"]));
+        assert_eq!(
+            eval(r#"join(["a", "b"], "-")"#).unwrap(),
+            Value::String("a-b".to_string())
+        );
+        assert_eq!(
+            eval(r#"join(split("x y z", " "), "+")"#).unwrap(),
+            Value::String("x+y+z".to_string())
+        );
+        assert_eq!(eval(r#"join(["a", 1], "-")"#), Err(EvalError::TypeMismatch));
+
+        assert_eq!(
+            eval(r#"to_upper("Hello")"#).unwrap(),
+            Value::String("HELLO".to_string())
+        );
+        assert_eq!(
+            eval(r#"to_lower("Hello")"#).unwrap(),
+            Value::String("hello".to_string())
+        );
+        assert_eq!(
+            eval(r#"trim("  padded  ")"#).unwrap(),
+            Value::String("padded".to_string())
+        );
+        assert_eq!(eval("to_upper(5)"), Err(EvalError::TypeMismatch));
+    }
+
+    #[test]
+    fn test_conversion_builtins() {
+        assert_eq!(eval("str(42)").unwrap(), Value::String("42".to_string()));
+        assert_eq!(eval("str(42) == \"42\"").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("str([1, true])").unwrap(), Value::String("[1, true]".to_string()));
+
+        assert_eq!(eval("int(\"7\") + 1").unwrap(), Value::Integer(8));
+        assert_eq!(eval("int(\" -12 \")").unwrap(), Value::Integer(-12));
+        assert_eq!(eval("int(5)").unwrap(), Value::Integer(5));
+        assert_eq!(
+            eval("int(\"nope\")"),
+            Err(EvalError::InvalidNumber("nope".to_string()))
+        );
+        assert_eq!(eval("int(true)"), Err(EvalError::TypeMismatch));
+
+        assert_eq!(eval("bool(0)").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("bool(\"\")").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("bool(false)").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("bool(if (false) { 1 })").unwrap(), Value::Boolean(false));
+    }
+
+    #[test]
+    fn test_bindings() {
+        let mut evaluator = Evaluator::new();
+        let program = Parser::new(Lexer::new("let b = 2; let a = 1;")).parse_program().unwrap();
+        evaluator.eval_program(program).unwrap();
+
+        assert_eq!(
+            evaluator.bindings(),
+            vec![
+                ("a".to_string(), Value::Integer(1)),
+                ("b".to_string(), Value::Integer(2)),
+            ]
+        );
+    }
+
     #[test]
     fn test_while_loop() {
         let input = "
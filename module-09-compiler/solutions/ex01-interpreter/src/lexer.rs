@@ -201,6 +201,8 @@ impl Lexer {
             "else" => Token::Else,
             "return" => Token::Return,
             "while" => Token::While,
+            "for" => Token::For,
+            "in" => Token::In,
             "true" => Token::True,
             "false" => Token::False,
             _ => Token::Ident(ident),
@@ -282,6 +284,17 @@ mod tests {
         assert_eq!(lexer.next_token(), Token::False);
     }
 
+    #[test]
+    fn test_comprehension_keywords() {
+        let input = "for x in items";
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(lexer.next_token(), Token::For);
+        assert_eq!(lexer.next_token(), Token::Ident("x".to_string()));
+        assert_eq!(lexer.next_token(), Token::In);
+        assert_eq!(lexer.next_token(), Token::Ident("items".to_string()));
+    }
+
     #[test]
     fn test_string_literal() {
         let input = r#""hello world""#;
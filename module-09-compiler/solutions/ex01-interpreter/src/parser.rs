@@ -127,24 +127,9 @@ impl Parser {
 
                 return Ok(Stmt::Assign { name, value });
             } else {
-                // Not an assignment, backtrack and parse as expression
-                // Put the identifier back and parse normally
-                let expr = if self.current_token == Token::LParen {
-                    // Function call
-                    let func = Expr::Identifier(name);
-                    self.parse_infix(func)?
-                } else if self.current_token == Token::LBracket {
-                    // Array/hash index
-                    let left = Expr::Identifier(name);
-                    self.parse_infix(left)?
-                } else if self.is_infix_token(&self.current_token) {
-                    // Infix expression
-                    let left = Expr::Identifier(name);
-                    self.parse_infix(left)?
-                } else {
-                    // Just an identifier
-                    Expr::Identifier(name)
-                };
+                // Not an assignment: carry on parsing the expression that
+                // starts with this identifier
+                let expr = self.parse_expression_from(Expr::Identifier(name), Precedence::Lowest)?;
 
                 if self.current_token == Token::Semicolon {
                     self.advance();
@@ -164,29 +149,13 @@ impl Parser {
         Ok(Stmt::Expression(expr))
     }
 
-    fn is_infix_token(&self, token: &Token) -> bool {
-        matches!(
-            token,
-            Token::Plus
-                | Token::Minus
-                | Token::Star
-                | Token::Slash
-                | Token::Eq
-                | Token::NotEq
-                | Token::Lt
-                | Token::Gt
-                | Token::LtEq
-                | Token::GtEq
-                | Token::And
-                | Token::Or
-                | Token::LParen
-                | Token::LBracket
-        )
-    }
-
     fn parse_expression(&mut self, precedence: Precedence) -> ParseResult<Expr> {
-        let mut left = self.parse_prefix()?;
+        let left = self.parse_prefix()?;
+        self.parse_expression_from(left, precedence)
+    }
 
+    /// Apply infix operators, calls and indexing to an already parsed `left`
+    fn parse_expression_from(&mut self, mut left: Expr, precedence: Precedence) -> ParseResult<Expr> {
         while self.current_token != Token::Semicolon
             && self.current_token != Token::Eof
             && precedence < self.current_precedence()
@@ -262,11 +231,58 @@ impl Parser {
     fn parse_array_literal(&mut self) -> ParseResult<Expr> {
         self.expect_token(Token::LBracket)?;
 
-        let elements = self.parse_expression_list(Token::RBracket)?;
+        if self.current_token == Token::RBracket {
+            self.advance();
+            return Ok(Expr::Array(Vec::new()));
+        }
+
+        let first = self.parse_expression(Precedence::Lowest)?;
+        if self.current_token == Token::For {
+            return self.parse_comprehension(first);
+        }
+
+        let mut elements = vec![first];
+        while self.current_token == Token::Comma {
+            self.advance();
+            elements.push(self.parse_expression(Precedence::Lowest)?);
+        }
+
+        self.expect_token(Token::RBracket)?;
 
         Ok(Expr::Array(elements))
     }
 
+    /// The rest of `[element for variable in iterable if condition]` once
+    /// the element has been parsed; the `if` part is optional
+    fn parse_comprehension(&mut self, element: Expr) -> ParseResult<Expr> {
+        self.expect_token(Token::For)?;
+
+        let variable = match &self.current_token {
+            Token::Ident(name) => name.clone(),
+            _ => return Err(ParseError::ExpectedIdentifier),
+        };
+        self.advance();
+
+        self.expect_token(Token::In)?;
+        let iterable = self.parse_expression(Precedence::Lowest)?;
+
+        let condition = if self.current_token == Token::If {
+            self.advance();
+            Some(Box::new(self.parse_expression(Precedence::Lowest)?))
+        } else {
+            None
+        };
+
+        self.expect_token(Token::RBracket)?;
+
+        Ok(Expr::Comprehension {
+            element: Box::new(element),
+            variable,
+            iterable: Box::new(iterable),
+            condition,
+        })
+    }
+
     fn parse_hash_literal(&mut self) -> ParseResult<Expr> {
         self.expect_token(Token::LBrace)?;
 
@@ -553,4 +569,47 @@ mod tests {
             _ => panic!("Expected function"),
         }
     }
+
+    #[test]
+    fn test_parse_comprehension() {
+        let input = "[x * 2 for x in xs if x > 1]";
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+
+        match &program[0] {
+            Stmt::Expression(Expr::Comprehension {
+                element,
+                variable,
+                iterable,
+                condition,
+            }) => {
+                assert!(matches!(**element, Expr::Infix { operator: InfixOp::Multiply, .. }));
+                assert_eq!(variable, "x");
+                assert_eq!(**iterable, Expr::Identifier("xs".to_string()));
+                assert!(condition.is_some());
+            }
+            other => panic!("Expected comprehension, got {:?}", other),
+        }
+
+        let mut parser = Parser::new(Lexer::new("[x for 1 in xs]"));
+        assert_eq!(parser.parse_program(), Err(ParseError::ExpectedIdentifier));
+    }
+
+    #[test]
+    fn test_parse_statement_starting_with_call() {
+        let input = "len(x) + 1 == y";
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+
+        assert_eq!(program.len(), 1);
+        match &program[0] {
+            Stmt::Expression(Expr::Infix { left, operator, .. }) => {
+                assert_eq!(operator, &InfixOp::Equal);
+                assert!(matches!(**left, Expr::Infix { operator: InfixOp::Plus, .. }));
+            }
+            other => panic!("Expected comparison, got {:?}", other),
+        }
+    }
 }
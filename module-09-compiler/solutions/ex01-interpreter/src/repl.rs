@@ -1,30 +1,85 @@
-use crate::eval::Evaluator;
+use crate::eval::{with_eval_stack, Evaluator};
 use crate::lexer::Lexer;
 use crate::parser::Parser;
 use crate::value::Value;
-use std::io::{self, Write};
+use crossterm::cursor::MoveToColumn;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::queue;
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType};
+use std::io::{self, IsTerminal, Write};
+
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
 
 pub fn run_repl() {
+    // One evaluator thread for the whole session rather than one per line
+    with_eval_stack(repl_loop)
+}
+
+fn repl_loop() {
     let mut evaluator = Evaluator::new();
+    let mut history = History::new();
+    let mut pending = PendingInput::new();
+    let interactive = io::stdin().is_terminal();
 
     println!("Welcome to the Monkey REPL!");
-    println!("Type 'exit' to quit");
+    println!("Type 'exit' to quit, ':env' to list bindings, ':history' to show history,");
+    println!("or ':reset' to start over with a fresh environment");
     println!();
 
     loop {
-        print!(">> ");
-        io::stdout().flush().unwrap();
+        let prompt = if pending.is_empty() { PROMPT } else { CONTINUATION_PROMPT };
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
+        let line = match read_line(prompt, &mut history, interactive) {
+            Ok(Line::Input(line)) => line,
+            Ok(Line::Interrupted) => {
+                pending.clear();
+                continue;
+            }
+            Ok(Line::Eof) => break,
+            Err(e) => {
+                eprintln!("Error reading input: {}", e);
+                break;
+            }
+        };
 
-        let input = input.trim();
-        if input == "exit" || input == "quit" {
-            break;
+        if !line.trim().is_empty() {
+            history.push(line.clone());
         }
 
-        if input.is_empty() {
-            continue;
+        // Keep reading while brackets are open
+        let input = match pending.push_line(&line) {
+            Some(input) => input,
+            None => continue,
+        };
+        let input = input.trim();
+
+        match input {
+            "" => continue,
+            "exit" | "quit" => break,
+            ":reset" => {
+                evaluator = Evaluator::new();
+                println!("Environment reset");
+                continue;
+            }
+            ":env" => {
+                for (name, value) in evaluator.bindings() {
+                    println!("{} = {}", name, value);
+                }
+                continue;
+            }
+            ":history" => {
+                for (i, entry) in history.entries().iter().enumerate() {
+                    println!("{:>4}  {}", i + 1, entry);
+                }
+                continue;
+            }
+            command if command.starts_with(':') => {
+                println!("Unknown command: {}", command);
+                continue;
+            }
+            _ => {}
         }
 
         let lexer = Lexer::new(input);
@@ -45,3 +100,292 @@ pub fn run_repl() {
 
     println!("Goodbye!");
 }
+
+/// Source lines collected until their brackets balance
+#[derive(Debug, Default)]
+pub struct PendingInput {
+    source: String,
+}
+
+impl PendingInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a line, returning the whole input once it's complete
+    pub fn push_line(&mut self, line: &str) -> Option<String> {
+        self.source.push_str(line);
+        self.source.push('\n');
+
+        if needs_more_input(&self.source) {
+            None
+        } else {
+            Some(std::mem::take(&mut self.source))
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.source.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.source.clear();
+    }
+}
+
+/// Whether `source` has unclosed brackets or an unterminated string
+///
+/// Stray closing brackets count as complete so the parser can report them.
+pub fn needs_more_input(source: &str) -> bool {
+    let mut depth = 0i32;
+    let mut chars = source.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if !chars.any(|c| c == '"') => return true,
+            '/' if chars.peek() == Some(&'/') => {
+                chars.find(|&c| c == '\n');
+            }
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth > 0
+}
+
+/// Lines entered this session, recalled with the up and down keys
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<String>,
+    /// Entry currently shown while navigating, `None` when editing a new line
+    position: Option<usize>,
+    /// What was typed before navigation started, restored past the newest entry
+    draft: String,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a line, skipping immediate repeats, and stop navigating
+    pub fn push(&mut self, line: String) {
+        if self.entries.last() != Some(&line) {
+            self.entries.push(line);
+        }
+        self.reset_navigation();
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Step back to an older entry; `current` is saved as the draft on the first step
+    pub fn older(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+            Some(0) => return None,
+            Some(position) => position - 1,
+        };
+
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// Step forward to a newer entry, ending at the saved draft
+    pub fn newer(&mut self) -> Option<&str> {
+        let position = self.position?;
+
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            Some(&self.entries[position + 1])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+
+    pub fn reset_navigation(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+}
+
+enum Line {
+    Input(String),
+    /// Ctrl+C: discard the current input
+    Interrupted,
+    /// Ctrl+D or end of piped input
+    Eof,
+}
+
+fn read_line(prompt: &str, history: &mut History, interactive: bool) -> io::Result<Line> {
+    if !interactive {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        return match io::stdin().read_line(&mut input)? {
+            0 => Ok(Line::Eof),
+            _ => Ok(Line::Input(input.trim_end_matches(['\r', '\n']).to_string())),
+        };
+    }
+
+    terminal::enable_raw_mode()?;
+    let result = edit_line(prompt, history);
+    terminal::disable_raw_mode()?;
+    result
+}
+
+/// Minimal line editor: cursor movement, backspace/delete and history recall
+fn edit_line(prompt: &str, history: &mut History) -> io::Result<Line> {
+    let mut stdout = io::stdout();
+    let mut buffer: Vec<char> = Vec::new();
+    let mut cursor = 0;
+
+    history.reset_navigation();
+    redraw(&mut stdout, prompt, &buffer, cursor)?;
+
+    loop {
+        let Event::Key(KeyEvent { code, modifiers, kind, .. }) = event::read()? else {
+            continue;
+        };
+        if kind == KeyEventKind::Release {
+            continue;
+        }
+
+        match code {
+            KeyCode::Enter => {
+                queue!(stdout, Print("\r\n"))?;
+                stdout.flush()?;
+                return Ok(Line::Input(buffer.into_iter().collect()));
+            }
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                queue!(stdout, Print("^C\r\n"))?;
+                stdout.flush()?;
+                return Ok(Line::Interrupted);
+            }
+            KeyCode::Char('d') if modifiers.contains(KeyModifiers::CONTROL) => {
+                if buffer.is_empty() {
+                    queue!(stdout, Print("\r\n"))?;
+                    stdout.flush()?;
+                    return Ok(Line::Eof);
+                }
+            }
+            KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                buffer.insert(cursor, c);
+                cursor += 1;
+            }
+            KeyCode::Backspace if cursor > 0 => {
+                cursor -= 1;
+                buffer.remove(cursor);
+            }
+            KeyCode::Delete if cursor < buffer.len() => {
+                buffer.remove(cursor);
+            }
+            KeyCode::Left => cursor = cursor.saturating_sub(1),
+            KeyCode::Right => cursor = (cursor + 1).min(buffer.len()),
+            KeyCode::Home => cursor = 0,
+            KeyCode::End => cursor = buffer.len(),
+            KeyCode::Up => {
+                let current: String = buffer.iter().collect();
+                if let Some(entry) = history.older(&current) {
+                    buffer = entry.chars().collect();
+                    cursor = buffer.len();
+                }
+            }
+            KeyCode::Down => {
+                if let Some(entry) = history.newer() {
+                    buffer = entry.chars().collect();
+                    cursor = buffer.len();
+                }
+            }
+            _ => continue,
+        }
+
+        redraw(&mut stdout, prompt, &buffer, cursor)?;
+    }
+}
+
+fn redraw(stdout: &mut io::Stdout, prompt: &str, buffer: &[char], cursor: usize) -> io::Result<()> {
+    let line: String = buffer.iter().collect();
+    let column = prompt.chars().count() + cursor;
+
+    queue!(
+        stdout,
+        MoveToColumn(0),
+        Clear(ClearType::CurrentLine),
+        Print(prompt),
+        Print(line),
+        MoveToColumn(column as u16)
+    )?;
+    stdout.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multi_line_function() {
+        let mut pending = PendingInput::new();
+        let lines = [
+            "let max = fn(a, b) {",
+            "    if (a > b) {",
+            "        a",
+            "    } else {",
+            "        b // pick the larger {",
+            "    }",
+            "};",
+        ];
+
+        for line in &lines[..lines.len() - 1] {
+            assert_eq!(pending.push_line(line), None, "completed early at {:?}", line);
+            assert!(!pending.is_empty());
+        }
+        let source = pending.push_line(lines[lines.len() - 1]).unwrap();
+        assert!(pending.is_empty());
+
+        let mut evaluator = Evaluator::new();
+        let program = Parser::new(Lexer::new(&source)).parse_program().unwrap();
+        evaluator.eval_program(program).unwrap();
+
+        let program = Parser::new(Lexer::new("max(3, 7)")).parse_program().unwrap();
+        assert_eq!(evaluator.eval_program(program).unwrap(), Value::Integer(7));
+    }
+
+    #[test]
+    fn test_needs_more_input() {
+        assert!(!needs_more_input("let x = 5;"));
+        assert!(needs_more_input("push([1, 2,"));
+        assert!(needs_more_input("let s = \"open {"));
+        assert!(!needs_more_input("let s = \"}\";"));
+        assert!(!needs_more_input("let s = \"{\";"));
+        // Stray closers are the parser's problem
+        assert!(!needs_more_input("}"));
+    }
+
+    #[test]
+    fn test_history_navigation() {
+        let mut history = History::new();
+        assert_eq!(history.older("typed"), None);
+
+        history.push("let a = 1;".to_string());
+        history.push("let b = 2;".to_string());
+        history.push("let b = 2;".to_string());
+        assert_eq!(history.entries().len(), 2);
+
+        assert_eq!(history.older("typed"), Some("let b = 2;"));
+        assert_eq!(history.older("ignored"), Some("let a = 1;"));
+        assert_eq!(history.older("ignored"), None);
+        assert_eq!(history.newer(), Some("let b = 2;"));
+        assert_eq!(history.newer(), Some("typed"));
+        assert_eq!(history.newer(), None);
+    }
+}
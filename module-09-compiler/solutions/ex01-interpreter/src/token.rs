@@ -16,6 +16,8 @@ pub enum Token {
     Else,
     Return,
     While,
+    For,
+    In,
 
     // Operators
     Assign,
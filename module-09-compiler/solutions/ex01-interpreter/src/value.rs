@@ -45,6 +45,11 @@ impl PartialEq for Value {
 }
 
 impl Value {
+    /// Only `null` and `false` are falsy
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Null | Value::Boolean(false))
+    }
+
     pub fn to_hash_key(&self) -> Result<HashKey> {
         match self {
             Value::Integer(n) => Ok(HashKey::Integer(*n)),
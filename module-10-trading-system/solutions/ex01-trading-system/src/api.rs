@@ -1,17 +1,25 @@
-use crate::engine::SharedEngine;
+use crate::engine::{SharedEngine, MAX_RECENT_TRADES};
 use crate::types::*;
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     routing::{delete, get, post},
     Json, Router,
 };
 use rust_decimal_macros::dec;
+use std::future::Future;
 use std::sync::Arc;
+use tokio::sync::broadcast;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
 
+/// Trades returned by the trades endpoint when no limit is given
+const DEFAULT_TRADES_LIMIT: usize = 50;
+
 pub struct ApiServer {
     engine: SharedEngine,
 }
@@ -28,20 +36,80 @@ impl ApiServer {
             .route("/api/v1/orders", post(place_order))
             .route("/api/v1/orders/:symbol/:id", delete(cancel_order))
             .route("/api/v1/market-data/:symbol", get(get_market_data))
+            .route("/api/v1/trades/:symbol", get(get_recent_trades))
+            .route("/ws/marketdata/:symbol", get(market_data_stream))
+            .route("/metrics", get(metrics))
             .route("/health", get(health_check))
             .layer(CorsLayer::permissive())
             .layer(TraceLayer::new_for_http())
             .with_state(state)
     }
 
+    /// Serve until Ctrl+C (or SIGTERM on Unix)
     pub async fn serve(self, addr: &str) -> Result<(), Box<dyn std::error::Error>> {
         let listener = tokio::net::TcpListener::bind(addr).await?;
         tracing::info!("API server listening on {}", addr);
-        axum::serve(listener, self.router()).await?;
+        self.serve_with_shutdown(listener, shutdown_signal()).await
+    }
+
+    /// Serve until `shutdown` resolves, then drain in-flight requests
+    ///
+    /// Returns once every open request has completed and no handler holds
+    /// the engine lock, so the engine is safe to persist afterwards.
+    pub async fn serve_with_shutdown<F>(
+        self,
+        listener: tokio::net::TcpListener,
+        shutdown: F,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let engine = self.engine.clone();
+
+        axum::serve(listener, self.router())
+            .with_graceful_shutdown(shutdown)
+            .await?;
+
+        // Wait out any engine update still holding the lock
+        let _engine = engine.write().await;
+        tracing::info!("API server shut down, all requests drained");
         Ok(())
     }
 }
 
+/// Resolve when the process is asked to stop
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+
+    tracing::info!("Shutdown signal received, draining connections");
+}
+
 async fn place_order(
     State(server): State<Arc<ApiServer>>,
     Json(req): Json<NewOrderRequest>,
@@ -53,7 +121,8 @@ async fn place_order(
         req.quantity,
         req.price,
         req.client_order_id,
-    );
+    )
+    .with_account(req.account);
 
     let order_id = order.id;
     let mut engine = server.engine.write().await;
@@ -130,6 +199,98 @@ async fn get_market_data(
     }
 }
 
+async fn get_recent_trades(
+    State(server): State<Arc<ApiServer>>,
+    Path(symbol): Path<String>,
+    Query(query): Query<RecentTradesQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.unwrap_or(DEFAULT_TRADES_LIMIT).min(MAX_RECENT_TRADES);
+    let engine = server.engine.read().await;
+
+    match engine.recent_trades(&symbol, limit) {
+        Ok(trades) => (StatusCode::OK, Json(trades)),
+        Err(_) => (StatusCode::NOT_FOUND, Json(vec![])),
+    }
+}
+
+async fn market_data_stream(
+    State(server): State<Arc<ApiServer>>,
+    Path(symbol): Path<String>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    // Subscribe before upgrading so unknown symbols get a plain 404
+    let subscription = server.engine.read().await.subscribe_market_data(&symbol);
+
+    match subscription {
+        Ok((snapshot, updates)) => ws.on_upgrade(move |socket| {
+            stream_market_data(socket, server, symbol, snapshot, updates)
+        }),
+        Err(_) => (StatusCode::NOT_FOUND, "Unknown symbol").into_response(),
+    }
+}
+
+/// Send the snapshot, then forward every update until the client leaves
+async fn stream_market_data(
+    mut socket: WebSocket,
+    server: Arc<ApiServer>,
+    symbol: String,
+    snapshot: MarketDepth,
+    mut updates: broadcast::Receiver<MarketDataEvent>,
+) {
+    if send_event(&mut socket, &MarketDataEvent::Snapshot(snapshot)).await.is_err() {
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => {
+                let event = match update {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        // Too slow to keep up: resync from a fresh snapshot
+                        tracing::warn!("Market-data subscriber for {} lagged by {}", symbol, skipped);
+                        let engine = server.engine.read().await;
+                        match engine.subscribe_market_data(&symbol) {
+                            Ok((snapshot, fresh)) => {
+                                updates = fresh;
+                                MarketDataEvent::Snapshot(snapshot)
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                if send_event(&mut socket, &event).await.is_err() {
+                    break;
+                }
+            }
+
+            message = socket.recv() => {
+                match message {
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    // Nothing to handle from the client besides close
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+}
+
+async fn send_event(socket: &mut WebSocket, event: &MarketDataEvent) -> Result<(), axum::Error> {
+    let json = serde_json::to_string(event).expect("market data serializes");
+    socket.send(Message::Text(json)).await
+}
+
+/// Prometheus scrape endpoint
+async fn metrics(State(server): State<Arc<ApiServer>>) -> impl IntoResponse {
+    let body = server.engine.read().await.render_metrics();
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        body,
+    )
+}
+
 async fn health_check() -> impl IntoResponse {
     (StatusCode::OK, "OK")
 }
@@ -149,4 +310,221 @@ mod tests {
         let _server = ApiServer::new(engine);
         // Server created successfully
     }
+
+    #[tokio::test]
+    async fn test_serve_stops_on_shutdown_signal() {
+        let mut engine = MatchingEngine::new();
+        engine.add_symbol("TEST".to_string());
+        let server = ApiServer::new(Arc::new(RwLock::new(engine)));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+
+        let handle = tokio::spawn(async move {
+            server
+                .serve_with_shutdown(listener, async {
+                    let _ = shutdown_rx.await;
+                })
+                .await
+                .map_err(|e| e.to_string())
+        });
+
+        let response = reqwest::get(format!("http://{}/health", addr)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        shutdown_tx.send(()).unwrap();
+
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), handle)
+            .await
+            .expect("server did not shut down")
+            .unwrap();
+        assert!(result.is_ok());
+    }
+
+    async fn spawn_test_server() -> std::net::SocketAddr {
+        let mut engine = MatchingEngine::new();
+        engine.add_symbol("TEST".to_string());
+        let server = ApiServer::new(Arc::new(RwLock::new(engine)));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = server
+                .serve_with_shutdown(listener, std::future::pending())
+                .await
+                .map_err(|e| e.to_string());
+        });
+        addr
+    }
+
+    async fn place_order(
+        client: &reqwest::Client,
+        addr: std::net::SocketAddr,
+        side: Side,
+        quantity: u64,
+        price: rust_decimal::Decimal,
+    ) {
+        client
+            .post(format!("http://{}/api/v1/orders", addr))
+            .json(&NewOrderRequest {
+                symbol: "TEST".to_string(),
+                side,
+                order_type: OrderType::Limit,
+                quantity,
+                price: Some(price),
+                client_order_id: String::new(),
+                account: String::new(),
+            })
+            .send()
+            .await
+            .unwrap();
+    }
+
+    async fn next_market_event<S>(socket: &mut S) -> MarketDataEvent
+    where
+        S: futures::Stream<
+                Item = Result<
+                    tokio_tungstenite::tungstenite::Message,
+                    tokio_tungstenite::tungstenite::Error,
+                >,
+            > + Unpin,
+    {
+        use futures::StreamExt;
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("no market data received")
+            .unwrap()
+            .unwrap();
+
+        match message {
+            WsMessage::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_market_data_stream() {
+        let addr = spawn_test_server().await;
+
+        let (mut socket, _) =
+            tokio_tungstenite::connect_async(format!("ws://{}/ws/marketdata/TEST", addr))
+                .await
+                .unwrap();
+
+        match next_market_event(&mut socket).await {
+            MarketDataEvent::Snapshot(depth) => assert!(depth.bids.is_empty()),
+            other => panic!("Expected snapshot, got {:?}", other),
+        }
+
+        let client = reqwest::Client::new();
+        place_order(&client, addr, Side::Buy, 10, dec!(100)).await;
+        match next_market_event(&mut socket).await {
+            MarketDataEvent::Depth(depth) => {
+                assert_eq!(depth.bids.len(), 1);
+                assert_eq!(depth.bids[0].quantity, 10);
+            }
+            other => panic!("Expected depth update, got {:?}", other),
+        }
+
+        place_order(&client, addr, Side::Sell, 10, dec!(100)).await;
+        match next_market_event(&mut socket).await {
+            MarketDataEvent::Trade(trade) => {
+                assert_eq!(trade.quantity, 10);
+                assert_eq!(trade.price, dec!(100));
+            }
+            other => panic!("Expected trade, got {:?}", other),
+        }
+        match next_market_event(&mut socket).await {
+            MarketDataEvent::Depth(depth) => assert!(depth.bids.is_empty()),
+            other => panic!("Expected depth update, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let addr = spawn_test_server().await;
+
+        let client = reqwest::Client::new();
+        let scrape = || async {
+            client
+                .get(format!("http://{}/metrics", addr))
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap()
+        };
+
+        let text = scrape().await;
+        for name in [
+            "trading_orders_submitted_total",
+            "trading_trades_executed_total",
+            "trading_last_price",
+            "trading_best_bid",
+            "trading_best_ask",
+            "trading_book_depth",
+        ] {
+            assert!(text.contains(&format!("# TYPE {} ", name)), "missing {}", name);
+        }
+        assert!(text.contains("trading_orders_submitted_total 0\n"));
+
+        place_order(&client, addr, Side::Buy, 10, dec!(99.5)).await;
+
+        let text = scrape().await;
+        assert!(text.contains("trading_orders_submitted_total 1\n"));
+        assert!(text.contains("trading_best_bid{symbol=\"TEST\"} 99.5\n"));
+        assert!(text.contains("trading_book_depth{symbol=\"TEST\",side=\"bid\"} 10\n"));
+    }
+
+    #[tokio::test]
+    async fn test_recent_trades_endpoint() {
+        let addr = spawn_test_server().await;
+
+        let client = reqwest::Client::new();
+        for (side, quantity, price) in [
+            (Side::Sell, 10, dec!(101)),
+            (Side::Sell, 10, dec!(102)),
+            (Side::Buy, 15, dec!(102)),
+        ] {
+            place_order(&client, addr, side, quantity, price).await;
+        }
+
+        let trades: Vec<TradePrint> = client
+            .get(format!("http://{}/api/v1/trades/TEST?limit=10", addr))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        // Newest first: the buy swept 101 then 102
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].price, dec!(102));
+        assert_eq!(trades[0].quantity, 5);
+        assert_eq!(trades[1].price, dec!(101));
+        assert_eq!(trades[1].quantity, 10);
+        assert!(trades.iter().all(|t| t.side == Side::Buy && t.timestamp > 0));
+
+        let trades: Vec<TradePrint> = client
+            .get(format!("http://{}/api/v1/trades/TEST?limit=1", addr))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(trades.len(), 1);
+
+        let response = client
+            .get(format!("http://{}/api/v1/trades/NOPE", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
 }
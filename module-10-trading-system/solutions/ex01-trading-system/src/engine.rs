@@ -1,42 +1,336 @@
 use crate::error::{Result, TradingError};
+use crate::metrics::{self, BookGauges, EngineMetrics};
 use crate::orderbook::OrderBook;
+use crate::risk::{PositionRecord, Positions, RiskLimits};
 use crate::types::*;
-use std::collections::HashMap;
+use crate::wal::{Wal, WalEntry, WalRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
+
+/// Price levels per side included in market-data updates
+pub const MARKET_DATA_DEPTH: usize = 10;
+
+/// Events buffered per symbol before slow subscribers start lagging
+const MARKET_DATA_CAPACITY: usize = 1024;
+
+/// Trades kept per symbol for the time & sales tape
+pub const MAX_RECENT_TRADES: usize = 1000;
+
+/// Current snapshot file format version
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// On-disk representation of the engine
+#[derive(Serialize, Deserialize)]
+struct EngineSnapshot<B> {
+    version: u32,
+    books: Vec<B>,
+    /// Last write-ahead log record reflected in `books`
+    #[serde(default)]
+    wal_seq: u64,
+    /// Net positions risk limits are checked against
+    #[serde(default)]
+    positions: Vec<PositionRecord>,
+}
 
 /// Matching engine that manages multiple order books
 pub struct MatchingEngine {
     books: HashMap<String, OrderBook>,
+    /// Market-data feed for each symbol
+    feeds: HashMap<String, broadcast::Sender<MarketDataEvent>>,
+    /// Most recent trades per symbol, oldest first
+    tapes: HashMap<String, VecDeque<TradePrint>>,
+    /// Pre-trade limits; no checks when unset
+    risk_limits: Option<RiskLimits>,
+    positions: Positions,
+    metrics: EngineMetrics,
+    /// Write-ahead log, once attached by [`recover`](Self::recover)
+    wal: Option<Wal>,
+    /// Sequence number of the last logged change
+    wal_seq: u64,
 }
 
 impl MatchingEngine {
     pub fn new() -> Self {
         MatchingEngine {
             books: HashMap::new(),
+            feeds: HashMap::new(),
+            tapes: HashMap::new(),
+            risk_limits: None,
+            positions: Positions::new(),
+            metrics: EngineMetrics::default(),
+            wal: None,
+            wal_seq: 0,
         }
     }
 
+    /// Reject orders that would breach `limits`
+    pub fn with_risk_limits(mut self, limits: RiskLimits) -> Self {
+        self.risk_limits = Some(limits);
+        self
+    }
+
+    /// Net filled position of an account in a symbol
+    pub fn position(&self, account: &str, symbol: &str) -> i64 {
+        self.positions.get(account, symbol)
+    }
+
+    /// Start trading a symbol; an existing book is kept as is
     pub fn add_symbol(&mut self, symbol: String) {
-        self.books.insert(symbol.clone(), OrderBook::new(symbol));
+        if self.books.contains_key(&symbol) {
+            return;
+        }
+        self.insert_book(OrderBook::new(symbol));
+    }
+
+    fn insert_book(&mut self, book: OrderBook) {
+        let (feed, _) = broadcast::channel(MARKET_DATA_CAPACITY);
+        self.feeds.insert(book.symbol().to_string(), feed);
+        self.tapes.insert(book.symbol().to_string(), VecDeque::new());
+        self.books.insert(book.symbol().to_string(), book);
+    }
+
+    /// Write every order book and account position to `path` as JSON
+    ///
+    /// The file is written next to `path` first and renamed into place, so
+    /// a crash mid-write leaves the previous snapshot intact. The
+    /// write-ahead log is emptied afterwards since the snapshot covers it.
+    pub fn save_snapshot(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+
+        let mut books: Vec<&OrderBook> = self.books.values().collect();
+        books.sort_by(|a, b| a.symbol().cmp(b.symbol()));
+
+        let json = serde_json::to_vec(&EngineSnapshot {
+            version: SNAPSHOT_VERSION,
+            books,
+            wal_seq: self.wal_seq,
+            positions: self.positions.records(),
+        })
+        .map_err(|e| TradingError::Snapshot(e.to_string()))?;
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, json)?;
+        std::fs::rename(&tmp_path, path)?;
+
+        if let Some(wal) = &self.wal {
+            wal.truncate()?;
+        }
+        Ok(())
+    }
+
+    /// Rebuild an engine from a snapshot written by [`save_snapshot`](Self::save_snapshot)
+    pub fn load_snapshot(path: impl AsRef<Path>) -> Result<MatchingEngine> {
+        let bytes = std::fs::read(path)?;
+        let snapshot: EngineSnapshot<OrderBook> =
+            serde_json::from_slice(&bytes).map_err(|e| TradingError::Snapshot(e.to_string()))?;
+
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(TradingError::Snapshot(format!(
+                "unsupported snapshot version {}",
+                snapshot.version
+            )));
+        }
+
+        let mut engine = MatchingEngine::new();
+        engine.wal_seq = snapshot.wal_seq;
+        engine.positions = Positions::from_records(snapshot.positions);
+        for book in snapshot.books {
+            // New orders must not reuse restored IDs
+            if let Some(max_id) = book.max_order_id() {
+                OrderId::reserve_past(max_id);
+            }
+            engine.insert_book(book);
+        }
+
+        Ok(engine)
+    }
+
+    /// Replay the write-ahead log at `path` on top of this engine, then
+    /// log every further order and cancel to it
+    ///
+    /// Call on the engine loaded from the latest snapshot, or on a new one;
+    /// records the snapshot already reflects are skipped.
+    pub fn recover(mut self, path: impl AsRef<Path>) -> Result<MatchingEngine> {
+        let (wal, records) = Wal::open(path)?;
+
+        let mut replayed = 0;
+        for record in records {
+            if record.seq <= self.wal_seq {
+                continue;
+            }
+
+            match record.entry {
+                WalEntry::Order(order) => {
+                    OrderId::reserve_past(order.id);
+                    self.add_symbol(order.symbol.clone());
+                    self.execute_order(order);
+                }
+                WalEntry::Cancel { symbol, order_id } => {
+                    // A cancel that failed the first time fails the same way again
+                    let _ = self.execute_cancel(&symbol, order_id);
+                }
+            }
+            self.wal_seq = record.seq;
+            replayed += 1;
+        }
+
+        if replayed > 0 {
+            tracing::info!("Replayed {} write-ahead log records", replayed);
+        }
+        self.wal = Some(wal);
+        Ok(self)
+    }
+
+    /// Record a change in the write-ahead log, if there is one
+    fn log(&mut self, entry: WalEntry) -> Result<()> {
+        if let Some(wal) = &mut self.wal {
+            let seq = self.wal_seq + 1;
+            wal.append(&WalRecord { seq, entry })?;
+            self.wal_seq = seq;
+        }
+        Ok(())
     }
 
     pub fn add_order(&mut self, order: Order) -> Result<Vec<Trade>> {
+        self.metrics.orders_submitted += 1;
+
+        let result = self.match_order(order);
+        match &result {
+            Ok(trades) => self.metrics.trades_executed += trades.len() as u64,
+            Err(_) => self.metrics.orders_rejected += 1,
+        }
+        result
+    }
+
+    /// Submit orders in sequence, returning each one's trades in order
+    ///
+    /// Orders are routed to their symbol's book exactly as with
+    /// [`add_order`](Self::add_order); a rejected order gets an empty list.
+    pub fn add_orders(&mut self, orders: Vec<Order>) -> Vec<Vec<Trade>> {
+        orders
+            .into_iter()
+            .map(|order| {
+                let order_id = order.id;
+                self.add_order(order).unwrap_or_else(|e| {
+                    tracing::warn!("Rejected order {:?} in batch: {}", order_id, e);
+                    Vec::new()
+                })
+            })
+            .collect()
+    }
+
+    fn match_order(&mut self, order: Order) -> Result<Vec<Trade>> {
         let book = self
             .books
-            .get_mut(&order.symbol)
+            .get(&order.symbol)
             .ok_or_else(|| TradingError::SymbolNotFound(order.symbol.clone()))?;
 
-        Ok(book.add_order(order))
+        if let Some(limits) = &self.risk_limits {
+            // Market orders are valued at the best price they could trade at
+            let price = order.price.or(match order.side {
+                Side::Buy => book.get_best_ask(),
+                Side::Sell => book.get_best_bid(),
+            });
+            let open_notional = self
+                .books
+                .values()
+                .map(|book| book.open_notional(&order.account))
+                .sum();
+
+            limits.check(
+                &order,
+                self.positions.get(&order.account, &order.symbol),
+                book.open_quantity(&order.account, order.side),
+                open_notional,
+                price,
+            )?;
+        }
+
+        self.log(WalEntry::Order(order.clone()))?;
+        Ok(self.execute_order(order))
+    }
+
+    /// Match an order the engine has already accepted
+    fn execute_order(&mut self, order: Order) -> Vec<Trade> {
+        let book = self
+            .books
+            .get_mut(&order.symbol)
+            .expect("symbol checked before execution");
+
+        let side = order.side;
+        let trades = book.add_order(order);
+
+        for trade in &trades {
+            let account = |id| book.get_order(id).map(|o| o.account.as_str()).unwrap_or("");
+            self.positions
+                .apply_trade(trade, account(trade.buyer_order_id), account(trade.seller_order_id));
+        }
+
+        if let Some(tape) = self.tapes.get_mut(book.symbol()) {
+            for trade in &trades {
+                if tape.len() == MAX_RECENT_TRADES {
+                    tape.pop_front();
+                }
+                tape.push_back(TradePrint::new(trade, side));
+            }
+        }
+
+        if let Some(feed) = self.feeds.get(book.symbol()) {
+            // Sending only fails when nobody is subscribed
+            for trade in &trades {
+                let _ = feed.send(MarketDataEvent::Trade(trade.clone()));
+            }
+            let _ = feed.send(MarketDataEvent::Depth(book.get_depth(MARKET_DATA_DEPTH)));
+        }
+
+        trades
     }
 
     pub fn cancel_order(&mut self, symbol: &str, order_id: OrderId) -> Result<Order> {
+        if !self.books.contains_key(symbol) {
+            return Err(TradingError::SymbolNotFound(symbol.to_string()));
+        }
+
+        self.log(WalEntry::Cancel {
+            symbol: symbol.to_string(),
+            order_id,
+        })?;
+        self.execute_cancel(symbol, order_id)
+    }
+
+    fn execute_cancel(&mut self, symbol: &str, order_id: OrderId) -> Result<Order> {
         let book = self
             .books
             .get_mut(symbol)
+            .expect("symbol checked before execution");
+
+        let order = book.cancel_order(order_id)?;
+        self.metrics.orders_canceled += 1;
+
+        if let Some(feed) = self.feeds.get(symbol) {
+            let _ = feed.send(MarketDataEvent::Depth(book.get_depth(MARKET_DATA_DEPTH)));
+        }
+
+        Ok(order)
+    }
+
+    /// Subscribe to a symbol's market data
+    ///
+    /// Returns the current book alongside the receiver; updates made after
+    /// the snapshot are delivered through the receiver.
+    pub fn subscribe_market_data(
+        &self,
+        symbol: &str,
+    ) -> Result<(MarketDepth, broadcast::Receiver<MarketDataEvent>)> {
+        let feed = self
+            .feeds
+            .get(symbol)
             .ok_or_else(|| TradingError::SymbolNotFound(symbol.to_string()))?;
 
-        book.cancel_order(order_id)
+        Ok((self.get_market_depth(symbol, MARKET_DATA_DEPTH)?, feed.subscribe()))
     }
 
     pub fn get_market_depth(&self, symbol: &str, levels: usize) -> Result<MarketDepth> {
@@ -48,9 +342,42 @@ impl MatchingEngine {
         Ok(book.get_depth(levels))
     }
 
+    /// Up to `limit` of the symbol's most recent trades, newest first
+    pub fn recent_trades(&self, symbol: &str, limit: usize) -> Result<Vec<TradePrint>> {
+        let tape = self
+            .tapes
+            .get(symbol)
+            .ok_or_else(|| TradingError::SymbolNotFound(symbol.to_string()))?;
+
+        Ok(tape.iter().rev().take(limit).cloned().collect())
+    }
+
     pub fn get_order(&self, symbol: &str, order_id: OrderId) -> Option<&Order> {
         self.books.get(symbol).and_then(|book| book.get_order(order_id))
     }
+
+    pub fn metrics(&self) -> &EngineMetrics {
+        &self.metrics
+    }
+
+    /// Counters and per-symbol book gauges in Prometheus text format
+    pub fn render_metrics(&self) -> String {
+        let mut books: Vec<BookGauges> = self
+            .books
+            .values()
+            .map(|book| BookGauges {
+                symbol: book.symbol().to_string(),
+                last_price: book.last_price(),
+                best_bid: book.get_best_bid(),
+                best_ask: book.get_best_ask(),
+                bid_depth: book.resting_quantity(Side::Buy),
+                ask_depth: book.resting_quantity(Side::Sell),
+            })
+            .collect();
+        books.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+        metrics::render(&self.metrics, &books)
+    }
 }
 
 pub type SharedEngine = Arc<RwLock<MatchingEngine>>;
@@ -60,3 +387,374 @@ impl Default for MatchingEngine {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn limit(symbol: &str, side: Side, quantity: u64, price: rust_decimal::Decimal) -> Order {
+        Order::new(
+            symbol.to_string(),
+            side,
+            OrderType::Limit,
+            quantity,
+            Some(price),
+            String::new(),
+        )
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("engine.json");
+
+        let mut engine = MatchingEngine::new();
+        engine.add_symbol("AAPL".to_string());
+        engine.add_symbol("MSFT".to_string());
+
+        engine.add_order(limit("AAPL", Side::Buy, 100, dec!(149.50))).unwrap();
+        engine.add_order(limit("AAPL", Side::Buy, 50, dec!(149.50))).unwrap();
+        engine.add_order(limit("AAPL", Side::Sell, 75, dec!(150.25))).unwrap();
+        // Partially fills the ask and sets the last price
+        engine.add_order(limit("AAPL", Side::Buy, 25, dec!(150.25))).unwrap();
+        let resting = limit("MSFT", Side::Sell, 10, dec!(400));
+        let resting_id = resting.id;
+        engine.add_order(resting).unwrap();
+
+        engine.save_snapshot(&path).unwrap();
+        let restored = MatchingEngine::load_snapshot(&path).unwrap();
+
+        for symbol in ["AAPL", "MSFT"] {
+            let before = engine.get_market_depth(symbol, 10).unwrap();
+            let after = restored.get_market_depth(symbol, 10).unwrap();
+            assert_eq!(
+                serde_json::to_value(&before).unwrap(),
+                serde_json::to_value(&after).unwrap()
+            );
+        }
+
+        let depth = restored.get_market_depth("AAPL", 10).unwrap();
+        assert_eq!(depth.bids[0].quantity, 150);
+        assert_eq!(depth.asks[0].quantity, 50);
+        assert_eq!(depth.last_trade_price, Some(dec!(150.25)));
+
+        assert_eq!(restored.positions.records(), engine.positions.records());
+
+        // Restored orders can still be canceled, and new IDs don't collide
+        let mut restored = restored;
+        restored.cancel_order("MSFT", resting_id).unwrap();
+        assert!(OrderId::new().0 > resting_id.0);
+    }
+
+    #[test]
+    fn test_recent_trades_bounded() {
+        let mut engine = MatchingEngine::new();
+        engine.add_symbol("AAPL".to_string());
+
+        for _ in 0..MAX_RECENT_TRADES + 5 {
+            engine.add_order(limit("AAPL", Side::Sell, 1, dec!(10))).unwrap();
+            engine.add_order(limit("AAPL", Side::Buy, 1, dec!(10))).unwrap();
+        }
+
+        let trades = engine.recent_trades("AAPL", usize::MAX).unwrap();
+        assert_eq!(trades.len(), MAX_RECENT_TRADES);
+        assert!(trades[0].trade_id.0 > trades[1].trade_id.0);
+        assert_eq!(trades[0].side, Side::Buy);
+
+        assert_eq!(engine.recent_trades("AAPL", 3).unwrap().len(), 3);
+        assert!(engine.recent_trades("NOPE", 3).is_err());
+    }
+
+    #[test]
+    fn test_risk_limits() {
+        let mut engine = MatchingEngine::new().with_risk_limits(RiskLimits {
+            max_position: 100,
+            max_open_notional: dec!(10000),
+        });
+        engine.add_symbol("AAPL".to_string());
+
+        // Within limits: rests in the book
+        engine
+            .add_order(limit("AAPL", Side::Sell, 60, dec!(100)).with_account("maker"))
+            .unwrap();
+        engine
+            .add_order(limit("AAPL", Side::Buy, 60, dec!(100)).with_account("taker"))
+            .unwrap();
+        assert_eq!(engine.position("taker", "AAPL"), 60);
+        assert_eq!(engine.position("maker", "AAPL"), -60);
+
+        // Would take the taker to 120 shares
+        let breach = limit("AAPL", Side::Buy, 60, dec!(99)).with_account("taker");
+        let breach_id = breach.id;
+        assert!(matches!(
+            engine.add_order(breach),
+            Err(TradingError::RiskLimitExceeded(_))
+        ));
+        assert!(engine.get_order("AAPL", breach_id).is_none());
+        assert!(engine.get_market_depth("AAPL", 10).unwrap().bids.is_empty());
+
+        // 40 resting at 99 (3,960) plus 60 at 110 (6,600) breaches the 10,000 notional cap
+        engine
+            .add_order(limit("AAPL", Side::Buy, 40, dec!(99)).with_account("other"))
+            .unwrap();
+        assert!(engine
+            .add_order(limit("AAPL", Side::Buy, 60, dec!(110)).with_account("other"))
+            .is_err());
+        assert_eq!(engine.get_market_depth("AAPL", 10).unwrap().bids.len(), 1);
+    }
+
+    #[test]
+    fn test_risk_limits_count_resting_orders() {
+        let mut engine = MatchingEngine::new().with_risk_limits(RiskLimits {
+            max_position: 100,
+            max_open_notional: dec!(100000),
+        });
+        engine.add_symbol("AAPL".to_string());
+
+        engine
+            .add_order(limit("AAPL", Side::Buy, 60, dec!(100)).with_account("taker"))
+            .unwrap();
+        // Fine alone, but both filling would take the taker to 120 shares
+        assert!(matches!(
+            engine.add_order(limit("AAPL", Side::Buy, 60, dec!(99)).with_account("taker")),
+            Err(TradingError::RiskLimitExceeded(_))
+        ));
+        // Resting sells don't count against a buy
+        engine
+            .add_order(limit("AAPL", Side::Sell, 50, dec!(110)).with_account("taker"))
+            .unwrap();
+        engine
+            .add_order(limit("AAPL", Side::Buy, 40, dec!(99)).with_account("taker"))
+            .unwrap();
+        assert_eq!(engine.get_market_depth("AAPL", 10).unwrap().bids.len(), 2);
+    }
+
+    #[test]
+    fn test_risk_limits_hold_after_snapshot_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("engine.json");
+        let limits = RiskLimits {
+            max_position: 100,
+            max_open_notional: dec!(100000),
+        };
+
+        let mut engine = MatchingEngine::new().with_risk_limits(limits.clone());
+        engine.add_symbol("AAPL".to_string());
+        engine
+            .add_order(limit("AAPL", Side::Sell, 80, dec!(100)).with_account("maker"))
+            .unwrap();
+        engine
+            .add_order(limit("AAPL", Side::Buy, 80, dec!(100)).with_account("taker"))
+            .unwrap();
+        engine.save_snapshot(&path).unwrap();
+
+        let mut restored = MatchingEngine::load_snapshot(&path)
+            .unwrap()
+            .with_risk_limits(limits);
+        assert_eq!(restored.position("taker", "AAPL"), 80);
+        assert_eq!(restored.position("maker", "AAPL"), -80);
+
+        // A flat account could buy 40, but the taker already holds 80
+        assert!(matches!(
+            restored.add_order(limit("AAPL", Side::Buy, 40, dec!(99)).with_account("taker")),
+            Err(TradingError::RiskLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_market_order_into_empty_book_is_rejected_by_risk() {
+        let mut engine = MatchingEngine::new().with_risk_limits(RiskLimits {
+            max_position: 100,
+            max_open_notional: dec!(100000),
+        });
+        engine.add_symbol("AAPL".to_string());
+
+        let market = Order::new(
+            "AAPL".to_string(),
+            Side::Buy,
+            OrderType::Market,
+            10,
+            None,
+            String::new(),
+        )
+        .with_account("taker");
+        assert!(matches!(
+            engine.add_order(market),
+            Err(TradingError::RiskLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_metrics_counters() {
+        let mut engine = MatchingEngine::new();
+        engine.add_symbol("AAPL".to_string());
+
+        let resting = limit("AAPL", Side::Sell, 10, dec!(100));
+        let resting_id = resting.id;
+        engine.add_order(resting).unwrap();
+        engine.add_order(limit("AAPL", Side::Sell, 10, dec!(101))).unwrap();
+        engine.add_order(limit("AAPL", Side::Buy, 15, dec!(101))).unwrap();
+        assert!(engine.add_order(limit("NOPE", Side::Buy, 1, dec!(1))).is_err());
+        assert!(engine.cancel_order("AAPL", resting_id).is_err());
+
+        let metrics = engine.metrics();
+        assert_eq!(metrics.orders_submitted, 4);
+        assert_eq!(metrics.orders_rejected, 1);
+        assert_eq!(metrics.trades_executed, 2);
+        assert_eq!(metrics.orders_canceled, 0);
+    }
+
+    #[test]
+    fn test_add_orders_matches_individual_submission() {
+        let stream = || {
+            vec![
+                limit("AAPL", Side::Sell, 50, dec!(101)),
+                limit("MSFT", Side::Buy, 20, dec!(400)),
+                limit("AAPL", Side::Sell, 50, dec!(102)),
+                limit("NOPE", Side::Buy, 1, dec!(1)),
+                limit("AAPL", Side::Buy, 80, dec!(102)),
+                limit("MSFT", Side::Sell, 30, dec!(399)),
+                limit("AAPL", Side::Buy, 10, dec!(102)),
+            ]
+        };
+        let new_engine = || {
+            let mut engine = MatchingEngine::new();
+            engine.add_symbol("AAPL".to_string());
+            engine.add_symbol("MSFT".to_string());
+            engine
+        };
+        let summary = |trades: &[Trade]| -> Vec<(String, rust_decimal::Decimal, u64)> {
+            trades.iter().map(|t| (t.symbol.clone(), t.price, t.quantity)).collect()
+        };
+
+        let mut batched = new_engine();
+        let batch_trades = batched.add_orders(stream());
+
+        let mut individual = new_engine();
+        let individual_trades: Vec<Vec<Trade>> = stream()
+            .into_iter()
+            .map(|order| individual.add_order(order).unwrap_or_default())
+            .collect();
+
+        assert_eq!(batch_trades.len(), 7);
+        for (batch, single) in batch_trades.iter().zip(&individual_trades) {
+            assert_eq!(summary(batch), summary(single));
+        }
+
+        // The sweep crossed both asks, then each symbol's last order crossed
+        let all: Vec<Trade> = batch_trades.into_iter().flatten().collect();
+        assert_eq!(
+            summary(&all),
+            vec![
+                ("AAPL".to_string(), dec!(101), 50),
+                ("AAPL".to_string(), dec!(102), 30),
+                ("MSFT".to_string(), dec!(400), 20),
+                ("AAPL".to_string(), dec!(102), 10),
+            ]
+        );
+
+        for symbol in ["AAPL", "MSFT"] {
+            assert_eq!(
+                serde_json::to_value(batched.get_market_depth(symbol, 10).unwrap()).unwrap(),
+                serde_json::to_value(individual.get_market_depth(symbol, 10).unwrap()).unwrap()
+            );
+        }
+        assert_eq!(batched.metrics().orders_rejected, 1);
+    }
+
+    fn depth_json(engine: &MatchingEngine, symbol: &str) -> serde_json::Value {
+        serde_json::to_value(engine.get_market_depth(symbol, 10).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_recover_from_wal_after_crash() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal_path = dir.path().join("engine.wal");
+
+        let mut engine = MatchingEngine::new().recover(&wal_path).unwrap();
+        engine.add_symbol("AAPL".to_string());
+        engine.add_symbol("MSFT".to_string());
+
+        engine.add_order(limit("AAPL", Side::Buy, 100, dec!(149.50))).unwrap();
+        let canceled = limit("AAPL", Side::Buy, 30, dec!(149));
+        let canceled_id = canceled.id;
+        engine.add_order(canceled).unwrap();
+        engine.add_order(limit("AAPL", Side::Sell, 75, dec!(150.25))).unwrap();
+        engine.add_order(limit("AAPL", Side::Buy, 25, dec!(150.25))).unwrap();
+        engine.add_order(limit("MSFT", Side::Sell, 10, dec!(400))).unwrap();
+        engine.cancel_order("AAPL", canceled_id).unwrap();
+        // Rejected before being logged
+        assert!(engine.add_order(limit("NOPE", Side::Buy, 1, dec!(1))).is_err());
+
+        let expected: Vec<_> = ["AAPL", "MSFT"].iter().map(|s| depth_json(&engine, s)).collect();
+        drop(engine);
+
+        let recovered = MatchingEngine::new().recover(&wal_path).unwrap();
+        for (symbol, expected) in ["AAPL", "MSFT"].iter().zip(&expected) {
+            assert_eq!(&depth_json(&recovered, symbol), expected);
+        }
+        let order = recovered.get_order("AAPL", canceled_id).unwrap();
+        assert_eq!(order.status, OrderStatus::Canceled);
+        assert_eq!(recovered.recent_trades("AAPL", 10).unwrap().len(), 1);
+        assert!(recovered.get_market_depth("NOPE", 10).is_err());
+    }
+
+    #[test]
+    fn test_recover_replays_only_past_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot_path = dir.path().join("engine.json");
+        let wal_path = dir.path().join("engine.wal");
+
+        let mut engine = MatchingEngine::new().recover(&wal_path).unwrap();
+        engine.add_symbol("AAPL".to_string());
+        engine.add_order(limit("AAPL", Side::Buy, 100, dec!(100))).unwrap();
+        engine.save_snapshot(&snapshot_path).unwrap();
+        assert_eq!(std::fs::metadata(&wal_path).unwrap().len(), 0);
+
+        engine.add_order(limit("AAPL", Side::Buy, 50, dec!(100))).unwrap();
+        engine.add_order(limit("AAPL", Side::Sell, 30, dec!(100))).unwrap();
+        let expected = depth_json(&engine, "AAPL");
+        drop(engine);
+
+        // A torn append from the crash is ignored
+        let mut file = std::fs::OpenOptions::new().append(true).open(&wal_path).unwrap();
+        std::io::Write::write_all(&mut file, br#"{"seq": 4, "entry": {"ty"#).unwrap();
+        drop(file);
+
+        let recovered = MatchingEngine::load_snapshot(&snapshot_path)
+            .unwrap()
+            .recover(&wal_path)
+            .unwrap();
+        assert_eq!(depth_json(&recovered, "AAPL"), expected);
+        assert_eq!(recovered.get_market_depth("AAPL", 10).unwrap().bids[0].quantity, 120);
+
+        // Crashing after a snapshot but before the log is emptied must not
+        // apply the logged orders twice
+        let mut recovered = recovered;
+        recovered.add_order(limit("AAPL", Side::Buy, 5, dec!(99))).unwrap();
+        let expected = depth_json(&recovered, "AAPL");
+        let log = std::fs::read(&wal_path).unwrap();
+        recovered.save_snapshot(&snapshot_path).unwrap();
+        drop(recovered);
+        std::fs::write(&wal_path, log).unwrap();
+
+        let recovered = MatchingEngine::load_snapshot(&snapshot_path)
+            .unwrap()
+            .recover(&wal_path)
+            .unwrap();
+        assert_eq!(depth_json(&recovered, "AAPL"), expected);
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("engine.json");
+        std::fs::write(&path, r#"{"version": 99, "books": []}"#).unwrap();
+
+        assert!(matches!(
+            MatchingEngine::load_snapshot(&path),
+            Err(TradingError::Snapshot(_))
+        ));
+    }
+}
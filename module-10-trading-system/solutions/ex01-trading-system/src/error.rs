@@ -22,6 +22,18 @@ pub enum TradingError {
 
     #[error("Order value exceeded")]
     OrderValueExceeded,
+
+    #[error("Risk limit exceeded: {0}")]
+    RiskLimitExceeded(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Snapshot error: {0}")]
+    Snapshot(String),
+
+    #[error("Write-ahead log error: {0}")]
+    Wal(String),
 }
 
 pub type Result<T> = std::result::Result<T, TradingError>;
@@ -1,8 +1,11 @@
 pub mod api;
 pub mod engine;
 pub mod error;
+pub mod metrics;
 pub mod orderbook;
+pub mod risk;
 pub mod types;
+pub mod wal;
 
 pub use engine::MatchingEngine;
 pub use types::*;
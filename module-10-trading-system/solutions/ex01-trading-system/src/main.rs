@@ -1,8 +1,15 @@
+use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use trading_system::{api::ApiServer, engine::MatchingEngine};
+use rust_decimal_macros::dec;
+use trading_system::{api::ApiServer, engine::MatchingEngine, risk::RiskLimits};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+const SNAPSHOT_PATH: &str = "orderbooks.json";
+
+/// Orders and cancels since the last snapshot
+const WAL_PATH: &str = "orderbooks.wal";
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing
@@ -16,8 +23,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tracing::info!("Starting trading system");
 
-    // Create matching engine and add symbols
-    let mut engine = MatchingEngine::new();
+    // Restore resting orders from the last run, if any
+    let mut engine = if Path::new(SNAPSHOT_PATH).exists() {
+        let engine = MatchingEngine::load_snapshot(SNAPSHOT_PATH)?;
+        tracing::info!("Restored order books from {}", SNAPSHOT_PATH);
+        engine
+    } else {
+        MatchingEngine::new()
+    };
+
+    // Reapply anything accepted after that snapshot was taken
+    engine = engine.recover(WAL_PATH)?.with_risk_limits(RiskLimits {
+        max_position: 10_000,
+        max_open_notional: dec!(5_000_000),
+    });
+
     engine.add_symbol("AAPL".to_string());
     engine.add_symbol("GOOGL".to_string());
     engine.add_symbol("MSFT".to_string());
@@ -25,9 +45,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let engine = Arc::new(RwLock::new(engine));
 
-    // Start API server
-    let server = ApiServer::new(engine);
+    // Start API server; returns after Ctrl+C once in-flight requests drain
+    let server = ApiServer::new(engine.clone());
     server.serve("127.0.0.1:8080").await?;
 
+    engine.read().await.save_snapshot(SNAPSHOT_PATH)?;
+    tracing::info!("Saved order books to {}", SNAPSHOT_PATH);
+
+    tracing::info!("Trading system stopped");
+
     Ok(())
 }
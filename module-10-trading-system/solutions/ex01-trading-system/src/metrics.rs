@@ -0,0 +1,117 @@
+use rust_decimal::Decimal;
+use std::fmt::Write;
+
+/// Running totals kept by the matching engine
+#[derive(Debug, Clone, Default)]
+pub struct EngineMetrics {
+    /// Every order passed to the engine, including rejected ones
+    pub orders_submitted: u64,
+    pub orders_rejected: u64,
+    pub orders_canceled: u64,
+    pub trades_executed: u64,
+}
+
+/// Current state of one order book, exported as gauges
+#[derive(Debug, Clone)]
+pub struct BookGauges {
+    pub symbol: String,
+    pub last_price: Option<Decimal>,
+    pub best_bid: Option<Decimal>,
+    pub best_ask: Option<Decimal>,
+    /// Resting quantity on the bid side
+    pub bid_depth: u64,
+    /// Resting quantity on the ask side
+    pub ask_depth: u64,
+}
+
+/// Format the engine's metrics in the Prometheus text exposition format
+///
+/// Prices that don't exist yet (no trades, an empty side) are left out
+/// rather than reported as zero.
+pub fn render(metrics: &EngineMetrics, books: &[BookGauges]) -> String {
+    let mut out = String::new();
+
+    let counters = [
+        ("trading_orders_submitted_total", "Orders submitted to the engine", metrics.orders_submitted),
+        ("trading_orders_rejected_total", "Orders rejected by the engine", metrics.orders_rejected),
+        ("trading_orders_canceled_total", "Orders canceled", metrics.orders_canceled),
+        ("trading_trades_executed_total", "Trades executed", metrics.trades_executed),
+    ];
+    for (name, help, value) in counters {
+        header(&mut out, name, "counter", help);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+
+    header(&mut out, "trading_last_price", "gauge", "Price of the most recent trade");
+    for book in books {
+        if let Some(price) = book.last_price {
+            let _ = writeln!(out, "trading_last_price{{symbol=\"{}\"}} {}", book.symbol, price);
+        }
+    }
+
+    header(&mut out, "trading_best_bid", "gauge", "Highest resting buy price");
+    for book in books {
+        if let Some(price) = book.best_bid {
+            let _ = writeln!(out, "trading_best_bid{{symbol=\"{}\"}} {}", book.symbol, price);
+        }
+    }
+
+    header(&mut out, "trading_best_ask", "gauge", "Lowest resting sell price");
+    for book in books {
+        if let Some(price) = book.best_ask {
+            let _ = writeln!(out, "trading_best_ask{{symbol=\"{}\"}} {}", book.symbol, price);
+        }
+    }
+
+    header(&mut out, "trading_book_depth", "gauge", "Resting quantity per side of the book");
+    for book in books {
+        for (side, depth) in [("bid", book.bid_depth), ("ask", book.ask_depth)] {
+            let _ = writeln!(
+                out,
+                "trading_book_depth{{symbol=\"{}\",side=\"{}\"}} {}",
+                book.symbol, side, depth
+            );
+        }
+    }
+
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_render_skips_missing_prices() {
+        let metrics = EngineMetrics {
+            orders_submitted: 3,
+            trades_executed: 1,
+            ..Default::default()
+        };
+        let books = [BookGauges {
+            symbol: "AAPL".to_string(),
+            last_price: Some(dec!(150.25)),
+            best_bid: Some(dec!(150)),
+            best_ask: None,
+            bid_depth: 40,
+            ask_depth: 0,
+        }];
+
+        let text = render(&metrics, &books);
+        assert!(text.contains(
+            "# TYPE trading_orders_submitted_total counter\ntrading_orders_submitted_total 3\n"
+        ));
+        assert!(text.contains("trading_trades_executed_total 1\n"));
+        assert!(text.contains("trading_last_price{symbol=\"AAPL\"} 150.25\n"));
+        assert!(text.contains("trading_best_bid{symbol=\"AAPL\"} 150\n"));
+        assert!(!text.contains("trading_best_ask{"));
+        assert!(text.contains("trading_book_depth{symbol=\"AAPL\",side=\"bid\"} 40\n"));
+        assert!(text.contains("trading_book_depth{symbol=\"AAPL\",side=\"ask\"} 0\n"));
+    }
+}
@@ -1,10 +1,12 @@
 use crate::error::{Result, TradingError};
 use crate::types::*;
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::time::SystemTime;
 
 /// Order book for a single symbol with price-time priority matching
+#[derive(Serialize, Deserialize)]
 pub struct OrderBook {
     symbol: String,
     /// Buy orders: price (descending) -> queue of orders
@@ -333,6 +335,89 @@ impl OrderBook {
         }
     }
 
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn last_price(&self) -> Option<Decimal> {
+        self.last_price
+    }
+
+    /// Total quantity resting on one side of the book
+    pub fn resting_quantity(&self, side: Side) -> u64 {
+        self.top_quantity(side, usize::MAX)
+    }
+
+    /// Quantity resting in the best `levels` price levels of one side
+    fn top_quantity(&self, side: Side, levels: usize) -> u64 {
+        let level_quantity =
+            |orders: &VecDeque<Order>| orders.iter().map(|o| o.remaining_quantity()).sum::<u64>();
+
+        match side {
+            Side::Buy => self.bids.values().rev().take(levels).map(level_quantity).sum(),
+            Side::Sell => self.asks.values().take(levels).map(level_quantity).sum(),
+        }
+    }
+
+    /// Order-flow imbalance over the top `levels` price levels
+    ///
+    /// `(bid_qty - ask_qty) / (bid_qty + ask_qty)`, from -1 (all asks) to
+    /// 1 (all bids). `None` when either side is empty.
+    pub fn imbalance(&self, levels: usize) -> Option<Decimal> {
+        let bid_qty = self.top_quantity(Side::Buy, levels);
+        let ask_qty = self.top_quantity(Side::Sell, levels);
+        if bid_qty == 0 || ask_qty == 0 {
+            return None;
+        }
+
+        let (bid_qty, ask_qty) = (Decimal::from(bid_qty), Decimal::from(ask_qty));
+        Some((bid_qty - ask_qty) / (bid_qty + ask_qty))
+    }
+
+    /// Mid price weighted by the quantity at the top of the book
+    ///
+    /// `(best_bid * ask_qty + best_ask * bid_qty) / (bid_qty + ask_qty)`,
+    /// which leans towards the side with less quantity. `None` when either
+    /// side is empty.
+    pub fn microprice(&self) -> Option<Decimal> {
+        let best_bid = self.get_best_bid()?;
+        let best_ask = self.get_best_ask()?;
+        let bid_qty = Decimal::from(self.top_quantity(Side::Buy, 1));
+        let ask_qty = Decimal::from(self.top_quantity(Side::Sell, 1));
+
+        Some((best_bid * ask_qty + best_ask * bid_qty) / (bid_qty + ask_qty))
+    }
+
+    /// Value of an account's limit orders still resting in the book
+    pub fn open_notional(&self, account: &str) -> Decimal {
+        self.bids
+            .values()
+            .chain(self.asks.values())
+            .flatten()
+            .filter(|order| order.account == account)
+            .map(|order| order.price.unwrap_or_default() * Decimal::from(order.remaining_quantity()))
+            .sum()
+    }
+
+    /// Quantity of an account's orders still resting on one side of the book
+    pub fn open_quantity(&self, account: &str, side: Side) -> u64 {
+        let levels = match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
+        };
+        levels
+            .values()
+            .flatten()
+            .filter(|order| order.account == account)
+            .map(|order| order.remaining_quantity())
+            .sum()
+    }
+
+    /// Highest order ID known to this book
+    pub fn max_order_id(&self) -> Option<OrderId> {
+        self.orders.keys().max_by_key(|id| id.0).copied()
+    }
+
     pub fn get_order(&self, order_id: OrderId) -> Option<&Order> {
         self.orders.get(&order_id)
     }
@@ -464,6 +549,48 @@ mod tests {
         assert_eq!(book.get_depth(10).bids.len(), 0);
     }
 
+    fn resting(book: &mut OrderBook, side: Side, quantity: u64, price: Decimal) {
+        book.add_order(Order::new(
+            "AAPL".to_string(),
+            side,
+            OrderType::Limit,
+            quantity,
+            Some(price),
+            String::new(),
+        ));
+    }
+
+    #[test]
+    fn test_imbalance_and_microprice() {
+        let mut book = OrderBook::new("AAPL".to_string());
+        assert_eq!(book.imbalance(3), None);
+        assert_eq!(book.microprice(), None);
+
+        // Bids: 300 @ 100 (two orders), 100 @ 99, 500 @ 98
+        resting(&mut book, Side::Buy, 200, dec!(100));
+        resting(&mut book, Side::Buy, 100, dec!(100));
+        resting(&mut book, Side::Buy, 100, dec!(99));
+        resting(&mut book, Side::Buy, 500, dec!(98));
+        assert_eq!(book.imbalance(3), None);
+        assert_eq!(book.microprice(), None);
+
+        // Asks: 100 @ 101, 200 @ 102, 50 @ 110
+        resting(&mut book, Side::Sell, 100, dec!(101));
+        resting(&mut book, Side::Sell, 200, dec!(102));
+        resting(&mut book, Side::Sell, 50, dec!(110));
+
+        // Top level: (300 - 100) / (300 + 100)
+        assert_eq!(book.imbalance(1), Some(dec!(0.5)));
+        // Top two: (400 - 300) / (400 + 300)
+        assert_eq!(book.imbalance(2), Some(dec!(100) / dec!(700)));
+        // Everything: (900 - 350) / (900 + 350)
+        assert_eq!(book.imbalance(10), Some(dec!(0.44)));
+        assert_eq!(book.imbalance(0), None);
+
+        // (100 * 100 + 101 * 300) / 400: pulled towards the thin ask
+        assert_eq!(book.microprice(), Some(dec!(100.75)));
+    }
+
     #[test]
     fn test_market_depth() {
         let mut book = OrderBook::new("AAPL".to_string());
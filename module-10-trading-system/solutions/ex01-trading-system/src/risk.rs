@@ -0,0 +1,216 @@
+use crate::error::{Result, TradingError};
+use crate::types::*;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Per-account limits enforced before an order reaches the book
+#[derive(Debug, Clone)]
+pub struct RiskLimits {
+    /// Largest absolute net position allowed in any one symbol
+    pub max_position: u64,
+    /// Largest total value of an account's open orders across all symbols
+    pub max_open_notional: Decimal,
+}
+
+impl RiskLimits {
+    /// Check whether `order` may be accepted
+    ///
+    /// `position` is the account's current net position in the symbol,
+    /// `resting` the quantity of its orders on the same side already in the
+    /// book, `open_notional` the value of its resting orders, and `price` the
+    /// price used to value the order (its limit, or the best opposite price
+    /// for market orders). A market order with no opposite price can't be
+    /// valued and is rejected.
+    pub fn check(
+        &self,
+        order: &Order,
+        position: i64,
+        resting: u64,
+        open_notional: Decimal,
+        price: Option<Decimal>,
+    ) -> Result<()> {
+        // Assume the whole order fills, along with everything resting on its side
+        let quantity = order.quantity.saturating_add(resting) as i64;
+        let signed_quantity = match order.side {
+            Side::Buy => quantity,
+            Side::Sell => -quantity,
+        };
+        let projected = position.saturating_add(signed_quantity);
+        if projected.unsigned_abs() > self.max_position {
+            return Err(TradingError::RiskLimitExceeded(format!(
+                "position in {} would reach {} (limit {})",
+                order.symbol, projected, self.max_position
+            )));
+        }
+
+        let price = price.ok_or_else(|| {
+            TradingError::RiskLimitExceeded(format!(
+                "no price to value order in {}: the opposite side of the book is empty",
+                order.symbol
+            ))
+        })?;
+        let order_notional = price * Decimal::from(order.quantity);
+        let total = open_notional + order_notional;
+        if total > self.max_open_notional {
+            return Err(TradingError::RiskLimitExceeded(format!(
+                "open notional would reach {} (limit {})",
+                total, self.max_open_notional
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// One account's net position in one symbol, as kept in snapshots
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionRecord {
+    pub account: String,
+    pub symbol: String,
+    pub quantity: i64,
+}
+
+/// Net filled position of each account per symbol
+#[derive(Debug, Default)]
+pub struct Positions {
+    positions: HashMap<(String, String), i64>,
+}
+
+impl Positions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, account: &str, symbol: &str) -> i64 {
+        self.positions
+            .get(&(account.to_string(), symbol.to_string()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Every open position, sorted by account then symbol
+    pub fn records(&self) -> Vec<PositionRecord> {
+        let mut records: Vec<PositionRecord> = self
+            .positions
+            .iter()
+            .filter(|(_, &quantity)| quantity != 0)
+            .map(|((account, symbol), &quantity)| PositionRecord {
+                account: account.clone(),
+                symbol: symbol.clone(),
+                quantity,
+            })
+            .collect();
+        records.sort_by(|a, b| (&a.account, &a.symbol).cmp(&(&b.account, &b.symbol)));
+        records
+    }
+
+    /// Rebuild positions from [`records`](Self::records)
+    pub fn from_records(records: impl IntoIterator<Item = PositionRecord>) -> Self {
+        Positions {
+            positions: records
+                .into_iter()
+                .map(|record| ((record.account, record.symbol), record.quantity))
+                .collect(),
+        }
+    }
+
+    /// Record a fill between two accounts
+    pub fn apply_trade(&mut self, trade: &Trade, buyer: &str, seller: &str) {
+        let quantity = trade.quantity as i64;
+        *self
+            .positions
+            .entry((buyer.to_string(), trade.symbol.clone()))
+            .or_insert(0) += quantity;
+        *self
+            .positions
+            .entry((seller.to_string(), trade.symbol.clone()))
+            .or_insert(0) -= quantity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn order(side: Side, quantity: u64) -> Order {
+        Order::new(
+            "AAPL".to_string(),
+            side,
+            OrderType::Limit,
+            quantity,
+            Some(dec!(10)),
+            String::new(),
+        )
+    }
+
+    #[test]
+    fn test_position_limit() {
+        let limits = RiskLimits {
+            max_position: 100,
+            max_open_notional: dec!(1_000_000),
+        };
+
+        assert!(limits.check(&order(Side::Buy, 100), 0, 0, dec!(0), Some(dec!(10))).is_ok());
+        assert!(limits.check(&order(Side::Buy, 101), 0, 0, dec!(0), Some(dec!(10))).is_err());
+        // Selling reduces a long position
+        assert!(limits.check(&order(Side::Sell, 150), 80, 0, dec!(0), Some(dec!(10))).is_ok());
+        assert!(limits.check(&order(Side::Sell, 150), 0, 0, dec!(0), Some(dec!(10))).is_err());
+        // Resting orders on the same side count as if filled
+        assert!(limits.check(&order(Side::Buy, 50), 0, 50, dec!(0), Some(dec!(10))).is_ok());
+        assert!(limits.check(&order(Side::Buy, 51), 0, 50, dec!(0), Some(dec!(10))).is_err());
+    }
+
+    #[test]
+    fn test_notional_limit() {
+        let limits = RiskLimits {
+            max_position: 1_000,
+            max_open_notional: dec!(1000),
+        };
+
+        assert!(limits.check(&order(Side::Buy, 50), 0, 0, dec!(500), Some(dec!(10))).is_ok());
+        assert!(limits.check(&order(Side::Buy, 51), 0, 0, dec!(500), Some(dec!(10))).is_err());
+    }
+
+    #[test]
+    fn test_market_order_needs_a_price() {
+        let limits = RiskLimits {
+            max_position: 1_000,
+            max_open_notional: dec!(1000),
+        };
+        let mut market = order(Side::Buy, 10);
+        market.order_type = OrderType::Market;
+        market.price = None;
+
+        assert!(limits.check(&market, 0, 0, dec!(0), Some(dec!(10))).is_ok());
+        assert!(matches!(
+            limits.check(&market, 0, 0, dec!(0), None),
+            Err(TradingError::RiskLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_apply_trade() {
+        let mut positions = Positions::new();
+        let trade = Trade {
+            id: TradeId::new(),
+            symbol: "AAPL".to_string(),
+            price: dec!(10),
+            quantity: 30,
+            buyer_order_id: OrderId(1),
+            seller_order_id: OrderId(2),
+            timestamp: std::time::SystemTime::now(),
+        };
+
+        positions.apply_trade(&trade, "alice", "bob");
+        assert_eq!(positions.get("alice", "AAPL"), 30);
+        assert_eq!(positions.get("bob", "AAPL"), -30);
+        assert_eq!(positions.get("carol", "AAPL"), 0);
+
+        let restored = Positions::from_records(positions.records());
+        assert_eq!(restored.get("alice", "AAPL"), 30);
+        assert_eq!(restored.get("bob", "AAPL"), -30);
+        assert_eq!(restored.records(), positions.records());
+    }
+}
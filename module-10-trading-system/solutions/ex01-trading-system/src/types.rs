@@ -13,6 +13,11 @@ impl OrderId {
     pub fn new() -> Self {
         OrderId(ORDER_ID_COUNTER.fetch_add(1, Ordering::SeqCst))
     }
+
+    /// Make sure future IDs are greater than `id` (used after restoring orders)
+    pub fn reserve_past(id: OrderId) {
+        ORDER_ID_COUNTER.fetch_max(id.0 + 1, Ordering::SeqCst);
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -58,6 +63,9 @@ pub struct Order {
     #[serde(skip, default = "SystemTime::now")]
     pub timestamp: SystemTime,
     pub client_order_id: String,
+    /// Account the order belongs to, used for risk checks
+    #[serde(default)]
+    pub account: String,
 }
 
 impl Order {
@@ -80,9 +88,15 @@ impl Order {
             status: OrderStatus::New,
             timestamp: SystemTime::now(),
             client_order_id,
+            account: String::new(),
         }
     }
 
+    pub fn with_account(mut self, account: impl Into<String>) -> Self {
+        self.account = account.into();
+        self
+    }
+
     pub fn remaining_quantity(&self) -> u64 {
         self.quantity.saturating_sub(self.filled_quantity)
     }
@@ -115,6 +129,52 @@ pub struct MarketDepth {
     pub last_trade_price: Option<Decimal>,
 }
 
+/// Entry in a symbol's time & sales tape
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradePrint {
+    pub trade_id: TradeId,
+    pub price: Decimal,
+    pub quantity: u64,
+    /// Side of the incoming (aggressor) order
+    pub side: Side,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl TradePrint {
+    pub fn new(trade: &Trade, side: Side) -> Self {
+        let timestamp = trade
+            .timestamp
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+
+        TradePrint {
+            trade_id: trade.id,
+            price: trade.price,
+            quantity: trade.quantity,
+            side,
+            timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecentTradesQuery {
+    pub limit: Option<usize>,
+}
+
+/// Update pushed to market-data subscribers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum MarketDataEvent {
+    /// Full book state sent when a client subscribes
+    Snapshot(MarketDepth),
+    /// Book state after an order was added or canceled
+    Depth(MarketDepth),
+    Trade(Trade),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewOrderRequest {
     pub symbol: String,
@@ -123,6 +183,8 @@ pub struct NewOrderRequest {
     pub quantity: u64,
     pub price: Option<Decimal>,
     pub client_order_id: String,
+    #[serde(default)]
+    pub account: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,87 @@
+use crate::error::{Result, TradingError};
+use crate::types::{Order, OrderId};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Engine change recorded before it's applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WalEntry {
+    /// An order that passed validation and risk checks
+    Order(Order),
+    Cancel { symbol: String, order_id: OrderId },
+}
+
+/// One line of the log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalRecord {
+    /// Increases by one per record; snapshots remember the last one they include
+    pub seq: u64,
+    pub entry: WalEntry,
+}
+
+/// Append-only write-ahead log of JSON lines
+///
+/// Each record is handed to the OS before the change is applied, so a
+/// crashed process loses nothing. Records aren't fsynced, so a power
+/// failure can still lose the most recent ones.
+pub struct Wal {
+    file: File,
+}
+
+impl Wal {
+    /// Open the log at `path`, creating it if needed, and read its records
+    ///
+    /// A torn final line left by a crash mid-append is cut off; any other
+    /// unreadable record is an error.
+    pub fn open(path: impl AsRef<Path>) -> Result<(Wal, Vec<WalRecord>)> {
+        let path = path.as_ref();
+        let contents = match std::fs::read(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut records: Vec<WalRecord> = Vec::new();
+        let mut valid_len = 0;
+
+        for line in contents.split_inclusive(|&b| b == b'\n') {
+            // Records are written in one go, newline last, so only a crash
+            // mid-append leaves a line without one
+            if !line.ends_with(b"\n") {
+                tracing::warn!("Discarding torn record at the end of {}", path.display());
+                break;
+            }
+
+            let record = serde_json::from_slice(line).map_err(|e| {
+                let after = records.last().map_or(0, |r| r.seq);
+                TradingError::Wal(format!("corrupt record after seq {}: {}", after, e))
+            })?;
+            records.push(record);
+            valid_len += line.len();
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        if valid_len < contents.len() {
+            file.set_len(valid_len as u64)?;
+        }
+
+        Ok((Wal { file }, records))
+    }
+
+    pub fn append(&mut self, record: &WalRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record).map_err(|e| TradingError::Wal(e.to_string()))?;
+        line.push(b'\n');
+        // One write per record so a crash can only tear the last line
+        self.file.write_all(&line)?;
+        Ok(())
+    }
+
+    /// Drop every record, once a snapshot covers them
+    pub fn truncate(&self) -> Result<()> {
+        self.file.set_len(0)?;
+        Ok(())
+    }
+}
@@ -11,7 +11,11 @@ def main():
     print(f"5 + 3 = {calc.add(5, 3)}")
     print(f"Memory: {calc.memory}")
     print(f"10 * 4 = {calc.multiply(10, 4)}")
-    print(f"Memory: {calc.get_memory()}")
+    print(f"Memory: {calc.recall()}")
+    print(f"2 ** 8 = {calc.power(2, 8)}")
+    print(f"sqrt(81) = {calc.sqrt(81)}")
+    calc.store(100)
+    print(f"Stored: {calc.recall()}")
 
     # Test Fibonacci
     print("\n=== Fibonacci ===")
@@ -49,5 +53,13 @@ def main():
     print(f"Std Dev: {processor.std_dev():.2f}")
     print(f"Filtered (2σ): {processor.filter_outliers(2.0)}")
 
+    # Test RunningStats
+    print("\n=== Running Stats ===")
+    stats = rust_py_lib.RunningStats()
+    for x in data:
+        stats.push(x)
+    print(f"Count: {stats.count()}, Min: {stats.min()}, Max: {stats.max()}")
+    print(f"Mean: {stats.mean():.2f}, Std Dev: {stats.std_dev():.2f}")
+
 if __name__ == "__main__":
     main()
@@ -45,11 +45,44 @@ impl Calculator {
         Ok(result)
     }
 
+    fn power(&mut self, base: f64, exp: f64) -> f64 {
+        let result = base.powf(exp);
+        self.memory = result;
+        result
+    }
+
+    fn sqrt(&mut self, x: f64) -> PyResult<f64> {
+        self.try_sqrt(x).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>("Square root of a negative number")
+        })
+    }
+
+    /// Read back the last result (or stored value)
+    fn recall(&self) -> f64 {
+        self.memory
+    }
+
+    fn store(&mut self, value: f64) {
+        self.memory = value;
+    }
+
     fn clear_memory(&mut self) {
         self.memory = 0.0;
     }
 }
 
+impl Calculator {
+    /// Square root that updates memory, or `None` for a negative input
+    fn try_sqrt(&mut self, x: f64) -> Option<f64> {
+        if x < 0.0 {
+            return None;
+        }
+        let result = x.sqrt();
+        self.memory = result;
+        Some(result)
+    }
+}
+
 /// Process a list of numbers - demonstrates working with Python collections
 #[pyfunction]
 fn process_numbers(numbers: Vec<f64>) -> PyResult<(f64, f64, f64)> {
@@ -162,11 +195,72 @@ impl DataProcessor {
     }
 }
 
+/// Streaming statistics in constant memory (Welford's online algorithm)
+#[pyclass]
+#[derive(Default)]
+struct RunningStats {
+    count: u64,
+    mean: f64,
+    /// Sum of squared differences from the running mean
+    m2: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+#[pymethods]
+impl RunningStats {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+
+        self.min = Some(self.min.map_or(x, |min| min.min(x)));
+        self.max = Some(self.max.map_or(x, |max| max.max(x)));
+    }
+
+    fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Population variance, matching `DataProcessor.std_dev`
+    fn variance(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.m2 / self.count as f64
+    }
+
+    fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Smallest value seen, or `None` before the first push
+    fn min(&self) -> Option<f64> {
+        self.min
+    }
+
+    /// Largest value seen, or `None` before the first push
+    fn max(&self) -> Option<f64> {
+        self.max
+    }
+}
+
 /// Python module definition
 #[pymodule]
 fn rust_py_lib(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<Calculator>()?;
     m.add_class::<DataProcessor>()?;
+    m.add_class::<RunningStats>()?;
     m.add_function(wrap_pyfunction!(process_numbers, m)?)?;
     m.add_function(wrap_pyfunction!(fibonacci, m)?)?;
     m.add_function(wrap_pyfunction!(reverse_string, m)?)?;
@@ -186,6 +280,40 @@ mod tests {
         assert_eq!(calc.multiply(2.0, 4.0), 8.0);
     }
 
+    #[test]
+    fn test_calculator_power() {
+        let mut calc = Calculator::new();
+        assert_eq!(calc.power(2.0, 10.0), 1024.0);
+        assert_eq!(calc.recall(), 1024.0);
+        assert_eq!(calc.power(9.0, 0.5), 3.0);
+    }
+
+    // Building a `PyErr` needs libpython, which extension modules don't link,
+    // so the error path is tested through `try_sqrt`
+    #[test]
+    fn test_calculator_sqrt() {
+        let mut calc = Calculator::new();
+        assert_eq!(calc.try_sqrt(16.0), Some(4.0));
+        assert_eq!(calc.recall(), 4.0);
+
+        // A failed operation leaves memory untouched
+        assert_eq!(calc.try_sqrt(-1.0), None);
+        assert_eq!(calc.recall(), 4.0);
+    }
+
+    #[test]
+    fn test_calculator_store_recall() {
+        let mut calc = Calculator::new();
+        assert_eq!(calc.recall(), 0.0);
+
+        calc.store(42.5);
+        assert_eq!(calc.recall(), 42.5);
+        assert_eq!(calc.memory, 42.5);
+
+        calc.clear_memory();
+        assert_eq!(calc.recall(), 0.0);
+    }
+
     #[test]
     fn test_fibonacci() {
         let fib = fibonacci(10);
@@ -199,4 +327,35 @@ mod tests {
         assert_eq!(processor.mean(), 3.0);
         assert_eq!(processor.median(), 3.0);
     }
+
+    #[test]
+    fn test_running_stats_matches_batch() {
+        let data = vec![2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0, -3.5, 1e6];
+        let processor = DataProcessor::new(data.clone());
+
+        let mut stats = RunningStats::new();
+        for &x in &data {
+            stats.push(x);
+        }
+
+        assert_eq!(stats.count(), data.len() as u64);
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * b.abs().max(1.0);
+        assert!(close(stats.mean(), processor.mean()));
+        assert!(close(stats.std_dev(), processor.std_dev()));
+        assert!(close(stats.variance(), processor.std_dev().powi(2)));
+        assert_eq!(stats.min(), Some(-3.5));
+        assert_eq!(stats.max(), Some(1e6));
+    }
+
+    #[test]
+    fn test_running_stats_empty() {
+        let stats = RunningStats::new();
+        let processor = DataProcessor::new(vec![]);
+
+        assert_eq!(stats.count(), 0);
+        assert_eq!(stats.mean(), processor.mean());
+        assert_eq!(stats.std_dev(), processor.std_dev());
+        assert_eq!(stats.min(), None);
+        assert_eq!(stats.max(), None);
+    }
 }
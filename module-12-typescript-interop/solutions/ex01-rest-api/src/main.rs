@@ -1,12 +1,13 @@
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     routing::get,
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use thiserror::Error;
 use tokio::sync::RwLock;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
@@ -44,9 +45,50 @@ struct ErrorResponse {
     details: Option<String>,
 }
 
-impl IntoResponse for ErrorResponse {
-    fn into_response(self) -> axum::response::Response {
-        (StatusCode::BAD_REQUEST, Json(self)).into_response()
+#[derive(Debug, Error)]
+enum ApiError {
+    #[error("User not found")]
+    NotFound,
+
+    #[error("Validation failed")]
+    Validation(String),
+
+    // No handler produces these yet, but they're part of the error contract
+    #[allow(dead_code)]
+    #[error("Conflict")]
+    Conflict(String),
+
+    #[allow(dead_code)]
+    #[error("Internal server error")]
+    Internal(String),
+}
+
+impl ApiError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound => StatusCode::NOT_FOUND,
+            ApiError::Validation(_) => StatusCode::BAD_REQUEST,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let error = self.to_string();
+        let details = match self {
+            ApiError::NotFound => None,
+            ApiError::Validation(details) | ApiError::Conflict(details) => Some(details),
+            ApiError::Internal(details) => {
+                // Logged, but not leaked to the client
+                tracing::error!("Internal error: {}", details);
+                None
+            }
+        };
+
+        (status, Json(ErrorResponse { error, details })).into_response()
     }
 }
 
@@ -98,13 +140,10 @@ async fn health_check() -> &'static str {
 async fn create_user(
     State(state): State<AppState>,
     Json(payload): Json<CreateUserRequest>,
-) -> Result<(StatusCode, Json<User>), ErrorResponse> {
+) -> Result<(StatusCode, Json<User>), ApiError> {
     // Validate email
     if !payload.email.contains('@') {
-        return Err(ErrorResponse {
-            error: "Invalid email".to_string(),
-            details: Some("Email must contain @".to_string()),
-        });
+        return Err(ApiError::Validation("Email must contain @".to_string()));
     }
 
     let user = User {
@@ -136,7 +175,7 @@ async fn list_users(
 async fn get_user(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-) -> Result<Json<User>, StatusCode> {
+) -> Result<Json<User>, ApiError> {
     let users = state.users.read().await;
 
     users
@@ -144,20 +183,20 @@ async fn get_user(
         .find(|u| u.id == id)
         .cloned()
         .map(Json)
-        .ok_or(StatusCode::NOT_FOUND)
+        .ok_or(ApiError::NotFound)
 }
 
 async fn update_user(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateUserRequest>,
-) -> Result<Json<User>, StatusCode> {
+) -> Result<Json<User>, ApiError> {
     let mut users = state.users.write().await;
 
     let user = users
         .iter_mut()
         .find(|u| u.id == id)
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .ok_or(ApiError::NotFound)?;
 
     if let Some(name) = payload.name {
         user.name = name;
@@ -173,15 +212,16 @@ async fn update_user(
 async fn delete_user(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-) -> StatusCode {
+) -> Result<StatusCode, ApiError> {
     let mut users = state.users.write().await;
 
-    if let Some(pos) = users.iter().position(|u| u.id == id) {
-        users.remove(pos);
-        StatusCode::NO_CONTENT
-    } else {
-        StatusCode::NOT_FOUND
-    }
+    let pos = users
+        .iter()
+        .position(|u| u.id == id)
+        .ok_or(ApiError::NotFound)?;
+    users.remove(pos);
+
+    Ok(StatusCode::NO_CONTENT)
 }
 
 #[cfg(test)]
@@ -260,6 +300,85 @@ mod tests {
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
+    async fn json_body(response: axum::response::Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_missing_user_is_not_found() {
+        let app = create_app();
+        let uri = format!("/api/users/{}", Uuid::new_v4());
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/json"
+        );
+        let body = json_body(response).await;
+        assert_eq!(body["error"], "User not found");
+
+        for method in ["PUT", "DELETE"] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method(method)
+                        .uri(&uri)
+                        .header("content-type", "application/json")
+                        .body(Body::from(r#"{"name":"Bob"}"#))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", method);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validation_error_body() {
+        let app = create_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/users")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"Alice","email":"invalid"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = json_body(response).await;
+        assert_eq!(body["error"], "Validation failed");
+        assert_eq!(body["details"], "Email must contain @");
+    }
+
+    #[test]
+    fn test_error_statuses() {
+        assert_eq!(ApiError::NotFound.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            ApiError::Conflict("taken".to_string()).status(),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            ApiError::Internal("boom".to_string()).status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
     #[tokio::test]
     async fn test_list_users() {
         let app = create_app();
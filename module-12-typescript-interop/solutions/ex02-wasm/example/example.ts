@@ -2,6 +2,7 @@
 import init, {
   greet,
   fibonacci,
+  fibonacci_checked,
   sum_array,
   analyze_text,
   DataProcessor,
@@ -28,6 +29,10 @@ async function main() {
   for (let i of [5, 10, 20, 30]) {
     console.log(`fibonacci(${i}) = ${fibonacci(i)}`);
   }
+  // Past n = 93 the result no longer fits in a u64
+  for (let i of [93, 94]) {
+    console.log(`fibonacci_checked(${i}) = ${fibonacci_checked(i) ?? 'overflow'}`);
+  }
   console.log();
 
   // Array processing
@@ -36,34 +36,76 @@ pub fn fibonacci(n: u32) -> u64 {
     curr
 }
 
+/// Compute fibonacci number, or `None` (`undefined` in JS) if it overflows `u64`
+///
+/// The largest representable value is `fibonacci_checked(93)`.
+#[wasm_bindgen]
+pub fn fibonacci_checked(n: u32) -> Option<u64> {
+    if n == 0 {
+        return Some(0);
+    }
+
+    let mut prev = 0u64;
+    let mut curr = 1u64;
+
+    for _ in 2..=n {
+        let next = prev.checked_add(curr)?;
+        prev = curr;
+        curr = next;
+    }
+
+    Some(curr)
+}
+
 /// Sum an array of numbers
 #[wasm_bindgen]
 pub fn sum_array(numbers: &[f64]) -> f64 {
     numbers.iter().sum()
 }
 
+/// Word, character and line counts for a piece of text
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TextStats {
+    words: usize,
+    characters: usize,
+    lines: usize,
+    unique_words: usize,
+}
+
+impl TextStats {
+    fn from_text(text: &str) -> Self {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let unique_words: std::collections::HashSet<&str> = words.iter().copied().collect();
+
+        TextStats {
+            words: words.len(),
+            characters: text.chars().count(),
+            lines: text.lines().count(),
+            unique_words: unique_words.len(),
+        }
+    }
+}
+
 /// Process text: count words, characters, and lines
 #[wasm_bindgen]
 pub fn analyze_text(text: &str) -> JsValue {
-    #[derive(Serialize)]
-    struct TextStats {
-        words: usize,
-        characters: usize,
-        lines: usize,
-        unique_words: usize,
-    }
+    serde_wasm_bindgen::to_value(&TextStats::from_text(text)).unwrap()
+}
 
-    let words: Vec<&str> = text.split_whitespace().collect();
-    let unique_words: std::collections::HashSet<&str> = words.iter().copied().collect();
+/// Analyze many documents in a single call across the JS/WASM boundary
+///
+/// Takes an array of strings and returns an array of stats in the same order.
+#[wasm_bindgen]
+pub fn analyze_texts(texts: JsValue) -> Result<JsValue, JsValue> {
+    let texts: Vec<String> = serde_wasm_bindgen::from_value(texts)
+        .map_err(|e| JsValue::from_str(&format!("Expected an array of strings: {}", e)))?;
 
-    let stats = TextStats {
-        words: words.len(),
-        characters: text.chars().count(),
-        lines: text.lines().count(),
-        unique_words: unique_words.len(),
-    };
+    serde_wasm_bindgen::to_value(&analyze_batch(&texts))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize result: {}", e)))
+}
 
-    serde_wasm_bindgen::to_value(&stats).unwrap()
+fn analyze_batch(texts: &[String]) -> Vec<TextStats> {
+    texts.iter().map(|text| TextStats::from_text(text)).collect()
 }
 
 /// DataProcessor class - demonstrates stateful WASM structs
@@ -212,6 +254,36 @@ mod tests {
         assert_eq!(fibonacci(20), 6765);
     }
 
+    #[test]
+    fn test_fibonacci_checked() {
+        assert_eq!(fibonacci_checked(0), Some(0));
+        assert_eq!(fibonacci_checked(1), Some(1));
+        assert_eq!(fibonacci_checked(20), Some(fibonacci(20)));
+        assert_eq!(fibonacci_checked(93), Some(12_200_160_415_121_876_738));
+        assert_eq!(fibonacci_checked(94), None);
+        assert_eq!(fibonacci_checked(u32::MAX), None);
+    }
+
+    #[test]
+    fn test_analyze_batch() {
+        let texts = vec![
+            "hello world hello".to_string(),
+            "one\ntwo\nthree".to_string(),
+            String::new(),
+        ];
+
+        let stats = analyze_batch(&texts);
+        assert_eq!(
+            stats,
+            vec![
+                TextStats { words: 3, characters: 17, lines: 1, unique_words: 2 },
+                TextStats { words: 3, characters: 13, lines: 3, unique_words: 3 },
+                TextStats { words: 0, characters: 0, lines: 0, unique_words: 0 },
+            ]
+        );
+        assert!(analyze_batch(&[]).is_empty());
+    }
+
     #[test]
     fn test_sum_array() {
         assert_eq!(sum_array(&[1.0, 2.0, 3.0, 4.0, 5.0]), 15.0);
@@ -261,3 +333,29 @@ mod tests {
         assert_ne!(hash1, hash3);
     }
 }
+
+// JsValue conversions only work inside a WASM runtime (`wasm-pack test --node`)
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_analyze_texts() {
+        let input = js_sys::Array::of2(&"a b a".into(), &"".into());
+        let result = js_sys::Array::from(&analyze_texts(input.into()).unwrap());
+
+        assert_eq!(result.length(), 2);
+        let first = js_sys::Reflect::get(&result.get(0), &"unique_words".into()).unwrap();
+        assert_eq!(first.as_f64(), Some(2.0));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_analyze_texts_empty_and_invalid() {
+        let empty = analyze_texts(js_sys::Array::new().into()).unwrap();
+        assert_eq!(js_sys::Array::from(&empty).length(), 0);
+
+        let mixed = js_sys::Array::of2(&"text".into(), &JsValue::from_f64(42.0));
+        assert!(analyze_texts(mixed.into()).is_err());
+    }
+}